@@ -0,0 +1 @@
+DROP TABLE ecash_receives;
@@ -0,0 +1,22 @@
+CREATE TABLE ecash_receives
+(
+    operation_id   TEXT PRIMARY KEY NOT NULL,
+    fedimint_id    TEXT REFERENCES fedimint (id),
+    cashu_mint_url TEXT REFERENCES cashu_mint (mint_url),
+    token          TEXT             NOT NULL,
+    amount_msats   BIGINT           NOT NULL,
+    fee_msats      BIGINT           NOT NULL,
+    status         INTEGER          NOT NULL,
+    created_at     TIMESTAMP        NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at     TIMESTAMP        NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TRIGGER update_timestamp_ecash_receives
+    AFTER UPDATE
+    ON ecash_receives
+    FOR EACH ROW
+BEGIN
+UPDATE ecash_receives
+SET updated_at = CURRENT_TIMESTAMP
+WHERE operation_id = OLD.operation_id;
+END;
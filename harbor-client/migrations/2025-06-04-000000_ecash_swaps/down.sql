@@ -0,0 +1,4 @@
+ALTER TABLE ecash_receives DROP COLUMN swap_quote_id;
+ALTER TABLE ecash_receives DROP COLUMN swap_claimed;
+ALTER TABLE ecash_receives DROP COLUMN swap_payment_hash;
+ALTER TABLE ecash_receives DROP COLUMN swap_mint_url;
@@ -0,0 +1,6 @@
+-- Tokens from mints we don't use are melted into an invoice on one of ours
+ALTER TABLE ecash_receives ADD COLUMN swap_mint_url TEXT;
+ALTER TABLE ecash_receives ADD COLUMN swap_payment_hash TEXT;
+-- So a retried swap knows whether the token was claimed and which melt it started
+ALTER TABLE ecash_receives ADD COLUMN swap_claimed INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE ecash_receives ADD COLUMN swap_quote_id TEXT;
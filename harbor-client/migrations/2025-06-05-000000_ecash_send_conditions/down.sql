@@ -0,0 +1,3 @@
+ALTER TABLE ecash_sends DROP COLUMN reminded;
+ALTER TABLE ecash_sends DROP COLUMN locktime;
+ALTER TABLE ecash_sends DROP COLUMN spending_conditions;
@@ -0,0 +1,5 @@
+-- NUT-11 conditions of locked tokens, locktime is a unix timestamp
+ALTER TABLE ecash_sends ADD COLUMN spending_conditions TEXT;
+ALTER TABLE ecash_sends ADD COLUMN locktime BIGINT;
+-- set once the user was reminded that the locktime is about to pass
+ALTER TABLE ecash_sends ADD COLUMN reminded INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1 @@
+ALTER TABLE lightning_payments DROP COLUMN parent_operation_id;
@@ -0,0 +1,2 @@
+-- Multi-path payments keep one row per part, linked to a parent row for history
+ALTER TABLE lightning_payments ADD COLUMN parent_operation_id TEXT;
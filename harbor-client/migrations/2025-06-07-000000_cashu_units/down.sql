@@ -0,0 +1,13 @@
+ALTER TABLE ecash_receives DROP COLUMN unit_fee;
+ALTER TABLE ecash_receives DROP COLUMN unit_amount;
+ALTER TABLE ecash_receives DROP COLUMN cashu_unit;
+ALTER TABLE ecash_sends DROP COLUMN unit_fee;
+ALTER TABLE ecash_sends DROP COLUMN unit_amount;
+ALTER TABLE ecash_sends DROP COLUMN cashu_unit;
+ALTER TABLE lightning_receives DROP COLUMN unit_fee;
+ALTER TABLE lightning_receives DROP COLUMN unit_amount;
+ALTER TABLE lightning_receives DROP COLUMN cashu_unit;
+ALTER TABLE lightning_payments DROP COLUMN unit_fee;
+ALTER TABLE lightning_payments DROP COLUMN unit_amount;
+ALTER TABLE lightning_payments DROP COLUMN cashu_unit;
+DROP TABLE cashu_mint_units;
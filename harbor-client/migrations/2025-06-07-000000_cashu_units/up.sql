@@ -0,0 +1,28 @@
+-- A cashu mint can be joined once for each currency unit it supports
+CREATE TABLE cashu_mint_units
+(
+    mint_url TEXT    NOT NULL REFERENCES cashu_mint (mint_url),
+    unit     TEXT    NOT NULL,
+    active   INTEGER NOT NULL DEFAULT 1,
+    PRIMARY KEY (mint_url, unit)
+);
+
+INSERT INTO cashu_mint_units (mint_url, unit, active)
+SELECT mint_url, 'sat', active
+FROM cashu_mint;
+
+-- NULL means sat. Other units have no msats value, their amount and fee are
+-- stored in the unit's base amount (e.g. cents for usd) and the msats columns
+-- are left at 0
+ALTER TABLE lightning_payments ADD COLUMN cashu_unit TEXT;
+ALTER TABLE lightning_payments ADD COLUMN unit_amount BIGINT;
+ALTER TABLE lightning_payments ADD COLUMN unit_fee BIGINT;
+ALTER TABLE lightning_receives ADD COLUMN cashu_unit TEXT;
+ALTER TABLE lightning_receives ADD COLUMN unit_amount BIGINT;
+ALTER TABLE lightning_receives ADD COLUMN unit_fee BIGINT;
+ALTER TABLE ecash_sends ADD COLUMN cashu_unit TEXT;
+ALTER TABLE ecash_sends ADD COLUMN unit_amount BIGINT;
+ALTER TABLE ecash_sends ADD COLUMN unit_fee BIGINT;
+ALTER TABLE ecash_receives ADD COLUMN cashu_unit TEXT;
+ALTER TABLE ecash_receives ADD COLUMN unit_amount BIGINT;
+ALTER TABLE ecash_receives ADD COLUMN unit_fee BIGINT;
@@ -0,0 +1,3 @@
+ALTER TABLE cashu_mint DROP COLUMN dleq_unverified;
+ALTER TABLE cashu_mint DROP COLUMN dleq_invalid;
+ALTER TABLE cashu_mint DROP COLUMN dleq_missing;
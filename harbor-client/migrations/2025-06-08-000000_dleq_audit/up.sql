@@ -0,0 +1,5 @@
+-- Counts of mint signatures that came without a DLEQ proof, with one that didn't verify,
+-- or that we couldn't verify because the mint didn't give us the keys it signed with
+ALTER TABLE cashu_mint ADD COLUMN dleq_missing INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE cashu_mint ADD COLUMN dleq_invalid INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE cashu_mint ADD COLUMN dleq_unverified INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,2 @@
+ALTER TABLE profile DROP COLUMN consolidation_enabled;
+DROP TABLE cashu_consolidations;
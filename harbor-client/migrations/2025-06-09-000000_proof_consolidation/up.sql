@@ -0,0 +1,15 @@
+-- Swaps that merged fragmented cashu proofs into fewer, larger ones, amounts
+-- are in the unit's base amount
+CREATE TABLE cashu_consolidations
+(
+    id             TEXT PRIMARY KEY NOT NULL,
+    cashu_mint_url TEXT             NOT NULL REFERENCES cashu_mint (mint_url),
+    cashu_unit     TEXT             NOT NULL,
+    amount         BIGINT           NOT NULL,
+    fee            BIGINT           NOT NULL,
+    proofs_before  INTEGER          NOT NULL,
+    proofs_after   INTEGER          NOT NULL,
+    created_at     TIMESTAMP        NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+ALTER TABLE profile ADD COLUMN consolidation_enabled INTEGER NOT NULL DEFAULT 1;
@@ -0,0 +1,2 @@
+ALTER TABLE lightning_receives DROP COLUMN quote_key_index;
+ALTER TABLE profile DROP COLUMN next_quote_key_index;
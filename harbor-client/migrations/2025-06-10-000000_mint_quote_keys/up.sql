@@ -0,0 +1,3 @@
+-- NUT-20 mint quotes are locked to a key derived from the seed at this index
+ALTER TABLE profile ADD COLUMN next_quote_key_index INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE lightning_receives ADD COLUMN quote_key_index INTEGER;
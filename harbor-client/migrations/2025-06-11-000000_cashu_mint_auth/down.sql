@@ -0,0 +1,2 @@
+DROP TABLE cashu_blind_auth_tokens;
+DROP TABLE cashu_mint_auth;
@@ -0,0 +1,17 @@
+-- Login state for cashu mints that require clear (NUT-21) or blind (NUT-22) auth
+CREATE TABLE cashu_mint_auth
+(
+    mint_url      TEXT PRIMARY KEY NOT NULL,
+    settings      TEXT             NOT NULL,
+    access_token  TEXT             NOT NULL,
+    refresh_token TEXT,
+    expires_at    BIGINT
+);
+
+-- Unspent blind auth tokens, each one is good for a single protected request
+CREATE TABLE cashu_blind_auth_tokens
+(
+    token      TEXT PRIMARY KEY NOT NULL,
+    mint_url   TEXT             NOT NULL REFERENCES cashu_mint_auth (mint_url),
+    created_at TIMESTAMP        NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
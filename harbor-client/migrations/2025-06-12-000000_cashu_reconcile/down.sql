@@ -0,0 +1 @@
+ALTER TABLE cashu_mint DROP COLUMN needs_reconcile;
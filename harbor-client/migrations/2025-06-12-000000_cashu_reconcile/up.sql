@@ -0,0 +1,2 @@
+-- Set when a request that spends proofs failed and we don't know if the mint processed it
+ALTER TABLE cashu_mint ADD COLUMN needs_reconcile INTEGER NOT NULL DEFAULT 0;
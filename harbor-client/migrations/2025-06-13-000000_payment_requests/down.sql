@@ -0,0 +1,2 @@
+DROP TRIGGER update_timestamp_payment_requests;
+DROP TABLE payment_requests;
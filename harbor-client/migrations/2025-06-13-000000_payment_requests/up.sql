@@ -0,0 +1,25 @@
+-- Cashu payment requests (NUT-18) we published, and the ecash receive that paid each one.
+-- The amount is in the base amount of the unit, e.g. cents for usd
+CREATE TABLE payment_requests
+(
+    id               TEXT PRIMARY KEY NOT NULL,
+    cashu_mint_url   TEXT             NOT NULL REFERENCES cashu_mint (mint_url),
+    cashu_unit       TEXT             NOT NULL,
+    amount           BIGINT,
+    description      TEXT,
+    request          TEXT             NOT NULL,
+    status           INTEGER          NOT NULL,
+    ecash_receive_id TEXT REFERENCES ecash_receives (operation_id),
+    created_at       TIMESTAMP        NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at       TIMESTAMP        NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TRIGGER update_timestamp_payment_requests
+    AFTER UPDATE
+    ON payment_requests
+    FOR EACH ROW
+BEGIN
+UPDATE payment_requests
+SET updated_at = CURRENT_TIMESTAMP
+WHERE id = OLD.id;
+END;
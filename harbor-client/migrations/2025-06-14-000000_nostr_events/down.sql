@@ -0,0 +1,2 @@
+ALTER TABLE profile DROP COLUMN nostr_enabled;
+DROP TABLE nostr_events;
@@ -0,0 +1,14 @@
+-- Nostr events with ecash for us, relays replay gift wraps on every reconnect.
+-- The event is kept until it's redeemed so a failed redeem can be retried.
+CREATE TABLE nostr_events
+(
+    event_id   TEXT PRIMARY KEY NOT NULL,
+    event      TEXT,
+    status     INTEGER          NOT NULL,
+    attempts   INTEGER          NOT NULL DEFAULT 0,
+    retry_at   TIMESTAMP,
+    created_at TIMESTAMP        NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Listening for DMs connects to public relays, so it's off until turned on
+ALTER TABLE profile ADD COLUMN nostr_enabled INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,3 @@
+-- When the settings kept in the wallet manifest last changed, so a restore
+-- keeps whichever side changed them last
+ALTER TABLE profile ADD COLUMN settings_updated_at BIGINT;
@@ -0,0 +1,289 @@
+//! The wallet manifest we keep in every federation backup, so restoring from
+//! the seed can bring back the rest of the wallet: the other federations, the
+//! cashu mints and the user's settings.
+//!
+//! It is encrypted with a key derived from the seed before it goes into the
+//! backup metadata, on top of fedimint's own encryption of the backup.
+
+use crate::db::DBConnection;
+use crate::keys;
+use anyhow::anyhow;
+use bip39::Mnemonic;
+use cdk::mint_url::MintUrl;
+use cdk::nuts::CurrencyUnit;
+use fedimint_client::ClientHandleArc;
+use fedimint_client::backup::Metadata;
+use fedimint_core::invite_code::InviteCode;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+/// Argon2 salt for the manifest key, the key material is already random so it can be fixed
+const MANIFEST_SALT: &str = "harborwalletmanifest";
+
+const MANIFEST_VERSION: u32 = 1;
+
+/// Restores in progress, backups wait for them so a half restored wallet
+/// can't overwrite the manifest we're restoring from
+static RESTORES_IN_PROGRESS: AtomicUsize = AtomicUsize::new(0);
+
+/// Set when a backup was held off by a restore, the last restore to finish runs it
+static BACKUP_QUEUED: AtomicBool = AtomicBool::new(false);
+
+/// Holds off federation backups until it's dropped
+pub(crate) struct BackupPause(());
+
+impl BackupPause {
+    pub(crate) fn new() -> Self {
+        RESTORES_IN_PROGRESS.fetch_add(1, Ordering::SeqCst);
+        Self(())
+    }
+
+    /// Asks for a backup once every restore is done
+    pub(crate) fn queue_backup(&self) {
+        BACKUP_QUEUED.store(true, Ordering::SeqCst);
+    }
+
+    /// Ends the pause. Returns true if no other restore is running and a
+    /// backup was queued meanwhile, which the caller then runs.
+    pub(crate) fn finish(self) -> bool {
+        drop(self);
+        RESTORES_IN_PROGRESS.load(Ordering::SeqCst) == 0
+            && BACKUP_QUEUED.swap(false, Ordering::SeqCst)
+    }
+}
+
+impl Drop for BackupPause {
+    fn drop(&mut self) {
+        RESTORES_IN_PROGRESS.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Everything besides ecash needed to rebuild the wallet from its seed
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WalletManifest {
+    pub version: u32,
+    pub federations: Vec<InviteCode>,
+    pub cashu_mints: Vec<(MintUrl, CurrencyUnit)>,
+    pub onchain_receive_enabled: bool,
+    pub tor_enabled: bool,
+    pub consolidation_enabled: bool,
+    /// Unix time the settings above last changed, 0 if never
+    pub settings_updated_at: u64,
+}
+
+/// How the manifest sits in the backup metadata
+#[derive(Serialize, Deserialize)]
+struct EncryptedManifest {
+    harbor_manifest: String,
+}
+
+impl WalletManifest {
+    /// The manifest for the wallet as it is now
+    pub fn from_storage(storage: &dyn DBConnection) -> anyhow::Result<Self> {
+        let profile = storage
+            .get_profile()?
+            .ok_or(anyhow!("Wallet has no profile"))?;
+
+        let federations = storage
+            .list_federations()?
+            .into_iter()
+            .filter_map(|id| {
+                storage
+                    .get_federation_invite_code(id.parse().ok()?)
+                    .ok()
+                    .flatten()
+            })
+            .collect();
+
+        Ok(Self {
+            version: MANIFEST_VERSION,
+            federations,
+            cashu_mints: storage.list_cashu_mints()?,
+            onchain_receive_enabled: profile.onchain_receive_enabled(),
+            tor_enabled: profile.tor_enabled(),
+            consolidation_enabled: profile.consolidation_enabled(),
+            settings_updated_at: profile.settings_updated_at().unwrap_or_default(),
+        })
+    }
+
+    /// Combines two manifests, keeping the mints from both and the settings
+    /// of the newer one
+    pub fn union(mut self, other: Self) -> Self {
+        for invite_code in other.federations {
+            let id = invite_code.federation_id();
+            if !self.federations.iter().any(|f| f.federation_id() == id) {
+                self.federations.push(invite_code);
+            }
+        }
+        for mint in other.cashu_mints {
+            if !self.cashu_mints.contains(&mint) {
+                self.cashu_mints.push(mint);
+            }
+        }
+        if (other.version, other.settings_updated_at) > (self.version, self.settings_updated_at) {
+            self.onchain_receive_enabled = other.onchain_receive_enabled;
+            self.tor_enabled = other.tor_enabled;
+            self.consolidation_enabled = other.consolidation_enabled;
+            self.settings_updated_at = other.settings_updated_at;
+        }
+        self.version = self.version.max(other.version);
+        self
+    }
+
+    pub fn encrypt(&self, mnemonic: &Mnemonic) -> anyhow::Result<Metadata> {
+        let key = manifest_key(mnemonic)?;
+        let ciphertext = fedimint_aead::encrypt(serde_json::to_vec(self)?, &key)?;
+        Ok(Metadata::from_json_serialized(EncryptedManifest {
+            harbor_manifest: hex::encode(ciphertext),
+        }))
+    }
+
+    /// Reads the manifest back out of backup metadata, None if the backup doesn't have one
+    pub fn decrypt(metadata: &Metadata, mnemonic: &Mnemonic) -> anyhow::Result<Option<Self>> {
+        if metadata.is_empty() {
+            return Ok(None);
+        }
+        let Ok(encrypted) = metadata.to_json_deserialized::<EncryptedManifest>() else {
+            return Ok(None);
+        };
+
+        let key = manifest_key(mnemonic)?;
+        let mut ciphertext = hex::decode(encrypted.harbor_manifest)?;
+        let plaintext = fedimint_aead::decrypt(&mut ciphertext, &key)
+            .map_err(|_| anyhow!("Could not decrypt the wallet manifest"))?;
+        Ok(Some(serde_json::from_slice(plaintext)?))
+    }
+}
+
+fn manifest_key(mnemonic: &Mnemonic) -> anyhow::Result<fedimint_aead::LessSafeKey> {
+    let secret = keys::backup_manifest_secret(mnemonic)?;
+    fedimint_aead::get_encryption_key(&hex::encode(secret), MANIFEST_SALT)
+}
+
+/// Backs the client up to its federation, carrying the current wallet manifest.
+/// While a restore is in progress the backup is queued instead, and runs once
+/// the last restore finishes.
+pub(crate) async fn backup_to_federation(
+    client: &ClientHandleArc,
+    storage: &dyn DBConnection,
+) -> anyhow::Result<()> {
+    if RESTORES_IN_PROGRESS.load(Ordering::SeqCst) > 0 {
+        log::debug!(
+            "Queueing backup to {} until the restore is done",
+            client.federation_id()
+        );
+        BACKUP_QUEUED.store(true, Ordering::SeqCst);
+        return Ok(());
+    }
+    let mnemonic = storage.retrieve_mnemonic()?;
+    let metadata = WalletManifest::from_storage(storage)?.encrypt(&mnemonic)?;
+    client.backup_to_federation(metadata).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    const INVITE_CODE: &str = "fed11qgqzc2nhwden5te0vejkg6tdd9h8gepwvejkg6tdd9h8garhduhx6at5d9h8jmn9wshxxmmd9uqqzgxg6s3evnr6m9zdxr6hxkdkukexpcs3mn7mj3g5pc5dfh63l4tj6g9zk4er";
+    const SEED_WORDS: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+    #[test]
+    fn test_manifest_round_trip() {
+        let mnemonic = Mnemonic::from_str(SEED_WORDS).unwrap();
+        let manifest = WalletManifest {
+            version: MANIFEST_VERSION,
+            federations: vec![],
+            cashu_mints: vec![(
+                MintUrl::from_str("https://mint.example.com").unwrap(),
+                CurrencyUnit::Sat,
+            )],
+            onchain_receive_enabled: true,
+            tor_enabled: false,
+            consolidation_enabled: true,
+            settings_updated_at: 1_700_000_000,
+        };
+
+        let metadata = manifest.encrypt(&mnemonic).unwrap();
+        assert_eq!(
+            WalletManifest::decrypt(&metadata, &mnemonic).unwrap(),
+            Some(manifest)
+        );
+
+        // only our seed can read it
+        let other =
+            Mnemonic::from_str("zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo wrong").unwrap();
+        assert!(WalletManifest::decrypt(&metadata, &other).is_err());
+
+        // backups from before we kept a manifest
+        assert_eq!(
+            WalletManifest::decrypt(&Metadata::empty(), &mnemonic).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_manifest_union() {
+        let mint = |url: &str| (MintUrl::from_str(url).unwrap(), CurrencyUnit::Sat);
+        let local = WalletManifest {
+            version: MANIFEST_VERSION,
+            federations: vec![InviteCode::from_str(INVITE_CODE).unwrap()],
+            cashu_mints: vec![mint("https://local.example.com")],
+            onchain_receive_enabled: false,
+            tor_enabled: true,
+            consolidation_enabled: true,
+            settings_updated_at: 1_700_000_000,
+        };
+        let restored = WalletManifest {
+            version: MANIFEST_VERSION,
+            federations: vec![InviteCode::from_str(INVITE_CODE).unwrap()],
+            cashu_mints: vec![
+                mint("https://restored.example.com"),
+                mint("https://local.example.com"),
+            ],
+            onchain_receive_enabled: true,
+            tor_enabled: false,
+            consolidation_enabled: false,
+            settings_updated_at: 1_800_000_000,
+        };
+
+        let merged = local.clone().union(restored.clone());
+        assert_eq!(merged.federations.len(), 1);
+        assert_eq!(
+            merged.cashu_mints,
+            vec![
+                mint("https://local.example.com"),
+                mint("https://restored.example.com"),
+            ]
+        );
+        // the restored settings changed last
+        assert!(merged.onchain_receive_enabled);
+        assert!(!merged.tor_enabled);
+        assert!(!merged.consolidation_enabled);
+        assert_eq!(merged.settings_updated_at, 1_800_000_000);
+
+        // a wallet changed since the backup keeps its own settings
+        let changed = WalletManifest {
+            settings_updated_at: 1_900_000_000,
+            ..local
+        };
+        let merged = changed.union(restored);
+        assert!(!merged.onchain_receive_enabled);
+        assert!(merged.tor_enabled);
+        assert!(merged.consolidation_enabled);
+        assert_eq!(merged.cashu_mints.len(), 2);
+    }
+
+    #[test]
+    fn test_backup_queued_during_restore() {
+        let first = BackupPause::new();
+        let second = BackupPause::new();
+        first.queue_backup();
+
+        // runs once, after the last restore
+        assert!(!first.finish());
+        assert!(second.finish());
+        assert!(!BackupPause::new().finish());
+    }
+}
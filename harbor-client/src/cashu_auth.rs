@@ -0,0 +1,591 @@
+//! Login for cashu mints that gate endpoints behind clear (NUT-21) or blind
+//! (NUT-22) auth tokens.
+//!
+//! Clear auth is an OpenID Connect access token, we get it with the device
+//! authorization flow (RFC 8628) so the user logs in from their browser and we
+//! never see their password. Blind auth tokens are single use ecash tokens of
+//! the "auth" unit that we mint with the clear token, so the mint can't link
+//! the requests they protect to the user.
+
+use crate::db::DBConnection;
+use crate::http::{RequestBody, make_raw_request};
+use anyhow::anyhow;
+use bitcoin::base64::Engine;
+use bitcoin::base64::engine::general_purpose::URL_SAFE;
+use cdk::dhke::{blind_message, unblind_message};
+use cdk::mint_url::MintUrl;
+use cdk::nuts::{BlindedMessage, KeysResponse, KeysetResponse, MintResponse};
+use cdk::secret::Secret;
+use cdk::util::unix_time;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+/// The most blind auth tokens we mint at once, even if the mint allows more
+const MAX_BLIND_TOKENS: u64 = 50;
+
+/// Refresh the access token when it has less than this many seconds left
+const EXPIRY_MARGIN: u64 = 60;
+
+/// An endpoint the mint requires an auth token for
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ProtectedEndpoint {
+    pub method: String,
+    pub path: String,
+}
+
+impl ProtectedEndpoint {
+    /// Paths are either exact or a prefix ending in `*`, mints also write
+    /// them as anchored regexes like `^/v1/mint/.*$` which we treat the same
+    fn matches(&self, method: &str, path: &str) -> bool {
+        if !self.method.eq_ignore_ascii_case(method) {
+            return false;
+        }
+
+        let pattern = self.path.trim_start_matches('^').trim_end_matches('$');
+        match pattern
+            .strip_suffix(".*")
+            .or_else(|| pattern.strip_suffix('*'))
+        {
+            Some(prefix) => path.starts_with(prefix),
+            None => pattern == path,
+        }
+    }
+}
+
+/// What a mint told us about its auth requirements in its info
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AuthSettings {
+    /// OpenID Connect discovery url of the mint's identity provider
+    pub openid_discovery: String,
+    pub client_id: String,
+    pub clear_endpoints: Vec<ProtectedEndpoint>,
+    pub blind_endpoints: Vec<ProtectedEndpoint>,
+    /// How many blind auth tokens the mint signs in one request
+    pub bat_max_mint: u64,
+}
+
+impl AuthSettings {
+    pub fn requires_auth(&self) -> bool {
+        !self.clear_endpoints.is_empty() || !self.blind_endpoints.is_empty()
+    }
+}
+
+#[derive(Deserialize)]
+struct RawInfo {
+    #[serde(default)]
+    nuts: RawNuts,
+}
+
+#[derive(Deserialize, Default)]
+struct RawNuts {
+    #[serde(rename = "21")]
+    nut21: Option<Nut21Settings>,
+    #[serde(rename = "22")]
+    nut22: Option<Nut22Settings>,
+}
+
+#[derive(Deserialize)]
+struct Nut21Settings {
+    openid_discovery: String,
+    client_id: String,
+    #[serde(default)]
+    protected_endpoints: Vec<ProtectedEndpoint>,
+}
+
+#[derive(Deserialize)]
+struct Nut22Settings {
+    #[serde(default)]
+    bat_max_mint: u64,
+    #[serde(default)]
+    protected_endpoints: Vec<ProtectedEndpoint>,
+}
+
+/// Reads the auth settings from the mint's info, cdk doesn't parse them
+/// without its auth feature so we fetch the info ourselves.
+///
+/// Returns None if the mint doesn't require auth for anything.
+pub async fn get_auth_settings(
+    mint_url: &MintUrl,
+    tor_enabled: bool,
+) -> anyhow::Result<Option<AuthSettings>> {
+    let url = mint_url.join_paths(&["v1", "info"])?;
+    let info: RawInfo = get_json(url.as_str(), &[], tor_enabled).await?;
+
+    let Some(nut21) = info.nuts.nut21 else {
+        return Ok(None);
+    };
+
+    let (bat_max_mint, blind_endpoints) = info
+        .nuts
+        .nut22
+        .map(|n| (n.bat_max_mint, n.protected_endpoints))
+        .unwrap_or_default();
+
+    let settings = AuthSettings {
+        openid_discovery: nut21.openid_discovery,
+        client_id: nut21.client_id,
+        clear_endpoints: nut21.protected_endpoints,
+        blind_endpoints,
+        bat_max_mint,
+    };
+
+    Ok(settings.requires_auth().then_some(settings))
+}
+
+#[derive(Deserialize)]
+struct OidcConfig {
+    token_endpoint: String,
+    device_authorization_endpoint: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct DeviceAuthorizationResponse {
+    device_code: String,
+    user_code: String,
+    verification_uri: String,
+    verification_uri_complete: Option<String>,
+    expires_in: u64,
+    #[serde(default = "default_poll_interval")]
+    interval: u64,
+}
+
+const fn default_poll_interval() -> u64 {
+    5
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    refresh_token: Option<String>,
+    expires_in: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct TokenErrorResponse {
+    error: String,
+    error_description: Option<String>,
+}
+
+/// Tokens from the mint's identity provider
+#[derive(Debug, Clone)]
+pub struct AuthTokens {
+    pub access_token: String,
+    pub refresh_token: Option<String>,
+    /// Unix time the access token expires at
+    pub expires_at: Option<u64>,
+}
+
+impl From<TokenResponse> for AuthTokens {
+    fn from(response: TokenResponse) -> Self {
+        Self {
+            access_token: response.access_token,
+            refresh_token: response.refresh_token,
+            expires_at: response.expires_in.map(|e| unix_time() + e),
+        }
+    }
+}
+
+/// A pending device login, the user finishes it by entering the code at the
+/// verification uri
+pub struct DeviceLogin {
+    client_id: String,
+    token_endpoint: String,
+    device_code: String,
+    pub user_code: String,
+    pub verification_uri: String,
+    interval: u64,
+    expires_at: u64,
+}
+
+impl DeviceLogin {
+    pub async fn start(settings: &AuthSettings, tor_enabled: bool) -> anyhow::Result<Self> {
+        let config: OidcConfig = get_json(&settings.openid_discovery, &[], tor_enabled).await?;
+        let endpoint = config.device_authorization_endpoint.ok_or(anyhow!(
+            "Mint's identity provider does not support device login"
+        ))?;
+
+        let (status, body) = post_form(
+            &endpoint,
+            &[("client_id", settings.client_id.as_str())],
+            tor_enabled,
+        )
+        .await?;
+        if !status.is_success() {
+            return Err(token_error(&body));
+        }
+        let response: DeviceAuthorizationResponse = serde_json::from_slice(&body)?;
+
+        Ok(Self {
+            client_id: settings.client_id.clone(),
+            token_endpoint: config.token_endpoint,
+            device_code: response.device_code,
+            user_code: response.user_code,
+            verification_uri: response
+                .verification_uri_complete
+                .unwrap_or(response.verification_uri),
+            interval: response.interval.max(1),
+            expires_at: unix_time() + response.expires_in,
+        })
+    }
+
+    /// Polls the identity provider until the user approved or denied the login,
+    /// or `cancel` is set
+    pub async fn wait(
+        mut self,
+        tor_enabled: bool,
+        cancel: &AtomicBool,
+    ) -> anyhow::Result<AuthTokens> {
+        loop {
+            if unix_time() > self.expires_at {
+                return Err(anyhow!("Login expired before it was approved"));
+            }
+
+            tokio::time::sleep(Duration::from_secs(self.interval)).await;
+            if cancel.load(Ordering::Relaxed) {
+                return Err(anyhow!("Login cancelled"));
+            }
+
+            let (status, body) = post_form(
+                &self.token_endpoint,
+                &[
+                    ("grant_type", "urn:ietf:params:oauth:grant-type:device_code"),
+                    ("device_code", self.device_code.as_str()),
+                    ("client_id", self.client_id.as_str()),
+                ],
+                tor_enabled,
+            )
+            .await?;
+
+            if status.is_success() {
+                let response: TokenResponse = serde_json::from_slice(&body)?;
+                return Ok(response.into());
+            }
+
+            match serde_json::from_slice::<TokenErrorResponse>(&body) {
+                Ok(e) if e.error == "authorization_pending" => {}
+                Ok(e) if e.error == "slow_down" => self.interval += 5,
+                _ => return Err(token_error(&body)),
+            }
+        }
+    }
+}
+
+/// Adds the auth tokens a mint requires to our requests, refreshing the
+/// access token and minting blind auth tokens as needed
+pub struct CashuAuth {
+    mint_url: MintUrl,
+    settings: AuthSettings,
+    storage: Arc<dyn DBConnection + Send + Sync>,
+    tor_enabled: bool,
+    // so concurrent requests don't refresh or mint blind tokens twice
+    lock: Mutex<()>,
+}
+
+impl std::fmt::Debug for CashuAuth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CashuAuth")
+            .field("mint_url", &self.mint_url)
+            .field("settings", &self.settings)
+            .finish_non_exhaustive()
+    }
+}
+
+impl CashuAuth {
+    pub fn new(
+        mint_url: MintUrl,
+        settings: AuthSettings,
+        storage: Arc<dyn DBConnection + Send + Sync>,
+        tor_enabled: bool,
+    ) -> Self {
+        Self {
+            mint_url,
+            settings,
+            storage,
+            tor_enabled,
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// The auth header a request to the endpoint needs, if it is protected
+    pub async fn header(
+        &self,
+        method: &str,
+        path: &str,
+    ) -> anyhow::Result<Option<(&'static str, String)>> {
+        if self
+            .settings
+            .clear_endpoints
+            .iter()
+            .any(|e| e.matches(method, path))
+        {
+            let _guard = self.lock.lock().await;
+            return Ok(Some(("Clear-auth", self.access_token().await?)));
+        }
+
+        if self
+            .settings
+            .blind_endpoints
+            .iter()
+            .any(|e| e.matches(method, path))
+        {
+            return Ok(Some(("Blind-auth", self.blind_token().await?)));
+        }
+
+        Ok(None)
+    }
+
+    /// A valid access token, refreshed if it is about to expire.
+    /// Callers must hold the lock.
+    async fn access_token(&self) -> anyhow::Result<String> {
+        let auth = self
+            .storage
+            .get_cashu_mint_auth(&self.mint_url)?
+            .ok_or(anyhow!("Not logged in to {}", self.mint_url))?;
+
+        let expiring = auth
+            .expires_at()
+            .is_some_and(|t| t <= unix_time() + EXPIRY_MARGIN);
+        if !expiring {
+            return Ok(auth.access_token);
+        }
+
+        let Some(refresh_token) = auth.refresh_token else {
+            return Err(anyhow!(
+                "Login to {} expired, remove and add the mint again to log in",
+                self.mint_url
+            ));
+        };
+
+        log::info!("Refreshing access token for {}", self.mint_url);
+        let config: OidcConfig =
+            get_json(&self.settings.openid_discovery, &[], self.tor_enabled).await?;
+        let (status, body) = post_form(
+            &config.token_endpoint,
+            &[
+                ("grant_type", "refresh_token"),
+                ("refresh_token", refresh_token.as_str()),
+                ("client_id", self.settings.client_id.as_str()),
+            ],
+            self.tor_enabled,
+        )
+        .await?;
+        if !status.is_success() {
+            return Err(anyhow!(
+                "Could not refresh login to {}, remove and add the mint again to log in: {}",
+                self.mint_url,
+                token_error(&body)
+            ));
+        }
+
+        let response: TokenResponse = serde_json::from_slice(&body)?;
+        let mut tokens = AuthTokens::from(response);
+        // providers may keep using the same refresh token
+        if tokens.refresh_token.is_none() {
+            tokens.refresh_token = Some(refresh_token);
+        }
+        self.storage.update_cashu_mint_auth_tokens(
+            &self.mint_url,
+            tokens.access_token.clone(),
+            tokens.refresh_token,
+            tokens.expires_at,
+        )?;
+
+        Ok(tokens.access_token)
+    }
+
+    async fn blind_token(&self) -> anyhow::Result<String> {
+        if let Some(token) = self.storage.take_cashu_blind_auth_token(&self.mint_url)? {
+            return Ok(token);
+        }
+
+        let _guard = self.lock.lock().await;
+        // another request may have minted some while we waited
+        if let Some(token) = self.storage.take_cashu_blind_auth_token(&self.mint_url)? {
+            return Ok(token);
+        }
+
+        self.mint_blind_tokens().await?;
+        self.storage
+            .take_cashu_blind_auth_token(&self.mint_url)?
+            .ok_or(anyhow!("Mint did not issue any blind auth tokens"))
+    }
+
+    /// Puts back a blind auth token taken for a request that didn't reach the
+    /// mint. If the mint did see it, it rejects the token as spent next time
+    /// and it's dropped then.
+    pub fn return_blind_token(&self, token: String) {
+        if let Err(e) = self
+            .storage
+            .add_cashu_blind_auth_tokens(&self.mint_url, vec![token])
+        {
+            log::error!("Could not keep blind auth token for {}: {e}", self.mint_url);
+        }
+    }
+
+    /// Mints a batch of blind auth tokens, the mint endpoint is protected by
+    /// clear auth. Callers must hold the lock.
+    async fn mint_blind_tokens(&self) -> anyhow::Result<()> {
+        log::info!("Minting blind auth tokens from {}", self.mint_url);
+
+        let url = self
+            .mint_url
+            .join_paths(&["v1", "auth", "blind", "keysets"])?;
+        let keysets: KeysetResponse = get_json(url.as_str(), &[], self.tor_enabled).await?;
+        let keyset = keysets
+            .keysets
+            .into_iter()
+            .find(|k| k.active && k.unit.to_string() == "auth")
+            .ok_or(anyhow!("Mint has no active blind auth keyset"))?;
+
+        let url =
+            self.mint_url
+                .join_paths(&["v1", "auth", "blind", "keys", &keyset.id.to_string()])?;
+        let keys: KeysResponse = get_json(url.as_str(), &[], self.tor_enabled).await?;
+        let key = keys
+            .keysets
+            .into_iter()
+            .find(|k| k.id == keyset.id)
+            .and_then(|k| k.keys.amount_key(cdk::Amount::ONE))
+            .ok_or(anyhow!("Mint has no blind auth key"))?;
+
+        let count = self.settings.bat_max_mint.clamp(1, MAX_BLIND_TOKENS);
+        let mut secrets = Vec::with_capacity(count as usize);
+        let mut outputs = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let secret = Secret::generate();
+            let (blinded, r) = blind_message(secret.as_bytes(), None)?;
+            outputs.push(BlindedMessage::new(cdk::Amount::ONE, keyset.id, blinded));
+            secrets.push((secret, r));
+        }
+
+        let access_token = self.access_token().await?;
+        let url = self.mint_url.join_paths(&["v1", "auth", "blind", "mint"])?;
+        let (status, body) = make_raw_request(
+            url.as_str(),
+            Some(RequestBody::Json(serde_json::to_string(
+                &serde_json::json!({ "outputs": outputs }),
+            )?)),
+            &[("Clear-auth", access_token)],
+            self.tor_enabled,
+            Arc::new(AtomicBool::new(false)),
+        )
+        .await?;
+        if !status.is_success() {
+            return Err(anyhow!(
+                "Mint refused to issue blind auth tokens: {}",
+                String::from_utf8_lossy(&body)
+            ));
+        }
+        let response: MintResponse = serde_json::from_slice(&body)?;
+
+        let mut tokens = Vec::with_capacity(response.signatures.len());
+        for ((secret, r), signature) in secrets.into_iter().zip(response.signatures) {
+            let c = unblind_message(&signature.c, &r, &key)?;
+            let proof = serde_json::json!({
+                "id": keyset.id,
+                "secret": secret,
+                "C": c,
+            });
+            tokens.push(format!(
+                "authA{}",
+                URL_SAFE.encode(serde_json::to_vec(&proof)?)
+            ));
+        }
+
+        log::info!(
+            "Minted {} blind auth tokens from {}",
+            tokens.len(),
+            self.mint_url
+        );
+        self.storage
+            .add_cashu_blind_auth_tokens(&self.mint_url, tokens)
+    }
+}
+
+async fn get_json<T: DeserializeOwned>(
+    url: &str,
+    headers: &[(&str, String)],
+    tor_enabled: bool,
+) -> anyhow::Result<T> {
+    let (status, body) = make_raw_request(
+        url,
+        None,
+        headers,
+        tor_enabled,
+        Arc::new(AtomicBool::new(false)),
+    )
+    .await?;
+    if !status.is_success() {
+        return Err(anyhow!("HTTP request failed with status: {status}"));
+    }
+    Ok(serde_json::from_slice(&body)?)
+}
+
+async fn post_form(
+    url: &str,
+    params: &[(&str, &str)],
+    tor_enabled: bool,
+) -> anyhow::Result<(hyper::StatusCode, hyper::body::Bytes)> {
+    let body = url::form_urlencoded::Serializer::new(String::new())
+        .extend_pairs(params)
+        .finish();
+    make_raw_request(
+        url,
+        Some(RequestBody::Form(body)),
+        &[],
+        tor_enabled,
+        Arc::new(AtomicBool::new(false)),
+    )
+    .await
+}
+
+fn token_error(body: &[u8]) -> anyhow::Error {
+    match serde_json::from_slice::<TokenErrorResponse>(body) {
+        Ok(e) => match e.error_description {
+            Some(description) => anyhow!("Login failed: {} ({description})", e.error),
+            None => anyhow!("Login failed: {}", e.error),
+        },
+        Err(_) => anyhow!("Login failed: {}", String::from_utf8_lossy(body)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn endpoint(method: &str, path: &str) -> ProtectedEndpoint {
+        ProtectedEndpoint {
+            method: method.to_string(),
+            path: path.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_protected_endpoint_matches() {
+        let exact = endpoint("POST", "/v1/swap");
+        assert!(exact.matches("POST", "/v1/swap"));
+        assert!(exact.matches("post", "/v1/swap"));
+        assert!(!exact.matches("GET", "/v1/swap"));
+        assert!(!exact.matches("POST", "/v1/swap/extra"));
+        assert!(!exact.matches("POST", "/v1/swa"));
+
+        let prefix = endpoint("GET", "/v1/mint/quote/bolt11/*");
+        assert!(prefix.matches("GET", "/v1/mint/quote/bolt11/abc"));
+        assert!(prefix.matches("GET", "/v1/mint/quote/bolt11/"));
+        assert!(!prefix.matches("GET", "/v1/mint/quote/bolt12/abc"));
+        assert!(!prefix.matches("POST", "/v1/mint/quote/bolt11/abc"));
+
+        let regex = endpoint("POST", "^/v1/mint/.*$");
+        assert!(regex.matches("POST", "/v1/mint/bolt11"));
+        assert!(regex.matches("POST", "/v1/mint/quote/bolt11"));
+        assert!(!regex.matches("POST", "/v1/melt/bolt11"));
+
+        let anchored = endpoint("POST", "^/v1/melt/bolt11$");
+        assert!(anchored.matches("POST", "/v1/melt/bolt11"));
+        assert!(!anchored.matches("POST", "/v1/melt/bolt11/extra"));
+    }
+}
@@ -1,53 +1,111 @@
+use crate::cashu_auth::CashuAuth;
 use crate::db::DBConnection;
+use crate::db_models::{CashuMintAuth, DleqAudit};
 use crate::fedimint_client::update_history;
-use crate::http::{make_get_request_tor, make_tor_request};
+use crate::http::{RequestBody, make_raw_request};
+use crate::websocket::WebSocket;
 use crate::{
     CoreUIMsg, CoreUIMsgPacket, HarborCore, MintIdentifier, ReceiveSuccessMsg, SendSuccessMsg,
 };
+use anyhow::anyhow;
 use async_trait::async_trait;
 use bitcoin::hex::FromHex;
 use cdk::amount::SplitTarget;
+use cdk::error::ErrorResponse;
 use cdk::mint_url::MintUrl;
 use cdk::nuts::{
-    CheckStateRequest, CheckStateResponse, Id, KeySet, KeysResponse, KeysetResponse,
-    MeltQuoteBolt11Request, MeltQuoteBolt11Response, MeltRequest, MintInfo, MintQuoteBolt11Request,
-    MintQuoteBolt11Response, MintQuoteState, MintRequest, MintResponse, RestoreRequest,
-    RestoreResponse, SwapRequest, SwapResponse,
+    BlindSignature, BlindedMessage, CheckStateRequest, CheckStateResponse, CurrencyUnit, Id,
+    KeySet, KeySetInfo, Keys, KeysResponse, KeysetResponse, MeltOptions, MeltQuoteBolt11Request,
+    MeltQuoteBolt11Response, MeltQuoteState, MeltRequest, MintInfo, MintQuoteBolt11Request,
+    MintQuoteBolt11Response, MintQuoteState, MintRequest, MintResponse, PaymentMethod, Proofs,
+    ProofsMethods, PublicKey, RestoreRequest, RestoreResponse, SecretKey, State, SwapRequest,
+    SwapResponse,
 };
+use cdk::types::ProofInfo;
 use cdk::util::unix_time;
 use cdk::wallet::{MeltQuote, MintConnector, MintQuote};
 use cdk::{Error, Wallet};
 use fedimint_core::Amount;
 use futures::channel::mpsc::Sender;
+use futures::future::join_all;
 use log::error;
-use serde::Serialize;
 use serde::de::DeserializeOwned;
-use std::sync::Arc;
-use std::sync::atomic::AtomicBool;
-use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, LazyLock};
+use std::time::{Duration, Instant};
 use tokio::spawn;
+use tokio::sync::{Mutex, OnceCell, OwnedMutexGuard, RwLock};
 use url::Url;
 use uuid::Uuid;
 
+/// Most times we send a request to an endpoint the mint caches responses for
+const CACHED_REQUEST_MAX_ATTEMPTS: u32 = 5;
+
+/// Wait before the first retry of a cached request, doubled after every attempt
+const CACHED_REQUEST_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// The endpoints a mint caches responses for (NUT-19)
+#[derive(Debug, Clone, Default, Deserialize)]
+struct CacheSettings {
+    /// Seconds the mint keeps a response, forever if not set
+    ttl: Option<u64>,
+    #[serde(default)]
+    cached_endpoints: Vec<CachedEndpoint>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct CachedEndpoint {
+    method: String,
+    path: String,
+}
+
+#[derive(Deserialize)]
+struct CacheInfo {
+    #[serde(default)]
+    nuts: CacheNuts,
+}
+
+#[derive(Deserialize, Default)]
+struct CacheNuts {
+    #[serde(rename = "19", default)]
+    nut19: CacheSettings,
+}
+
 #[derive(Debug, Clone)]
 pub struct TorMintConnector {
     mint_url: MintUrl,
     cancel_handle: Arc<AtomicBool>,
+    /// Auth tokens for mints that require them (NUT-21/22)
+    auth: Option<Arc<CashuAuth>>,
+    /// Goes over plain https when false
+    use_tor: bool,
+    /// Fetched from the mint info the first time we post to a cacheable endpoint
+    cache: Arc<OnceCell<CacheSettings>>,
 }
 
 impl TorMintConnector {
-    pub fn new(mint_url: MintUrl, cancel_handle: Arc<AtomicBool>) -> Self {
+    /// Attaches the auth headers the mint requires to protected endpoints, if any
+    pub fn new(
+        mint_url: MintUrl,
+        cancel_handle: Arc<AtomicBool>,
+        auth: Option<Arc<CashuAuth>>,
+        use_tor: bool,
+    ) -> Self {
         Self {
             mint_url,
             cancel_handle,
+            auth,
+            use_tor,
+            cache: Arc::new(OnceCell::new()),
         }
     }
 
     async fn http_get<R: DeserializeOwned + Send + 'static>(&self, url: Url) -> Result<R, Error> {
-        let res: R = make_get_request_tor(url.as_str(), self.cancel_handle.clone())
+        self.send_raw(&url, None)
             .await
-            .map_err(|e| Error::Custom(e.to_string()))?;
-        Ok(res)
+            .map_err(|e| Error::Custom(e.to_string()))?
     }
 
     #[inline]
@@ -56,10 +114,188 @@ impl TorMintConnector {
         url: Url,
         payload: P,
     ) -> Result<R, Error> {
-        let res: R = make_tor_request(url.as_str(), Some(payload), self.cancel_handle.clone())
+        let body = serde_json::to_string(&payload).map_err(|e| Error::Custom(e.to_string()))?;
+        self.send_raw(&url, Some(body))
+            .await
+            .map_err(|e| Error::Custom(e.to_string()))?
+    }
+
+    /// Posts to an endpoint that spends inputs. If the mint caches its responses
+    /// (NUT-19) a request lost in transit is replayed with backoff, the mint
+    /// answers a replay with the original response instead of failing on inputs
+    /// it already spent.
+    ///
+    /// Like [`Self::send_raw`] the outer error means we never got the mint's
+    /// answer, so it may or may not have spent the inputs.
+    async fn post_spending<P: Serialize, R: DeserializeOwned + Send + 'static>(
+        &self,
+        paths: &[&str],
+        payload: P,
+    ) -> anyhow::Result<Result<R, Error>> {
+        let url = match self.mint_url.join_paths(paths) {
+            Ok(url) => url,
+            Err(e) => return Ok(Err(e.into())),
+        };
+        let body = match serde_json::to_string(&payload) {
+            Ok(body) => body,
+            Err(e) => return Ok(Err(Error::Custom(e.to_string()))),
+        };
+        let path = match self.endpoint_path(&url) {
+            Ok(path) => path,
+            Err(e) => return Ok(Err(e)),
+        };
+        let Some(ttl) = self.cache_ttl(&path).await else {
+            return self.send_raw(&url, Some(body)).await;
+        };
+
+        let start = Instant::now();
+        let mut delay = CACHED_REQUEST_RETRY_DELAY;
+        let mut attempt = 1;
+        loop {
+            match self.send_raw(&url, Some(body.clone())).await {
+                Ok(res) => return Ok(res),
+                Err(e) => {
+                    let expired = start.elapsed() + delay >= ttl;
+                    if attempt >= CACHED_REQUEST_MAX_ATTEMPTS
+                        || expired
+                        || self.cancel_handle.load(Ordering::Relaxed)
+                    {
+                        return Err(anyhow!(
+                            "Request to {path} failed after {attempt} attempts: {e}"
+                        ));
+                    }
+
+                    log::warn!(
+                        "Request to {path} at {} failed, retrying in {}s: {e}",
+                        self.mint_url,
+                        delay.as_secs()
+                    );
+                    tokio::time::sleep(delay).await;
+                    delay *= 2;
+                    attempt += 1;
+                }
+            }
+        }
+    }
+
+    /// How long the mint caches responses to POSTs at the path, None if it doesn't
+    async fn cache_ttl(&self, path: &str) -> Option<Duration> {
+        let settings = self
+            .cache
+            .get_or_try_init(|| async {
+                let url = self.mint_url.join_paths(&["v1", "info"])?;
+                Ok::<_, Error>(self.http_get::<CacheInfo>(url).await?.nuts.nut19)
+            })
+            .await;
+        let settings = match settings {
+            Ok(settings) => settings,
+            Err(e) => {
+                log::warn!("Could not get cache settings of {}: {e}", self.mint_url);
+                return None;
+            }
+        };
+
+        settings
+            .cached_endpoints
+            .iter()
+            .any(|e| e.method.eq_ignore_ascii_case("POST") && e.path == path)
+            .then(|| settings.ttl.map_or(Duration::MAX, Duration::from_secs))
+    }
+
+    /// The path of the url relative to the mint url, which may have a path itself
+    fn endpoint_path(&self, url: &Url) -> Result<String, Error> {
+        let base = self.mint_url.join_paths(&[])?;
+        Ok(url
+            .path()
+            .strip_prefix(base.path().trim_end_matches('/'))
+            .unwrap_or(url.path())
+            .to_string())
+    }
+
+    /// Mint Tokens [NUT-04], see [`Self::post_spending`] for the errors
+    async fn post_mint_raw(
+        &self,
+        request: MintRequest<String>,
+    ) -> anyhow::Result<Result<MintResponse, Error>> {
+        self.post_spending(&["v1", "mint", "bolt11"], &request)
             .await
-            .map_err(|e| Error::Custom(e.to_string()))?;
-        Ok(res)
+    }
+
+    /// Melt [NUT-05], see [`Self::post_spending`] for the errors
+    async fn post_melt_raw(
+        &self,
+        request: MeltRequest<String>,
+    ) -> anyhow::Result<Result<MeltQuoteBolt11Response<String>, Error>> {
+        self.post_spending(&["v1", "melt", "bolt11"], &request)
+            .await
+    }
+
+    /// Swap Token [NUT-03], see [`Self::post_spending`] for the errors
+    async fn post_swap_raw(
+        &self,
+        request: SwapRequest,
+    ) -> anyhow::Result<Result<SwapResponse, Error>> {
+        self.post_spending(&["v1", "swap"], &request).await
+    }
+
+    /// Sends the request with any auth header it needs. The outer error means the
+    /// request or its response got lost on the way and is safe to retry, the
+    /// inner one is the mint's answer.
+    async fn send_raw<R: DeserializeOwned + Send + 'static>(
+        &self,
+        url: &Url,
+        body: Option<String>,
+    ) -> anyhow::Result<Result<R, Error>> {
+        let mut headers: Vec<(&str, String)> = vec![];
+        if let Some(auth) = &self.auth {
+            let method = if body.is_some() { "POST" } else { "GET" };
+            let path = match self.endpoint_path(url) {
+                Ok(path) => path,
+                Err(e) => return Ok(Err(e)),
+            };
+            match auth.header(method, &path).await {
+                Ok(header) => headers.extend(header),
+                Err(e) => return Ok(Err(Error::Custom(e.to_string()))),
+            }
+        }
+
+        let response = make_raw_request(
+            url.as_str(),
+            body.map(RequestBody::Json),
+            &headers,
+            self.use_tor,
+            self.cancel_handle.clone(),
+        )
+        .await;
+
+        // proxies in front of the mint answer these when it is unreachable
+        let unreachable = match &response {
+            Ok((status, _)) => matches!(status.as_u16(), 502..=504),
+            Err(_) => true,
+        };
+        if unreachable {
+            // the mint most likely never saw a blind auth token we sent, keep it
+            if let (Some(auth), Some((_, token))) = (
+                &self.auth,
+                headers.into_iter().find(|(name, _)| *name == "Blind-auth"),
+            ) {
+                auth.return_blind_token(token);
+            }
+        }
+        let (status, body) = response?;
+        if unreachable {
+            return Err(anyhow!("Mint unavailable: {status}"));
+        }
+
+        if !status.is_success() {
+            let body = String::from_utf8_lossy(&body);
+            return Ok(Err(match ErrorResponse::from_json(&body) {
+                Ok(e) => e.into(),
+                Err(_) => Error::Custom(format!("HTTP request failed with status: {status}")),
+            }));
+        }
+
+        Ok(serde_json::from_slice(&body).map_err(|e| Error::Custom(e.to_string())))
     }
 }
 
@@ -115,8 +351,9 @@ impl MintConnector for TorMintConnector {
 
     /// Mint Tokens [NUT-04]
     async fn post_mint(&self, request: MintRequest<String>) -> Result<MintResponse, Error> {
-        let url = self.mint_url.join_paths(&["v1", "mint", "bolt11"])?;
-        self.http_post(url, &request).await
+        self.post_mint_raw(request)
+            .await
+            .map_err(|e| Error::Custom(e.to_string()))?
     }
 
     /// Melt Quote [NUT-05]
@@ -148,14 +385,16 @@ impl MintConnector for TorMintConnector {
         &self,
         request: MeltRequest<String>,
     ) -> Result<MeltQuoteBolt11Response<String>, Error> {
-        let url = self.mint_url.join_paths(&["v1", "melt", "bolt11"])?;
-        self.http_post(url, &request).await
+        self.post_melt_raw(request)
+            .await
+            .map_err(|e| Error::Custom(e.to_string()))?
     }
 
     /// Swap Token [NUT-03]
     async fn post_swap(&self, swap_request: SwapRequest) -> Result<SwapResponse, Error> {
-        let url = self.mint_url.join_paths(&["v1", "swap"])?;
-        self.http_post(url, &swap_request).await
+        self.post_swap_raw(swap_request)
+            .await
+            .map_err(|e| Error::Custom(e.to_string()))?
     }
 
     /// Get Mint Info [NUT-06]
@@ -180,6 +419,264 @@ impl MintConnector for TorMintConnector {
     }
 }
 
+/// Checks the DLEQ proof (NUT-12) on every signature a mint hands us, so we
+/// notice if it signs our ecash with keys other than the ones it publishes.
+///
+/// Signatures are never rejected since the mint has already taken our inputs,
+/// missing and invalid proofs are counted against the mint instead, as are
+/// signatures we can't check because the mint won't give us its keys.
+#[derive(Clone)]
+pub struct DleqVerifyingConnector {
+    inner: TorMintConnector,
+    mint_url: MintUrl,
+    storage: Arc<dyn DBConnection + Send + Sync>,
+    keys: Arc<RwLock<HashMap<Id, Keys>>>,
+}
+
+impl std::fmt::Debug for DleqVerifyingConnector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DleqVerifyingConnector")
+            .field("mint_url", &self.mint_url)
+            .finish_non_exhaustive()
+    }
+}
+
+impl DleqVerifyingConnector {
+    /// Talks to the mint over Tor when enabled, otherwise over plain https.
+    /// Sends the auth tokens the mint requires if we have logged in to it.
+    pub fn new(
+        mint_url: MintUrl,
+        tor_enabled: bool,
+        storage: Arc<dyn DBConnection + Send + Sync>,
+    ) -> Self {
+        let settings = match storage
+            .get_cashu_mint_auth(&mint_url)
+            .and_then(|auth| auth.as_ref().map(CashuMintAuth::settings).transpose())
+        {
+            Ok(settings) => settings,
+            Err(e) => {
+                error!("Could not get auth for {mint_url}: {e}");
+                None
+            }
+        };
+
+        let auth = settings.map(|settings| {
+            Arc::new(CashuAuth::new(
+                mint_url.clone(),
+                settings,
+                storage.clone(),
+                tor_enabled,
+            ))
+        });
+        let inner = TorMintConnector::new(
+            mint_url.clone(),
+            Arc::new(AtomicBool::new(false)),
+            auth,
+            tor_enabled,
+        );
+
+        Self {
+            inner,
+            mint_url,
+            storage,
+            keys: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    async fn amount_key(&self, id: Id, amount: cdk::Amount) -> anyhow::Result<PublicKey> {
+        if let Some(keys) = self.keys.read().await.get(&id) {
+            return keys
+                .amount_key(amount)
+                .ok_or(anyhow!("Keyset {id} has no key for {amount}"));
+        }
+
+        let keyset = self.inner.get_mint_keyset(id).await?;
+        // a mint could hand us other keys under this id, they have to hash to it
+        if keyset.id != id || Id::from(&keyset.keys) != id {
+            return Err(anyhow!("Mint returned keys that don't match keyset {id}"));
+        }
+        let key = keyset
+            .keys
+            .amount_key(amount)
+            .ok_or(anyhow!("Keyset {id} has no key for {amount}"))?;
+        self.keys.write().await.insert(id, keyset.keys);
+        Ok(key)
+    }
+
+    /// We never got the mint's answer to a request that spends proofs, so it may
+    /// still have processed it and we check our proofs against it later.
+    /// A mint refusing the request is an answer and doesn't need this.
+    fn request_lost(&self, e: &anyhow::Error) -> Error {
+        if let Err(e) = self
+            .storage
+            .set_cashu_mint_needs_reconcile(&self.mint_url, true)
+        {
+            error!("Could not flag {} for reconciliation: {e}", self.mint_url);
+        }
+        Error::Custom(e.to_string())
+    }
+
+    async fn verify_signatures(&self, outputs: &[BlindedMessage], signatures: &[BlindSignature]) {
+        let mut audit = DleqAudit::default();
+        for (output, signature) in outputs.iter().zip(signatures) {
+            let key = if signature.dleq.is_some() {
+                match self.amount_key(signature.keyset_id, signature.amount).await {
+                    Ok(key) => Some(key),
+                    Err(e) => {
+                        log::warn!(
+                            "Could not get keys to verify DLEQ from {}: {e}",
+                            self.mint_url
+                        );
+                        None
+                    }
+                }
+            } else {
+                None
+            };
+            audit_dleq(&mut audit, output, signature, key);
+        }
+
+        if audit.has_failures() {
+            log::warn!(
+                "Mint {} returned {} signatures without, {} with invalid and {} with unverifiable DLEQ proofs",
+                self.mint_url,
+                audit.missing,
+                audit.invalid,
+                audit.unverified
+            );
+            if let Err(e) = self
+                .storage
+                .record_cashu_dleq_failures(&self.mint_url, audit)
+            {
+                error!("Could not record DLEQ failures: {e}");
+            }
+        }
+    }
+}
+
+/// Counts the signature against the mint unless its DLEQ proof shows it was made
+/// with `key`, the mint's published key for its amount. None if we couldn't get it.
+fn audit_dleq(
+    audit: &mut DleqAudit,
+    output: &BlindedMessage,
+    signature: &BlindSignature,
+    key: Option<PublicKey>,
+) {
+    if signature.dleq.is_none() {
+        audit.missing += 1;
+        return;
+    }
+
+    let Some(key) = key else {
+        audit.unverified += 1;
+        return;
+    };
+
+    if let Err(e) = signature.verify_dleq(key, output.blinded_secret) {
+        error!("Invalid DLEQ proof: {e}");
+        audit.invalid += 1;
+    }
+}
+
+#[async_trait]
+impl MintConnector for DleqVerifyingConnector {
+    async fn get_mint_keys(&self) -> Result<Vec<KeySet>, Error> {
+        self.inner.get_mint_keys().await
+    }
+
+    async fn get_mint_keyset(&self, keyset_id: Id) -> Result<KeySet, Error> {
+        self.inner.get_mint_keyset(keyset_id).await
+    }
+
+    async fn get_mint_keysets(&self) -> Result<KeysetResponse, Error> {
+        self.inner.get_mint_keysets().await
+    }
+
+    async fn post_mint_quote(
+        &self,
+        request: MintQuoteBolt11Request,
+    ) -> Result<MintQuoteBolt11Response<String>, Error> {
+        self.inner.post_mint_quote(request).await
+    }
+
+    async fn get_mint_quote_status(
+        &self,
+        quote_id: &str,
+    ) -> Result<MintQuoteBolt11Response<String>, Error> {
+        self.inner.get_mint_quote_status(quote_id).await
+    }
+
+    async fn post_mint(&self, request: MintRequest<String>) -> Result<MintResponse, Error> {
+        let outputs = request.outputs.clone();
+        let response = self
+            .inner
+            .post_mint_raw(request)
+            .await
+            .map_err(|e| self.request_lost(&e))??;
+        self.verify_signatures(&outputs, &response.signatures).await;
+        Ok(response)
+    }
+
+    async fn post_melt_quote(
+        &self,
+        request: MeltQuoteBolt11Request,
+    ) -> Result<MeltQuoteBolt11Response<String>, Error> {
+        self.inner.post_melt_quote(request).await
+    }
+
+    async fn get_melt_quote_status(
+        &self,
+        quote_id: &str,
+    ) -> Result<MeltQuoteBolt11Response<String>, Error> {
+        self.inner.get_melt_quote_status(quote_id).await
+    }
+
+    async fn post_melt(
+        &self,
+        request: MeltRequest<String>,
+    ) -> Result<MeltQuoteBolt11Response<String>, Error> {
+        let outputs = request.outputs().clone().unwrap_or_default();
+        let response = self
+            .inner
+            .post_melt_raw(request)
+            .await
+            .map_err(|e| self.request_lost(&e))??;
+        if let Some(change) = &response.change {
+            self.verify_signatures(&outputs, change).await;
+        }
+        Ok(response)
+    }
+
+    async fn post_swap(&self, swap_request: SwapRequest) -> Result<SwapResponse, Error> {
+        let outputs = swap_request.outputs().clone();
+        let response = self
+            .inner
+            .post_swap_raw(swap_request)
+            .await
+            .map_err(|e| self.request_lost(&e))??;
+        self.verify_signatures(&outputs, &response.signatures).await;
+        Ok(response)
+    }
+
+    async fn get_mint_info(&self) -> Result<MintInfo, Error> {
+        self.inner.get_mint_info().await
+    }
+
+    async fn post_check_state(
+        &self,
+        request: CheckStateRequest,
+    ) -> Result<CheckStateResponse, Error> {
+        self.inner.post_check_state(request).await
+    }
+
+    async fn post_restore(&self, request: RestoreRequest) -> Result<RestoreResponse, Error> {
+        let response = self.inner.post_restore(request).await?;
+        self.verify_signatures(&response.outputs, &response.signatures)
+            .await;
+        Ok(response)
+    }
+}
+
 pub fn spawn_lightning_payment_thread(
     mut sender: Sender<CoreUIMsgPacket>,
     client: Wallet,
@@ -187,17 +684,21 @@ pub fn spawn_lightning_payment_thread(
     quote: MeltQuote,
     msg_id: Uuid,
     is_transfer: bool,
+    tor_enabled: bool,
 ) {
     spawn(async move {
-        match client.melt(&quote.id).await {
-            Ok(outgoing) => {
-                log::info!(
-                    "Payment completed: {}, preimage: {:?}",
-                    quote.id,
-                    outgoing.preimage
-                );
-                let preimage: [u8; 32] = FromHex::from_hex(&outgoing.preimage.unwrap())
-                    .expect("preimage must be valid hex");
+        let result =
+            melt_and_wait(&client, &quote, tor_enabled)
+                .await
+                .and_then(|(preimage, fee_paid)| {
+                    let preimage: [u8; 32] = FromHex::from_hex(
+                        &preimage.ok_or(anyhow!("Mint did not return a preimage"))?,
+                    )?;
+                    Ok((preimage, fee_paid))
+                });
+        match result {
+            Ok((preimage, fee_paid)) => {
+                log::info!("Payment completed: {}", quote.id);
                 let params = if is_transfer {
                     SendSuccessMsg::Transfer
                 } else {
@@ -215,13 +716,13 @@ pub fn spawn_lightning_payment_thread(
                     &mut sender,
                     Some(msg_id),
                     CoreUIMsg::MintBalanceUpdated {
-                        id: MintIdentifier::Cashu(client.mint_url.clone()),
+                        id: MintIdentifier::Cashu(client.mint_url.clone(), client.unit.clone()),
                         balance: Amount::from_sats(bal),
                     },
                 )
                 .await;
 
-                let fee = Amount::from_sats(outgoing.fee_paid.into());
+                let fee = Amount::from_sats(fee_paid);
 
                 if let Err(e) =
                     storage.set_lightning_as_complete(quote.id, preimage, Some(fee.msats))
@@ -248,6 +749,80 @@ pub fn spawn_lightning_payment_thread(
     });
 }
 
+/// Melts every part of a NUT-15 multi-path payment at once, the receiver only
+/// settles once all parts have arrived
+pub fn spawn_multi_path_payment_thread(
+    mut sender: Sender<CoreUIMsgPacket>,
+    parts: Vec<(Wallet, MeltQuote)>,
+    storage: Arc<dyn DBConnection + Send + Sync>,
+    msg_id: Uuid,
+    tor_enabled: bool,
+) {
+    spawn(async move {
+        let results = join_all(
+            parts
+                .iter()
+                .map(|(client, quote)| melt_and_wait(client, quote, tor_enabled)),
+        )
+        .await;
+
+        let mut preimage = None;
+        let mut failure = None;
+        for ((client, quote), result) in parts.iter().zip(results) {
+            let result = result.and_then(|(part_preimage, fee_paid)| {
+                let part_preimage: [u8; 32] = FromHex::from_hex(
+                    &part_preimage.ok_or(anyhow!("Mint did not return a preimage"))?,
+                )?;
+                Ok((part_preimage, fee_paid))
+            });
+            match result {
+                Ok((part_preimage, fee_paid)) => {
+                    log::info!("Payment part completed: {}", quote.id);
+                    let fee = Amount::from_sats(fee_paid);
+                    if let Err(e) = storage.set_lightning_as_complete(
+                        quote.id.clone(),
+                        part_preimage,
+                        Some(fee.msats),
+                    ) {
+                        error!("Could not set preimage for lightning payment: {e}");
+                    }
+                    preimage = Some(part_preimage);
+                }
+                Err(e) => {
+                    log::error!("Payment part failed: {e}");
+                    if let Err(e) = storage.mark_lightning_payment_as_failed(quote.id.clone()) {
+                        error!("Could not mark lightning payment as failed: {e}");
+                    }
+                    failure = Some(e.to_string());
+                }
+            }
+
+            if let Ok(bal) = client.total_balance().await {
+                HarborCore::send_msg(
+                    &mut sender,
+                    Some(msg_id),
+                    CoreUIMsg::MintBalanceUpdated {
+                        id: MintIdentifier::Cashu(client.mint_url.clone(), client.unit.clone()),
+                        balance: Amount::from_sats(bal.into()),
+                    },
+                )
+                .await;
+            }
+        }
+
+        let msg = match (failure, preimage) {
+            (None, Some(preimage)) => {
+                CoreUIMsg::SendSuccess(SendSuccessMsg::Lightning { preimage })
+            }
+            (Some(e), _) => CoreUIMsg::SendFailure(e),
+            (None, None) => CoreUIMsg::SendFailure("No payment parts were sent".to_string()),
+        };
+        HarborCore::send_msg(&mut sender, Some(msg_id), msg).await;
+
+        update_history(storage, msg_id, &mut sender).await;
+    });
+}
+
 pub fn spawn_lightning_receive_thread(
     mut sender: Sender<CoreUIMsgPacket>,
     client: Wallet,
@@ -255,24 +830,23 @@ pub fn spawn_lightning_receive_thread(
     quote: MintQuote,
     msg_id: Uuid,
     is_transfer: bool,
+    tor_enabled: bool,
 ) {
     spawn(async move {
-        let mut error_counter = 0;
-        loop {
-            let mint_quote_response = match client.mint_quote_state(&quote.id).await {
-                Ok(response) => response,
-                Err(e) => {
-                    error!("Error getting mint quote state: {e}");
-                    tokio::time::sleep(Duration::from_secs(1)).await;
-                    error_counter += 1;
-                    if error_counter > 5 {
-                        return;
-                    }
-                    continue;
-                }
-            };
+        let paid = watch_quote(
+            &client,
+            QuoteKind::Mint,
+            &quote.id,
+            // 0 means the mint didn't set an expiry
+            (quote.expiry != 0).then_some(quote.expiry),
+            tor_enabled,
+            |state: &MintQuoteState| *state == MintQuoteState::Paid,
+            || async { Ok::<_, Error>(client.mint_quote_state(&quote.id).await?.state) },
+        )
+        .await;
 
-            if mint_quote_response.state == MintQuoteState::Paid {
+        match paid {
+            Ok(Some(_)) => {
                 client
                     .mint(&quote.id, SplitTarget::default(), None)
                     .await
@@ -295,16 +869,15 @@ pub fn spawn_lightning_receive_thread(
                     &mut sender,
                     Some(msg_id),
                     CoreUIMsg::MintBalanceUpdated {
-                        id: MintIdentifier::Cashu(client.mint_url.clone()),
+                        id: MintIdentifier::Cashu(client.mint_url.clone(), client.unit.clone()),
                         balance: Amount::from_sats(new_balance.into()),
                     },
                 )
                 .await;
 
                 update_history(storage, msg_id, &mut sender).await;
-
-                break;
-            } else if quote.expiry.le(&unix_time()) {
+            }
+            Ok(None) => {
                 client
                     .localstore
                     .remove_mint_quote(&quote.id)
@@ -314,10 +887,822 @@ pub fn spawn_lightning_receive_thread(
                 if let Err(e) = storage.mark_ln_receive_as_failed(quote.id) {
                     error!("Could not mark lightning receive as failed: {e}");
                 }
-
-                break;
             }
-            tokio::time::sleep(Duration::from_secs(1)).await;
+            Err(e) => error!("Error getting mint quote state: {e}"),
         }
     });
 }
+
+/// What paying a melt quote costs, in the wallet's unit.
+/// On top of the lightning fee reserve the mint charges an input fee (NUT-02)
+/// for every proof we spend, which depends on the proofs we select.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MeltCost {
+    pub amount: u64,
+    pub fee_reserve: u64,
+    pub input_fee: u64,
+}
+
+impl MeltCost {
+    pub const fn fee(&self) -> u64 {
+        self.fee_reserve + self.input_fee
+    }
+
+    pub const fn total(&self) -> u64 {
+        self.amount + self.fee()
+    }
+}
+
+/// The melt quote the user confirmed the fees of, as long as it is still
+/// good for paying `invoice`. None if it expired and we have to quote again.
+pub async fn confirmed_melt_quote(
+    client: &Wallet,
+    quote_id: &str,
+    invoice: &str,
+) -> anyhow::Result<Option<MeltQuote>> {
+    let quote = client.localstore.get_melt_quote(quote_id).await?;
+    Ok(quote.filter(|q| {
+        q.request == invoice && q.state == MeltQuoteState::Unpaid && q.expiry > unix_time()
+    }))
+}
+
+/// Selects the proofs to melt the quote with, including enough to cover their own input fees
+pub async fn select_melt_proofs(
+    client: &Wallet,
+    quote: &MeltQuote,
+) -> anyhow::Result<(Proofs, MeltCost)> {
+    let needed = quote.amount + quote.fee_reserve;
+    let keyset_fees = client.get_keyset_fees().await?;
+    let active_keyset_ids = client
+        .get_active_mint_keysets()
+        .await?
+        .into_iter()
+        .map(|k| k.id)
+        .collect::<Vec<_>>();
+    let available = client.get_unspent_proofs().await?;
+    let balance: u64 = available.total_amount()?.into();
+
+    let proofs = Wallet::select_proofs(needed, available, &active_keyset_ids, &keyset_fees, true)
+        .map_err(|_| {
+        anyhow!(
+            "Insufficient balance: {needed} {} plus input fees needed, {balance} available",
+            client.unit
+        )
+    })?;
+    let input_fee = client.get_proofs_fee(&proofs).await?;
+
+    let cost = MeltCost {
+        amount: quote.amount.into(),
+        fee_reserve: quote.fee_reserve.into(),
+        input_fee: input_fee.into(),
+    };
+    Ok((proofs, cost))
+}
+
+/// How many times we shrink a part of a multi-path payment to fit its fees
+const MULTI_PATH_QUOTE_ATTEMPTS: usize = 3;
+
+/// Quotes the largest part of `wanted` msats the wallet can pay as part of a
+/// NUT-15 multi-path payment, once the quote's fee reserve and the input fees
+/// are covered. Returns the quote, the part in msats and what it costs, or None
+/// if the wallet can't pay any part.
+pub async fn quote_multi_path_part(
+    client: &Wallet,
+    invoice: &str,
+    wanted: u64,
+) -> anyhow::Result<Option<(MeltQuote, u64, MeltCost)>> {
+    let balance: u64 = client.total_balance().await?.into();
+    let mut part = wanted.min(balance * 1_000);
+    for _ in 0..MULTI_PATH_QUOTE_ATTEMPTS {
+        if part == 0 {
+            break;
+        }
+        let quote = client
+            .melt_quote(invoice.to_string(), Some(MeltOptions::new_mpp(part)))
+            .await?;
+        if let Ok((_, cost)) = select_melt_proofs(client, &quote).await {
+            return Ok(Some((quote, part, cost)));
+        }
+
+        // spending every proof is the most the input fees can be
+        let proofs = client.get_unspent_proofs().await?;
+        let input_fee: u64 = client.get_proofs_fee(&proofs).await?.into();
+        let fee = u64::from(quote.fee_reserve) + input_fee;
+        let affordable = balance.saturating_sub(fee) * 1_000;
+        part = if affordable < part {
+            affordable
+        } else {
+            part.saturating_sub(fee.max(1) * 1_000)
+        };
+    }
+    Ok(None)
+}
+
+/// Requests a mint quote locked to `secret_key` (NUT-20) so nobody else who learns
+/// the quote id can mint its tokens. The quote is stored with the key so that
+/// minting signs the request. Like cdk, a quote without an expiry is stored with 0.
+pub async fn mint_quote_locked(
+    client: &Wallet,
+    amount: cdk::Amount,
+    secret_key: SecretKey,
+) -> anyhow::Result<MintQuote> {
+    let request = MintQuoteBolt11Request {
+        amount,
+        unit: client.unit.clone(),
+        description: None,
+        pubkey: Some(secret_key.public_key()),
+    };
+    let response = client.client.post_mint_quote(request).await?;
+
+    let quote = MintQuote::new(
+        response.quote,
+        client.mint_url.clone(),
+        PaymentMethod::Bolt11,
+        Some(amount),
+        client.unit.clone(),
+        response.request,
+        response.expiry.unwrap_or_default(),
+        Some(secret_key),
+    );
+    client.localstore.add_mint_quote(quote.clone()).await?;
+
+    Ok(quote)
+}
+
+/// Swaps any proofs signed by keysets the mint has since deactivated into its
+/// active keyset, before the old keyset is retired. Returns the amount moved.
+///
+/// The outer error means we couldn't check the keysets, the inner one that the
+/// swap itself failed.
+pub async fn swap_inactive_keyset_proofs(client: &Wallet) -> anyhow::Result<anyhow::Result<u64>> {
+    let keysets = client.get_mint_keysets().await?;
+
+    let _guard = lock_wallet(client).await;
+    let proofs = client.get_unspent_proofs().await?;
+    let inactive = inactive_keyset_proofs(proofs, &keysets, &client.unit)?;
+    if inactive.is_empty() {
+        return Ok(Ok(0));
+    }
+
+    let amount: u64 = inactive.total_amount()?.into();
+    log::info!(
+        "Swapping {} proofs worth {amount} {} from inactive keysets at {}",
+        inactive.len(),
+        client.unit,
+        client.mint_url
+    );
+    Ok(client
+        .swap(None, SplitTarget::default(), inactive, None, false)
+        .await
+        .map(|_| amount)
+        .map_err(Into::into))
+}
+
+/// The proofs not signed by one of the mint's active keysets for `unit`
+fn inactive_keyset_proofs(
+    proofs: Proofs,
+    keysets: &[KeySetInfo],
+    unit: &CurrencyUnit,
+) -> anyhow::Result<Proofs> {
+    let active_keyset_ids: HashSet<Id> = keysets
+        .iter()
+        .filter(|k| k.active && k.unit == *unit)
+        .map(|k| k.id)
+        .collect();
+    if active_keyset_ids.is_empty() {
+        return Err(anyhow!("Mint has no active {unit} keyset"));
+    }
+
+    Ok(proofs
+        .into_iter()
+        .filter(|p| !active_keyset_ids.contains(&p.keyset_id))
+        .collect())
+}
+
+/// One lock per wallet, keyed by mint and unit
+type WalletLocks = std::sync::Mutex<HashMap<(MintUrl, CurrencyUnit), Arc<Mutex<()>>>>;
+
+static WALLET_LOCKS: LazyLock<WalletLocks> = LazyLock::new(Default::default);
+
+/// Locks the wallet's proofs. Everything that marks proofs pending and spends
+/// them at the mint holds it until the mint answered, so reconciling can't
+/// release proofs that are still on their way.
+pub async fn lock_wallet(client: &Wallet) -> OwnedMutexGuard<()> {
+    let lock = WALLET_LOCKS
+        .lock()
+        .expect("wallet locks poisoned")
+        .entry((client.mint_url.clone(), client.unit.clone()))
+        .or_default()
+        .clone();
+    lock.lock_owned().await
+}
+
+/// Most proofs we check the state of in one request
+const RECONCILE_BATCH_SIZE: usize = 100;
+
+/// What reconciling a wallet with its mint changed, amounts in the wallet's unit
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ProofReconciliation {
+    /// Proofs the mint had already spent, removed from the wallet
+    pub spent: u64,
+    /// Pending proofs the mint never spent, spendable again
+    pub released: u64,
+}
+
+/// Brings a wallet back in line with its mint after a swap, mint or melt failed
+/// without an answer, when we can't tell whether the mint processed it.
+///
+/// Proofs the mint spent are removed and pending ones it didn't are released.
+/// If a request we lost spent pending proofs, or pending proofs were released,
+/// a restore then recovers any outputs the mint signed whose response we lost,
+/// which also moves the keyset counter past them.
+pub async fn reconcile_proofs(client: &Wallet) -> anyhow::Result<ProofReconciliation> {
+    let _guard = lock_wallet(client).await;
+
+    let proofs: Vec<ProofInfo> = client
+        .localstore
+        .get_proofs(
+            Some(client.mint_url.clone()),
+            Some(client.unit.clone()),
+            Some(vec![State::Unspent, State::Pending]),
+            None,
+        )
+        .await?;
+
+    let mut result = ProofReconciliation::default();
+    let mut request_lost = false;
+    for batch in proofs.chunks(RECONCILE_BATCH_SIZE) {
+        let states = client
+            .check_proofs_spent(batch.iter().map(|p| p.proof.clone()).collect())
+            .await?;
+
+        let mut spent = vec![];
+        let mut released = vec![];
+        for (info, state) in batch.iter().zip(states) {
+            let amount: u64 = info.proof.amount.into();
+            match (info.state, state.state) {
+                (local, State::Spent) => {
+                    // the mint processed a request we never got the answer to
+                    request_lost |= local == State::Pending;
+                    result.spent += amount;
+                    spent.push(info.y);
+                }
+                (State::Pending, State::Unspent) => {
+                    result.released += amount;
+                    released.push(info.y);
+                }
+                // still in flight at the mint, e.g. a pending lightning payment
+                _ => {}
+            }
+        }
+
+        if !spent.is_empty() {
+            client.localstore.update_proofs(vec![], spent).await?;
+        }
+        if !released.is_empty() {
+            client
+                .localstore
+                .update_proofs_state(released, State::Unspent)
+                .await?;
+        }
+    }
+
+    if request_lost || result.released > 0 {
+        client.restore().await?;
+    }
+
+    Ok(result)
+}
+
+/// Only worth a swap once a wallet holds this many proofs
+const CONSOLIDATION_MIN_PROOFS: usize = 32;
+
+/// Most proofs we merge in one swap, mints limit the inputs of a single request
+const CONSOLIDATION_MAX_INPUTS: usize = 100;
+
+/// Skip the swap when its input fees would take more than this share, in parts
+/// per thousand, of the amount we merge
+const CONSOLIDATION_MAX_FEE_PPK: u64 = 10;
+
+/// The outcome of merging a wallet's fragmented proofs, amounts in the wallet's unit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProofConsolidation {
+    pub amount: u64,
+    pub fee: u64,
+    pub proofs_before: u64,
+    pub proofs_after: u64,
+}
+
+/// Swaps the smallest proofs of a fragmented wallet into an optimal power of two split.
+/// Returns None when the wallet isn't fragmented enough or the keyset fees make it not worth it.
+pub async fn consolidate_proofs(client: &Wallet) -> anyhow::Result<Option<ProofConsolidation>> {
+    // a send selecting proofs while we swap them would fail at the mint
+    let _guard = lock_wallet(client).await;
+    let mut proofs = client.get_unspent_proofs().await?;
+    let proofs_before = proofs.len();
+    let balance: u64 = proofs.total_amount()?.into();
+    // a power of two split needs one proof per set bit of the balance
+    let optimal = balance.count_ones() as usize;
+    if proofs_before < CONSOLIDATION_MIN_PROOFS || proofs_before <= optimal * 2 {
+        return Ok(None);
+    }
+
+    proofs.sort_by_key(|p| p.amount);
+    proofs.truncate(CONSOLIDATION_MAX_INPUTS);
+    let amount: u64 = proofs.total_amount()?.into();
+    let fee: u64 = client.get_proofs_fee(&proofs).await?.into();
+    if fee * 1_000 > amount * CONSOLIDATION_MAX_FEE_PPK {
+        log::debug!(
+            "Not consolidating {} proofs at {}, input fee {fee} is too high for {amount}",
+            proofs.len(),
+            client.mint_url
+        );
+        return Ok(None);
+    }
+
+    log::info!(
+        "Consolidating {} of {proofs_before} proofs at {} ({})",
+        proofs.len(),
+        client.mint_url,
+        client.unit
+    );
+    client
+        .swap(None, SplitTarget::default(), proofs, None, false)
+        .await?;
+
+    let proofs_after = client.get_unspent_proofs().await?.len();
+    Ok(Some(ProofConsolidation {
+        amount,
+        fee,
+        proofs_before: proofs_before as u64,
+        proofs_after: proofs_after as u64,
+    }))
+}
+
+/// Melts the quote and, if the mint reports the payment as pending,
+/// waits for it to settle. Returns the preimage and the fee paid, including input fees.
+pub async fn melt_and_wait(
+    client: &Wallet,
+    quote: &MeltQuote,
+    tor_enabled: bool,
+) -> anyhow::Result<(Option<String>, u64)> {
+    let guard = lock_wallet(client).await;
+    // select the proofs ourselves so we spend exactly what we estimated
+    let (proofs, cost) = select_melt_proofs(client, quote).await?;
+    log::info!(
+        "Melting {} with fee reserve {} and input fee {}",
+        quote.id,
+        cost.fee_reserve,
+        cost.input_fee
+    );
+
+    let melted = client.melt_proofs(&quote.id, proofs).await?;
+    drop(guard);
+    // the fee covers everything we spent that didn't go to the payment or come back as change
+    let fee_paid: u64 = melted.fee_paid.into();
+    if melted.state != MeltQuoteState::Pending {
+        return Ok((melted.preimage, fee_paid));
+    }
+
+    log::info!("Payment pending: {}, waiting for it to settle", quote.id);
+
+    watch_quote(
+        client,
+        QuoteKind::Melt,
+        &quote.id,
+        None,
+        tor_enabled,
+        |state: &MeltQuoteState| *state != MeltQuoteState::Pending,
+        || async { Ok::<_, Error>(client.melt_quote_status(&quote.id).await?.state) },
+    )
+    .await?;
+
+    // fetching the final status also stores any change the mint returned
+    let response = client.melt_quote_status(&quote.id).await?;
+    if response.state != MeltQuoteState::Paid {
+        return Err(anyhow!("Payment failed: {}", response.state));
+    }
+
+    // while pending the whole fee reserve counts as paid, what wasn't needed comes back as change
+    let change: u64 = response
+        .change
+        .iter()
+        .flatten()
+        .map(|s| u64::from(s.amount))
+        .sum();
+    Ok((response.payment_preimage, fee_paid.saturating_sub(change)))
+}
+
+const POLL_INITIAL_DELAY: Duration = Duration::from_secs(1);
+const POLL_MAX_DELAY: Duration = Duration::from_secs(30);
+const MAX_POLL_ERRORS: u32 = 5;
+
+/// How long the mint's websocket may stay quiet before we check it's still there
+const SUBSCRIPTION_KEEPALIVE: Duration = Duration::from_secs(30);
+/// How long we wait on a subscription for a quote without an expiry before
+/// falling back to polling, in case the mint never pushes the update
+const SUBSCRIPTION_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// The quote types we can subscribe to over NUT-17
+#[derive(Debug, Clone, Copy)]
+enum QuoteKind {
+    Mint,
+    Melt,
+}
+
+impl QuoteKind {
+    const fn command(self) -> &'static str {
+        match self {
+            Self::Mint => "bolt11_mint_quote",
+            Self::Melt => "bolt11_melt_quote",
+        }
+    }
+}
+
+/// NUT-17 settings from the mint info, parsed loosely so unknown commands don't break us
+#[derive(Debug, Default, Deserialize)]
+struct Nut17Settings {
+    #[serde(default)]
+    supported: Vec<Nut17Method>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Nut17Method {
+    method: String,
+    unit: String,
+    #[serde(default)]
+    commands: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct WsRequest<'a> {
+    jsonrpc: &'static str,
+    id: u64,
+    method: &'static str,
+    params: WsParams<'a>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct WsParams<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    kind: Option<&'static str>,
+    sub_id: &'a str,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    filters: Vec<&'a str>,
+}
+
+/// Either a response to one of our requests or a notification for a subscription
+#[derive(Deserialize)]
+struct WsMessage {
+    error: Option<WsError>,
+    params: Option<WsNotification>,
+}
+
+#[derive(Deserialize)]
+struct WsError {
+    code: i64,
+    message: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WsNotification {
+    sub_id: String,
+    payload: serde_json::Value,
+}
+
+#[derive(Deserialize)]
+struct QuoteUpdate<S> {
+    state: S,
+}
+
+/// Whether the mint pushes state updates for this kind of quote in the wallet's unit
+async fn supports_subscription(client: &Wallet, kind: QuoteKind) -> bool {
+    let info = match client.localstore.get_mint(client.mint_url.clone()).await {
+        Ok(Some(info)) => Some(info),
+        _ => client.get_mint_info().await.ok().flatten(),
+    };
+    let Some(info) = info else {
+        return false;
+    };
+
+    let settings: Nut17Settings = serde_json::to_value(&info.nuts.nut17)
+        .and_then(serde_json::from_value)
+        .unwrap_or_default();
+    let unit = client.unit.to_string();
+    settings.supported.iter().any(|m| {
+        m.method == "bolt11" && m.unit == unit && m.commands.iter().any(|c| c == kind.command())
+    })
+}
+
+/// Waits until the quote reaches a state `done` accepts, returning None if
+/// `expiry` passes first. Uses a NUT-17 subscription when the mint supports it,
+/// otherwise (or if the connection drops) polls with backoff.
+async fn watch_quote<S, D, P, F>(
+    client: &Wallet,
+    kind: QuoteKind,
+    quote_id: &str,
+    expiry: Option<u64>,
+    tor_enabled: bool,
+    done: D,
+    poll: P,
+) -> anyhow::Result<Option<S>>
+where
+    S: DeserializeOwned,
+    D: Fn(&S) -> bool,
+    P: Fn() -> F,
+    F: Future<Output = Result<S, Error>>,
+{
+    if supports_subscription(client, kind).await {
+        match subscribe_quote(client, kind, quote_id, expiry, tor_enabled, &done).await {
+            Ok(state) => return Ok(state),
+            Err(e) => log::warn!("Quote subscription for {quote_id} failed, polling instead: {e}"),
+        }
+    }
+
+    let mut delay = POLL_INITIAL_DELAY;
+    let mut error_counter = 0;
+    loop {
+        match poll().await {
+            Ok(state) if done(&state) => return Ok(Some(state)),
+            Ok(_) => error_counter = 0,
+            Err(e) => {
+                error_counter += 1;
+                if error_counter > MAX_POLL_ERRORS {
+                    return Err(e.into());
+                }
+            }
+        }
+
+        if expiry.is_some_and(|expiry| expiry <= unix_time()) {
+            return Ok(None);
+        }
+
+        tokio::time::sleep(delay).await;
+        delay = (delay * 2).min(POLL_MAX_DELAY);
+    }
+}
+
+/// Subscribes to state updates for a quote over the mint's websocket (NUT-17)
+async fn subscribe_quote<S, D>(
+    client: &Wallet,
+    kind: QuoteKind,
+    quote_id: &str,
+    expiry: Option<u64>,
+    tor_enabled: bool,
+    done: &D,
+) -> anyhow::Result<Option<S>>
+where
+    S: DeserializeOwned,
+    D: Fn(&S) -> bool,
+{
+    let mut url = client.mint_url.join_paths(&["v1", "ws"])?;
+    let scheme = if url.scheme() == "http" { "ws" } else { "wss" };
+    url.set_scheme(scheme)
+        .map_err(|()| anyhow!("Could not build websocket url for {url}"))?;
+
+    let mut ws = WebSocket::connect(url.as_str(), tor_enabled, Arc::new(AtomicBool::new(false)))
+        .await?
+        .keepalive(SUBSCRIPTION_KEEPALIVE);
+
+    let sub_id = Uuid::new_v4().to_string();
+    ws.send_json(&WsRequest {
+        jsonrpc: "2.0",
+        id: 0,
+        method: "subscribe",
+        params: WsParams {
+            kind: Some(kind.command()),
+            sub_id: &sub_id,
+            filters: vec![quote_id],
+        },
+    })
+    .await?;
+
+    log::debug!("Subscribed to {} updates for {quote_id}", kind.command());
+
+    let deadline = tokio::time::Instant::now() + SUBSCRIPTION_TIMEOUT;
+    let state = loop {
+        let next = match expiry {
+            Some(expiry) => {
+                let remaining = Duration::from_secs(expiry.saturating_sub(unix_time()));
+                match tokio::time::timeout(remaining, ws.next_text()).await {
+                    Ok(next) => next?,
+                    Err(_) => break None,
+                }
+            }
+            None => {
+                let next = tokio::time::timeout_at(deadline, ws.next_text()).await;
+                let Ok(next) = next else {
+                    ws.close().await;
+                    return Err(anyhow!("No update for {quote_id} over the websocket"));
+                };
+                next?
+            }
+        };
+        let Some(text) = next else {
+            return Err(anyhow!("Mint closed the websocket"));
+        };
+
+        if let Some(state) = parse_quote_update::<S>(&text, &sub_id)? {
+            if done(&state) {
+                break Some(state);
+            }
+        }
+    };
+
+    let unsubscribe = ws
+        .send_json(&WsRequest {
+            jsonrpc: "2.0",
+            id: 1,
+            method: "unsubscribe",
+            params: WsParams {
+                kind: None,
+                sub_id: &sub_id,
+                filters: vec![],
+            },
+        })
+        .await;
+    if let Err(e) = unsubscribe {
+        log::debug!("Could not unsubscribe from {quote_id}: {e}");
+    }
+    ws.close().await;
+
+    Ok(state)
+}
+
+/// Reads a message from the mint's websocket, returning the quote's new state
+/// if it's a notification for our subscription
+fn parse_quote_update<S: DeserializeOwned>(text: &str, sub_id: &str) -> anyhow::Result<Option<S>> {
+    let msg: WsMessage = serde_json::from_str(text)?;
+    if let Some(e) = msg.error {
+        return Err(anyhow!(
+            "Mint rejected subscription ({}): {}",
+            e.code,
+            e.message
+        ));
+    }
+    let Some(notification) = msg.params.filter(|n| n.sub_id == sub_id) else {
+        return Ok(None);
+    };
+
+    let update: QuoteUpdate<S> = serde_json::from_value(notification.payload)?;
+    Ok(Some(update.state))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const ACTIVE: &str = "009a1f293253e41e";
+    const INACTIVE: &str = "00ad268c4d1f5826";
+    const OTHER_UNIT: &str = "00456a94ab4e1c46";
+
+    fn proof(amount: u64, keyset: &str) -> cdk::nuts::Proof {
+        serde_json::from_value(json!({
+            "amount": amount,
+            "id": keyset,
+            "secret": "407915bc212be61a77e3e6d2aeb4c727980bda51cd06a6afc29e2861768a7837",
+            "C": "02bc9097997d81afb2cc7346b5e4345a9346bd2a506eb7958598a72f0cf85163ea",
+        }))
+        .unwrap()
+    }
+
+    fn keyset(id: &str, unit: &str, active: bool) -> KeySetInfo {
+        serde_json::from_value(json!({
+            "id": id,
+            "unit": unit,
+            "active": active,
+            "input_fee_ppk": 0,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_audit_dleq() {
+        let keyset_id: Id = ACTIVE.parse().unwrap();
+        let amount = cdk::Amount::from(8);
+        let mint_key = SecretKey::generate();
+        let (blinded, _) = cdk::dhke::blind_message(b"secret", None).unwrap();
+        let output = BlindedMessage::new(amount, keyset_id, blinded);
+        let c = cdk::dhke::sign_message(&mint_key, &blinded).unwrap();
+        let signature =
+            BlindSignature::new(amount, c, keyset_id, &blinded, mint_key.clone()).unwrap();
+
+        let mut audit = DleqAudit::default();
+        audit_dleq(&mut audit, &output, &signature, Some(mint_key.public_key()));
+        assert!(!audit.has_failures());
+
+        // signed with a key other than the one the mint publishes
+        let other_key = SecretKey::generate();
+        let tampered = BlindSignature {
+            c: cdk::dhke::sign_message(&other_key, &blinded).unwrap(),
+            ..signature.clone()
+        };
+        audit_dleq(&mut audit, &output, &tampered, Some(mint_key.public_key()));
+        assert_eq!(audit.invalid, 1);
+
+        // without the mint's keys there is nothing to check the proof against
+        audit_dleq(&mut audit, &output, &signature, None);
+        let without_proof = BlindSignature {
+            dleq: None,
+            ..signature
+        };
+        audit_dleq(&mut audit, &output, &without_proof, None);
+        assert_eq!(
+            audit,
+            DleqAudit {
+                missing: 1,
+                invalid: 1,
+                unverified: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn test_inactive_keyset_proofs() {
+        let keysets = [
+            keyset(ACTIVE, "sat", true),
+            keyset(INACTIVE, "sat", false),
+            keyset(OTHER_UNIT, "usd", true),
+        ];
+        let proofs = vec![
+            proof(1, ACTIVE),
+            proof(2, INACTIVE),
+            proof(4, OTHER_UNIT),
+            proof(8, ACTIVE),
+        ];
+
+        let inactive =
+            inactive_keyset_proofs(proofs.clone(), &keysets, &CurrencyUnit::Sat).unwrap();
+        let amounts: Vec<u64> = inactive.iter().map(|p| p.amount.into()).collect();
+        // an active keyset of another unit doesn't count for sat proofs
+        assert_eq!(amounts, vec![2, 4]);
+
+        // nothing to swap into
+        let retired = [keyset(ACTIVE, "sat", false)];
+        assert!(inactive_keyset_proofs(proofs, &retired, &CurrencyUnit::Sat).is_err());
+    }
+
+    #[test]
+    fn test_parse_quote_update() {
+        let sub_id = "9d4e0dbc-6d1a-4e8c-b5a4-3c2f6e1e0c7a";
+        let notification = |sub_id: &str, state: &str| {
+            json!({
+                "jsonrpc": "2.0",
+                "method": "subscribe",
+                "params": {
+                    "subId": sub_id,
+                    "payload": {
+                        "quote": "DSGLX9kevM",
+                        "amount": 100,
+                        "fee_reserve": 2,
+                        "state": state,
+                        "expiry": 1_701_704_757,
+                    },
+                },
+            })
+            .to_string()
+        };
+
+        let paid = notification(sub_id, "PAID");
+        let state = parse_quote_update::<MeltQuoteState>(&paid, sub_id).unwrap();
+        assert_eq!(state, Some(MeltQuoteState::Paid));
+
+        let pending = notification(sub_id, "PENDING");
+        let state = parse_quote_update::<MeltQuoteState>(&pending, sub_id).unwrap();
+        assert_eq!(state, Some(MeltQuoteState::Pending));
+
+        // another subscription on the same socket
+        let other = notification("other", "PAID");
+        assert_eq!(
+            parse_quote_update::<MeltQuoteState>(&other, sub_id).unwrap(),
+            None
+        );
+
+        // the mint acknowledging our subscribe request
+        let ack = json!({
+            "jsonrpc": "2.0",
+            "result": { "status": "OK", "subId": sub_id },
+            "id": 0,
+        })
+        .to_string();
+        assert_eq!(
+            parse_quote_update::<MeltQuoteState>(&ack, sub_id).unwrap(),
+            None
+        );
+
+        let error = json!({
+            "jsonrpc": "2.0",
+            "error": { "code": -32601, "message": "Method not found" },
+            "id": 0,
+        })
+        .to_string();
+        assert!(parse_quote_update::<MeltQuoteState>(&error, sub_id).is_err());
+
+        assert!(parse_quote_update::<MeltQuoteState>("not json", sub_id).is_err());
+        let unknown_state = notification(sub_id, "EXPLODED");
+        assert!(parse_quote_update::<MeltQuoteState>(&unknown_state, sub_id).is_err());
+    }
+}
@@ -1,7 +1,9 @@
+use crate::cashu_auth::AuthSettings;
 use crate::db_models::mint_metadata::MintMetadata;
 use crate::db_models::transaction_item::TransactionItem;
 use crate::db_models::{
-    CashuMint, Fedimint, LightningPayment, LightningReceive, NewFedimint, NewProfile,
+    CashuConsolidation, CashuMint, CashuMintAuth, CashuPaymentRequest, DleqAudit, EcashReceive,
+    EcashSend, Fedimint, LightningPayment, LightningReceive, NewFedimint, NewProfile, NostrEvent,
     OnChainPayment, OnChainReceive, Profile,
 };
 use crate::metadata::FederationMeta;
@@ -9,6 +11,7 @@ use anyhow::anyhow;
 use bip39::{Language, Mnemonic};
 use bitcoin::{Address, Txid};
 use cdk::mint_url::MintUrl;
+use cdk::nuts::{CurrencyUnit, SpendingConditions};
 use diesel::{
     SqliteConnection,
     connection::SimpleConnection,
@@ -84,6 +87,12 @@ pub trait DBConnection {
     // Sets the tor enabled flag
     fn set_tor_enabled(&self, enabled: bool) -> anyhow::Result<()>;
 
+    // Sets whether fragmented cashu proofs get consolidated in the background
+    fn set_consolidation_enabled(&self, enabled: bool) -> anyhow::Result<()>;
+
+    // Sets whether we listen for ecash DMs on nostr relays
+    fn set_nostr_enabled(&self, enabled: bool) -> anyhow::Result<()>;
+
     // Retrieves the mnemonic from the DB
     fn retrieve_mnemonic(&self) -> anyhow::Result<Mnemonic>;
 
@@ -96,7 +105,7 @@ pub trait DBConnection {
     // Removes a federation from the DB
     fn remove_federation(&self, f: FederationId) -> anyhow::Result<()>;
 
-    fn remove_cashu_mint(&self, f: &MintUrl) -> anyhow::Result<()>;
+    fn remove_cashu_mint(&self, f: &MintUrl, unit: &CurrencyUnit) -> anyhow::Result<()>;
 
     // Sets a federation as active
     fn set_federation_active(&self, f: FederationId) -> anyhow::Result<()>;
@@ -112,36 +121,132 @@ pub trait DBConnection {
 
     fn get_archived_fedimints(&self) -> anyhow::Result<Vec<MintMetadata>>;
 
-    fn list_cashu_mints(&self) -> anyhow::Result<Vec<String>>;
+    fn list_cashu_mints(&self) -> anyhow::Result<Vec<(MintUrl, CurrencyUnit)>>;
+
+    fn list_archived_cashu_mints(&self) -> anyhow::Result<Vec<(MintUrl, CurrencyUnit)>>;
+
+    fn insert_new_cashu_mint(&self, url: String, unit: CurrencyUnit) -> anyhow::Result<()>;
+
+    fn set_cashu_mint_active(&self, url: String, unit: CurrencyUnit) -> anyhow::Result<()>;
+
+    fn record_cashu_dleq_failures(&self, url: &MintUrl, failures: DleqAudit) -> anyhow::Result<()>;
 
-    fn list_archived_cashu_mints(&self) -> anyhow::Result<Vec<MintUrl>>;
+    fn get_cashu_dleq_audit(&self, url: &MintUrl) -> anyhow::Result<DleqAudit>;
 
-    fn insert_new_cashu_mint(&self, url: String) -> anyhow::Result<()>;
+    /// Flags the mint for a proof state check after a request that spends
+    /// proofs failed without a response, cleared once we've checked
+    fn set_cashu_mint_needs_reconcile(
+        &self,
+        url: &MintUrl,
+        needs_reconcile: bool,
+    ) -> anyhow::Result<()>;
+
+    fn cashu_mint_needs_reconcile(&self, url: &MintUrl) -> anyhow::Result<bool>;
 
-    fn set_cashu_mint_active(&self, url: String) -> anyhow::Result<()>;
+    #[allow(clippy::too_many_arguments)]
+    fn create_cashu_consolidation(
+        &self,
+        id: String,
+        mint_url: &MintUrl,
+        unit: &CurrencyUnit,
+        amount: u64,
+        fee: u64,
+        proofs_before: u64,
+        proofs_after: u64,
+    ) -> anyhow::Result<()>;
 
     // updates the federation data
     fn update_fedimint_data(&self, id: String, value: Vec<u8>) -> anyhow::Result<()>;
 
+    #[allow(clippy::too_many_arguments)]
     fn create_ln_receive(
         &self,
         operation_id: String,
         fedimint_id: Option<FederationId>,
         cashu_mint_url: Option<MintUrl>,
+        cashu_unit: Option<CurrencyUnit>,
         bolt11: Bolt11Invoice,
         amount: Amount,
         fee: Amount,
+        quote_key_index: Option<u32>,
     ) -> anyhow::Result<()>;
 
+    // Reserves the index of the next seed derived mint quote key
+    fn next_cashu_quote_key_index(&self) -> anyhow::Result<u32>;
+
+    fn get_cashu_mint_auth(&self, url: &MintUrl) -> anyhow::Result<Option<CashuMintAuth>>;
+
+    fn save_cashu_mint_auth(
+        &self,
+        url: &MintUrl,
+        settings: &AuthSettings,
+        access_token: String,
+        refresh_token: Option<String>,
+        expires_at: Option<u64>,
+    ) -> anyhow::Result<()>;
+
+    fn update_cashu_mint_auth_tokens(
+        &self,
+        url: &MintUrl,
+        access_token: String,
+        refresh_token: Option<String>,
+        expires_at: Option<u64>,
+    ) -> anyhow::Result<()>;
+
+    fn add_cashu_blind_auth_tokens(&self, url: &MintUrl, tokens: Vec<String>)
+    -> anyhow::Result<()>;
+
+    fn take_cashu_blind_auth_token(&self, url: &MintUrl) -> anyhow::Result<Option<String>>;
+
+    fn create_payment_request(
+        &self,
+        id: String,
+        mint_url: &MintUrl,
+        unit: &CurrencyUnit,
+        amount: Option<u64>,
+        description: Option<String>,
+        request: String,
+    ) -> anyhow::Result<()>;
+
+    fn get_payment_request(&self, id: &str) -> anyhow::Result<Option<CashuPaymentRequest>>;
+
+    fn mark_payment_request_paid(&self, id: &str, ecash_receive_id: String) -> anyhow::Result<()>;
+
+    /// Returns false if the nostr event was already handled or isn't due for a retry
+    fn claim_nostr_event(&self, event_id: &str, event: &str) -> anyhow::Result<bool>;
+
+    fn mark_nostr_event_as_redeemed(&self, event_id: &str) -> anyhow::Result<()>;
+
+    /// Returns how many times redeeming the event failed so far
+    fn mark_nostr_event_as_failed(&self, event_id: &str) -> anyhow::Result<u32>;
+
+    fn get_retryable_nostr_events(&self) -> anyhow::Result<Vec<NostrEvent>>;
+
+    fn release_unfinished_nostr_events(&self) -> anyhow::Result<()>;
+
     fn mark_ln_receive_as_success(&self, operation_id: String) -> anyhow::Result<()>;
 
     fn mark_ln_receive_as_failed(&self, operation_id: String) -> anyhow::Result<()>;
 
+    #[allow(clippy::too_many_arguments)]
     fn create_lightning_payment(
         &self,
         operation_id: String,
         fedimint_id: Option<FederationId>,
         cashu_mint_url: Option<MintUrl>,
+        cashu_unit: Option<CurrencyUnit>,
+        bolt11: Bolt11Invoice,
+        amount: Amount,
+        fee: Amount,
+    ) -> anyhow::Result<()>;
+
+    #[allow(clippy::too_many_arguments)]
+    fn create_lightning_payment_part(
+        &self,
+        operation_id: String,
+        parent_operation_id: String,
+        cashu_mint_url: MintUrl,
+        cashu_unit: CurrencyUnit,
         bolt11: Bolt11Invoice,
         amount: Amount,
         fee: Amount,
@@ -190,6 +295,92 @@ pub trait DBConnection {
 
     fn mark_onchain_receive_as_confirmed(&self, operation_id: String) -> anyhow::Result<()>;
 
+    #[allow(clippy::too_many_arguments)]
+    fn create_ecash_send(
+        &self,
+        operation_id: String,
+        fedimint_id: Option<FederationId>,
+        cashu_mint_url: Option<MintUrl>,
+        cashu_unit: Option<CurrencyUnit>,
+        token: String,
+        amount: Amount,
+        fee: Amount,
+        spending_conditions: Option<SpendingConditions>,
+    ) -> anyhow::Result<()>;
+
+    fn create_unconfirmed_ecash_send(
+        &self,
+        operation_id: String,
+        cashu_mint_url: MintUrl,
+        cashu_unit: CurrencyUnit,
+        amount: Amount,
+        fee: Amount,
+        spending_conditions: Option<SpendingConditions>,
+    ) -> anyhow::Result<()>;
+
+    fn confirm_ecash_send(&self, operation_id: String, token: String) -> anyhow::Result<()>;
+
+    fn get_ecash_send(&self, operation_id: String) -> anyhow::Result<Option<EcashSend>>;
+
+    fn get_ecash_send_by_token(&self, token: &str) -> anyhow::Result<Option<EcashSend>>;
+
+    fn mark_ecash_send_as_success(&self, operation_id: String) -> anyhow::Result<()>;
+
+    fn mark_ecash_send_as_failed(&self, operation_id: String) -> anyhow::Result<()>;
+
+    fn mark_ecash_send_as_partially_reclaimed(
+        &self,
+        operation_id: String,
+        claimed: Amount,
+    ) -> anyhow::Result<()>;
+
+    fn get_pending_ecash_sends(&self) -> anyhow::Result<Vec<EcashSend>>;
+
+    fn get_pending_timelocked_ecash_sends(&self) -> anyhow::Result<Vec<EcashSend>>;
+
+    fn mark_ecash_send_as_reminded(&self, operation_id: String) -> anyhow::Result<()>;
+
+    fn create_ecash_receive(
+        &self,
+        operation_id: String,
+        fedimint_id: Option<FederationId>,
+        cashu_mint_url: Option<MintUrl>,
+        cashu_unit: Option<CurrencyUnit>,
+        token: String,
+        amount: Amount,
+    ) -> anyhow::Result<()>;
+
+    #[allow(clippy::too_many_arguments)]
+    fn create_ecash_swap(
+        &self,
+        operation_id: String,
+        fedimint_id: Option<FederationId>,
+        cashu_mint_url: Option<MintUrl>,
+        cashu_unit: Option<CurrencyUnit>,
+        swap_mint_url: MintUrl,
+        token: String,
+        amount: Amount,
+    ) -> anyhow::Result<()>;
+
+    fn mark_ecash_swap_as_claimed(&self, operation_id: String) -> anyhow::Result<()>;
+
+    fn set_ecash_swap_melt_quote(
+        &self,
+        operation_id: String,
+        payment_hash: String,
+        quote_id: String,
+    ) -> anyhow::Result<()>;
+
+    fn mark_ecash_receive_as_success(
+        &self,
+        operation_id: String,
+        fee: Amount,
+    ) -> anyhow::Result<()>;
+
+    fn mark_ecash_receive_as_failed(&self, operation_id: String) -> anyhow::Result<()>;
+
+    fn get_pending_ecash_receives(&self) -> anyhow::Result<Vec<EcashReceive>>;
+
     fn get_transaction_history(&self) -> anyhow::Result<Vec<TransactionItem>>;
 
     fn get_pending_onchain_receives(&self) -> anyhow::Result<Vec<OnChainReceive>>;
@@ -214,6 +405,11 @@ pub trait DBConnection {
         operation_id: String,
     ) -> anyhow::Result<Option<LightningPayment>>;
 
+    fn get_lightning_receive_by_payment_hash(
+        &self,
+        payment_hash: &str,
+    ) -> anyhow::Result<Option<LightningReceive>>;
+
     fn get_federation_metadata(&self, id: FederationId) -> anyhow::Result<Option<FederationMeta>>;
 
     fn upsert_federation_metadata(
@@ -254,6 +450,18 @@ impl DBConnection for SQLConnection {
         Ok(())
     }
 
+    fn set_consolidation_enabled(&self, enabled: bool) -> anyhow::Result<()> {
+        let conn = &mut self.db.get()?;
+        Profile::set_consolidation_enabled(conn, enabled)?;
+        Ok(())
+    }
+
+    fn set_nostr_enabled(&self, enabled: bool) -> anyhow::Result<()> {
+        let conn = &mut self.db.get()?;
+        Profile::set_nostr_enabled(conn, enabled)?;
+        Ok(())
+    }
+
     fn get_federation_value(&self, id: String) -> anyhow::Result<Option<Vec<u8>>> {
         let conn = &mut self.db.get()?;
         Fedimint::get_value(conn, id)
@@ -279,19 +487,71 @@ impl DBConnection for SQLConnection {
         Fedimint::set_active(conn, f.to_string())
     }
 
-    fn list_cashu_mints(&self) -> anyhow::Result<Vec<String>> {
+    fn list_cashu_mints(&self) -> anyhow::Result<Vec<(MintUrl, CurrencyUnit)>> {
         let conn = &mut self.db.get()?;
-        CashuMint::get_mints(conn)
+        CashuMint::get_mints(conn)?
+            .into_iter()
+            .map(|(url, unit)| Ok((MintUrl::from_str(&url)?, CurrencyUnit::from_str(&unit)?)))
+            .collect()
     }
 
-    fn insert_new_cashu_mint(&self, url: String) -> anyhow::Result<()> {
+    fn insert_new_cashu_mint(&self, url: String, unit: CurrencyUnit) -> anyhow::Result<()> {
         let conn = &mut self.db.get()?;
-        CashuMint::insert(conn, url)
+        CashuMint::insert(conn, url, unit.to_string())
     }
 
-    fn set_cashu_mint_active(&self, url: String) -> anyhow::Result<()> {
+    fn set_cashu_mint_active(&self, url: String, unit: CurrencyUnit) -> anyhow::Result<()> {
+        let conn = &mut self.db.get()?;
+        CashuMint::set_active(conn, &url, &unit.to_string())
+    }
+
+    fn record_cashu_dleq_failures(&self, url: &MintUrl, failures: DleqAudit) -> anyhow::Result<()> {
+        let conn = &mut self.db.get()?;
+        CashuMint::record_dleq_failures(conn, &url.to_string(), failures)
+    }
+
+    fn get_cashu_dleq_audit(&self, url: &MintUrl) -> anyhow::Result<DleqAudit> {
+        let conn = &mut self.db.get()?;
+        Ok(CashuMint::get(conn, url.to_string())?
+            .map(|m| m.dleq_audit())
+            .unwrap_or_default())
+    }
+
+    fn set_cashu_mint_needs_reconcile(
+        &self,
+        url: &MintUrl,
+        needs_reconcile: bool,
+    ) -> anyhow::Result<()> {
         let conn = &mut self.db.get()?;
-        CashuMint::set_active(conn, &url)
+        CashuMint::set_needs_reconcile(conn, &url.to_string(), needs_reconcile)
+    }
+
+    fn cashu_mint_needs_reconcile(&self, url: &MintUrl) -> anyhow::Result<bool> {
+        let conn = &mut self.db.get()?;
+        Ok(CashuMint::get(conn, url.to_string())?.is_some_and(|m| m.needs_reconcile()))
+    }
+
+    fn create_cashu_consolidation(
+        &self,
+        id: String,
+        mint_url: &MintUrl,
+        unit: &CurrencyUnit,
+        amount: u64,
+        fee: u64,
+        proofs_before: u64,
+        proofs_after: u64,
+    ) -> anyhow::Result<()> {
+        let conn = &mut self.db.get()?;
+        CashuConsolidation::create(
+            conn,
+            id,
+            mint_url,
+            unit,
+            amount,
+            fee,
+            proofs_before,
+            proofs_after,
+        )
     }
 
     fn create_ln_receive(
@@ -299,9 +559,11 @@ impl DBConnection for SQLConnection {
         operation_id: String,
         fedimint_id: Option<FederationId>,
         cashu_mint_url: Option<MintUrl>,
+        cashu_unit: Option<CurrencyUnit>,
         bolt11: Bolt11Invoice,
         amount: Amount,
         fee: Amount,
+        quote_key_index: Option<u32>,
     ) -> anyhow::Result<()> {
         let conn = &mut self.db.get()?;
 
@@ -310,14 +572,124 @@ impl DBConnection for SQLConnection {
             operation_id,
             fedimint_id,
             cashu_mint_url,
+            cashu_unit,
             bolt11,
             amount,
             fee,
+            quote_key_index,
         )?;
 
         Ok(())
     }
 
+    fn next_cashu_quote_key_index(&self) -> anyhow::Result<u32> {
+        let conn = &mut self.db.get()?;
+        Profile::take_quote_key_index(conn)
+    }
+
+    fn get_cashu_mint_auth(&self, url: &MintUrl) -> anyhow::Result<Option<CashuMintAuth>> {
+        let conn = &mut self.db.get()?;
+        CashuMintAuth::get(conn, &url.to_string())
+    }
+
+    fn save_cashu_mint_auth(
+        &self,
+        url: &MintUrl,
+        settings: &AuthSettings,
+        access_token: String,
+        refresh_token: Option<String>,
+        expires_at: Option<u64>,
+    ) -> anyhow::Result<()> {
+        let conn = &mut self.db.get()?;
+        CashuMintAuth::upsert(
+            conn,
+            &url.to_string(),
+            settings,
+            access_token,
+            refresh_token,
+            expires_at,
+        )
+    }
+
+    fn update_cashu_mint_auth_tokens(
+        &self,
+        url: &MintUrl,
+        access_token: String,
+        refresh_token: Option<String>,
+        expires_at: Option<u64>,
+    ) -> anyhow::Result<()> {
+        let conn = &mut self.db.get()?;
+        CashuMintAuth::set_tokens(
+            conn,
+            &url.to_string(),
+            access_token,
+            refresh_token,
+            expires_at,
+        )
+    }
+
+    fn add_cashu_blind_auth_tokens(
+        &self,
+        url: &MintUrl,
+        tokens: Vec<String>,
+    ) -> anyhow::Result<()> {
+        let conn = &mut self.db.get()?;
+        CashuMintAuth::add_blind_tokens(conn, &url.to_string(), tokens)
+    }
+
+    fn take_cashu_blind_auth_token(&self, url: &MintUrl) -> anyhow::Result<Option<String>> {
+        let conn = &mut self.db.get()?;
+        CashuMintAuth::take_blind_token(conn, &url.to_string())
+    }
+
+    fn create_payment_request(
+        &self,
+        id: String,
+        mint_url: &MintUrl,
+        unit: &CurrencyUnit,
+        amount: Option<u64>,
+        description: Option<String>,
+        request: String,
+    ) -> anyhow::Result<()> {
+        let conn = &mut self.db.get()?;
+        CashuPaymentRequest::create(conn, id, mint_url, unit, amount, description, request)
+    }
+
+    fn get_payment_request(&self, id: &str) -> anyhow::Result<Option<CashuPaymentRequest>> {
+        let conn = &mut self.db.get()?;
+        CashuPaymentRequest::get(conn, id)
+    }
+
+    fn mark_payment_request_paid(&self, id: &str, ecash_receive_id: String) -> anyhow::Result<()> {
+        let conn = &mut self.db.get()?;
+        CashuPaymentRequest::mark_as_paid(conn, id, ecash_receive_id)
+    }
+
+    fn claim_nostr_event(&self, event_id: &str, event: &str) -> anyhow::Result<bool> {
+        let conn = &mut self.db.get()?;
+        NostrEvent::claim(conn, event_id, event)
+    }
+
+    fn mark_nostr_event_as_redeemed(&self, event_id: &str) -> anyhow::Result<()> {
+        let conn = &mut self.db.get()?;
+        NostrEvent::mark_as_redeemed(conn, event_id)
+    }
+
+    fn mark_nostr_event_as_failed(&self, event_id: &str) -> anyhow::Result<u32> {
+        let conn = &mut self.db.get()?;
+        NostrEvent::mark_as_failed(conn, event_id)
+    }
+
+    fn get_retryable_nostr_events(&self) -> anyhow::Result<Vec<NostrEvent>> {
+        let conn = &mut self.db.get()?;
+        NostrEvent::get_retryable(conn)
+    }
+
+    fn release_unfinished_nostr_events(&self) -> anyhow::Result<()> {
+        let conn = &mut self.db.get()?;
+        NostrEvent::release_unfinished(conn)
+    }
+
     fn mark_ln_receive_as_success(&self, operation_id: String) -> anyhow::Result<()> {
         let conn = &mut self.db.get()?;
 
@@ -339,6 +711,7 @@ impl DBConnection for SQLConnection {
         operation_id: String,
         fedimint_id: Option<FederationId>,
         cashu_mint_url: Option<MintUrl>,
+        cashu_unit: Option<CurrencyUnit>,
         bolt11: Bolt11Invoice,
         amount: Amount,
         fee: Amount,
@@ -350,6 +723,33 @@ impl DBConnection for SQLConnection {
             operation_id,
             fedimint_id,
             cashu_mint_url,
+            cashu_unit,
+            bolt11,
+            amount,
+            fee,
+        )?;
+
+        Ok(())
+    }
+
+    fn create_lightning_payment_part(
+        &self,
+        operation_id: String,
+        parent_operation_id: String,
+        cashu_mint_url: MintUrl,
+        cashu_unit: CurrencyUnit,
+        bolt11: Bolt11Invoice,
+        amount: Amount,
+        fee: Amount,
+    ) -> anyhow::Result<()> {
+        let conn = &mut self.db.get()?;
+
+        LightningPayment::create_part(
+            conn,
+            operation_id,
+            parent_operation_id,
+            cashu_mint_url,
+            cashu_unit,
             bolt11,
             amount,
             fee,
@@ -366,7 +766,14 @@ impl DBConnection for SQLConnection {
     ) -> anyhow::Result<()> {
         let conn = &mut self.db.get()?;
 
-        LightningPayment::set_preimage_and_fee(conn, operation_id, preimage, fee_msats)?;
+        LightningPayment::set_preimage_and_fee(conn, operation_id.clone(), preimage, fee_msats)?;
+
+        // parts of a multi-path payment also settle their parent
+        let parent = LightningPayment::get_by_operation_id(conn, operation_id)?
+            .and_then(|p| p.parent_operation_id().map(str::to_string));
+        if let Some(parent) = parent {
+            LightningPayment::settle_parent(conn, &parent)?;
+        }
 
         Ok(())
     }
@@ -374,7 +781,14 @@ impl DBConnection for SQLConnection {
     fn mark_lightning_payment_as_failed(&self, operation_id: String) -> anyhow::Result<()> {
         let conn = &mut self.db.get()?;
 
-        LightningPayment::mark_as_failed(conn, operation_id)?;
+        LightningPayment::mark_as_failed(conn, operation_id.clone())?;
+
+        // parts of a multi-path payment also settle their parent
+        let parent = LightningPayment::get_by_operation_id(conn, operation_id)?
+            .and_then(|p| p.parent_operation_id().map(str::to_string));
+        if let Some(parent) = parent {
+            LightningPayment::settle_parent(conn, &parent)?;
+        }
 
         Ok(())
     }
@@ -463,6 +877,218 @@ impl DBConnection for SQLConnection {
         Ok(())
     }
 
+    fn create_ecash_send(
+        &self,
+        operation_id: String,
+        fedimint_id: Option<FederationId>,
+        cashu_mint_url: Option<MintUrl>,
+        cashu_unit: Option<CurrencyUnit>,
+        token: String,
+        amount: Amount,
+        fee: Amount,
+        spending_conditions: Option<SpendingConditions>,
+    ) -> anyhow::Result<()> {
+        let conn = &mut self.db.get()?;
+
+        EcashSend::create(
+            conn,
+            operation_id,
+            fedimint_id,
+            cashu_mint_url,
+            cashu_unit,
+            token,
+            amount,
+            fee,
+            spending_conditions.as_ref(),
+        )?;
+
+        Ok(())
+    }
+
+    fn create_unconfirmed_ecash_send(
+        &self,
+        operation_id: String,
+        cashu_mint_url: MintUrl,
+        cashu_unit: CurrencyUnit,
+        amount: Amount,
+        fee: Amount,
+        spending_conditions: Option<SpendingConditions>,
+    ) -> anyhow::Result<()> {
+        let conn = &mut self.db.get()?;
+
+        EcashSend::create_unconfirmed(
+            conn,
+            operation_id,
+            cashu_mint_url,
+            cashu_unit,
+            amount,
+            fee,
+            spending_conditions.as_ref(),
+        )?;
+
+        Ok(())
+    }
+
+    fn confirm_ecash_send(&self, operation_id: String, token: String) -> anyhow::Result<()> {
+        let conn = &mut self.db.get()?;
+
+        EcashSend::confirm(conn, operation_id, token)?;
+
+        Ok(())
+    }
+
+    fn get_ecash_send(&self, operation_id: String) -> anyhow::Result<Option<EcashSend>> {
+        let conn = &mut self.db.get()?;
+        EcashSend::get_by_operation_id(conn, operation_id)
+    }
+
+    fn get_ecash_send_by_token(&self, token: &str) -> anyhow::Result<Option<EcashSend>> {
+        let conn = &mut self.db.get()?;
+        EcashSend::get_by_token(conn, token)
+    }
+
+    fn mark_ecash_send_as_success(&self, operation_id: String) -> anyhow::Result<()> {
+        let conn = &mut self.db.get()?;
+
+        EcashSend::mark_as_success(conn, operation_id)?;
+
+        Ok(())
+    }
+
+    fn mark_ecash_send_as_failed(&self, operation_id: String) -> anyhow::Result<()> {
+        let conn = &mut self.db.get()?;
+
+        EcashSend::mark_as_failed(conn, operation_id)?;
+
+        Ok(())
+    }
+
+    fn mark_ecash_send_as_partially_reclaimed(
+        &self,
+        operation_id: String,
+        claimed: Amount,
+    ) -> anyhow::Result<()> {
+        let conn = &mut self.db.get()?;
+
+        EcashSend::mark_as_partially_reclaimed(conn, operation_id, claimed)?;
+
+        Ok(())
+    }
+
+    fn get_pending_ecash_sends(&self) -> anyhow::Result<Vec<EcashSend>> {
+        let conn = &mut self.db.get()?;
+        EcashSend::get_pending(conn)
+    }
+
+    fn get_pending_timelocked_ecash_sends(&self) -> anyhow::Result<Vec<EcashSend>> {
+        let conn = &mut self.db.get()?;
+        EcashSend::get_pending_timelocked(conn)
+    }
+
+    fn mark_ecash_send_as_reminded(&self, operation_id: String) -> anyhow::Result<()> {
+        let conn = &mut self.db.get()?;
+
+        EcashSend::mark_as_reminded(conn, operation_id)?;
+
+        Ok(())
+    }
+
+    fn create_ecash_receive(
+        &self,
+        operation_id: String,
+        fedimint_id: Option<FederationId>,
+        cashu_mint_url: Option<MintUrl>,
+        cashu_unit: Option<CurrencyUnit>,
+        token: String,
+        amount: Amount,
+    ) -> anyhow::Result<()> {
+        let conn = &mut self.db.get()?;
+
+        EcashReceive::create(
+            conn,
+            operation_id,
+            fedimint_id,
+            cashu_mint_url,
+            cashu_unit,
+            token,
+            amount,
+        )?;
+
+        Ok(())
+    }
+
+    fn create_ecash_swap(
+        &self,
+        operation_id: String,
+        fedimint_id: Option<FederationId>,
+        cashu_mint_url: Option<MintUrl>,
+        cashu_unit: Option<CurrencyUnit>,
+        swap_mint_url: MintUrl,
+        token: String,
+        amount: Amount,
+    ) -> anyhow::Result<()> {
+        let conn = &mut self.db.get()?;
+
+        EcashReceive::create_swap(
+            conn,
+            operation_id,
+            fedimint_id,
+            cashu_mint_url,
+            cashu_unit,
+            swap_mint_url,
+            token,
+            amount,
+        )?;
+
+        Ok(())
+    }
+
+    fn mark_ecash_swap_as_claimed(&self, operation_id: String) -> anyhow::Result<()> {
+        let conn = &mut self.db.get()?;
+
+        EcashReceive::mark_swap_as_claimed(conn, operation_id)?;
+
+        Ok(())
+    }
+
+    fn set_ecash_swap_melt_quote(
+        &self,
+        operation_id: String,
+        payment_hash: String,
+        quote_id: String,
+    ) -> anyhow::Result<()> {
+        let conn = &mut self.db.get()?;
+
+        EcashReceive::set_swap_melt_quote(conn, operation_id, payment_hash, quote_id)?;
+
+        Ok(())
+    }
+
+    fn mark_ecash_receive_as_success(
+        &self,
+        operation_id: String,
+        fee: Amount,
+    ) -> anyhow::Result<()> {
+        let conn = &mut self.db.get()?;
+
+        EcashReceive::mark_as_success(conn, operation_id, fee)?;
+
+        Ok(())
+    }
+
+    fn mark_ecash_receive_as_failed(&self, operation_id: String) -> anyhow::Result<()> {
+        let conn = &mut self.db.get()?;
+
+        EcashReceive::mark_as_failed(conn, operation_id)?;
+
+        Ok(())
+    }
+
+    fn get_pending_ecash_receives(&self) -> anyhow::Result<Vec<EcashReceive>> {
+        let conn = &mut self.db.get()?;
+        EcashReceive::get_pending(conn)
+    }
+
     fn get_transaction_history(&self) -> anyhow::Result<Vec<TransactionItem>> {
         let conn = &mut self.db.get()?;
 
@@ -470,12 +1096,18 @@ impl DBConnection for SQLConnection {
         let onchain_receives = OnChainReceive::get_history(conn)?;
         let lightning_payments = LightningPayment::get_history(conn)?;
         let lightning_receives = LightningReceive::get_history(conn)?;
+        let ecash_sends = EcashSend::get_history(conn)?;
+        let ecash_receives = EcashReceive::get_history(conn)?;
+        let consolidations = CashuConsolidation::get_history(conn)?;
 
         let mut items: Vec<TransactionItem> = Vec::with_capacity(
             onchain_payments.len()
                 + onchain_receives.len()
                 + lightning_payments.len()
-                + lightning_receives.len(),
+                + lightning_receives.len()
+                + ecash_sends.len()
+                + ecash_receives.len()
+                + consolidations.len(),
         );
 
         for onchain_payment in onchain_payments {
@@ -494,6 +1126,22 @@ impl DBConnection for SQLConnection {
             items.push(lightning_receive.into());
         }
 
+        for ecash_send in ecash_sends {
+            items.push(ecash_send.into());
+        }
+
+        for ecash_receive in ecash_receives {
+            items.push(ecash_receive.into());
+        }
+
+        for consolidation in consolidations {
+            let id = consolidation.id.clone();
+            match consolidation.try_into() {
+                Ok(item) => items.push(item),
+                Err(e) => error!("Skipping unreadable consolidation {id}: {e}"),
+            }
+        }
+
         // sort by timestamp so that the most recent items are at the top
         items.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
 
@@ -506,9 +1154,9 @@ impl DBConnection for SQLConnection {
         Ok(())
     }
 
-    fn remove_cashu_mint(&self, f: &MintUrl) -> anyhow::Result<()> {
+    fn remove_cashu_mint(&self, f: &MintUrl, unit: &CurrencyUnit) -> anyhow::Result<()> {
         let conn = &mut self.db.get()?;
-        CashuMint::remove_mint(conn, f.to_string())?;
+        CashuMint::remove_mint(conn, f.to_string(), unit.to_string())?;
         Ok(())
     }
 
@@ -526,6 +1174,7 @@ impl DBConnection for SQLConnection {
     }
 
     fn generate_mnemonic(&self, words: Option<Mnemonic>) -> anyhow::Result<Mnemonic> {
+        let restored = words.is_some();
         let seed = match words {
             Some(words) => words,
             None => Mnemonic::generate_in(Language::English, 12)?,
@@ -538,6 +1187,15 @@ impl DBConnection for SQLConnection {
 
         self.insert_new_profile(new_profile)?;
 
+        // another device with this seed may have locked quotes to any index, start
+        // somewhere random so we don't hand out their keys again. The low bits of a
+        // v4 uuid are random, and the top of the range stays free for new indexes.
+        if restored {
+            let offset = (uuid::Uuid::new_v4().as_u128() as u32) >> 2;
+            let conn = &mut self.db.get()?;
+            Profile::set_next_quote_key_index(conn, offset)?;
+        }
+
         info!("creating new seed");
         Ok(seed)
     }
@@ -588,6 +1246,14 @@ impl DBConnection for SQLConnection {
         LightningPayment::get_by_operation_id(conn, operation_id)
     }
 
+    fn get_lightning_receive_by_payment_hash(
+        &self,
+        payment_hash: &str,
+    ) -> anyhow::Result<Option<LightningReceive>> {
+        let conn = &mut self.db.get()?;
+        LightningReceive::get_by_payment_hash(conn, payment_hash)
+    }
+
     fn get_federation_metadata(&self, id: FederationId) -> anyhow::Result<Option<FederationMeta>> {
         let conn = &mut self.db.get()?;
         let meta = MintMetadata::get(conn, id.to_string())?.map(|i| i.into());
@@ -618,13 +1284,12 @@ impl DBConnection for SQLConnection {
         Ok(result)
     }
 
-    fn list_archived_cashu_mints(&self) -> anyhow::Result<Vec<MintUrl>> {
+    fn list_archived_cashu_mints(&self) -> anyhow::Result<Vec<(MintUrl, CurrencyUnit)>> {
         let conn = &mut self.db.get()?;
-        let ids = CashuMint::get_archived_mints(conn)?;
-        Ok(ids
+        CashuMint::get_archived_mints(conn)?
             .into_iter()
-            .map(|a| MintUrl::from_str(&a))
-            .collect::<Result<Vec<_>, _>>()?)
+            .map(|(url, unit)| Ok((MintUrl::from_str(&url)?, CurrencyUnit::from_str(&unit)?)))
+            .collect()
     }
 
     fn get_federation_invite_code(&self, f: FederationId) -> anyhow::Result<Option<InviteCode>> {
@@ -675,8 +1340,11 @@ impl diesel::r2d2::CustomizeConnection<SqliteConnection, diesel::r2d2::Error>
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::MintIdentifier;
+    use crate::db_models::transaction_item::TransactionItemKind;
     use crate::db_models::{
-        LightningPayment, LightningReceive, OnChainPayment, OnChainReceive, PaymentStatus,
+        EcashReceive, EcashSend, LightningPayment, LightningReceive, OnChainPayment,
+        OnChainReceive, PaymentStatus,
     };
     use bip39::{Language, Mnemonic};
     use bitcoin::hashes::Hash;
@@ -722,6 +1390,15 @@ mod tests {
         db
     }
 
+    /// A test db with https://mint.example.com joined for `unit`
+    fn setup_test_db_with_cashu_mint(unit: CurrencyUnit) -> (Arc<SQLConnection>, MintUrl) {
+        let db = setup_test_db_with_data();
+        let mint_url = MintUrl::from_str("https://mint.example.com").unwrap();
+        db.insert_new_cashu_mint(mint_url.to_string(), unit)
+            .unwrap();
+        (db, mint_url)
+    }
+
     #[test]
     fn test_seed() {
         let db = setup_test_db();
@@ -781,6 +1458,7 @@ mod tests {
             operation_id.fmt_full().to_string(),
             FederationId::from_str(FEDERATION_ID).ok(),
             None,
+            None,
             invoice.clone(),
             Amount::from_sats(1_000),
             Amount::from_sats(1),
@@ -837,9 +1515,11 @@ mod tests {
             operation_id.fmt_full().to_string(),
             FederationId::from_str(FEDERATION_ID).ok(),
             None,
+            None,
             invoice.clone(),
             Amount::from_sats(1_000),
             Amount::from_sats(1),
+            None,
         )
         .unwrap();
 
@@ -1017,4 +1697,733 @@ mod tests {
         assert_ne!(confirmed.updated_at, confirmed.created_at);
         assert_ne!(confirmed.updated_at, with_txid.updated_at);
     }
+
+    #[test]
+    fn test_ecash_send_db() {
+        let db = setup_test_db_with_data();
+        let pool = db.db.clone();
+        let mut conn = pool.get().unwrap();
+
+        let operation_id = OperationId::new_random().fmt_full().to_string();
+        let token = "cashuBo2FteBtodHRwczovL21pbnQubWludXRpbnluZXQuY29tYXVjc2F0YXSBomFpSABVDVKN3j2VYXCBpGFhAmFzeEAzYjQxNmE4YjJkYTNlNzY2MWM2ZjFmOWQ3ZmNiZjFmZTFlNjAxYjM4NjFlNWIxYjM0YTM5MzNmNDc2NDYyNjc1YWNYIQIwZDqDAJE8wd7uIBBgdTA3s9SJo4gJfBz9pZy6HpJmRGFkb2FlWCBDK4KO6hv5yB7LgpVlnRmSZCrV1jkkifv-kPu6a_ysGmFzWCCCgvvCu6bHyEn0ohWaF_dpLF9FHAXXSyMaEDpmrx6tlmFyWCBSdMb2lH3UjNu-OcrLZyIn09gM29M1HUiMwO2rdHX3Nw";
+
+        EcashSend::create(
+            &mut conn,
+            operation_id.clone(),
+            FederationId::from_str(FEDERATION_ID).ok(),
+            None,
+            None,
+            token.to_string(),
+            Amount::from_sats(2),
+            Amount::ZERO,
+            None,
+        )
+        .unwrap();
+
+        let send = EcashSend::get_by_operation_id(&mut conn, operation_id.clone())
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(send.operation_id, operation_id);
+        assert_eq!(
+            send.fedimint_id(),
+            FederationId::from_str(FEDERATION_ID).ok()
+        );
+        assert_eq!(send.token(), token);
+        assert_eq!(send.amount(), Amount::from_sats(2));
+        assert_eq!(send.fee(), Amount::ZERO);
+        assert_eq!(send.status(), PaymentStatus::Pending);
+        assert_eq!(send.spending_conditions(), None);
+        assert_eq!(send.locktime(), None);
+        assert!(db.get_pending_timelocked_ecash_sends().unwrap().is_empty());
+
+        let history = db.get_transaction_history().unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].token.as_deref(), Some(token));
+        assert_eq!(db.get_pending_ecash_sends().unwrap().len(), 1);
+        assert_eq!(
+            db.get_ecash_send_by_token(token)
+                .unwrap()
+                .unwrap()
+                .operation_id,
+            operation_id
+        );
+
+        // claimed tokens stay in history but are no longer pending
+        EcashSend::mark_as_success(&mut conn, operation_id.clone()).unwrap();
+        assert!(db.get_pending_ecash_sends().unwrap().is_empty());
+        assert_eq!(db.get_transaction_history().unwrap().len(), 1);
+
+        // partly reclaimed tokens stay in history with what was claimed
+        db.mark_ecash_send_as_partially_reclaimed(operation_id.clone(), Amount::from_sats(1))
+            .unwrap();
+        let send = EcashSend::get_by_operation_id(&mut conn, operation_id.clone())
+            .unwrap()
+            .unwrap();
+        assert_eq!(send.amount(), Amount::from_sats(1));
+        assert_eq!(send.status(), PaymentStatus::Success);
+        assert_eq!(db.get_transaction_history().unwrap()[0].amount, 1);
+
+        // reclaimed tokens drop out of history
+        EcashSend::mark_as_failed(&mut conn, operation_id).unwrap();
+        assert!(db.get_transaction_history().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_ecash_receive_db() {
+        let db = setup_test_db_with_data();
+        let pool = db.db.clone();
+        let mut conn = pool.get().unwrap();
+
+        let operation_id = OperationId::new_random().fmt_full().to_string();
+
+        EcashReceive::create(
+            &mut conn,
+            operation_id.clone(),
+            FederationId::from_str(FEDERATION_ID).ok(),
+            None,
+            None,
+            "cashuBtoken".to_string(),
+            Amount::from_sats(100),
+        )
+        .unwrap();
+
+        let receive = EcashReceive::get_by_operation_id(&mut conn, operation_id.clone())
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(receive.amount(), Amount::from_sats(100));
+        assert_eq!(receive.fee(), Amount::ZERO);
+        assert_eq!(receive.status(), PaymentStatus::Pending);
+
+        // pending receives should not show up in history
+        assert!(db.get_transaction_history().unwrap().is_empty());
+        assert_eq!(db.get_pending_ecash_receives().unwrap().len(), 1);
+
+        // sleep for a second to make sure the timestamps are different
+        std::thread::sleep(Duration::from_secs(1));
+
+        EcashReceive::mark_as_success(&mut conn, operation_id.clone(), Amount::from_sats(1))
+            .unwrap();
+
+        let success = EcashReceive::get_by_operation_id(&mut conn, operation_id)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(success.status(), PaymentStatus::Success);
+        assert_eq!(success.fee(), Amount::from_sats(1));
+        assert_ne!(success.updated_at, success.created_at);
+        assert_eq!(db.get_transaction_history().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_ecash_swap_db() {
+        let db = setup_test_db_with_data();
+        let pool = db.db.clone();
+        let mut conn = pool.get().unwrap();
+
+        let ln_operation_id = OperationId::new_random().fmt_full().to_string();
+        let invoice = Bolt11Invoice::from_str("lntbs10u1pny86cupp52lkv666juacc9evu0fpfmduac6l6qp0qypxr0yk9wfpze2u5sngshp57t8sp5tcchfv0y29yg46nqujktk2ufwcjcc7zvyd8rteadd7rjyscqzzsxqyz5vqsp5nnhtrhvyfh077g6rdfrs7ml9hqks4mj6f0e50nyeejc73ee7gl3q9qyyssq3urmp6hy3c95rtddevae0djrfn8au0rumgd05zvddzshg8krwupzc4htl38kqufp27el5ev5l8ea4736y3a3rpq5cewxwftsdk2v52cp9w25a0").unwrap();
+
+        LightningReceive::create(
+            &mut conn,
+            ln_operation_id.clone(),
+            FederationId::from_str(FEDERATION_ID).ok(),
+            None,
+            None,
+            invoice.clone(),
+            Amount::from_sats(1_000),
+            Amount::ZERO,
+            None,
+        )
+        .unwrap();
+        LightningReceive::mark_as_success(&mut conn, ln_operation_id).unwrap();
+        assert_eq!(db.get_transaction_history().unwrap().len(), 1);
+
+        let operation_id = OperationId::new_random().fmt_full().to_string();
+        let swap_mint = MintUrl::from_str("https://mint.example.com").unwrap();
+        EcashReceive::create_swap(
+            &mut conn,
+            operation_id.clone(),
+            FederationId::from_str(FEDERATION_ID).ok(),
+            None,
+            None,
+            swap_mint.clone(),
+            "cashuBtoken".to_string(),
+            Amount::from_sats(1_010),
+        )
+        .unwrap();
+        let swap = EcashReceive::get_by_operation_id(&mut conn, operation_id.clone())
+            .unwrap()
+            .unwrap();
+        assert_eq!(swap.swap_payment_hash(), None);
+        assert!(!swap.swap_claimed());
+
+        db.mark_ecash_swap_as_claimed(operation_id.clone()).unwrap();
+        db.set_ecash_swap_melt_quote(
+            operation_id.clone(),
+            invoice.payment_hash().to_string(),
+            "melt-quote".to_string(),
+        )
+        .unwrap();
+
+        let swap = EcashReceive::get_by_operation_id(&mut conn, operation_id.clone())
+            .unwrap()
+            .unwrap();
+        assert_eq!(swap.swap_mint_url(), Some(swap_mint));
+        assert!(swap.swap_claimed());
+        assert_eq!(swap.swap_quote_id(), Some("melt-quote"));
+        assert_eq!(
+            swap.swap_payment_hash(),
+            Some(invoice.payment_hash().to_string().as_str())
+        );
+        let receive = db
+            .get_lightning_receive_by_payment_hash(swap.swap_payment_hash().unwrap())
+            .unwrap()
+            .unwrap();
+        assert_eq!(receive.status(), PaymentStatus::Success);
+
+        // the linked lightning receive is hidden behind the swap
+        assert!(db.get_transaction_history().unwrap().is_empty());
+
+        EcashReceive::mark_as_success(&mut conn, operation_id, Amount::from_sats(10)).unwrap();
+
+        let history = db.get_transaction_history().unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].kind, TransactionItemKind::Ecash);
+    }
+
+    #[test]
+    fn test_cashu_mint_units_db() {
+        let (db, mint_url) = setup_test_db_with_cashu_mint(CurrencyUnit::Sat);
+        db.insert_new_cashu_mint(mint_url.to_string(), CurrencyUnit::Usd)
+            .unwrap();
+
+        let mints = db.list_cashu_mints().unwrap();
+        assert_eq!(mints.len(), 2);
+        assert!(mints.contains(&(mint_url.clone(), CurrencyUnit::Usd)));
+
+        // leaving one unit keeps the other wallet
+        db.remove_cashu_mint(&mint_url, &CurrencyUnit::Usd).unwrap();
+        assert_eq!(
+            db.list_cashu_mints().unwrap(),
+            vec![(mint_url.clone(), CurrencyUnit::Sat)]
+        );
+        assert_eq!(
+            db.list_archived_cashu_mints().unwrap(),
+            vec![(mint_url.clone(), CurrencyUnit::Usd)]
+        );
+
+        db.insert_new_cashu_mint(mint_url.to_string(), CurrencyUnit::Usd)
+            .unwrap();
+        assert_eq!(db.list_cashu_mints().unwrap().len(), 2);
+        assert!(db.list_archived_cashu_mints().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_cashu_dleq_audit_db() {
+        let (db, mint_url) = setup_test_db_with_cashu_mint(CurrencyUnit::Sat);
+        assert!(!db.get_cashu_dleq_audit(&mint_url).unwrap().has_failures());
+
+        db.record_cashu_dleq_failures(
+            &mint_url,
+            DleqAudit {
+                missing: 2,
+                invalid: 0,
+                unverified: 0,
+            },
+        )
+        .unwrap();
+        db.record_cashu_dleq_failures(
+            &mint_url,
+            DleqAudit {
+                missing: 1,
+                invalid: 3,
+                unverified: 1,
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            db.get_cashu_dleq_audit(&mint_url).unwrap(),
+            DleqAudit {
+                missing: 3,
+                invalid: 3,
+                unverified: 1,
+            }
+        );
+
+        // rejoining a mint keeps its history
+        db.insert_new_cashu_mint(mint_url.to_string(), CurrencyUnit::Usd)
+            .unwrap();
+        assert!(db.get_cashu_dleq_audit(&mint_url).unwrap().has_failures());
+    }
+
+    #[test]
+    fn test_cashu_needs_reconcile_db() {
+        let (db, mint_url) = setup_test_db_with_cashu_mint(CurrencyUnit::Sat);
+        assert!(!db.cashu_mint_needs_reconcile(&mint_url).unwrap());
+
+        db.set_cashu_mint_needs_reconcile(&mint_url, true).unwrap();
+        assert!(db.cashu_mint_needs_reconcile(&mint_url).unwrap());
+
+        db.set_cashu_mint_needs_reconcile(&mint_url, false).unwrap();
+        assert!(!db.cashu_mint_needs_reconcile(&mint_url).unwrap());
+
+        // mints we never joined have nothing to reconcile
+        let unknown = MintUrl::from_str("https://unknown.example.com").unwrap();
+        db.set_cashu_mint_needs_reconcile(&unknown, true).unwrap();
+        assert!(!db.cashu_mint_needs_reconcile(&unknown).unwrap());
+    }
+
+    #[test]
+    fn test_cashu_consolidation_db() {
+        let (db, mint_url) = setup_test_db_with_cashu_mint(CurrencyUnit::Sat);
+
+        let profile = db.get_profile().unwrap().unwrap();
+        assert!(profile.consolidation_enabled());
+        db.set_consolidation_enabled(false).unwrap();
+        let profile = db.get_profile().unwrap().unwrap();
+        assert!(!profile.consolidation_enabled());
+
+        db.create_cashu_consolidation(
+            uuid::Uuid::new_v4().to_string(),
+            &mint_url,
+            &CurrencyUnit::Sat,
+            1_000,
+            2,
+            40,
+            8,
+        )
+        .unwrap();
+
+        let history = db.get_transaction_history().unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].kind, TransactionItemKind::Consolidation);
+        assert_eq!(history[0].amount, 2);
+        assert_eq!(history[0].fee_msats, 2_000);
+        assert_eq!(
+            history[0].mint_identifier,
+            MintIdentifier::Cashu(mint_url, CurrencyUnit::Sat)
+        );
+    }
+
+    #[test]
+    fn test_cashu_quote_key_index_db() {
+        let (db, mint_url) = setup_test_db_with_cashu_mint(CurrencyUnit::Sat);
+
+        assert_eq!(db.next_cashu_quote_key_index().unwrap(), 0);
+        assert_eq!(db.next_cashu_quote_key_index().unwrap(), 1);
+
+        let invoice = Bolt11Invoice::from_str("lntbs10u1pny86cupp52lkv666juacc9evu0fpfmduac6l6qp0qypxr0yk9wfpze2u5sngshp57t8sp5tcchfv0y29yg46nqujktk2ufwcjcc7zvyd8rteadd7rjyscqzzsxqyz5vqsp5nnhtrhvyfh077g6rdfrs7ml9hqks4mj6f0e50nyeejc73ee7gl3q9qyyssq3urmp6hy3c95rtddevae0djrfn8au0rumgd05zvddzshg8krwupzc4htl38kqufp27el5ev5l8ea4736y3a3rpq5cewxwftsdk2v52cp9w25a0").unwrap();
+        let index = db.next_cashu_quote_key_index().unwrap();
+        db.create_ln_receive(
+            "quote-id".to_string(),
+            None,
+            Some(mint_url),
+            Some(CurrencyUnit::Sat),
+            invoice,
+            Amount::from_sats(1_000),
+            Amount::ZERO,
+            Some(index),
+        )
+        .unwrap();
+
+        // pending quotes keep their key index so they can be signed after a restart
+        let pending = db.get_pending_lightning_receives().unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].quote_key_index(), Some(2));
+
+        // a restored seed doesn't start over at the first index
+        let restored = setup_test_db();
+        restored
+            .generate_mnemonic(Some(Mnemonic::generate_in(Language::English, 12).unwrap()))
+            .unwrap();
+        let offset = restored.next_cashu_quote_key_index().unwrap();
+        assert_ne!(offset, 0);
+        assert!(offset < 1 << 30);
+        assert_eq!(restored.next_cashu_quote_key_index().unwrap(), offset + 1);
+    }
+
+    #[test]
+    fn test_payment_request_db() {
+        let (db, mint_url) = setup_test_db_with_cashu_mint(CurrencyUnit::Sat);
+        db.create_payment_request(
+            "request-id".to_string(),
+            &mint_url,
+            &CurrencyUnit::Sat,
+            Some(21),
+            Some("coffee".to_string()),
+            "creqA...".to_string(),
+        )
+        .unwrap();
+
+        let request = db.get_payment_request("request-id").unwrap().unwrap();
+        assert_eq!(request.mint_url(), mint_url);
+        assert_eq!(request.amount(), Some(21));
+        assert_eq!(request.status(), PaymentStatus::Pending);
+        assert_eq!(request.ecash_receive_id(), None);
+
+        db.create_ecash_receive(
+            "receive-id".to_string(),
+            None,
+            Some(mint_url.clone()),
+            Some(CurrencyUnit::Sat),
+            "cashuB...".to_string(),
+            Amount::from_sats(21),
+        )
+        .unwrap();
+        db.mark_payment_request_paid("request-id", "receive-id".to_string())
+            .unwrap();
+
+        let request = db.get_payment_request("request-id").unwrap().unwrap();
+        assert_eq!(request.status(), PaymentStatus::Success);
+        assert_eq!(request.ecash_receive_id(), Some("receive-id"));
+        assert!(db.get_payment_request("unknown").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_nostr_event_db() {
+        let db = setup_test_db_with_data();
+
+        assert!(!db.get_profile().unwrap().unwrap().nostr_enabled());
+        db.set_nostr_enabled(true).unwrap();
+        assert!(db.get_profile().unwrap().unwrap().nostr_enabled());
+
+        assert!(db.claim_nostr_event("event-id", "{}").unwrap());
+        // another relay delivering it while we redeem
+        assert!(!db.claim_nostr_event("event-id", "{}").unwrap());
+        assert!(db.claim_nostr_event("other-event-id", "{}").unwrap());
+
+        db.mark_nostr_event_as_redeemed("other-event-id").unwrap();
+        assert!(!db.claim_nostr_event("other-event-id", "{}").unwrap());
+
+        // a failed redeem waits for its retry
+        assert_eq!(db.mark_nostr_event_as_failed("event-id").unwrap(), 1);
+        assert!(!db.claim_nostr_event("event-id", "{}").unwrap());
+        assert!(db.get_retryable_nostr_events().unwrap().is_empty());
+
+        // stopped while redeeming
+        assert!(db.claim_nostr_event("third-event-id", "{}").unwrap());
+        db.release_unfinished_nostr_events().unwrap();
+        let retryable = db.get_retryable_nostr_events().unwrap();
+        assert_eq!(retryable.len(), 1);
+        assert_eq!(retryable[0].event_id, "third-event-id");
+        assert_eq!(retryable[0].event(), Some("{}"));
+        assert!(db.claim_nostr_event("third-event-id", "{}").unwrap());
+
+        for _ in 1..7 {
+            db.mark_nostr_event_as_failed("third-event-id").unwrap();
+        }
+        assert_eq!(db.mark_nostr_event_as_failed("third-event-id").unwrap(), 7);
+        assert_eq!(db.mark_nostr_event_as_failed("third-event-id").unwrap(), 8);
+        // given up
+        assert!(!db.claim_nostr_event("third-event-id", "{}").unwrap());
+    }
+
+    #[test]
+    fn test_cashu_mint_auth_db() {
+        let db = setup_test_db_with_data();
+
+        let mint_url = MintUrl::from_str("https://auth.mint.example.com").unwrap();
+        assert!(db.get_cashu_mint_auth(&mint_url).unwrap().is_none());
+
+        let settings = AuthSettings {
+            openid_discovery: "https://auth.example.com/.well-known/openid-configuration"
+                .to_string(),
+            client_id: "cashu-client".to_string(),
+            ..Default::default()
+        };
+        db.save_cashu_mint_auth(
+            &mint_url,
+            &settings,
+            "access".to_string(),
+            Some("refresh".to_string()),
+            Some(1_000),
+        )
+        .unwrap();
+
+        let auth = db.get_cashu_mint_auth(&mint_url).unwrap().unwrap();
+        assert_eq!(auth.settings().unwrap(), settings);
+        assert_eq!(auth.access_token, "access");
+        assert_eq!(auth.refresh_token.as_deref(), Some("refresh"));
+        assert_eq!(auth.expires_at(), Some(1_000));
+
+        db.update_cashu_mint_auth_tokens(&mint_url, "access2".to_string(), None, None)
+            .unwrap();
+        let auth = db.get_cashu_mint_auth(&mint_url).unwrap().unwrap();
+        assert_eq!(auth.access_token, "access2");
+        assert_eq!(auth.refresh_token, None);
+        assert_eq!(auth.expires_at(), None);
+
+        // blind auth tokens are handed out once each
+        db.add_cashu_blind_auth_tokens(&mint_url, vec!["authA1".to_string(), "authA2".to_string()])
+            .unwrap();
+        let mut taken = vec![
+            db.take_cashu_blind_auth_token(&mint_url).unwrap().unwrap(),
+            db.take_cashu_blind_auth_token(&mint_url).unwrap().unwrap(),
+        ];
+        taken.sort();
+        assert_eq!(taken, vec!["authA1".to_string(), "authA2".to_string()]);
+        assert!(db.take_cashu_blind_auth_token(&mint_url).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_multi_path_payment_db() {
+        let db = setup_test_db_with_data();
+
+        let mint_a = MintUrl::from_str("https://mint-a.example.com").unwrap();
+        let mint_b = MintUrl::from_str("https://mint-b.example.com").unwrap();
+        db.insert_new_cashu_mint(mint_a.to_string(), CurrencyUnit::Sat)
+            .unwrap();
+        db.insert_new_cashu_mint(mint_b.to_string(), CurrencyUnit::Sat)
+            .unwrap();
+
+        let invoice = Bolt11Invoice::from_str("lntbs10u1pny86cupp52lkv666juacc9evu0fpfmduac6l6qp0qypxr0yk9wfpze2u5sngshp57t8sp5tcchfv0y29yg46nqujktk2ufwcjcc7zvyd8rteadd7rjyscqzzsxqyz5vqsp5nnhtrhvyfh077g6rdfrs7ml9hqks4mj6f0e50nyeejc73ee7gl3q9qyyssq3urmp6hy3c95rtddevae0djrfn8au0rumgd05zvddzshg8krwupzc4htl38kqufp27el5ev5l8ea4736y3a3rpq5cewxwftsdk2v52cp9w25a0").unwrap();
+
+        let parent_id = uuid::Uuid::new_v4().to_string();
+        db.create_lightning_payment(
+            parent_id.clone(),
+            None,
+            Some(mint_a.clone()),
+            Some(CurrencyUnit::Sat),
+            invoice.clone(),
+            Amount::from_sats(1_000),
+            Amount::ZERO,
+        )
+        .unwrap();
+
+        let part_a = uuid::Uuid::new_v4().to_string();
+        let part_b = uuid::Uuid::new_v4().to_string();
+        db.create_lightning_payment_part(
+            part_a.clone(),
+            parent_id.clone(),
+            mint_a,
+            CurrencyUnit::Sat,
+            invoice.clone(),
+            Amount::from_sats(600),
+            Amount::from_sats(2),
+        )
+        .unwrap();
+        db.create_lightning_payment_part(
+            part_b.clone(),
+            parent_id.clone(),
+            mint_b,
+            CurrencyUnit::Sat,
+            invoice,
+            Amount::from_sats(400),
+            Amount::from_sats(2),
+        )
+        .unwrap();
+
+        // only the parts are resumed, the parent settles with them
+        let pending = db.get_pending_lightning_payments().unwrap();
+        assert_eq!(pending.len(), 2);
+        assert!(
+            pending
+                .iter()
+                .all(|p| p.parent_operation_id() == Some(parent_id.as_str()))
+        );
+
+        let preimage = [1; 32];
+        db.set_lightning_as_complete(part_a, preimage, Some(1_000))
+            .unwrap();
+        let parent = db
+            .get_lightning_payment(parent_id.clone())
+            .unwrap()
+            .unwrap();
+        assert_eq!(parent.status(), PaymentStatus::Pending);
+        assert!(db.get_transaction_history().unwrap().is_empty());
+
+        db.set_lightning_as_complete(part_b, preimage, Some(2_000))
+            .unwrap();
+        let parent = db.get_lightning_payment(parent_id).unwrap().unwrap();
+        assert_eq!(parent.status(), PaymentStatus::Success);
+        assert_eq!(parent.preimage(), Some(preimage));
+        assert_eq!(parent.fee(), Amount::from_sats(3));
+
+        // history shows a single send for the whole invoice
+        let history = db.get_transaction_history().unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].amount, 1_000);
+    }
+
+    #[test]
+    fn test_unconfirmed_ecash_send_db() {
+        let (db, mint_url) = setup_test_db_with_cashu_mint(CurrencyUnit::Sat);
+
+        let operation_id = OperationId::new_random().fmt_full().to_string();
+        db.create_unconfirmed_ecash_send(
+            operation_id.clone(),
+            mint_url.clone(),
+            CurrencyUnit::Sat,
+            Amount::from_sats(2),
+            Amount::ZERO,
+            None,
+        )
+        .unwrap();
+
+        // not shown or checked until we have the token
+        let send = db.get_ecash_send(operation_id.clone()).unwrap().unwrap();
+        assert_eq!(send.status(), PaymentStatus::WaitingConfirmation);
+        assert!(db.get_pending_ecash_sends().unwrap().is_empty());
+        assert!(db.get_transaction_history().unwrap().is_empty());
+
+        db.confirm_ecash_send(operation_id.clone(), "cashuBtoken".to_string())
+            .unwrap();
+        let send = db.get_ecash_send(operation_id).unwrap().unwrap();
+        assert_eq!(send.status(), PaymentStatus::Pending);
+        assert_eq!(send.token(), "cashuBtoken");
+        assert_eq!(send.mint_url(), Some(mint_url));
+        assert_eq!(db.get_pending_ecash_sends().unwrap().len(), 1);
+        assert_eq!(db.get_transaction_history().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_timelocked_ecash_send_db() {
+        let db = setup_test_db_with_data();
+        let pool = db.db.clone();
+        let mut conn = pool.get().unwrap();
+
+        let ours = cdk::nuts::SecretKey::generate().public_key();
+        let beneficiary = cdk::nuts::SecretKey::generate().public_key();
+        let locktime = 1_900_000_000;
+        let conditions = SpendingConditions::new_p2pk(
+            ours,
+            Some(cdk::nuts::Conditions {
+                locktime: Some(locktime),
+                refund_keys: Some(vec![beneficiary]),
+                ..Default::default()
+            }),
+        );
+
+        let operation_id = OperationId::new_random().fmt_full().to_string();
+        EcashSend::create(
+            &mut conn,
+            operation_id.clone(),
+            FederationId::from_str(FEDERATION_ID).ok(),
+            None,
+            None,
+            "cashuBtoken".to_string(),
+            Amount::from_sats(21),
+            Amount::ZERO,
+            Some(&conditions),
+        )
+        .unwrap();
+
+        let send = EcashSend::get_by_operation_id(&mut conn, operation_id.clone())
+            .unwrap()
+            .unwrap();
+        assert_eq!(send.spending_conditions(), Some(conditions));
+        assert_eq!(send.locktime(), Some(locktime));
+
+        assert!(!send.reminded());
+
+        let timelocked = db.get_pending_timelocked_ecash_sends().unwrap();
+        assert_eq!(timelocked.len(), 1);
+
+        // a reminder is only sent once, even across restarts
+        db.mark_ecash_send_as_reminded(operation_id.clone())
+            .unwrap();
+        assert!(db.get_pending_timelocked_ecash_sends().unwrap().is_empty());
+        let send = EcashSend::get_by_operation_id(&mut conn, operation_id.clone())
+            .unwrap()
+            .unwrap();
+        assert!(send.reminded());
+
+        // reclaimed tokens no longer need a reminder
+        let operation_id = OperationId::new_random().fmt_full().to_string();
+        EcashSend::create(
+            &mut conn,
+            operation_id.clone(),
+            FederationId::from_str(FEDERATION_ID).ok(),
+            None,
+            None,
+            "cashuBtoken2".to_string(),
+            Amount::from_sats(21),
+            Amount::ZERO,
+            Some(&conditions),
+        )
+        .unwrap();
+        assert_eq!(db.get_pending_timelocked_ecash_sends().unwrap().len(), 1);
+        EcashSend::mark_as_failed(&mut conn, operation_id).unwrap();
+        assert!(db.get_pending_timelocked_ecash_sends().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_cashu_unit_amounts_db() {
+        use crate::db_models::schema::{ecash_sends, lightning_payments};
+
+        let (db, mint_url) = setup_test_db_with_cashu_mint(CurrencyUnit::Usd);
+        let pool = db.db.clone();
+        let mut conn = pool.get().unwrap();
+
+        // 1.50 usd is stored as 150 cents, not as msats
+        let operation_id = OperationId::new_random().fmt_full().to_string();
+        db.create_ecash_send(
+            operation_id.clone(),
+            None,
+            Some(mint_url.clone()),
+            Some(CurrencyUnit::Usd),
+            "cashuBtoken".to_string(),
+            Amount::from_sats(150),
+            Amount::from_sats(2),
+            None,
+        )
+        .unwrap();
+        let columns = ecash_sends::table
+            .filter(ecash_sends::operation_id.eq(&operation_id))
+            .select((
+                ecash_sends::amount_msats,
+                ecash_sends::unit_amount,
+                ecash_sends::unit_fee,
+            ))
+            .first::<(i64, Option<i64>, Option<i64>)>(&mut conn)
+            .unwrap();
+        assert_eq!(columns, (0, Some(150), Some(2)));
+
+        db.mark_ecash_send_as_partially_reclaimed(operation_id.clone(), Amount::from_sats(100))
+            .unwrap();
+        let send = db.get_ecash_send(operation_id).unwrap().unwrap();
+        assert_eq!(send.amount(), Amount::from_sats(100));
+        assert_eq!(send.fee(), Amount::from_sats(2));
+
+        let history = db.get_transaction_history().unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].amount, 100);
+        assert_eq!(history[0].fee_msats, 2_000);
+        assert_eq!(history[0].unit, CurrencyUnit::Usd);
+
+        // the fee paid is stored in cents too
+        let operation_id = OperationId::new_random().fmt_full().to_string();
+        let invoice = Bolt11Invoice::from_str("lntbs10u1pny86cupp52lkv666juacc9evu0fpfmduac6l6qp0qypxr0yk9wfpze2u5sngshp57t8sp5tcchfv0y29yg46nqujktk2ufwcjcc7zvyd8rteadd7rjyscqzzsxqyz5vqsp5nnhtrhvyfh077g6rdfrs7ml9hqks4mj6f0e50nyeejc73ee7gl3q9qyyssq3urmp6hy3c95rtddevae0djrfn8au0rumgd05zvddzshg8krwupzc4htl38kqufp27el5ev5l8ea4736y3a3rpq5cewxwftsdk2v52cp9w25a0").unwrap();
+        db.create_lightning_payment(
+            operation_id.clone(),
+            None,
+            Some(mint_url),
+            Some(CurrencyUnit::Usd),
+            invoice,
+            Amount::from_sats(60),
+            Amount::from_sats(3),
+        )
+        .unwrap();
+        db.set_lightning_as_complete(
+            operation_id.clone(),
+            [1; 32],
+            Some(Amount::from_sats(1).msats),
+        )
+        .unwrap();
+        let columns = lightning_payments::table
+            .filter(lightning_payments::operation_id.eq(&operation_id))
+            .select((
+                lightning_payments::fee_msats,
+                lightning_payments::unit_amount,
+                lightning_payments::unit_fee,
+            ))
+            .first::<(i64, Option<i64>, Option<i64>)>(&mut conn)
+            .unwrap();
+        assert_eq!(columns, (0, Some(60), Some(1)));
+        let payment = db.get_lightning_payment(operation_id).unwrap().unwrap();
+        assert_eq!(payment.amount(), Amount::from_sats(60));
+        assert_eq!(payment.fee(), Amount::from_sats(1));
+    }
 }
@@ -0,0 +1,121 @@
+use crate::MintIdentifier;
+use crate::db_models::PaymentStatus;
+use crate::db_models::schema::cashu_consolidations;
+use crate::db_models::transaction_item::{
+    TransactionDirection, TransactionItem, TransactionItemKind,
+};
+use cdk::mint_url::MintUrl;
+use cdk::nuts::CurrencyUnit;
+use diesel::prelude::*;
+use fedimint_core::Amount;
+use std::str::FromStr;
+
+/// A swap that merged a mint's fragmented proofs into an optimal denomination split
+#[derive(QueryableByName, Queryable, Debug, Clone, PartialEq, Eq)]
+#[diesel(table_name = cashu_consolidations)]
+pub struct CashuConsolidation {
+    pub id: String,
+    cashu_mint_url: String,
+    cashu_unit: String,
+    amount: i64,
+    fee: i64,
+    proofs_before: i32,
+    proofs_after: i32,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+#[derive(Insertable, Clone)]
+#[diesel(table_name = cashu_consolidations)]
+struct NewCashuConsolidation {
+    id: String,
+    cashu_mint_url: String,
+    cashu_unit: String,
+    amount: i64,
+    fee: i64,
+    proofs_before: i32,
+    proofs_after: i32,
+}
+
+impl CashuConsolidation {
+    pub fn mint_url(&self) -> anyhow::Result<MintUrl> {
+        Ok(MintUrl::from_str(&self.cashu_mint_url)?)
+    }
+
+    pub fn unit(&self) -> anyhow::Result<CurrencyUnit> {
+        Ok(CurrencyUnit::from_str(&self.cashu_unit)?)
+    }
+
+    /// In the base amount of the unit
+    pub fn amount(&self) -> u64 {
+        self.amount as u64
+    }
+
+    /// In the base amount of the unit
+    pub fn fee(&self) -> u64 {
+        self.fee as u64
+    }
+
+    pub fn proofs_before(&self) -> u64 {
+        self.proofs_before as u64
+    }
+
+    pub fn proofs_after(&self) -> u64 {
+        self.proofs_after as u64
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn create(
+        conn: &mut SqliteConnection,
+        id: String,
+        mint_url: &MintUrl,
+        unit: &CurrencyUnit,
+        amount: u64,
+        fee: u64,
+        proofs_before: u64,
+        proofs_after: u64,
+    ) -> anyhow::Result<()> {
+        let new = NewCashuConsolidation {
+            id,
+            cashu_mint_url: mint_url.to_string(),
+            cashu_unit: unit.to_string(),
+            amount: amount as i64,
+            fee: fee as i64,
+            proofs_before: proofs_before as i32,
+            proofs_after: proofs_after as i32,
+        };
+
+        diesel::insert_into(cashu_consolidations::table)
+            .values(new)
+            .execute(conn)?;
+
+        Ok(())
+    }
+
+    pub fn get_history(conn: &mut SqliteConnection) -> anyhow::Result<Vec<Self>> {
+        Ok(cashu_consolidations::table.load::<Self>(conn)?)
+    }
+}
+
+/// The consolidated proofs stay in the wallet, so the item only shows the fee that left it
+impl TryFrom<CashuConsolidation> for TransactionItem {
+    type Error = anyhow::Error;
+
+    fn try_from(consolidation: CashuConsolidation) -> anyhow::Result<Self> {
+        let mint_url = consolidation.mint_url()?;
+        let unit = consolidation.unit()?;
+        Ok(Self {
+            kind: TransactionItemKind::Consolidation,
+            amount: consolidation.fee(),
+            unit: unit.clone(),
+            fee_msats: Amount::from_sats(consolidation.fee()).msats,
+            txid: None,
+            preimage: None,
+            token: None,
+            reclaimable: false,
+            direction: TransactionDirection::Outgoing,
+            mint_identifier: MintIdentifier::Cashu(mint_url, unit),
+            status: PaymentStatus::Success,
+            timestamp: consolidation.created_at.and_utc().timestamp() as u64,
+        })
+    }
+}
@@ -1,4 +1,4 @@
-use crate::db_models::schema::cashu_mint;
+use crate::db_models::schema::{cashu_mint, cashu_mint_units};
 use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
 
@@ -17,6 +17,34 @@ use serde::{Deserialize, Serialize};
 pub struct CashuMint {
     pub mint_url: String,
     pub active: i32,
+    pub dleq_missing: i64,
+    pub dleq_invalid: i64,
+    pub dleq_unverified: i64,
+    pub needs_reconcile: i32,
+}
+
+/// How many signatures from a mint we couldn't verify with a DLEQ proof (NUT-12)
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DleqAudit {
+    pub missing: u64,
+    pub invalid: u64,
+    /// We couldn't get the keys the mint signed with to check the proof
+    pub unverified: u64,
+}
+
+impl DleqAudit {
+    pub const fn has_failures(&self) -> bool {
+        self.missing > 0 || self.invalid > 0 || self.unverified > 0
+    }
+}
+
+/// A currency unit we hold a wallet for at a cashu mint
+#[derive(Insertable, QueryableByName, Queryable, Debug, Clone, PartialEq)]
+#[diesel(table_name = cashu_mint_units)]
+pub struct CashuMintUnit {
+    pub mint_url: String,
+    pub unit: String,
+    pub active: i32,
 }
 
 impl CashuMint {
@@ -27,12 +55,17 @@ impl CashuMint {
             .optional()?)
     }
 
-    pub fn remove_mint(conn: &mut SqliteConnection, url: String) -> anyhow::Result<()> {
-        // First check if the federation exists and is active
-        let exists = cashu_mint::table
-            .filter(cashu_mint::mint_url.eq(&url))
-            .filter(cashu_mint::active.eq(1))
-            .first::<Self>(conn)
+    pub fn remove_mint(
+        conn: &mut SqliteConnection,
+        url: String,
+        unit: String,
+    ) -> anyhow::Result<()> {
+        // First check if the unit exists and is active
+        let exists = cashu_mint_units::table
+            .filter(cashu_mint_units::mint_url.eq(&url))
+            .filter(cashu_mint_units::unit.eq(&unit))
+            .filter(cashu_mint_units::active.eq(1))
+            .first::<CashuMintUnit>(conn)
             .optional()?
             .is_some();
 
@@ -40,56 +73,79 @@ impl CashuMint {
             return Err(anyhow::anyhow!("Mint not found or already inactive"));
         }
 
-        // Mark the federation as inactive
-        diesel::update(cashu_mint::table)
-            .filter(cashu_mint::mint_url.eq(&url))
-            .set(cashu_mint::active.eq(0))
+        // Mark the unit as inactive
+        diesel::update(cashu_mint_units::table)
+            .filter(cashu_mint_units::mint_url.eq(&url))
+            .filter(cashu_mint_units::unit.eq(&unit))
+            .set(cashu_mint_units::active.eq(0))
             .execute(conn)?;
 
+        // The mint itself is only inactive once none of its units are left
+        let remaining = cashu_mint_units::table
+            .filter(cashu_mint_units::mint_url.eq(&url))
+            .filter(cashu_mint_units::active.eq(1))
+            .count()
+            .get_result::<i64>(conn)?;
+        if remaining == 0 {
+            diesel::update(cashu_mint::table)
+                .filter(cashu_mint::mint_url.eq(&url))
+                .set(cashu_mint::active.eq(0))
+                .execute(conn)?;
+        }
+
         Ok(())
     }
 
-    pub fn set_active(conn: &mut SqliteConnection, url: &String) -> anyhow::Result<()> {
+    pub fn set_active(
+        conn: &mut SqliteConnection,
+        url: &String,
+        unit: &String,
+    ) -> anyhow::Result<()> {
         diesel::update(cashu_mint::table)
             .filter(cashu_mint::mint_url.eq(url))
             .set(cashu_mint::active.eq(1))
             .execute(conn)?;
+        diesel::update(cashu_mint_units::table)
+            .filter(cashu_mint_units::mint_url.eq(url))
+            .filter(cashu_mint_units::unit.eq(unit))
+            .set(cashu_mint_units::active.eq(1))
+            .execute(conn)?;
         Ok(())
     }
 
-    pub fn get_mints(conn: &mut SqliteConnection) -> anyhow::Result<Vec<String>> {
-        Ok(cashu_mint::table
-            .filter(cashu_mint::active.eq(1))
-            .load::<Self>(conn)?
+    /// Returns the (mint url, unit) pairs we have active wallets for
+    pub fn get_mints(conn: &mut SqliteConnection) -> anyhow::Result<Vec<(String, String)>> {
+        Ok(cashu_mint_units::table
+            .filter(cashu_mint_units::active.eq(1))
+            .load::<CashuMintUnit>(conn)?
             .into_iter()
-            .map(|f| f.mint_url)
+            .map(|m| (m.mint_url, m.unit))
             .collect())
     }
 
-    pub fn get_archived_mints(conn: &mut SqliteConnection) -> anyhow::Result<Vec<String>> {
-        Ok(cashu_mint::table
-            .filter(cashu_mint::active.eq(0))
-            .load::<Self>(conn)?
+    pub fn get_archived_mints(
+        conn: &mut SqliteConnection,
+    ) -> anyhow::Result<Vec<(String, String)>> {
+        Ok(cashu_mint_units::table
+            .filter(cashu_mint_units::active.eq(0))
+            .load::<CashuMintUnit>(conn)?
             .into_iter()
-            .map(|f| f.mint_url)
+            .map(|m| (m.mint_url, m.unit))
             .collect())
     }
 
-    pub fn insert(conn: &mut SqliteConnection, mint_url: String) -> anyhow::Result<()> {
-        // First check if the federation exists and is active
-        let exists = cashu_mint::table
-            .filter(cashu_mint::mint_url.eq(&mint_url))
-            .first::<Self>(conn)
-            .optional()?
-            .is_some();
-
-        if exists {
-            Self::set_active(conn, &mint_url)?;
-        }
-
+    pub fn insert(
+        conn: &mut SqliteConnection,
+        mint_url: String,
+        unit: String,
+    ) -> anyhow::Result<()> {
         let mint = Self {
-            mint_url,
+            mint_url: mint_url.clone(),
             active: 1,
+            dleq_missing: 0,
+            dleq_invalid: 0,
+            dleq_unverified: 0,
+            needs_reconcile: 0,
         };
 
         diesel::insert_into(cashu_mint::table)
@@ -97,6 +153,61 @@ impl CashuMint {
             .on_conflict_do_nothing()
             .execute(conn)?;
 
+        // reactivates the mint and unit if we had them before
+        Self::set_active(conn, &mint_url, &unit)?;
+
+        let mint_unit = CashuMintUnit {
+            mint_url,
+            unit,
+            active: 1,
+        };
+
+        diesel::insert_into(cashu_mint_units::table)
+            .values(mint_unit)
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+
+        Ok(())
+    }
+
+    pub fn dleq_audit(&self) -> DleqAudit {
+        DleqAudit {
+            missing: self.dleq_missing as u64,
+            invalid: self.dleq_invalid as u64,
+            unverified: self.dleq_unverified as u64,
+        }
+    }
+
+    pub fn record_dleq_failures(
+        conn: &mut SqliteConnection,
+        url: &str,
+        failures: DleqAudit,
+    ) -> anyhow::Result<()> {
+        diesel::update(cashu_mint::table)
+            .filter(cashu_mint::mint_url.eq(url))
+            .set((
+                cashu_mint::dleq_missing.eq(cashu_mint::dleq_missing + failures.missing as i64),
+                cashu_mint::dleq_invalid.eq(cashu_mint::dleq_invalid + failures.invalid as i64),
+                cashu_mint::dleq_unverified
+                    .eq(cashu_mint::dleq_unverified + failures.unverified as i64),
+            ))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    pub fn needs_reconcile(&self) -> bool {
+        self.needs_reconcile == 1
+    }
+
+    pub fn set_needs_reconcile(
+        conn: &mut SqliteConnection,
+        url: &str,
+        needs_reconcile: bool,
+    ) -> anyhow::Result<()> {
+        diesel::update(cashu_mint::table)
+            .filter(cashu_mint::mint_url.eq(url))
+            .set(cashu_mint::needs_reconcile.eq(i32::from(needs_reconcile)))
+            .execute(conn)?;
         Ok(())
     }
 }
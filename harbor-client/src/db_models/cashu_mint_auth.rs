@@ -0,0 +1,132 @@
+use crate::cashu_auth::AuthSettings;
+use crate::db_models::schema::{cashu_blind_auth_tokens, cashu_mint_auth};
+use anyhow::anyhow;
+use diesel::prelude::*;
+
+/// Our login at a cashu mint that requires auth (NUT-21/22)
+#[derive(Insertable, QueryableByName, Queryable, AsChangeset, Debug, Clone, PartialEq, Eq)]
+#[diesel(table_name = cashu_mint_auth)]
+pub struct CashuMintAuth {
+    pub mint_url: String,
+    settings: String,
+    pub access_token: String,
+    pub refresh_token: Option<String>,
+    expires_at: Option<i64>,
+}
+
+#[derive(Insertable, Clone)]
+#[diesel(table_name = cashu_blind_auth_tokens)]
+struct NewBlindAuthToken {
+    token: String,
+    mint_url: String,
+}
+
+impl CashuMintAuth {
+    pub fn settings(&self) -> anyhow::Result<AuthSettings> {
+        serde_json::from_str(&self.settings)
+            .map_err(|e| anyhow!("Invalid auth settings for {}: {e}", self.mint_url))
+    }
+
+    /// Unix time the access token expires at, if the provider told us
+    pub fn expires_at(&self) -> Option<u64> {
+        self.expires_at.map(|t| t as u64)
+    }
+
+    pub fn get(conn: &mut SqliteConnection, url: &str) -> anyhow::Result<Option<Self>> {
+        Ok(cashu_mint_auth::table
+            .filter(cashu_mint_auth::mint_url.eq(url))
+            .first::<Self>(conn)
+            .optional()?)
+    }
+
+    /// Saves a fresh login, replacing any previous one for the mint
+    pub fn upsert(
+        conn: &mut SqliteConnection,
+        url: &str,
+        settings: &AuthSettings,
+        access_token: String,
+        refresh_token: Option<String>,
+        expires_at: Option<u64>,
+    ) -> anyhow::Result<()> {
+        let auth = Self {
+            mint_url: url.to_string(),
+            settings: serde_json::to_string(settings)?,
+            access_token,
+            refresh_token,
+            expires_at: expires_at.map(|t| t as i64),
+        };
+
+        diesel::insert_into(cashu_mint_auth::table)
+            .values(&auth)
+            .on_conflict(cashu_mint_auth::mint_url)
+            .do_update()
+            .set(&auth)
+            .execute(conn)?;
+
+        Ok(())
+    }
+
+    pub fn set_tokens(
+        conn: &mut SqliteConnection,
+        url: &str,
+        access_token: String,
+        refresh_token: Option<String>,
+        expires_at: Option<u64>,
+    ) -> anyhow::Result<()> {
+        diesel::update(cashu_mint_auth::table)
+            .filter(cashu_mint_auth::mint_url.eq(url))
+            .set((
+                cashu_mint_auth::access_token.eq(access_token),
+                cashu_mint_auth::refresh_token.eq(refresh_token),
+                cashu_mint_auth::expires_at.eq(expires_at.map(|t| t as i64)),
+            ))
+            .execute(conn)?;
+
+        Ok(())
+    }
+
+    pub fn add_blind_tokens(
+        conn: &mut SqliteConnection,
+        url: &str,
+        tokens: Vec<String>,
+    ) -> anyhow::Result<()> {
+        let new = tokens
+            .into_iter()
+            .map(|token| NewBlindAuthToken {
+                token,
+                mint_url: url.to_string(),
+            })
+            .collect::<Vec<_>>();
+
+        diesel::insert_into(cashu_blind_auth_tokens::table)
+            .values(new)
+            .execute(conn)?;
+
+        Ok(())
+    }
+
+    /// Removes and returns the oldest blind auth token we have for the mint,
+    /// a token is spent as soon as the mint sees it so it is never handed out twice
+    pub fn take_blind_token(
+        conn: &mut SqliteConnection,
+        url: &str,
+    ) -> anyhow::Result<Option<String>> {
+        conn.transaction(|conn| {
+            let token = cashu_blind_auth_tokens::table
+                .filter(cashu_blind_auth_tokens::mint_url.eq(url))
+                .order(cashu_blind_auth_tokens::created_at.asc())
+                .select(cashu_blind_auth_tokens::token)
+                .first::<String>(conn)
+                .optional()?;
+
+            if let Some(token) = &token {
+                diesel::delete(
+                    cashu_blind_auth_tokens::table.filter(cashu_blind_auth_tokens::token.eq(token)),
+                )
+                .execute(conn)?;
+            }
+
+            Ok(token)
+        })
+    }
+}
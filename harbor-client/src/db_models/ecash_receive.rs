@@ -0,0 +1,278 @@
+use crate::MintIdentifier;
+use crate::db_models::schema::ecash_receives;
+use crate::db_models::transaction_item::{
+    TransactionDirection, TransactionItem, TransactionItemKind,
+};
+use crate::db_models::{PaymentStatus, amount_columns, column_amount};
+use cdk::mint_url::MintUrl;
+use cdk::nuts::CurrencyUnit;
+use diesel::prelude::*;
+use fedimint_core::Amount;
+use fedimint_core::config::FederationId;
+use std::str::FromStr;
+
+#[derive(QueryableByName, Queryable, Debug, Clone, PartialEq, Eq)]
+#[diesel(table_name = ecash_receives)]
+pub struct EcashReceive {
+    pub operation_id: String,
+    fedimint_id: Option<String>,
+    cashu_mint_url: Option<String>,
+    token: String,
+    amount_msats: i64,
+    fee_msats: i64,
+    status: i32,
+    pub created_at: chrono::NaiveDateTime,
+    pub updated_at: chrono::NaiveDateTime,
+    swap_mint_url: Option<String>,
+    swap_payment_hash: Option<String>,
+    swap_claimed: i32,
+    swap_quote_id: Option<String>,
+    cashu_unit: Option<String>,
+    unit_amount: Option<i64>,
+    unit_fee: Option<i64>,
+}
+
+#[derive(Insertable, Clone)]
+#[diesel(table_name = ecash_receives)]
+struct NewEcashReceive {
+    operation_id: String,
+    fedimint_id: Option<String>,
+    cashu_mint_url: Option<String>,
+    token: String,
+    amount_msats: i64,
+    fee_msats: i64,
+    status: i32,
+    swap_mint_url: Option<String>,
+    swap_payment_hash: Option<String>,
+    cashu_unit: Option<String>,
+    unit_amount: Option<i64>,
+    unit_fee: Option<i64>,
+}
+
+impl EcashReceive {
+    pub fn fedimint_id(&self) -> Option<FederationId> {
+        self.fedimint_id
+            .as_ref()
+            .map(|f| FederationId::from_str(f).expect("invalid fedimint_id"))
+    }
+
+    pub fn mint_url(&self) -> Option<MintUrl> {
+        self.cashu_mint_url
+            .as_ref()
+            .map(|url| MintUrl::from_str(url).expect("invalid mint url"))
+    }
+
+    pub fn mint_identifier(&self) -> MintIdentifier {
+        match self.fedimint_id() {
+            Some(f) => MintIdentifier::Fedimint(f),
+            None => MintIdentifier::Cashu(self.mint_url().expect("missing mint url"), self.unit()),
+        }
+    }
+
+    /// Rows from before we supported other units are all in sats
+    pub fn unit(&self) -> CurrencyUnit {
+        self.cashu_unit.as_ref().map_or(CurrencyUnit::Sat, |u| {
+            CurrencyUnit::from_str(u).expect("invalid unit")
+        })
+    }
+
+    /// The mint that issued the token when it was swapped into one of ours
+    pub fn swap_mint_url(&self) -> Option<MintUrl> {
+        self.swap_mint_url
+            .as_ref()
+            .map(|url| MintUrl::from_str(url).expect("invalid mint url"))
+    }
+
+    pub fn swap_payment_hash(&self) -> Option<&str> {
+        self.swap_payment_hash.as_deref()
+    }
+
+    /// Whether the swapped token was claimed at its mint
+    pub fn swap_claimed(&self) -> bool {
+        self.swap_claimed == 1
+    }
+
+    /// The melt quote at the token's mint paying our invoice
+    pub fn swap_quote_id(&self) -> Option<&str> {
+        self.swap_quote_id.as_deref()
+    }
+
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+
+    pub fn amount(&self) -> Amount {
+        column_amount(self.amount_msats, self.unit_amount)
+    }
+
+    pub fn fee(&self) -> Amount {
+        column_amount(self.fee_msats, self.unit_fee)
+    }
+
+    pub fn status(&self) -> PaymentStatus {
+        PaymentStatus::from_i32(self.status)
+    }
+
+    pub fn create(
+        conn: &mut SqliteConnection,
+        operation_id: String,
+        fedimint_id: Option<FederationId>,
+        cashu_mint_url: Option<MintUrl>,
+        cashu_unit: Option<CurrencyUnit>,
+        token: String,
+        amount: Amount,
+    ) -> anyhow::Result<()> {
+        let (amount_msats, unit_amount) = amount_columns(amount, cashu_unit.as_ref());
+        let new = NewEcashReceive {
+            operation_id,
+            fedimint_id: fedimint_id.map(|f| f.to_string()),
+            cashu_mint_url: cashu_mint_url.map(|f| f.to_string()),
+            cashu_unit: cashu_unit.as_ref().map(ToString::to_string),
+            token,
+            amount_msats,
+            fee_msats: 0,
+            status: PaymentStatus::Pending as i32,
+            swap_mint_url: None,
+            swap_payment_hash: None,
+            unit_amount,
+            unit_fee: unit_amount.map(|_| 0),
+        };
+
+        diesel::insert_into(ecash_receives::table)
+            .values(new)
+            .execute(conn)?;
+
+        Ok(())
+    }
+
+    /// Records a token from `swap_mint_url` about to be melted into one of our
+    /// mints, the invoice it pays is set once we have it
+    pub fn create_swap(
+        conn: &mut SqliteConnection,
+        operation_id: String,
+        fedimint_id: Option<FederationId>,
+        cashu_mint_url: Option<MintUrl>,
+        cashu_unit: Option<CurrencyUnit>,
+        swap_mint_url: MintUrl,
+        token: String,
+        amount: Amount,
+    ) -> anyhow::Result<()> {
+        let (amount_msats, unit_amount) = amount_columns(amount, cashu_unit.as_ref());
+        let new = NewEcashReceive {
+            operation_id,
+            fedimint_id: fedimint_id.map(|f| f.to_string()),
+            cashu_mint_url: cashu_mint_url.map(|f| f.to_string()),
+            cashu_unit: cashu_unit.as_ref().map(ToString::to_string),
+            token,
+            amount_msats,
+            fee_msats: 0,
+            status: PaymentStatus::Pending as i32,
+            swap_mint_url: Some(swap_mint_url.to_string()),
+            swap_payment_hash: None,
+            unit_amount,
+            unit_fee: unit_amount.map(|_| 0),
+        };
+
+        diesel::insert_into(ecash_receives::table)
+            .values(new)
+            .execute(conn)?;
+
+        Ok(())
+    }
+
+    pub fn get_by_operation_id(
+        conn: &mut SqliteConnection,
+        operation_id: String,
+    ) -> anyhow::Result<Option<Self>> {
+        Ok(ecash_receives::table
+            .filter(ecash_receives::operation_id.eq(operation_id))
+            .first::<Self>(conn)
+            .optional()?)
+    }
+
+    pub fn mark_swap_as_claimed(
+        conn: &mut SqliteConnection,
+        operation_id: String,
+    ) -> anyhow::Result<()> {
+        diesel::update(ecash_receives::table.filter(ecash_receives::operation_id.eq(operation_id)))
+            .set(ecash_receives::swap_claimed.eq(1))
+            .execute(conn)?;
+
+        Ok(())
+    }
+
+    /// Records the invoice we're about to pay from the token's mint and the
+    /// melt quote paying it, before we melt
+    pub fn set_swap_melt_quote(
+        conn: &mut SqliteConnection,
+        operation_id: String,
+        swap_payment_hash: String,
+        swap_quote_id: String,
+    ) -> anyhow::Result<()> {
+        diesel::update(ecash_receives::table.filter(ecash_receives::operation_id.eq(operation_id)))
+            .set((
+                ecash_receives::swap_payment_hash.eq(Some(swap_payment_hash)),
+                ecash_receives::swap_quote_id.eq(Some(swap_quote_id)),
+            ))
+            .execute(conn)?;
+
+        Ok(())
+    }
+
+    pub fn mark_as_success(
+        conn: &mut SqliteConnection,
+        operation_id: String,
+        fee: Amount,
+    ) -> anyhow::Result<()> {
+        let unit = Self::get_by_operation_id(conn, operation_id.clone())?.map(|r| r.unit());
+        let (fee_msats, unit_fee) = amount_columns(fee, unit.as_ref());
+        diesel::update(ecash_receives::table.filter(ecash_receives::operation_id.eq(operation_id)))
+            .set((
+                ecash_receives::fee_msats.eq(fee_msats),
+                ecash_receives::unit_fee.eq(unit_fee),
+                ecash_receives::status.eq(PaymentStatus::Success as i32),
+            ))
+            .execute(conn)?;
+
+        Ok(())
+    }
+
+    pub fn mark_as_failed(conn: &mut SqliteConnection, operation_id: String) -> anyhow::Result<()> {
+        diesel::update(ecash_receives::table.filter(ecash_receives::operation_id.eq(operation_id)))
+            .set(ecash_receives::status.eq(PaymentStatus::Failed as i32))
+            .execute(conn)?;
+
+        Ok(())
+    }
+
+    pub fn get_pending(conn: &mut SqliteConnection) -> anyhow::Result<Vec<Self>> {
+        Ok(ecash_receives::table
+            .filter(ecash_receives::status.eq(PaymentStatus::Pending as i32))
+            .load::<Self>(conn)?)
+    }
+
+    pub fn get_history(conn: &mut SqliteConnection) -> anyhow::Result<Vec<Self>> {
+        Ok(ecash_receives::table
+            .filter(ecash_receives::status.eq(PaymentStatus::Success as i32))
+            .load::<Self>(conn)?)
+    }
+}
+
+impl From<EcashReceive> for TransactionItem {
+    fn from(receive: EcashReceive) -> Self {
+        Self {
+            kind: TransactionItemKind::Ecash,
+            amount: receive.amount().sats_round_down(),
+            unit: receive.unit(),
+            fee_msats: receive.fee().msats,
+            txid: None,
+            preimage: None,
+            token: None,
+            reclaimable: false,
+            direction: TransactionDirection::Incoming,
+            mint_identifier: receive.mint_identifier(),
+            status: receive.status(),
+            timestamp: receive.created_at.and_utc().timestamp() as u64,
+        }
+    }
+}
@@ -0,0 +1,341 @@
+use crate::MintIdentifier;
+use crate::db_models::schema::ecash_sends;
+use crate::db_models::transaction_item::{
+    TransactionDirection, TransactionItem, TransactionItemKind,
+};
+use crate::db_models::{PaymentStatus, amount_columns, column_amount};
+use cdk::mint_url::MintUrl;
+use cdk::nuts::CurrencyUnit;
+use cdk::nuts::SpendingConditions;
+use diesel::prelude::*;
+use fedimint_core::Amount;
+use fedimint_core::config::FederationId;
+use std::str::FromStr;
+
+#[derive(QueryableByName, Queryable, Debug, Clone, PartialEq, Eq)]
+#[diesel(table_name = ecash_sends)]
+pub struct EcashSend {
+    pub operation_id: String,
+    fedimint_id: Option<String>,
+    cashu_mint_url: Option<String>,
+    token: String,
+    amount_msats: i64,
+    fee_msats: i64,
+    status: i32,
+    pub created_at: chrono::NaiveDateTime,
+    pub updated_at: chrono::NaiveDateTime,
+    spending_conditions: Option<String>,
+    locktime: Option<i64>,
+    reminded: i32,
+    cashu_unit: Option<String>,
+    unit_amount: Option<i64>,
+    unit_fee: Option<i64>,
+}
+
+#[derive(Insertable, Clone)]
+#[diesel(table_name = ecash_sends)]
+struct NewEcashSend {
+    operation_id: String,
+    fedimint_id: Option<String>,
+    cashu_mint_url: Option<String>,
+    token: String,
+    amount_msats: i64,
+    fee_msats: i64,
+    status: i32,
+    spending_conditions: Option<String>,
+    locktime: Option<i64>,
+    cashu_unit: Option<String>,
+    unit_amount: Option<i64>,
+    unit_fee: Option<i64>,
+}
+
+impl EcashSend {
+    pub fn fedimint_id(&self) -> Option<FederationId> {
+        self.fedimint_id
+            .as_ref()
+            .map(|f| FederationId::from_str(f).expect("invalid fedimint_id"))
+    }
+
+    pub fn mint_url(&self) -> Option<MintUrl> {
+        self.cashu_mint_url
+            .as_ref()
+            .map(|url| MintUrl::from_str(url).expect("invalid mint url"))
+    }
+
+    pub fn mint_identifier(&self) -> MintIdentifier {
+        match self.fedimint_id() {
+            Some(f) => MintIdentifier::Fedimint(f),
+            None => MintIdentifier::Cashu(self.mint_url().expect("missing mint url"), self.unit()),
+        }
+    }
+
+    /// Rows from before we supported other units are all in sats
+    pub fn unit(&self) -> CurrencyUnit {
+        self.cashu_unit.as_ref().map_or(CurrencyUnit::Sat, |u| {
+            CurrencyUnit::from_str(u).expect("invalid unit")
+        })
+    }
+
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+
+    pub fn amount(&self) -> Amount {
+        column_amount(self.amount_msats, self.unit_amount)
+    }
+
+    pub fn fee(&self) -> Amount {
+        column_amount(self.fee_msats, self.unit_fee)
+    }
+
+    pub fn status(&self) -> PaymentStatus {
+        PaymentStatus::from_i32(self.status)
+    }
+
+    pub fn spending_conditions(&self) -> Option<SpendingConditions> {
+        self.spending_conditions
+            .as_ref()
+            .map(|c| serde_json::from_str(c).expect("invalid spending conditions"))
+    }
+
+    /// Unix timestamp after which the refund keys can spend the token
+    pub fn locktime(&self) -> Option<u64> {
+        self.locktime.map(|l| l as u64)
+    }
+
+    /// Whether we already reminded the user that the locktime is about to pass
+    pub fn reminded(&self) -> bool {
+        self.reminded != 0
+    }
+
+    /// Whether we can take the token back while it is unclaimed. Timelocked
+    /// tokens are locked to our own key, but a token locked to someone else's
+    /// key has no way back to us.
+    pub fn reclaimable(&self) -> bool {
+        self.spending_conditions.is_none() || self.locktime.is_some()
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn create(
+        conn: &mut SqliteConnection,
+        operation_id: String,
+        fedimint_id: Option<FederationId>,
+        cashu_mint_url: Option<MintUrl>,
+        cashu_unit: Option<CurrencyUnit>,
+        token: String,
+        amount: Amount,
+        fee: Amount,
+        spending_conditions: Option<&SpendingConditions>,
+    ) -> anyhow::Result<()> {
+        Self::insert(
+            conn,
+            operation_id,
+            fedimint_id,
+            cashu_mint_url,
+            cashu_unit,
+            token,
+            amount,
+            fee,
+            spending_conditions,
+            PaymentStatus::Pending,
+        )
+    }
+
+    /// Records a cashu send before its proofs are spent into a token, so the
+    /// send isn't lost if we stop before the token is saved. The token is set
+    /// with [`Self::confirm`].
+    pub fn create_unconfirmed(
+        conn: &mut SqliteConnection,
+        operation_id: String,
+        cashu_mint_url: MintUrl,
+        cashu_unit: CurrencyUnit,
+        amount: Amount,
+        fee: Amount,
+        spending_conditions: Option<&SpendingConditions>,
+    ) -> anyhow::Result<()> {
+        Self::insert(
+            conn,
+            operation_id,
+            None,
+            Some(cashu_mint_url),
+            Some(cashu_unit),
+            String::new(),
+            amount,
+            fee,
+            spending_conditions,
+            PaymentStatus::WaitingConfirmation,
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn insert(
+        conn: &mut SqliteConnection,
+        operation_id: String,
+        fedimint_id: Option<FederationId>,
+        cashu_mint_url: Option<MintUrl>,
+        cashu_unit: Option<CurrencyUnit>,
+        token: String,
+        amount: Amount,
+        fee: Amount,
+        spending_conditions: Option<&SpendingConditions>,
+        status: PaymentStatus,
+    ) -> anyhow::Result<()> {
+        let locktime = match spending_conditions {
+            Some(
+                SpendingConditions::P2PKConditions { conditions, .. }
+                | SpendingConditions::HTLCConditions { conditions, .. },
+            ) => conditions.as_ref().and_then(|c| c.locktime),
+            None => None,
+        };
+
+        let (amount_msats, unit_amount) = amount_columns(amount, cashu_unit.as_ref());
+        let (fee_msats, unit_fee) = amount_columns(fee, cashu_unit.as_ref());
+        let new = NewEcashSend {
+            operation_id,
+            fedimint_id: fedimint_id.map(|f| f.to_string()),
+            cashu_mint_url: cashu_mint_url.map(|f| f.to_string()),
+            cashu_unit: cashu_unit.as_ref().map(ToString::to_string),
+            token,
+            amount_msats,
+            fee_msats,
+            status: status as i32,
+            spending_conditions: spending_conditions.map(serde_json::to_string).transpose()?,
+            locktime: locktime.map(|l| l as i64),
+            unit_amount,
+            unit_fee,
+        };
+
+        diesel::insert_into(ecash_sends::table)
+            .values(new)
+            .execute(conn)?;
+
+        Ok(())
+    }
+
+    pub fn get_by_operation_id(
+        conn: &mut SqliteConnection,
+        operation_id: String,
+    ) -> anyhow::Result<Option<Self>> {
+        Ok(ecash_sends::table
+            .filter(ecash_sends::operation_id.eq(operation_id))
+            .first::<Self>(conn)
+            .optional()?)
+    }
+
+    pub fn get_by_token(conn: &mut SqliteConnection, token: &str) -> anyhow::Result<Option<Self>> {
+        Ok(ecash_sends::table
+            .filter(ecash_sends::token.eq(token))
+            .first::<Self>(conn)
+            .optional()?)
+    }
+
+    /// Saves the token of an unconfirmed send, it's pending until claimed
+    pub fn confirm(
+        conn: &mut SqliteConnection,
+        operation_id: String,
+        token: String,
+    ) -> anyhow::Result<()> {
+        diesel::update(ecash_sends::table.filter(ecash_sends::operation_id.eq(operation_id)))
+            .set((
+                ecash_sends::token.eq(token),
+                ecash_sends::status.eq(PaymentStatus::Pending as i32),
+            ))
+            .execute(conn)?;
+
+        Ok(())
+    }
+
+    pub fn mark_as_success(
+        conn: &mut SqliteConnection,
+        operation_id: String,
+    ) -> anyhow::Result<()> {
+        diesel::update(ecash_sends::table.filter(ecash_sends::operation_id.eq(operation_id)))
+            .set(ecash_sends::status.eq(PaymentStatus::Success as i32))
+            .execute(conn)?;
+
+        Ok(())
+    }
+
+    pub fn mark_as_failed(conn: &mut SqliteConnection, operation_id: String) -> anyhow::Result<()> {
+        diesel::update(ecash_sends::table.filter(ecash_sends::operation_id.eq(operation_id)))
+            .set(ecash_sends::status.eq(PaymentStatus::Failed as i32))
+            .execute(conn)?;
+
+        Ok(())
+    }
+
+    /// Part of the token was claimed before we reclaimed the rest, the send
+    /// only counts what the recipient got
+    pub fn mark_as_partially_reclaimed(
+        conn: &mut SqliteConnection,
+        operation_id: String,
+        claimed: Amount,
+    ) -> anyhow::Result<()> {
+        let unit = Self::get_by_operation_id(conn, operation_id.clone())?.map(|s| s.unit());
+        let (amount_msats, unit_amount) = amount_columns(claimed, unit.as_ref());
+        diesel::update(ecash_sends::table.filter(ecash_sends::operation_id.eq(operation_id)))
+            .set((
+                ecash_sends::amount_msats.eq(amount_msats),
+                ecash_sends::unit_amount.eq(unit_amount),
+                ecash_sends::status.eq(PaymentStatus::Success as i32),
+            ))
+            .execute(conn)?;
+
+        Ok(())
+    }
+
+    pub fn get_pending(conn: &mut SqliteConnection) -> anyhow::Result<Vec<Self>> {
+        Ok(ecash_sends::table
+            .filter(ecash_sends::status.eq(PaymentStatus::Pending as i32))
+            .load::<Self>(conn)?)
+    }
+
+    /// Unclaimed timelocked tokens the user hasn't been reminded about yet
+    pub fn get_pending_timelocked(conn: &mut SqliteConnection) -> anyhow::Result<Vec<Self>> {
+        Ok(ecash_sends::table
+            .filter(ecash_sends::status.eq(PaymentStatus::Pending as i32))
+            .filter(ecash_sends::locktime.is_not_null())
+            .filter(ecash_sends::reminded.eq(0))
+            .load::<Self>(conn)?)
+    }
+
+    pub fn mark_as_reminded(
+        conn: &mut SqliteConnection,
+        operation_id: String,
+    ) -> anyhow::Result<()> {
+        diesel::update(ecash_sends::table.filter(ecash_sends::operation_id.eq(operation_id)))
+            .set(ecash_sends::reminded.eq(1))
+            .execute(conn)?;
+
+        Ok(())
+    }
+
+    /// Unclaimed tokens are still shown so they can be copied again
+    pub fn get_history(conn: &mut SqliteConnection) -> anyhow::Result<Vec<Self>> {
+        Ok(ecash_sends::table
+            .filter(
+                ecash_sends::status
+                    .eq_any([PaymentStatus::Pending as i32, PaymentStatus::Success as i32]),
+            )
+            .load::<Self>(conn)?)
+    }
+}
+
+impl From<EcashSend> for TransactionItem {
+    fn from(payment: EcashSend) -> Self {
+        Self {
+            kind: TransactionItemKind::Ecash,
+            amount: payment.amount().sats_round_down(),
+            unit: payment.unit(),
+            fee_msats: payment.fee().msats,
+            txid: None,
+            preimage: None,
+            token: Some(payment.token.clone()),
+            reclaimable: payment.status() == PaymentStatus::Pending && payment.reclaimable(),
+            direction: TransactionDirection::Outgoing,
+            mint_identifier: payment.mint_identifier(),
+            status: payment.status(),
+            timestamp: payment.created_at.and_utc().timestamp() as u64,
+        }
+    }
+}
@@ -1,11 +1,12 @@
 use crate::MintIdentifier;
-use crate::db_models::PaymentStatus;
 use crate::db_models::schema::lightning_payments;
 use crate::db_models::transaction_item::{
     TransactionDirection, TransactionItem, TransactionItemKind,
 };
+use crate::db_models::{PaymentStatus, amount_columns, column_amount};
 use bitcoin::hashes::hex::FromHex;
 use cdk::mint_url::MintUrl;
+use cdk::nuts::CurrencyUnit;
 use diesel::prelude::*;
 use fedimint_core::Amount;
 use fedimint_core::config::FederationId;
@@ -27,6 +28,10 @@ pub struct LightningPayment {
     status: i32,
     pub created_at: chrono::NaiveDateTime,
     pub updated_at: chrono::NaiveDateTime,
+    parent_operation_id: Option<String>,
+    cashu_unit: Option<String>,
+    unit_amount: Option<i64>,
+    unit_fee: Option<i64>,
 }
 
 #[derive(Insertable, Clone)]
@@ -40,6 +45,10 @@ struct NewLightningPayment {
     amount_msats: i64,
     fee_msats: i64,
     status: i32,
+    parent_operation_id: Option<String>,
+    cashu_unit: Option<String>,
+    unit_amount: Option<i64>,
+    unit_fee: Option<i64>,
 }
 
 impl LightningPayment {
@@ -62,10 +71,17 @@ impl LightningPayment {
     pub fn mint_identifier(&self) -> MintIdentifier {
         match self.fedimint_id() {
             Some(f) => MintIdentifier::Fedimint(f),
-            None => MintIdentifier::Cashu(self.mint_url().expect("missing mint url")),
+            None => MintIdentifier::Cashu(self.mint_url().expect("missing mint url"), self.unit()),
         }
     }
 
+    /// Rows from before we supported other units are all in sats
+    pub fn unit(&self) -> CurrencyUnit {
+        self.cashu_unit.as_ref().map_or(CurrencyUnit::Sat, |u| {
+            CurrencyUnit::from_str(u).expect("invalid unit")
+        })
+    }
+
     pub fn payment_hash(&self) -> [u8; 32] {
         FromHex::from_hex(&self.payment_hash).expect("invalid payment hash")
     }
@@ -75,11 +91,11 @@ impl LightningPayment {
     }
 
     pub fn amount(&self) -> Amount {
-        Amount::from_msats(self.amount_msats as u64)
+        column_amount(self.amount_msats, self.unit_amount)
     }
 
     pub fn fee(&self) -> Amount {
-        Amount::from_msats(self.fee_msats as u64)
+        column_amount(self.fee_msats, self.unit_fee)
     }
 
     pub fn preimage(&self) -> Option<[u8; 32]> {
@@ -92,33 +108,47 @@ impl LightningPayment {
         PaymentStatus::from_i32(self.status)
     }
 
+    /// The payment this is one part of when an invoice was paid from several mints
+    pub fn parent_operation_id(&self) -> Option<&str> {
+        self.parent_operation_id.as_deref()
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub fn create(
         conn: &mut SqliteConnection,
         operation_id: String,
         fedimint_id: Option<FederationId>,
         cashu_mint_url: Option<MintUrl>,
+        cashu_unit: Option<CurrencyUnit>,
         bolt11: Bolt11Invoice,
         amount: Amount,
         fee: Amount,
     ) -> anyhow::Result<()> {
-        // Make sure the amount matches
-        if bolt11
-            .amount_milli_satoshis()
-            .is_some_and(|a| a != amount.msats)
+        // Make sure the amount matches, other units are priced by the mint
+        if cashu_unit.as_ref().is_none_or(|u| *u == CurrencyUnit::Sat)
+            && bolt11
+                .amount_milli_satoshis()
+                .is_some_and(|a| a != amount.msats)
         {
             return Err(anyhow::anyhow!("Internal error: amount mismatch"));
         }
 
         let payment_hash = bolt11.payment_hash().to_string();
+        let (amount_msats, unit_amount) = amount_columns(amount, cashu_unit.as_ref());
+        let (fee_msats, unit_fee) = amount_columns(fee, cashu_unit.as_ref());
         let new = NewLightningPayment {
             operation_id,
             fedimint_id: fedimint_id.map(|f| f.to_string()),
             cashu_mint_url: cashu_mint_url.map(|f| f.to_string()),
+            cashu_unit: cashu_unit.as_ref().map(ToString::to_string),
             payment_hash,
             bolt11: bolt11.to_string(),
-            amount_msats: amount.msats as i64,
-            fee_msats: fee.msats as i64,
+            amount_msats,
+            fee_msats,
             status: PaymentStatus::Pending as i32,
+            parent_operation_id: None,
+            unit_amount,
+            unit_fee,
         };
 
         diesel::insert_into(lightning_payments::table)
@@ -128,6 +158,82 @@ impl LightningPayment {
         Ok(())
     }
 
+    /// Records a partial payment of `bolt11` (NUT-15), the amount is only this part's share
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_part(
+        conn: &mut SqliteConnection,
+        operation_id: String,
+        parent_operation_id: String,
+        cashu_mint_url: MintUrl,
+        cashu_unit: CurrencyUnit,
+        bolt11: Bolt11Invoice,
+        amount: Amount,
+        fee: Amount,
+    ) -> anyhow::Result<()> {
+        let payment_hash = bolt11.payment_hash().to_string();
+        let (amount_msats, unit_amount) = amount_columns(amount, Some(&cashu_unit));
+        let (fee_msats, unit_fee) = amount_columns(fee, Some(&cashu_unit));
+        let new = NewLightningPayment {
+            operation_id,
+            fedimint_id: None,
+            cashu_mint_url: Some(cashu_mint_url.to_string()),
+            cashu_unit: Some(cashu_unit.to_string()),
+            payment_hash,
+            bolt11: bolt11.to_string(),
+            amount_msats,
+            fee_msats,
+            status: PaymentStatus::Pending as i32,
+            parent_operation_id: Some(parent_operation_id),
+            unit_amount,
+            unit_fee,
+        };
+
+        diesel::insert_into(lightning_payments::table)
+            .values(new)
+            .execute(conn)?;
+
+        Ok(())
+    }
+
+    pub fn get_parts(
+        conn: &mut SqliteConnection,
+        parent_operation_id: &str,
+    ) -> anyhow::Result<Vec<Self>> {
+        Ok(lightning_payments::table
+            .filter(lightning_payments::parent_operation_id.eq(parent_operation_id))
+            .load::<Self>(conn)?)
+    }
+
+    /// Settles a multi-path payment once its parts are done: it fails if any
+    /// part failed and succeeds with the total fee once every part succeeded
+    pub fn settle_parent(
+        conn: &mut SqliteConnection,
+        parent_operation_id: &str,
+    ) -> anyhow::Result<()> {
+        let parts = Self::get_parts(conn, parent_operation_id)?;
+
+        if parts.iter().any(|p| p.status() == PaymentStatus::Failed) {
+            Self::mark_as_failed(conn, parent_operation_id.to_string())?;
+            return Ok(());
+        }
+
+        let preimage = parts
+            .iter()
+            .find_map(Self::preimage)
+            .filter(|_| parts.iter().all(|p| p.status() == PaymentStatus::Success));
+        if let Some(preimage) = preimage {
+            let fee_msats = parts.iter().map(|p| p.fee().msats).sum();
+            Self::set_preimage_and_fee(
+                conn,
+                parent_operation_id.to_string(),
+                preimage,
+                Some(fee_msats),
+            )?;
+        }
+
+        Ok(())
+    }
+
     pub fn get_by_operation_id(
         conn: &mut SqliteConnection,
         operation_id: String,
@@ -157,13 +263,16 @@ impl LightningPayment {
                 .execute(conn)?;
             }
             Some(fee) => {
+                let unit = Self::get_by_operation_id(conn, operation_id.clone())?.map(|p| p.unit());
+                let (fee_msats, unit_fee) = amount_columns(Amount::from_msats(fee), unit.as_ref());
                 diesel::update(
                     lightning_payments::table
                         .filter(lightning_payments::operation_id.eq(operation_id)),
                 )
                 .set((
                     lightning_payments::preimage.eq(Some(hex::encode(preimage))),
-                    lightning_payments::fee_msats.eq(fee as i64),
+                    lightning_payments::fee_msats.eq(fee_msats),
+                    lightning_payments::unit_fee.eq(unit_fee),
                     lightning_payments::status.eq(PaymentStatus::Success as i32),
                 ))
                 .execute(conn)?;
@@ -183,18 +292,31 @@ impl LightningPayment {
         Ok(())
     }
 
+    /// Parts of a multi-path payment are shown through their parent
     pub fn get_history(conn: &mut SqliteConnection) -> anyhow::Result<Vec<Self>> {
         Ok(lightning_payments::table
             .filter(lightning_payments::status.eq(PaymentStatus::Success as i32))
+            .filter(lightning_payments::parent_operation_id.is_null())
             .load::<Self>(conn)?)
     }
 
+    /// Parents of multi-path payments are left out, they settle with their parts
     pub fn get_pending(conn: &mut SqliteConnection) -> anyhow::Result<Vec<Self>> {
+        let parts = diesel::alias!(lightning_payments as parts);
+        let parents = parts
+            .select(parts.field(lightning_payments::parent_operation_id))
+            .filter(
+                parts
+                    .field(lightning_payments::parent_operation_id)
+                    .is_not_null(),
+            );
+
         Ok(lightning_payments::table
             .filter(lightning_payments::status.eq_any([
                 PaymentStatus::Pending as i32,
                 PaymentStatus::WaitingConfirmation as i32,
             ]))
+            .filter(lightning_payments::operation_id.nullable().ne_all(parents))
             .load::<Self>(conn)?)
     }
 }
@@ -204,9 +326,12 @@ impl From<LightningPayment> for TransactionItem {
         Self {
             kind: TransactionItemKind::Lightning,
             amount: payment.amount().sats_round_down(),
-            fee_msats: payment.fee_msats as u64,
+            unit: payment.unit(),
+            fee_msats: payment.fee().msats,
             txid: None,
             preimage: payment.preimage(),
+            token: None,
+            reclaimable: false,
             direction: TransactionDirection::Outgoing,
             mint_identifier: payment.mint_identifier(),
             status: payment.status(),
@@ -1,11 +1,12 @@
 use crate::MintIdentifier;
-use crate::db_models::PaymentStatus;
-use crate::db_models::schema::lightning_receives;
+use crate::db_models::schema::{ecash_receives, lightning_receives};
 use crate::db_models::transaction_item::{
     TransactionDirection, TransactionItem, TransactionItemKind,
 };
+use crate::db_models::{PaymentStatus, amount_columns, column_amount};
 use bitcoin::hashes::hex::FromHex;
 use cdk::mint_url::MintUrl;
+use cdk::nuts::CurrencyUnit;
 use diesel::prelude::*;
 use fedimint_core::Amount;
 use fedimint_core::config::FederationId;
@@ -26,6 +27,10 @@ pub struct LightningReceive {
     status: i32,
     pub created_at: chrono::NaiveDateTime,
     pub updated_at: chrono::NaiveDateTime,
+    cashu_unit: Option<String>,
+    unit_amount: Option<i64>,
+    unit_fee: Option<i64>,
+    quote_key_index: Option<i32>,
 }
 
 #[derive(Insertable, Clone)]
@@ -39,6 +44,10 @@ struct NewLightningReceive {
     amount_msats: i64,
     fee_msats: i64,
     status: i32,
+    cashu_unit: Option<String>,
+    unit_amount: Option<i64>,
+    unit_fee: Option<i64>,
+    quote_key_index: Option<i32>,
 }
 
 impl LightningReceive {
@@ -61,10 +70,17 @@ impl LightningReceive {
     pub fn mint_identifier(&self) -> MintIdentifier {
         match self.fedimint_id() {
             Some(f) => MintIdentifier::Fedimint(f),
-            None => MintIdentifier::Cashu(self.mint_url().expect("missing mint url")),
+            None => MintIdentifier::Cashu(self.mint_url().expect("missing mint url"), self.unit()),
         }
     }
 
+    /// Rows from before we supported other units are all in sats
+    pub fn unit(&self) -> CurrencyUnit {
+        self.cashu_unit.as_ref().map_or(CurrencyUnit::Sat, |u| {
+            CurrencyUnit::from_str(u).expect("invalid unit")
+        })
+    }
+
     pub fn payment_hash(&self) -> [u8; 32] {
         FromHex::from_hex(&self.payment_hash).expect("invalid payment hash")
     }
@@ -74,44 +90,59 @@ impl LightningReceive {
     }
 
     pub fn amount(&self) -> Amount {
-        Amount::from_msats(self.amount_msats as u64)
+        column_amount(self.amount_msats, self.unit_amount)
     }
 
     pub fn fee(&self) -> Amount {
-        Amount::from_msats(self.fee_msats as u64)
+        column_amount(self.fee_msats, self.unit_fee)
     }
 
     pub fn status(&self) -> PaymentStatus {
         PaymentStatus::from_i32(self.status)
     }
 
+    /// Index of the seed derived key a cashu mint quote is locked to (NUT-20)
+    pub fn quote_key_index(&self) -> Option<u32> {
+        self.quote_key_index.map(|i| i as u32)
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub fn create(
         conn: &mut SqliteConnection,
         operation_id: String,
         fedimint_id: Option<FederationId>,
         cashu_mint_url: Option<MintUrl>,
+        cashu_unit: Option<CurrencyUnit>,
         bolt11: Bolt11Invoice,
         amount: Amount,
         fee: Amount,
+        quote_key_index: Option<u32>,
     ) -> anyhow::Result<()> {
-        // Make sure the amount matches
-        if bolt11
-            .amount_milli_satoshis()
-            .is_some_and(|a| a != amount.msats)
+        // Make sure the amount matches, other units are priced by the mint
+        if cashu_unit.as_ref().is_none_or(|u| *u == CurrencyUnit::Sat)
+            && bolt11
+                .amount_milli_satoshis()
+                .is_some_and(|a| a != amount.msats)
         {
             return Err(anyhow::anyhow!("Internal error: amount mismatch"));
         }
 
         let payment_hash = bolt11.payment_hash().to_string();
+        let (amount_msats, unit_amount) = amount_columns(amount, cashu_unit.as_ref());
+        let (fee_msats, unit_fee) = amount_columns(fee, cashu_unit.as_ref());
         let new = NewLightningReceive {
             operation_id,
             fedimint_id: fedimint_id.map(|f| f.to_string()),
             cashu_mint_url: cashu_mint_url.map(|f| f.to_string()),
+            cashu_unit: cashu_unit.as_ref().map(ToString::to_string),
             payment_hash,
             bolt11: bolt11.to_string(),
-            amount_msats: amount.msats as i64,
-            fee_msats: fee.msats as i64,
+            amount_msats,
+            fee_msats,
             status: PaymentStatus::Pending as i32,
+            unit_amount,
+            unit_fee,
+            quote_key_index: quote_key_index.map(|i| i as i32),
         };
 
         diesel::insert_into(lightning_receives::table)
@@ -131,6 +162,16 @@ impl LightningReceive {
             .optional()?)
     }
 
+    pub fn get_by_payment_hash(
+        conn: &mut SqliteConnection,
+        payment_hash: &str,
+    ) -> anyhow::Result<Option<Self>> {
+        Ok(lightning_receives::table
+            .filter(lightning_receives::payment_hash.eq(payment_hash))
+            .first::<Self>(conn)
+            .optional()?)
+    }
+
     pub fn mark_as_success(
         conn: &mut SqliteConnection,
         operation_id: String,
@@ -154,9 +195,15 @@ impl LightningReceive {
         Ok(())
     }
 
+    /// Invoices paid by swapping in ecash are shown as the ecash receive instead
     pub fn get_history(conn: &mut SqliteConnection) -> anyhow::Result<Vec<Self>> {
+        let swaps = ecash_receives::table
+            .select(ecash_receives::swap_payment_hash)
+            .filter(ecash_receives::swap_payment_hash.is_not_null());
+
         Ok(lightning_receives::table
             .filter(lightning_receives::status.eq(PaymentStatus::Success as i32))
+            .filter(lightning_receives::payment_hash.nullable().ne_all(swaps))
             .load::<Self>(conn)?)
     }
 
@@ -175,9 +222,12 @@ impl From<LightningReceive> for TransactionItem {
         Self {
             kind: TransactionItemKind::Lightning,
             amount: payment.amount().sats_round_down(),
-            fee_msats: payment.fee_msats as u64,
+            unit: payment.unit(),
+            fee_msats: payment.fee().msats,
             txid: None,
             preimage: None,
+            token: None,
+            reclaimable: false,
             direction: TransactionDirection::Incoming,
             mint_identifier: payment.mint_identifier(),
             status: payment.status(),
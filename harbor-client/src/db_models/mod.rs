@@ -7,6 +7,12 @@ pub use fedimint::*;
 pub mod cashu_mint;
 pub use cashu_mint::*;
 
+pub mod cashu_mint_auth;
+pub use cashu_mint_auth::*;
+
+pub mod cashu_consolidation;
+pub use cashu_consolidation::*;
+
 pub mod lightning_payment;
 pub use lightning_payment::*;
 
@@ -19,6 +25,18 @@ pub use onchain_payment::*;
 pub mod onchain_receive;
 pub use onchain_receive::*;
 
+pub mod ecash_send;
+pub use ecash_send::*;
+
+pub mod ecash_receive;
+pub use ecash_receive::*;
+
+pub mod payment_request;
+pub use payment_request::*;
+
+pub mod nostr_event;
+pub use nostr_event::*;
+
 pub(crate) mod schema;
 
 pub mod mint_metadata;
@@ -26,6 +44,8 @@ pub mod transaction_item;
 
 use crate::MintIdentifier;
 use crate::metadata::FederationMeta;
+use cdk::nuts::CurrencyUnit;
+use fedimint_core::Amount;
 use fedimint_core::config::FederationId;
 use fedimint_core::core::ModuleKind;
 
@@ -33,12 +53,20 @@ use fedimint_core::core::ModuleKind;
 pub struct MintItem {
     pub id: MintIdentifier,
     pub name: String,
+    /// In the base amount of `unit`, e.g. cents for usd
     pub balance: u64,
+    pub unit: CurrencyUnit,
+    /// Signatures from a cashu mint that failed DLEQ verification
+    pub dleq_audit: DleqAudit,
     pub guardians: Option<Vec<String>>,
     pub module_kinds: Option<Vec<ModuleKind>>,
     pub metadata: FederationMeta,
     pub on_chain_supported: bool,
     pub active: bool,
+    /// Still recovering ecash from a backup, it can't be used until this finishes
+    pub recovering: bool,
+    /// Why recovering ecash from a backup failed, it can't be used
+    pub recovery_error: Option<String>,
 }
 
 impl MintItem {
@@ -47,11 +75,15 @@ impl MintItem {
             id: MintIdentifier::Fedimint(id),
             name: "Unknown".to_string(),
             balance: 0,
+            unit: CurrencyUnit::Sat,
+            dleq_audit: DleqAudit::default(),
             guardians: None,
             module_kinds: None,
             metadata: FederationMeta::default(),
             on_chain_supported: false,
             active: true,
+            recovering: false,
+            recovery_error: None,
         }
     }
 }
@@ -95,3 +127,21 @@ impl PaymentStatus {
         }
     }
 }
+
+/// Cashu units other than sat have no msats value, their amounts are stored
+/// in the unit's base amount (e.g. cents for usd) and the msats column is left
+/// at 0. Outside the database they're carried as an [`Amount`] of that many sats.
+pub(crate) fn amount_columns(amount: Amount, unit: Option<&CurrencyUnit>) -> (i64, Option<i64>) {
+    match unit {
+        Some(unit) if *unit != CurrencyUnit::Sat => (0, Some(amount.sats_round_down() as i64)),
+        _ => (amount.msats as i64, None),
+    }
+}
+
+/// Reads back an amount stored with [`amount_columns`]
+pub(crate) fn column_amount(msats: i64, unit_amount: Option<i64>) -> Amount {
+    unit_amount.map_or_else(
+        || Amount::from_msats(msats as u64),
+        |base| Amount::from_sats(base as u64),
+    )
+}
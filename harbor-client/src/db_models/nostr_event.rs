@@ -0,0 +1,147 @@
+use crate::db_models::PaymentStatus;
+use crate::db_models::schema::nostr_events;
+use chrono::{NaiveDateTime, TimeDelta, Utc};
+use diesel::prelude::*;
+
+/// How many times we try to redeem the ecash in an event before giving up
+const MAX_ATTEMPTS: i32 = 8;
+
+/// Wait before the first retry of a failed redeem, doubled after every attempt
+const RETRY_DELAY: TimeDelta = TimeDelta::minutes(1);
+
+/// A nostr event with ecash for us, kept so it isn't redeemed twice and so a
+/// failed redeem is retried with backoff
+#[derive(QueryableByName, Queryable, Debug, Clone, PartialEq, Eq)]
+#[diesel(table_name = nostr_events)]
+pub struct NostrEvent {
+    pub event_id: String,
+    event: Option<String>,
+    status: i32,
+    attempts: i32,
+    retry_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable, Clone)]
+#[diesel(table_name = nostr_events)]
+struct NewNostrEvent {
+    event_id: String,
+    event: Option<String>,
+    status: i32,
+}
+
+impl NostrEvent {
+    /// The event as json, until its ecash is redeemed or we gave up on it
+    pub fn event(&self) -> Option<&str> {
+        self.event.as_deref()
+    }
+
+    pub fn status(&self) -> PaymentStatus {
+        PaymentStatus::from_i32(self.status)
+    }
+
+    pub fn attempts(&self) -> u32 {
+        self.attempts as u32
+    }
+
+    /// Claims the event for redeeming. Returns false if it was redeemed, given
+    /// up on, is being redeemed or isn't due for a retry yet.
+    pub fn claim(conn: &mut SqliteConnection, event_id: &str, event: &str) -> anyhow::Result<bool> {
+        conn.transaction(|conn| {
+            let existing = nostr_events::table
+                .find(event_id)
+                .first::<Self>(conn)
+                .optional()?;
+
+            match existing {
+                None => {
+                    diesel::insert_into(nostr_events::table)
+                        .values(NewNostrEvent {
+                            event_id: event_id.to_string(),
+                            event: Some(event.to_string()),
+                            status: PaymentStatus::Pending as i32,
+                        })
+                        .execute(conn)?;
+                    Ok(true)
+                }
+                Some(existing) if existing.due(Utc::now().naive_utc()) => {
+                    diesel::update(nostr_events::table.find(event_id))
+                        .set(nostr_events::retry_at.eq(None::<NaiveDateTime>))
+                        .execute(conn)?;
+                    Ok(true)
+                }
+                Some(_) => Ok(false),
+            }
+        })
+    }
+
+    pub fn mark_as_redeemed(conn: &mut SqliteConnection, event_id: &str) -> anyhow::Result<()> {
+        diesel::update(nostr_events::table.find(event_id))
+            .set((
+                nostr_events::status.eq(PaymentStatus::Success as i32),
+                nostr_events::event.eq(None::<String>),
+                nostr_events::retry_at.eq(None::<NaiveDateTime>),
+            ))
+            .execute(conn)?;
+
+        Ok(())
+    }
+
+    /// Schedules the next retry of a failed redeem, or gives up after
+    /// [`MAX_ATTEMPTS`]. Returns how many attempts failed so far.
+    pub fn mark_as_failed(conn: &mut SqliteConnection, event_id: &str) -> anyhow::Result<u32> {
+        conn.transaction(|conn| {
+            let attempts = nostr_events::table
+                .find(event_id)
+                .select(nostr_events::attempts)
+                .first::<i32>(conn)?
+                + 1;
+
+            if attempts >= MAX_ATTEMPTS {
+                diesel::update(nostr_events::table.find(event_id))
+                    .set((
+                        nostr_events::status.eq(PaymentStatus::Failed as i32),
+                        nostr_events::attempts.eq(attempts),
+                        nostr_events::event.eq(None::<String>),
+                        nostr_events::retry_at.eq(None::<NaiveDateTime>),
+                    ))
+                    .execute(conn)?;
+            } else {
+                let retry_at = Utc::now().naive_utc() + RETRY_DELAY * (1 << (attempts - 1));
+                diesel::update(nostr_events::table.find(event_id))
+                    .set((
+                        nostr_events::attempts.eq(attempts),
+                        nostr_events::retry_at.eq(Some(retry_at)),
+                    ))
+                    .execute(conn)?;
+            }
+
+            Ok(attempts as u32)
+        })
+    }
+
+    /// Events whose redeem failed and are due for another try
+    pub fn get_retryable(conn: &mut SqliteConnection) -> anyhow::Result<Vec<Self>> {
+        Ok(nostr_events::table
+            .filter(nostr_events::status.eq(PaymentStatus::Pending as i32))
+            .filter(nostr_events::retry_at.le(Utc::now().naive_utc()))
+            .load::<Self>(conn)?)
+    }
+
+    /// Events we stopped in the middle of redeeming are retried right away
+    pub fn release_unfinished(conn: &mut SqliteConnection) -> anyhow::Result<()> {
+        diesel::update(
+            nostr_events::table
+                .filter(nostr_events::status.eq(PaymentStatus::Pending as i32))
+                .filter(nostr_events::retry_at.is_null()),
+        )
+        .set(nostr_events::retry_at.eq(Some(Utc::now().naive_utc())))
+        .execute(conn)?;
+
+        Ok(())
+    }
+
+    fn due(&self, now: NaiveDateTime) -> bool {
+        self.status() == PaymentStatus::Pending && self.retry_at.is_some_and(|at| at <= now)
+    }
+}
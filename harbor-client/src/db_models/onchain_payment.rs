@@ -7,6 +7,7 @@ use crate::db_models::transaction_item::{
 use bitcoin::address::NetworkUnchecked;
 use bitcoin::{Address, Txid};
 use cdk::mint_url::MintUrl;
+use cdk::nuts::CurrencyUnit;
 use diesel::prelude::*;
 use fedimint_core::config::FederationId;
 use fedimint_core::core::OperationId;
@@ -59,7 +60,10 @@ impl OnChainPayment {
     pub fn mint_identifier(&self) -> MintIdentifier {
         match self.fedimint_id() {
             Some(f) => MintIdentifier::Fedimint(f),
-            None => MintIdentifier::Cashu(self.mint_url().expect("missing mint url")),
+            None => MintIdentifier::Cashu(
+                self.mint_url().expect("missing mint url"),
+                CurrencyUnit::Sat,
+            ),
         }
     }
 
@@ -162,12 +166,15 @@ impl From<OnChainPayment> for TransactionItem {
         Self {
             kind: TransactionItemKind::Onchain,
             amount: payment.amount_sats as u64,
+            unit: CurrencyUnit::Sat,
             fee_msats: payment.fee_sats as u64 * 1000, // Convert to msats
             txid: payment
                 .txid
                 .as_ref()
                 .map(|t| Txid::from_str(t).expect("invalid txid")),
             preimage: None,
+            token: None,
+            reclaimable: false,
             direction: TransactionDirection::Outgoing,
             mint_identifier: payment.mint_identifier(),
             status: payment.status(),
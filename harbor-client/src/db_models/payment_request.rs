@@ -0,0 +1,107 @@
+use crate::db_models::PaymentStatus;
+use crate::db_models::schema::payment_requests;
+use cdk::mint_url::MintUrl;
+use cdk::nuts::CurrencyUnit;
+use diesel::prelude::*;
+use std::str::FromStr;
+
+/// A cashu payment request (NUT-18) we published to get paid in ecash
+#[derive(QueryableByName, Queryable, Debug, Clone, PartialEq, Eq)]
+#[diesel(table_name = payment_requests)]
+pub struct CashuPaymentRequest {
+    pub id: String,
+    cashu_mint_url: String,
+    cashu_unit: String,
+    amount: Option<i64>,
+    pub description: Option<String>,
+    /// The encoded `creqA...` request
+    pub request: String,
+    status: i32,
+    ecash_receive_id: Option<String>,
+    pub created_at: chrono::NaiveDateTime,
+    pub updated_at: chrono::NaiveDateTime,
+}
+
+#[derive(Insertable, Clone)]
+#[diesel(table_name = payment_requests)]
+struct NewCashuPaymentRequest {
+    id: String,
+    cashu_mint_url: String,
+    cashu_unit: String,
+    amount: Option<i64>,
+    description: Option<String>,
+    request: String,
+    status: i32,
+}
+
+impl CashuPaymentRequest {
+    pub fn mint_url(&self) -> MintUrl {
+        MintUrl::from_str(&self.cashu_mint_url).expect("invalid mint url")
+    }
+
+    pub fn unit(&self) -> CurrencyUnit {
+        CurrencyUnit::from_str(&self.cashu_unit).expect("invalid unit")
+    }
+
+    /// In the base amount of the unit, None if the payer picks the amount
+    pub fn amount(&self) -> Option<u64> {
+        self.amount.map(|a| a as u64)
+    }
+
+    pub fn status(&self) -> PaymentStatus {
+        PaymentStatus::from_i32(self.status)
+    }
+
+    /// The ecash receive that paid the request
+    pub fn ecash_receive_id(&self) -> Option<&str> {
+        self.ecash_receive_id.as_deref()
+    }
+
+    pub fn create(
+        conn: &mut SqliteConnection,
+        id: String,
+        mint_url: &MintUrl,
+        unit: &CurrencyUnit,
+        amount: Option<u64>,
+        description: Option<String>,
+        request: String,
+    ) -> anyhow::Result<()> {
+        let new = NewCashuPaymentRequest {
+            id,
+            cashu_mint_url: mint_url.to_string(),
+            cashu_unit: unit.to_string(),
+            amount: amount.map(|a| a as i64),
+            description,
+            request,
+            status: PaymentStatus::Pending as i32,
+        };
+
+        diesel::insert_into(payment_requests::table)
+            .values(new)
+            .execute(conn)?;
+
+        Ok(())
+    }
+
+    pub fn get(conn: &mut SqliteConnection, id: &str) -> anyhow::Result<Option<Self>> {
+        Ok(payment_requests::table
+            .filter(payment_requests::id.eq(id))
+            .first::<Self>(conn)
+            .optional()?)
+    }
+
+    pub fn mark_as_paid(
+        conn: &mut SqliteConnection,
+        id: &str,
+        ecash_receive_id: String,
+    ) -> anyhow::Result<()> {
+        diesel::update(payment_requests::table.filter(payment_requests::id.eq(id)))
+            .set((
+                payment_requests::status.eq(PaymentStatus::Success as i32),
+                payment_requests::ecash_receive_id.eq(Some(ecash_receive_id)),
+            ))
+            .execute(conn)?;
+
+        Ok(())
+    }
+}
@@ -1,5 +1,6 @@
 use crate::db_models::schema::profile;
 use bip39::Mnemonic;
+use chrono::Utc;
 use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::str::FromStr;
@@ -13,6 +14,10 @@ pub struct Profile {
     pub seed_words: String,
     onchain_receive_enabled: i32,
     tor_enabled: i32,
+    consolidation_enabled: i32,
+    next_quote_key_index: i32,
+    nostr_enabled: i32,
+    settings_updated_at: Option<i64>,
 }
 
 impl Profile {
@@ -29,7 +34,10 @@ impl Profile {
             enabled
         );
         diesel::update(profile::table)
-            .set(profile::onchain_receive_enabled.eq(i32::from(enabled)))
+            .set((
+                profile::onchain_receive_enabled.eq(i32::from(enabled)),
+                profile::settings_updated_at.eq(Some(Utc::now().timestamp())),
+            ))
             .execute(conn)?;
         log::debug!("Successfully updated on-chain receive enabled setting in database");
         Ok(())
@@ -42,12 +50,57 @@ impl Profile {
     pub fn set_tor_enabled(conn: &mut SqliteConnection, enabled: bool) -> anyhow::Result<()> {
         log::debug!("Updating Tor enabled setting in database to: {}", enabled);
         diesel::update(profile::table)
-            .set(profile::tor_enabled.eq(i32::from(enabled)))
+            .set((
+                profile::tor_enabled.eq(i32::from(enabled)),
+                profile::settings_updated_at.eq(Some(Utc::now().timestamp())),
+            ))
             .execute(conn)?;
         log::debug!("Successfully updated Tor enabled setting in database");
         Ok(())
     }
 
+    pub fn set_consolidation_enabled(
+        conn: &mut SqliteConnection,
+        enabled: bool,
+    ) -> anyhow::Result<()> {
+        log::debug!("Updating proof consolidation setting in database to: {enabled}");
+        diesel::update(profile::table)
+            .set((
+                profile::consolidation_enabled.eq(i32::from(enabled)),
+                profile::settings_updated_at.eq(Some(Utc::now().timestamp())),
+            ))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    pub fn set_nostr_enabled(conn: &mut SqliteConnection, enabled: bool) -> anyhow::Result<()> {
+        log::debug!("Updating nostr DMs setting in database to: {enabled}");
+        diesel::update(profile::table)
+            .set(profile::nostr_enabled.eq(i32::from(enabled)))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    /// Reserves the next index for deriving a mint quote key (NUT-20)
+    pub fn take_quote_key_index(conn: &mut SqliteConnection) -> anyhow::Result<u32> {
+        conn.transaction(|conn| {
+            let index = profile::table
+                .select(profile::next_quote_key_index)
+                .first::<i32>(conn)?;
+            diesel::update(profile::table)
+                .set(profile::next_quote_key_index.eq(index + 1))
+                .execute(conn)?;
+            Ok(index as u32)
+        })
+    }
+
+    pub fn set_next_quote_key_index(conn: &mut SqliteConnection, index: u32) -> anyhow::Result<()> {
+        diesel::update(profile::table)
+            .set(profile::next_quote_key_index.eq(index as i32))
+            .execute(conn)?;
+        Ok(())
+    }
+
     pub fn mnemonic(&self) -> Mnemonic {
         Mnemonic::from_str(self.seed_words.as_str()).expect("valid mnemonic")
     }
@@ -55,6 +108,20 @@ impl Profile {
     pub fn tor_enabled(&self) -> bool {
         self.tor_enabled == 1
     }
+
+    pub fn consolidation_enabled(&self) -> bool {
+        self.consolidation_enabled == 1
+    }
+
+    pub fn nostr_enabled(&self) -> bool {
+        self.nostr_enabled == 1
+    }
+
+    /// Unix time the settings kept in the wallet manifest last changed,
+    /// None if they are still the defaults
+    pub fn settings_updated_at(&self) -> Option<u64> {
+        self.settings_updated_at.map(|t| t as u64)
+    }
 }
 
 #[derive(Insertable)]
@@ -71,6 +138,10 @@ impl From<&NewProfile> for Profile {
             seed_words: new_profile.seed_words.clone(),
             onchain_receive_enabled: 0,
             tor_enabled: 1,
+            consolidation_enabled: 1,
+            next_quote_key_index: 0,
+            nostr_enabled: 0,
+            settings_updated_at: None,
         }
     }
 }
@@ -4,6 +4,90 @@ diesel::table! {
     cashu_mint (mint_url) {
         mint_url -> Text,
         active -> Integer,
+        dleq_missing -> BigInt,
+        dleq_invalid -> BigInt,
+        dleq_unverified -> BigInt,
+        needs_reconcile -> Integer,
+    }
+}
+
+diesel::table! {
+    cashu_blind_auth_tokens (token) {
+        token -> Text,
+        mint_url -> Text,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    cashu_consolidations (id) {
+        id -> Text,
+        cashu_mint_url -> Text,
+        cashu_unit -> Text,
+        amount -> BigInt,
+        fee -> BigInt,
+        proofs_before -> Integer,
+        proofs_after -> Integer,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    cashu_mint_auth (mint_url) {
+        mint_url -> Text,
+        settings -> Text,
+        access_token -> Text,
+        refresh_token -> Nullable<Text>,
+        expires_at -> Nullable<BigInt>,
+    }
+}
+
+diesel::table! {
+    cashu_mint_units (mint_url, unit) {
+        mint_url -> Text,
+        unit -> Text,
+        active -> Integer,
+    }
+}
+
+diesel::table! {
+    ecash_receives (operation_id) {
+        operation_id -> Text,
+        fedimint_id -> Nullable<Text>,
+        cashu_mint_url -> Nullable<Text>,
+        token -> Text,
+        amount_msats -> BigInt,
+        fee_msats -> BigInt,
+        status -> Integer,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+        swap_mint_url -> Nullable<Text>,
+        swap_payment_hash -> Nullable<Text>,
+        swap_claimed -> Integer,
+        swap_quote_id -> Nullable<Text>,
+        cashu_unit -> Nullable<Text>,
+        unit_amount -> Nullable<BigInt>,
+        unit_fee -> Nullable<BigInt>,
+    }
+}
+
+diesel::table! {
+    ecash_sends (operation_id) {
+        operation_id -> Text,
+        fedimint_id -> Nullable<Text>,
+        cashu_mint_url -> Nullable<Text>,
+        token -> Text,
+        amount_msats -> BigInt,
+        fee_msats -> BigInt,
+        status -> Integer,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+        spending_conditions -> Nullable<Text>,
+        locktime -> Nullable<BigInt>,
+        reminded -> Integer,
+        cashu_unit -> Nullable<Text>,
+        unit_amount -> Nullable<BigInt>,
+        unit_fee -> Nullable<BigInt>,
     }
 }
 
@@ -29,6 +113,10 @@ diesel::table! {
         status -> Integer,
         created_at -> Timestamp,
         updated_at -> Timestamp,
+        parent_operation_id -> Nullable<Text>,
+        cashu_unit -> Nullable<Text>,
+        unit_amount -> Nullable<BigInt>,
+        unit_fee -> Nullable<BigInt>,
     }
 }
 
@@ -44,6 +132,10 @@ diesel::table! {
         status -> Integer,
         created_at -> Timestamp,
         updated_at -> Timestamp,
+        cashu_unit -> Nullable<Text>,
+        unit_amount -> Nullable<BigInt>,
+        unit_fee -> Nullable<BigInt>,
+        quote_key_index -> Nullable<Integer>,
     }
 }
 
@@ -61,6 +153,17 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    nostr_events (event_id) {
+        event_id -> Text,
+        event -> Nullable<Text>,
+        status -> Integer,
+        attempts -> Integer,
+        retry_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     on_chain_payments (operation_id) {
         operation_id -> Text,
@@ -91,15 +194,41 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    payment_requests (id) {
+        id -> Text,
+        cashu_mint_url -> Text,
+        cashu_unit -> Text,
+        amount -> Nullable<BigInt>,
+        description -> Nullable<Text>,
+        request -> Text,
+        status -> Integer,
+        ecash_receive_id -> Nullable<Text>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     profile (id) {
         id -> Text,
         seed_words -> Text,
         onchain_receive_enabled -> Integer,
         tor_enabled -> Integer,
+        consolidation_enabled -> Integer,
+        next_quote_key_index -> Integer,
+        nostr_enabled -> Integer,
+        settings_updated_at -> Nullable<BigInt>,
     }
 }
 
+diesel::joinable!(cashu_blind_auth_tokens -> cashu_mint_auth (mint_url));
+diesel::joinable!(cashu_consolidations -> cashu_mint (cashu_mint_url));
+diesel::joinable!(cashu_mint_units -> cashu_mint (mint_url));
+diesel::joinable!(ecash_receives -> cashu_mint (cashu_mint_url));
+diesel::joinable!(ecash_receives -> fedimint (fedimint_id));
+diesel::joinable!(ecash_sends -> cashu_mint (cashu_mint_url));
+diesel::joinable!(ecash_sends -> fedimint (fedimint_id));
 diesel::joinable!(lightning_payments -> cashu_mint (cashu_mint_url));
 diesel::joinable!(lightning_payments -> fedimint (fedimint_id));
 diesel::joinable!(lightning_receives -> cashu_mint (cashu_mint_url));
@@ -108,14 +237,24 @@ diesel::joinable!(on_chain_payments -> cashu_mint (cashu_mint_url));
 diesel::joinable!(on_chain_payments -> fedimint (fedimint_id));
 diesel::joinable!(on_chain_receives -> cashu_mint (cashu_mint_url));
 diesel::joinable!(on_chain_receives -> fedimint (fedimint_id));
+diesel::joinable!(payment_requests -> cashu_mint (cashu_mint_url));
+diesel::joinable!(payment_requests -> ecash_receives (ecash_receive_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    cashu_blind_auth_tokens,
+    cashu_consolidations,
     cashu_mint,
+    cashu_mint_auth,
+    cashu_mint_units,
+    ecash_receives,
+    ecash_sends,
     fedimint,
     lightning_payments,
     lightning_receives,
     mint_metadata,
+    nostr_events,
     on_chain_payments,
     on_chain_receives,
+    payment_requests,
     profile,
 );
@@ -2,12 +2,16 @@ use crate::MintIdentifier;
 use crate::db_models::PaymentStatus;
 use bitcoin::Txid;
 use bitcoin::hashes::Hash;
+use cdk::nuts::CurrencyUnit;
 use fedimint_core::config::FederationId;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TransactionItemKind {
     Lightning,
     Onchain,
+    Ecash,
+    /// Internal swap that merged fragmented cashu proofs, its amount is the fee that left the wallet
+    Consolidation,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -19,10 +23,15 @@ pub enum TransactionDirection {
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct TransactionItem {
     pub kind: TransactionItemKind,
+    /// In the base amount of `unit`, e.g. cents for usd
     pub amount: u64,
+    pub unit: CurrencyUnit,
     pub fee_msats: u64,
     pub txid: Option<Txid>,
     pub preimage: Option<[u8; 32]>,
+    pub token: Option<String>,
+    /// An unclaimed token we sent that we can still take back
+    pub reclaimable: bool,
     pub direction: TransactionDirection,
     pub mint_identifier: MintIdentifier,
     pub status: PaymentStatus,
@@ -34,9 +43,12 @@ impl TransactionItem {
         Self {
             kind: TransactionItemKind::Lightning,
             amount: 100,
+            unit: CurrencyUnit::Sat,
             fee_msats: 1000,
             txid: None,
             preimage: None,
+            token: None,
+            reclaimable: false,
             direction: TransactionDirection::Incoming,
             mint_identifier: MintIdentifier::Fedimint(FederationId::dummy()),
             status: PaymentStatus::Success,
@@ -48,9 +60,11 @@ impl TransactionItem {
         Self {
             kind: TransactionItemKind::Onchain,
             amount: 100,
+            unit: CurrencyUnit::Sat,
             fee_msats: 1000,
             txid: Some(Txid::all_zeros()),
             preimage: None,
+            token: None,
             direction: TransactionDirection::Outgoing,
             mint_identifier: MintIdentifier::Fedimint(FederationId::dummy()),
             status: PaymentStatus::Success,
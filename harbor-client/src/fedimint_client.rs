@@ -1,3 +1,4 @@
+use crate::backup::backup_to_federation;
 use crate::{
     CoreUIMsg, CoreUIMsgPacket, HarborCore, MintIdentifier, ReceiveSuccessMsg, SendSuccessMsg,
 };
@@ -12,6 +13,7 @@ use fedimint_client::ClientHandleArc;
 use fedimint_client::backup::Metadata;
 use fedimint_client::secret::{RootSecretStrategy, get_default_client_secret};
 use fedimint_client_module::oplog::UpdateStreamOrOutcome;
+use fedimint_core::Amount;
 use fedimint_core::config::FederationId;
 use fedimint_core::core::OperationId;
 use fedimint_core::db::IDatabaseTransactionOps;
@@ -26,7 +28,8 @@ use fedimint_ln_client::{
 };
 use fedimint_ln_common::LightningGateway;
 use fedimint_lnv2_client::{LightningOperationMeta, ReceiveOperationState, SendOperationState};
-use fedimint_mint_client::MintClientInit;
+use fedimint_mint_client::api::MintFederationApi;
+use fedimint_mint_client::{MintClientInit, OOBNotes, ReissueExternalNotesState, SpendOOBState};
 use fedimint_wallet_client::{DepositStateV2, WalletClientInit, WalletClientModule, WithdrawState};
 use futures::StreamExt;
 use futures::channel::mpsc::Sender;
@@ -45,6 +48,41 @@ use uuid::Uuid;
 pub struct FedimintClient {
     pub(crate) fedimint_client: ClientHandleArc,
     stop: Arc<AtomicBool>,
+    /// Metadata of the backup we recovered from when joining, if there was one
+    pub(crate) backup_metadata: Option<Metadata>,
+    recovery: RecoveryState,
+}
+
+/// How far a client joined from a backup got with recovering its ecash
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) enum RecoveryState {
+    /// Nothing to recover, or it finished
+    #[default]
+    Done,
+    /// Modules are still recovering, the client has to be reopened once they
+    /// finish before it can be used
+    Recovering,
+    /// Recovering stopped with this error, the client can't be used
+    Failed(String),
+}
+
+impl RecoveryState {
+    /// Only a recovery in progress can fail, a finished one stays usable
+    fn fail(&mut self, reason: String) {
+        if *self == Self::Recovering {
+            *self = Self::Failed(reason);
+        }
+    }
+
+    fn ensure_usable(&self) -> anyhow::Result<()> {
+        match self {
+            Self::Done => Ok(()),
+            Self::Recovering => Err(anyhow!(
+                "Mint is still recovering, try again once it finishes"
+            )),
+            Self::Failed(reason) => Err(anyhow!("Mint failed to recover: {reason}")),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -111,10 +149,23 @@ impl FedimintClient {
         let secret = get_default_client_secret(&root_secret, &federation_id);
 
         let fedimint_client = if is_initialized {
-            Arc::new(client_builder.open(secret).await.map_err(|e| {
+            let client = Arc::new(client_builder.open(secret).await.map_err(|e| {
                 error!("Could not open federation client: {e}");
                 e
-            })?)
+            })?);
+
+            // a recovery from before a restart, the core picks it up again
+            if client.has_pending_recoveries() {
+                info!("Federation {federation_id} is still recovering");
+                return Ok(Self {
+                    fedimint_client: client,
+                    stop,
+                    backup_metadata: None,
+                    recovery: RecoveryState::Recovering,
+                });
+            }
+
+            client
         } else if let FederationInviteOrId::Invite(ref invite_code) = invite_or_id {
             let download = Instant::now();
             let config = {
@@ -152,6 +203,7 @@ impl FedimintClient {
                         })?,
                 ),
                 Some(backup) => {
+                    let backup_metadata = backup.metadata.clone();
                     let client = client_builder
                         .recover(secret, config, invite_code.api_secret(), Some(backup))
                         .await
@@ -160,53 +212,23 @@ impl FedimintClient {
                             e
                         })?;
 
+                    info!("Recovering federation {federation_id} in the background");
                     HarborCore::send_msg(
                         &mut sender,
                         msg_id,
                         CoreUIMsg::StatusUpdate {
-                            message: "Recovering federation notes".to_string(),
+                            message: "Recovering federation notes in the background".to_string(),
                             operation_id: msg_id,
                         },
                     )
                     .await;
-                    match client.wait_for_all_recoveries().await {
-                        Ok(()) => {
-                            info!("Federation successfully recovered");
-                            HarborCore::send_msg(
-                                &mut sender,
-                                msg_id,
-                                CoreUIMsg::StatusUpdate {
-                                    message: "Successfully recovered federation notes".to_string(),
-                                    operation_id: msg_id,
-                                },
-                            )
-                            .await;
-
-                            let fut = Box::pin(Self::new(
-                                storage,
-                                invite_or_id,
-                                mnemonic,
-                                network,
-                                stop,
-                                sender,
-                                msg_id,
-                            ));
-                            return fut.await;
-                        }
-                        Err(e) => {
-                            error!("Could not recover federation: {e}");
-                            HarborCore::send_msg(
-                                &mut sender,
-                                msg_id,
-                                CoreUIMsg::StatusUpdate {
-                                    message: "Failed recovered federation notes".to_string(),
-                                    operation_id: msg_id,
-                                },
-                            )
-                            .await;
-                            return Err(e);
-                        }
-                    }
+
+                    return Ok(Self {
+                        fedimint_client: Arc::new(client),
+                        stop,
+                        backup_metadata: Some(backup_metadata),
+                        recovery: RecoveryState::Recovering,
+                    });
                 }
             }
         } else {
@@ -235,10 +257,11 @@ impl FedimintClient {
 
         // Create a backup
         let client = fedimint_client.clone();
+        let backup_storage = storage.clone();
         spawn(async move {
             info!("Creating backup to federation");
             let start = Instant::now();
-            match client.backup_to_federation(Metadata::empty()).await {
+            match backup_to_federation(&client, backup_storage.as_ref()).await {
                 Err(e) => error!("Could not create backup to federation: {e}"),
                 Ok(()) => info!("Successfully created backup to federation"),
             }
@@ -279,12 +302,81 @@ impl FedimintClient {
         Ok(Self {
             fedimint_client,
             stop,
+            backup_metadata: None,
+            recovery: RecoveryState::Done,
         })
     }
 
     pub fn federation_id(&self) -> FederationId {
         self.fedimint_client.federation_id()
     }
+
+    pub fn is_recovering(&self) -> bool {
+        self.recovery == RecoveryState::Recovering
+    }
+
+    /// Why recovering from the backup failed, if it did
+    pub fn recovery_error(&self) -> Option<&str> {
+        match &self.recovery {
+            RecoveryState::Failed(reason) => Some(reason),
+            _ => None,
+        }
+    }
+
+    /// Whether the client finished any recovery and can be used
+    pub fn is_usable(&self) -> bool {
+        self.recovery == RecoveryState::Done
+    }
+
+    /// Errors with why the client can't be used yet, if it can't
+    pub fn ensure_usable(&self) -> anyhow::Result<()> {
+        self.recovery.ensure_usable()
+    }
+
+    /// Stops showing the client as recovering once its recovery failed
+    pub(crate) fn fail_recovery(&mut self, reason: String) {
+        self.recovery.fail(reason);
+    }
+}
+
+/// Waits for the client's module recoveries to finish, forwarding their progress to the UI
+pub(crate) async fn wait_for_recovery(
+    client: &ClientHandleArc,
+    mut sender: Sender<CoreUIMsgPacket>,
+) -> anyhow::Result<()> {
+    let federation_id = client.federation_id();
+    let config = client.config().await;
+    let mut progress = Box::pin(client.subscribe_to_recovery_progress());
+
+    let forward_progress = async {
+        while let Some((module_id, progress)) = progress.next().await {
+            let Some(module) = config.modules.get(&module_id).map(|m| m.kind().clone()) else {
+                continue;
+            };
+            trace!(
+                "Recovery of {module} in {federation_id}: {}/{}",
+                progress.complete, progress.total
+            );
+            HarborCore::send_msg(
+                &mut sender,
+                None,
+                CoreUIMsg::FederationRecoveryProgress {
+                    id: federation_id,
+                    module,
+                    complete: progress.complete,
+                    total: progress.total,
+                },
+            )
+            .await;
+        }
+        // the recoveries finishing is what ends this
+        std::future::pending::<()>().await;
+    };
+
+    tokio::select! {
+        res = client.wait_for_all_recoveries() => res,
+        () = forward_progress => Ok(()),
+    }
 }
 
 pub(crate) async fn select_gateway(client: &ClientHandleArc) -> Option<LightningGateway> {
@@ -375,6 +467,27 @@ pub(crate) async fn get_ln_tx_fee(
     }
 }
 
+/// Asks the federation whether every note has been spent, so the recipient
+/// reissued them. The spend subscription only finds out once the refund
+/// timeout passes.
+pub(crate) async fn notes_spent(
+    client: &ClientHandleArc,
+    notes: &OOBNotes,
+) -> anyhow::Result<bool> {
+    let instance = client
+        .get_first_instance(&fedimint_mint_client::KIND)
+        .ok_or(anyhow!("Federation has no mint module"))?;
+    let api = client.api().with_module(instance);
+
+    for (_, note) in notes.notes().iter_items() {
+        if !api.check_note_spent(note.nonce()).await? {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
 pub(crate) async fn spawn_invoice_receive_subscription(
     mut sender: Sender<CoreUIMsgPacket>,
     client: ClientHandleArc,
@@ -441,10 +554,9 @@ pub(crate) async fn spawn_invoice_receive_subscription(
 
                     update_history(storage.clone(), msg_id, &mut sender).await;
 
-                    client
-                        .backup_to_federation(Metadata::empty())
-                        .await
-                        .expect("Could not backup");
+                    if let Err(e) = backup_to_federation(&client, storage.as_ref()).await {
+                        error!("Could not create backup to federation: {e}");
+                    }
 
                     break;
                 }
@@ -504,10 +616,9 @@ pub(crate) async fn spawn_lnv2_receive_subscription(
 
                     update_history(storage.clone(), msg_id, &mut sender).await;
 
-                    client
-                        .backup_to_federation(Metadata::empty())
-                        .await
-                        .expect("Could not backup");
+                    if let Err(e) = backup_to_federation(&client, storage.as_ref()).await {
+                        error!("Could not create backup to federation: {e}");
+                    }
 
                     break;
                 }
@@ -980,10 +1091,159 @@ pub(crate) async fn spawn_onchain_receive_subscription(
 
                     update_history(storage.clone(), msg_id, &mut sender).await;
 
-                    client
-                        .backup_to_federation(Metadata::empty())
-                        .await
-                        .expect("Could not backup");
+                    if let Err(e) = backup_to_federation(&client, storage.as_ref()).await {
+                        error!("Could not create backup to federation: {e}");
+                    }
+
+                    break;
+                }
+            }
+        }
+    });
+}
+
+/// Tracks out-of-band notes we handed out until they are claimed or refunded
+pub(crate) async fn spawn_spend_notes_subscription(
+    mut sender: Sender<CoreUIMsgPacket>,
+    client: ClientHandleArc,
+    storage: Arc<dyn DBConnection + Send + Sync>,
+    operation_id: OperationId,
+    msg_id: Uuid,
+    subscription: UpdateStreamOrOutcome<SpendOOBState>,
+) {
+    info!(
+        "Spawning spend notes subscription for operation id: {}",
+        operation_id.fmt_full()
+    );
+    spawn(async move {
+        let mut stream = subscription.into_stream();
+        while let Some(op_state) = stream.next().await {
+            match op_state {
+                SpendOOBState::Created | SpendOOBState::UserCanceledProcessing => {}
+                // the recipient reissued the notes before we could take them back
+                SpendOOBState::Success | SpendOOBState::UserCanceledFailure => {
+                    info!("Ecash notes claimed");
+                    if let Err(e) =
+                        storage.mark_ecash_send_as_success(operation_id.fmt_full().to_string())
+                    {
+                        error!("Could not mark ecash send as success: {e}");
+                    }
+
+                    update_history(storage, msg_id, &mut sender).await;
+
+                    break;
+                }
+                SpendOOBState::UserCanceledSuccess | SpendOOBState::Refunded => {
+                    info!("Ecash notes reclaimed");
+                    if let Err(e) =
+                        storage.mark_ecash_send_as_failed(operation_id.fmt_full().to_string())
+                    {
+                        error!("Could not mark ecash send as failed: {e}");
+                    }
+
+                    let new_balance = client.get_balance().await;
+                    HarborCore::send_msg(
+                        &mut sender,
+                        Some(msg_id),
+                        CoreUIMsg::MintBalanceUpdated {
+                            id: MintIdentifier::Fedimint(client.federation_id()),
+                            balance: new_balance,
+                        },
+                    )
+                    .await;
+
+                    update_history(storage, msg_id, &mut sender).await;
+
+                    break;
+                }
+            }
+        }
+    });
+}
+
+pub(crate) async fn spawn_reissue_notes_subscription(
+    mut sender: Sender<CoreUIMsgPacket>,
+    client: ClientHandleArc,
+    storage: Arc<dyn DBConnection + Send + Sync>,
+    operation_id: OperationId,
+    msg_id: Uuid,
+    subscription: UpdateStreamOrOutcome<ReissueExternalNotesState>,
+) {
+    info!(
+        "Spawning reissue notes subscription for operation id: {}",
+        operation_id.fmt_full()
+    );
+    spawn(async move {
+        let mut stream = subscription.into_stream();
+        while let Some(op_state) = stream.next().await {
+            match op_state {
+                ReissueExternalNotesState::Created => {
+                    HarborCore::send_msg(
+                        &mut sender,
+                        Some(msg_id),
+                        CoreUIMsg::StatusUpdate {
+                            message: "Submitting notes to federation".to_string(),
+                            operation_id: Some(msg_id),
+                        },
+                    )
+                    .await;
+                }
+                ReissueExternalNotesState::Issuing => {
+                    HarborCore::send_msg(
+                        &mut sender,
+                        Some(msg_id),
+                        CoreUIMsg::StatusUpdate {
+                            message: "Reissuing notes".to_string(),
+                            operation_id: Some(msg_id),
+                        },
+                    )
+                    .await;
+                }
+                ReissueExternalNotesState::Failed(error) => {
+                    error!("Reissuing notes failed: {error}");
+                    HarborCore::send_msg(
+                        &mut sender,
+                        Some(msg_id),
+                        CoreUIMsg::ReceiveFailed(error),
+                    )
+                    .await;
+
+                    if let Err(e) =
+                        storage.mark_ecash_receive_as_failed(operation_id.fmt_full().to_string())
+                    {
+                        error!("Could not mark ecash receive as failed: {e}");
+                    }
+
+                    break;
+                }
+                ReissueExternalNotesState::Done => {
+                    info!("Reissuing notes success");
+                    HarborCore::send_msg(
+                        &mut sender,
+                        Some(msg_id),
+                        CoreUIMsg::ReceiveSuccess(ReceiveSuccessMsg::Ecash),
+                    )
+                    .await;
+
+                    if let Err(e) = storage.mark_ecash_receive_as_success(
+                        operation_id.fmt_full().to_string(),
+                        Amount::ZERO,
+                    ) {
+                        error!("Could not mark ecash receive as success: {e}");
+                    }
+
+                    let new_balance = client.get_balance().await;
+                    HarborCore::send_msg(
+                        &mut sender,
+                        Some(msg_id),
+                        CoreUIMsg::MintBalanceUpdated {
+                            id: MintIdentifier::Fedimint(client.federation_id()),
+                            balance: new_balance,
+                        },
+                    )
+                    .await;
+
+                    update_history(storage, msg_id, &mut sender).await;
 
                     break;
                 }
@@ -1148,3 +1408,29 @@ impl IDatabaseTransactionOps for SQLPseudoTransaction<'_> {
         self.mem.set_tx_savepoint().await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_failed_recovery() {
+        let mut recovery = RecoveryState::Recovering;
+        assert!(recovery.ensure_usable().is_err());
+
+        // a failed recovery no longer counts as recovering, and says why it can't be used
+        recovery.fail("guardians unreachable".to_string());
+        assert_eq!(
+            recovery,
+            RecoveryState::Failed("guardians unreachable".to_string())
+        );
+        let e = recovery.ensure_usable().unwrap_err();
+        assert!(e.to_string().contains("guardians unreachable"));
+
+        // a client that finished recovering stays usable
+        let mut recovery = RecoveryState::Done;
+        recovery.fail("late error".to_string());
+        assert_eq!(recovery, RecoveryState::Done);
+        assert!(recovery.ensure_usable().is_ok());
+    }
+}
@@ -1,10 +1,10 @@
 use anyhow::anyhow;
-use arti_client::{TorAddr, TorClient};
+use arti_client::{DataStream, TorAddr, TorClient};
 use fedimint_core::util::SafeUrl;
 use http_body_util::Empty;
 use hyper::body::{Body, Bytes};
 use hyper::header::LOCATION;
-use hyper::{Request, Uri};
+use hyper::{Method, Request, StatusCode, Uri};
 use hyper_rustls::HttpsConnectorBuilder;
 use hyper_util::client::legacy::Client;
 use hyper_util::rt::TokioExecutor;
@@ -15,6 +15,7 @@ use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
 use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_rustls::client::TlsStream;
 use tokio_rustls::rustls::RootCertStore;
 use tor_rtcompat::PreferredRuntime;
 use url::Url;
@@ -112,6 +113,26 @@ where
         return Err(anyhow!("Only HTTPS is supported"));
     }
 
+    // Parse the URL properly
+    let parsed_url = Url::parse(url)?;
+    // Get the path and query string
+    let path = if let Some(query) = parsed_url.query() {
+        format!("{}?{}", parsed_url.path(), query)
+    } else {
+        parsed_url.path().to_string()
+    };
+
+    let (host, tls_stream) = connect_tor_tls(&safe_url, cancel_handle.clone()).await?;
+
+    make_request_tor(host, path, payload, tls_stream, cancel_handle).await
+}
+
+/// Opens a TLS stream to the url's host through the Tor network,
+/// returning the host name alongside it
+pub(crate) async fn connect_tor_tls(
+    safe_url: &SafeUrl,
+    cancel_handle: Arc<AtomicBool>,
+) -> anyhow::Result<(String, TlsStream<DataStream>)> {
     // Get a reference to the global TorClient
     let tor_client = get_tor_client()?;
 
@@ -149,15 +170,6 @@ where
     let port = safe_url
         .port_or_known_default()
         .ok_or_else(|| anyhow::anyhow!("Expected port number"))?;
-
-    // Parse the URL properly
-    let parsed_url = Url::parse(url)?;
-    // Get the path and query string
-    let path = if let Some(query) = parsed_url.query() {
-        format!("{}?{}", parsed_url.path(), query)
-    } else {
-        parsed_url.path().to_string()
-    };
     let is_onion = safe_url.is_onion_address();
 
     let tor_addr = TorAddr::from((host.as_str(), port))
@@ -221,6 +233,20 @@ where
     };
 
     // After getting the stream, wrap it in TLS
+    let tls_stream = tls_handshake(&host, stream, cancel_handle).await?;
+
+    Ok((host, tls_stream))
+}
+
+/// Wraps a stream in TLS using the webpki roots
+pub(crate) async fn tls_handshake<S>(
+    host: &str,
+    stream: S,
+    cancel_handle: Arc<AtomicBool>,
+) -> anyhow::Result<TlsStream<S>>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
     let mut root_store = RootCertStore::empty();
     root_store.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
 
@@ -229,7 +255,7 @@ where
         .with_no_client_auth();
 
     let connector = tokio_rustls::TlsConnector::from(Arc::new(config));
-    let server_name = rustls_pki_types::ServerName::try_from(host.as_str())
+    let server_name = rustls_pki_types::ServerName::try_from(host)
         .map_err(|_| anyhow!("Invalid DNS name: {}", host))?
         .to_owned();
 
@@ -259,7 +285,7 @@ where
         }
     };
 
-    make_request_tor(host, path, payload, tls_stream, cancel_handle).await
+    Ok(tls_stream)
 }
 
 async fn make_request_tor<T, S, P>(
@@ -497,6 +523,121 @@ fn build_post_request<P: Serialize + Sized>(
         .map_err(|e| anyhow!("Failed to build request: {}", e))
 }
 
+/// The body of a request made with [`make_raw_request`]
+pub(crate) enum RequestBody {
+    Json(String),
+    Form(String),
+}
+
+/// Make a request with extra headers, through Tor when enabled.
+///
+/// Unlike the other helpers this returns the status and body as they are, so
+/// callers can read the error responses of APIs that put details in them.
+/// Redirects are not followed.
+pub(crate) async fn make_raw_request(
+    url: &str,
+    body: Option<RequestBody>,
+    headers: &[(&str, String)],
+    tor_enabled: bool,
+    cancel_handle: Arc<AtomicBool>,
+) -> anyhow::Result<(StatusCode, Bytes)> {
+    let safe_url = SafeUrl::parse(url)?;
+    if safe_url.scheme() != "https" {
+        return Err(anyhow!("Only HTTPS is supported"));
+    }
+
+    let (method, content_type, body) = match body {
+        None => (Method::GET, None, String::new()),
+        Some(RequestBody::Json(body)) => (Method::POST, Some("application/json"), body),
+        Some(RequestBody::Form(body)) => (
+            Method::POST,
+            Some("application/x-www-form-urlencoded"),
+            body,
+        ),
+    };
+
+    let mut builder = Request::builder()
+        .method(method)
+        .header("User-Agent", USER_AGENT);
+    if let Some(content_type) = content_type {
+        builder = builder.header("Content-Type", content_type);
+    }
+    for (name, value) in headers {
+        builder = builder.header(*name, value);
+    }
+
+    let response = if tor_enabled {
+        log::debug!("Making raw request to tor: {}", url);
+
+        let parsed_url = Url::parse(url)?;
+        let path = if let Some(query) = parsed_url.query() {
+            format!("{}?{}", parsed_url.path(), query)
+        } else {
+            parsed_url.path().to_string()
+        };
+
+        let (host, tls_stream) = connect_tor_tls(&safe_url, cancel_handle.clone()).await?;
+        if cancel_handle.load(Ordering::Relaxed) {
+            return Err(anyhow!("Request cancelled"));
+        }
+
+        let request = builder
+            .uri(path)
+            .header("Host", host)
+            .body(body)
+            .map_err(|e| anyhow!("Failed to build request: {}", e))?;
+
+        let io = hyper_util::rt::TokioIo::new(tls_stream);
+        let (mut sender, conn) = hyper::client::conn::http1::handshake(io).await?;
+        tokio::spawn(async move {
+            if let Err(err) = conn.await {
+                log::error!("Connection driver failed: {:?}", err);
+            }
+        });
+
+        sender.send_request(request).await?
+    } else {
+        log::debug!("Making raw direct request to: {}", url);
+
+        let https = HttpsConnectorBuilder::new()
+            .with_webpki_roots()
+            .https_only()
+            .enable_http1()
+            .build();
+        let client: Client<_, String> = Client::builder(TokioExecutor::new())
+            .pool_idle_timeout(Duration::from_secs(30))
+            .pool_max_idle_per_host(1)
+            .build(https);
+
+        let uri: Uri = url
+            .parse()
+            .map_err(|e| anyhow!("Invalid URL '{}': {}", url, e))?;
+        let request = builder
+            .uri(uri)
+            .body(body)
+            .map_err(|e| anyhow!("Failed to build request: {}", e))?;
+
+        client
+            .request(request)
+            .await
+            .map_err(|e| anyhow!("HTTP request failed: {}", e))?
+    };
+
+    let status = response.status();
+    let body_bytes = http_body_util::BodyExt::collect(response.into_body())
+        .await?
+        .to_bytes();
+
+    if body_bytes.len() > MAX_RESPONSE_SIZE {
+        return Err(anyhow!(
+            "Response too large, exceeded {} bytes",
+            MAX_RESPONSE_SIZE
+        ));
+    }
+
+    Ok((status, body_bytes))
+}
+
 fn make_get_request_direct_internal<T>(
     url: String,
     redirect_count: u8,
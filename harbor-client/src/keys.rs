@@ -0,0 +1,168 @@
+use bip39::Mnemonic;
+use bitcoin::Network;
+use bitcoin::bip32::{ChildNumber, DerivationPath, Xpriv};
+use bitcoin::hashes::{Hash, HashEngine, Hmac, HmacEngine, sha256};
+use bitcoin::secp256k1::Secp256k1;
+use cdk::nuts::SecretKey;
+use nostr::Keys;
+use std::str::FromStr;
+
+/// Derivation path for the key Cashu tokens get locked to (NUT-11)
+const CASHU_P2PK_PATH: &str = "m/129372'/10'/0'/0'/0'";
+
+/// Derivation path for the keys Cashu mint quotes are locked to (NUT-20), indexed per quote
+const CASHU_MINT_QUOTE_PATH: &str = "m/129372'/20'/0'/0'";
+
+/// Derivation path for the key our wallet manifest is encrypted with before it goes into backups
+const BACKUP_MANIFEST_PATH: &str = "m/129372'/30'/0'/0'/0'";
+
+/// Derivation path for our nostr identity (NIP-06), ecash gets sent to it as DMs
+const NOSTR_PATH: &str = "m/44'/1237'/0'/0/0";
+
+/// Derivation path for the key that picks each domain's LNURL-auth linking key (LUD-05)
+const LNURL_AUTH_HASHING_PATH: &str = "m/138'/0";
+
+/// The LNURL-auth linking keys are derived under this, per domain (LUD-05)
+const LNURL_AUTH_HARDENED_INDEX: u32 = 138;
+
+/// Derives a private key from the wallet seed so it survives a restore
+fn derive_secret_key(
+    mnemonic: &Mnemonic,
+    path: &str,
+) -> anyhow::Result<bitcoin::secp256k1::SecretKey> {
+    derive_secret_key_at(mnemonic, &DerivationPath::from_str(path)?)
+}
+
+fn derive_secret_key_at(
+    mnemonic: &Mnemonic,
+    path: &DerivationPath,
+) -> anyhow::Result<bitcoin::secp256k1::SecretKey> {
+    let seed = mnemonic.to_seed_normalized("");
+    // the network only changes the xpriv encoding, not the derived keys
+    let root = Xpriv::new_master(Network::Bitcoin, &seed)?;
+    let xpriv = root.derive_priv(&Secp256k1::new(), path)?;
+    Ok(xpriv.private_key)
+}
+
+/// The key used to sign for P2PK locked Cashu tokens sent to us
+pub fn cashu_p2pk_key(mnemonic: &Mnemonic) -> anyhow::Result<SecretKey> {
+    let key = derive_secret_key(mnemonic, CASHU_P2PK_PATH)?;
+    Ok(SecretKey::from_slice(&key.secret_bytes())?)
+}
+
+/// The key a Cashu mint quote is locked to, so only we can mint its tokens
+pub fn cashu_mint_quote_key(mnemonic: &Mnemonic, index: u32) -> anyhow::Result<SecretKey> {
+    let key = derive_secret_key(mnemonic, &format!("{CASHU_MINT_QUOTE_PATH}/{index}'"))?;
+    Ok(SecretKey::from_slice(&key.secret_bytes())?)
+}
+
+/// The secret the wallet manifest in our federation backups is encrypted with
+pub fn backup_manifest_secret(mnemonic: &Mnemonic) -> anyhow::Result<[u8; 32]> {
+    let key = derive_secret_key(mnemonic, BACKUP_MANIFEST_PATH)?;
+    Ok(key.secret_bytes())
+}
+
+/// Our nostr keys, senders deliver ecash to them as encrypted DMs
+pub fn nostr_keys(mnemonic: &Mnemonic) -> anyhow::Result<Keys> {
+    let key = derive_secret_key(mnemonic, NOSTR_PATH)?;
+    Ok(Keys::new(nostr::SecretKey::from_slice(
+        &key.secret_bytes(),
+    )?))
+}
+
+/// The key we log into `domain` with over LNURL-auth. Every domain gets its own
+/// so services can't link our logins together.
+pub fn lnurl_auth_key(
+    mnemonic: &Mnemonic,
+    domain: &str,
+) -> anyhow::Result<bitcoin::secp256k1::SecretKey> {
+    let hashing_key = derive_secret_key(mnemonic, LNURL_AUTH_HASHING_PATH)?;
+    let mut engine = HmacEngine::<sha256::Hash>::new(&hashing_key.secret_bytes());
+    engine.input(domain.as_bytes());
+    let derivation_material = Hmac::<sha256::Hash>::from_engine(engine).to_byte_array();
+
+    // m/138'/<long1>/<long2>/<long3>/<long4> from the first 16 bytes
+    let mut path = vec![ChildNumber::from_hardened_idx(LNURL_AUTH_HARDENED_INDEX)?];
+    path.extend(derivation_material[..16].chunks_exact(4).map(|chunk| {
+        ChildNumber::from(u32::from_be_bytes(
+            chunk.try_into().expect("chunks are 4 bytes"),
+        ))
+    }));
+    derive_secret_key_at(mnemonic, &DerivationPath::from(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEED_WORDS: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+    #[test]
+    fn test_cashu_p2pk_key_is_deterministic() {
+        let mnemonic = Mnemonic::from_str(SEED_WORDS).unwrap();
+
+        let first = cashu_p2pk_key(&mnemonic).unwrap();
+        let second = cashu_p2pk_key(&mnemonic).unwrap();
+        assert_eq!(first.public_key(), second.public_key());
+
+        let other =
+            Mnemonic::from_str("zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo wrong").unwrap();
+        assert_ne!(
+            cashu_p2pk_key(&other).unwrap().public_key(),
+            first.public_key()
+        );
+    }
+
+    #[test]
+    fn test_cashu_mint_quote_key_per_index() {
+        let mnemonic = Mnemonic::from_str(SEED_WORDS).unwrap();
+
+        let first = cashu_mint_quote_key(&mnemonic, 0).unwrap();
+        assert_eq!(
+            first.public_key(),
+            cashu_mint_quote_key(&mnemonic, 0).unwrap().public_key()
+        );
+        assert_ne!(
+            first.public_key(),
+            cashu_mint_quote_key(&mnemonic, 1).unwrap().public_key()
+        );
+        assert_ne!(
+            first.public_key(),
+            cashu_p2pk_key(&mnemonic).unwrap().public_key()
+        );
+    }
+
+    #[test]
+    fn test_lnurl_auth_key_per_domain() {
+        let mnemonic = Mnemonic::from_str(SEED_WORDS).unwrap();
+        let secp = Secp256k1::new();
+
+        let site = lnurl_auth_key(&mnemonic, "site.com").unwrap();
+        assert_eq!(
+            site.public_key(&secp),
+            lnurl_auth_key(&mnemonic, "site.com")
+                .unwrap()
+                .public_key(&secp)
+        );
+        assert_ne!(
+            site.public_key(&secp),
+            lnurl_auth_key(&mnemonic, "other.site.com")
+                .unwrap()
+                .public_key(&secp)
+        );
+    }
+
+    #[test]
+    fn test_nostr_keys_nip06_vector() {
+        let mnemonic = Mnemonic::from_str(
+            "leader monkey parrot ring guide accident before fence cannon height naive bean",
+        )
+        .unwrap();
+
+        let keys = nostr_keys(&mnemonic).unwrap();
+        assert_eq!(
+            keys.public_key().to_hex(),
+            "17162c921dc4d2518f9a101db33695df1afb56ab82f5ff3e5da6eec3ca5cd917"
+        );
+    }
+}
@@ -22,18 +22,31 @@
     clippy::too_many_lines
 )]
 
+use crate::backup::{BackupPause, WalletManifest, backup_to_federation};
+use crate::cashu_auth::DeviceLogin;
 use crate::cashu_client::{
-    TorMintConnector, spawn_lightning_payment_thread, spawn_lightning_receive_thread,
+    DleqVerifyingConnector, MeltCost, confirmed_melt_quote, consolidate_proofs, lock_wallet,
+    melt_and_wait, mint_quote_locked, quote_multi_path_part, reconcile_proofs, select_melt_proofs,
+    spawn_lightning_payment_thread, spawn_lightning_receive_thread,
+    spawn_multi_path_payment_thread, swap_inactive_keyset_proofs,
 };
 use crate::db::DBConnection;
-use crate::db_models::MintItem;
 use crate::db_models::transaction_item::TransactionItem;
+use crate::db_models::{DleqAudit, EcashSend, MintItem, PaymentStatus};
 use crate::fedimint_client::{
-    FederationInviteOrId, FedimintClient, select_gateway, spawn_internal_payment_subscription,
-    spawn_invoice_payment_subscription, spawn_invoice_receive_subscription,
-    spawn_onchain_payment_subscription, spawn_onchain_receive_subscription,
+    FederationInviteOrId, FedimintClient, notes_spent, select_gateway,
+    spawn_internal_payment_subscription, spawn_invoice_payment_subscription,
+    spawn_invoice_receive_subscription, spawn_onchain_payment_subscription,
+    spawn_onchain_receive_subscription, spawn_reissue_notes_subscription,
+    spawn_spend_notes_subscription, update_history, wait_for_recovery,
 };
+use crate::http::{RequestBody, make_raw_request};
 use crate::metadata::{CACHE, FederationData, FederationMeta, get_federation_metadata};
+use crate::mint_discovery::{DiscoveredMint, discover_mints};
+use crate::nostr_client::{
+    DEFAULT_NOSTR_RELAYS, NostrEcash, NostrRelay, RelayMessage, dm_filter, parse_ecash_message,
+    send_dm, unwrap_dm,
+};
 use ::fedimint_client::ClientHandleArc;
 use anyhow::anyhow;
 use bip39::Mnemonic;
@@ -41,8 +54,13 @@ use bitcoin::address::NetworkUnchecked;
 use bitcoin::{Address, Network, Txid};
 use cdk::cdk_database::WalletDatabase;
 use cdk::mint_url::MintUrl;
-use cdk::nuts::{CurrencyUnit, MintInfo};
-use cdk::wallet::WalletBuilder;
+use cdk::nuts::nut18::{PaymentRequest, PaymentRequestPayload, Transport, TransportType};
+use cdk::nuts::{
+    Conditions, CurrencyUnit, MeltQuoteState, MintInfo, Proofs, ProofsMethods, PublicKey,
+    SpendingConditions, State, Token,
+};
+use cdk::util::unix_time;
+use cdk::wallet::{MeltQuote, ReceiveOptions, SendOptions, WalletBuilder};
 use cdk_sqlite::WalletSqliteDatabase;
 use fedimint_client::{spawn_lnv2_payment_subscription, spawn_lnv2_receive_subscription};
 use fedimint_core::Amount;
@@ -52,23 +70,59 @@ use fedimint_core::invite_code::InviteCode;
 use fedimint_ln_client::{LightningClientModule, PayType};
 use fedimint_ln_common::config::FeeToAmount;
 use fedimint_ln_common::lightning_invoice::{Bolt11InvoiceDescription, Description};
+use fedimint_mint_client::{MintClientModule, SelectNotesWithAtleastAmount, SpendOOBState};
 use fedimint_wallet_client::WalletClientModule;
-use futures::{SinkExt, channel::mpsc::Sender};
+use futures::{SinkExt, StreamExt, channel::mpsc::Sender};
 use lightning_address::make_lnurl_request;
 use lnurl::lnurl::LnUrl;
 use log::{error, trace};
+use nostr::nips::nip19::{FromBech32, Nip19Profile, ToBech32};
+use nostr::{JsonUtil, Keys, RelayUrl};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 use std::str::FromStr;
 use std::sync::Arc;
 use std::sync::atomic::AtomicBool;
 use std::sync::atomic::Ordering;
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tokio::spawn;
 use tokio::sync::RwLock;
 use uuid::Uuid;
 
+/// How long out-of-band fedimint notes stay claimable before we take them back
+const ECASH_RECLAIM_TIMEOUT: Duration = Duration::from_secs(60 * 60 * 24 * 7);
+
+/// How often we check if the recipient claimed a cashu token we sent
+const PENDING_ECASH_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 5);
+
+/// How often we look for timelocked tokens that are about to unlock
+const TIMELOCK_REMINDER_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// How long before a locktime passes we remind the user to reclaim or refresh the token
+const TIMELOCK_REMINDER_WINDOW: Duration = Duration::from_secs(60 * 60 * 24 * 3);
+
+/// How often we check for cashu proofs left on keysets the mint has rotated out
+const KEYSET_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// How often we check cashu mints with a failed swap, mint or melt against our proofs
+const RECONCILE_INTERVAL: Duration = Duration::from_secs(60 * 5);
+
+/// How often we look for fragmented cashu wallets to consolidate
+const CONSOLIDATION_INTERVAL: Duration = Duration::from_secs(60 * 60 * 6);
+
+/// How long we wait before reconnecting to a nostr relay that dropped us
+const NOSTR_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+/// How often a nostr listener waiting on its relay checks if we're shutting down
+const NOSTR_STOP_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How often we look for nostr DMs whose ecash failed to redeem and are due for a retry
+const NOSTR_RETRY_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How many invoices we try when swapping in a token before its mint's fee reserve fits
+const SWAP_QUOTE_ATTEMPTS: usize = 3;
+
 /// The directory where all application data is stored
 /// Defaults to ~/.harbor as the root directory
 /// Network-specific data goes in ~/.harbor/<network>
@@ -89,13 +143,47 @@ pub fn data_dir(network: Option<Network>) -> PathBuf {
     }
 }
 
+/// Lightning and LNURL amounts are in sats. Cashu wallets of other units count
+/// in the unit's base amount, so they can't take them without a conversion.
+fn require_sat_mint(mint: &MintIdentifier) -> anyhow::Result<()> {
+    let unit = mint.unit();
+    if unit == CurrencyUnit::Sat {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "Amounts here are in sats, pick a sat mint instead of a {unit} one"
+        ))
+    }
+}
+
+/// Our guess at a mint's lightning fee reserve before we have an invoice to
+/// quote, most mints reserve 1-2% with a small minimum
+fn estimated_fee_reserve(sats: u64) -> u64 {
+    (sats * 2).div_ceil(100).max(2)
+}
+
+/// Wallets for other units at the same mint need distinct names in pickers
+fn cashu_wallet_name(name: String, unit: &CurrencyUnit) -> String {
+    if *unit == CurrencyUnit::Sat {
+        name
+    } else {
+        format!("{name} ({})", unit.to_string().to_uppercase())
+    }
+}
+
+pub mod backup;
+pub mod cashu_auth;
 pub mod cashu_client;
 pub mod db;
 pub mod db_models;
 pub mod fedimint_client;
 mod http;
+pub mod keys;
 pub mod lightning_address;
 pub mod metadata;
+pub mod mint_discovery;
+pub mod nostr_client;
+mod websocket;
 
 pub use bip39;
 pub use bitcoin;
@@ -104,10 +192,12 @@ pub use cdk_sqlite;
 pub use fedimint_core;
 
 pub use fedimint_ln_common::lightning_invoice::Bolt11Invoice;
+pub use fedimint_mint_client::OOBNotes;
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum MintIdentifier {
-    Cashu(MintUrl),
+    /// A cashu mint holds a separate wallet for each unit we joined it with
+    Cashu(MintUrl, CurrencyUnit),
     Fedimint(FederationId),
 }
 
@@ -115,16 +205,24 @@ impl MintIdentifier {
     pub fn federation_id(&self) -> Option<FederationId> {
         match self {
             Self::Fedimint(id) => Some(*id),
-            Self::Cashu(_) => None,
+            Self::Cashu(..) => None,
         }
     }
 
     pub fn mint_url(&self) -> Option<MintUrl> {
         match self {
-            Self::Cashu(url) => Some(url.clone()),
+            Self::Cashu(url, _) => Some(url.clone()),
             Self::Fedimint(_) => None,
         }
     }
+
+    /// Federations only hold sats
+    pub fn unit(&self) -> CurrencyUnit {
+        match self {
+            Self::Cashu(_, unit) => unit.clone(),
+            Self::Fedimint(_) => CurrencyUnit::Sat,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
@@ -147,6 +245,15 @@ impl FromStr for MintConnectionInfo {
     }
 }
 
+impl std::fmt::Display for MintConnectionInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Cashu(url) => write!(f, "{url}"),
+            Self::Fedimint(invite_code) => write!(f, "{invite_code}"),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct UICoreMsgPacket {
     pub id: Uuid,
@@ -158,16 +265,30 @@ pub enum UICoreMsg {
     SendLightning {
         mint: MintIdentifier,
         invoice: Bolt11Invoice,
+        /// Melt quote from [`UICoreMsg::EstimateLightningSend`] the user confirmed
+        quote_id: Option<String>,
     },
     SendLnurlPay {
         mint: MintIdentifier,
         lnurl: LnUrl,
         amount_sats: u64,
     },
+    EstimateLightningSend {
+        mint: MintIdentifier,
+        invoice: Bolt11Invoice,
+    },
     ReceiveLightning {
         mint: MintIdentifier,
         amount: Amount,
     },
+    /// Logs into the service with its LNURL-auth challenge, once the user confirmed the domain
+    LnurlAuth(LnUrl),
+    /// Pulls funds from an LNURL-withdraw, e.g. a voucher, all of it if `amount` is None
+    ReceiveLnurlWithdraw {
+        mint: MintIdentifier,
+        lnurl: LnUrl,
+        amount: Option<Amount>,
+    },
     SendOnChain {
         mint: MintIdentifier,
         address: Address<NetworkUnchecked>,
@@ -176,6 +297,39 @@ pub enum UICoreMsg {
     ReceiveOnChain {
         mint: MintIdentifier,
     },
+    SendEcash {
+        mint: MintIdentifier,
+        amount: Amount,
+        lock_to: Option<PublicKey>,
+        /// Unix timestamp after which `lock_to` can claim the token,
+        /// until then only we can
+        unlock_at: Option<u64>,
+    },
+    ReceiveCashuToken(Token),
+    CreatePaymentRequest {
+        mint: MintIdentifier,
+        /// In the base amount of the mint's unit, e.g. cents for usd
+        amount: Option<u64>,
+        description: Option<String>,
+    },
+    PayPaymentRequest {
+        mint: MintIdentifier,
+        request: PaymentRequest,
+        /// Only used when the request leaves the amount to the payer, in the
+        /// base amount of the mint's unit
+        amount: Option<u64>,
+    },
+    /// Ecash the payer of one of our payment requests handed over in-band
+    ReceivePaymentRequestPayment {
+        id: String,
+        payment: String,
+    },
+    SwapInCashuToken {
+        token: Token,
+        mint: MintIdentifier,
+    },
+    ReclaimEcash(String),
+    ReceiveFedimintNotes(OOBNotes),
     Transfer {
         to: MintIdentifier,
         from: MintIdentifier,
@@ -183,10 +337,18 @@ pub enum UICoreMsg {
     },
     GetFederationInfo(InviteCode),
     GetCashuMintInfo(MintUrl),
+    /// Looks for mints announced and recommended on nostr
+    DiscoverMints,
     AddFederation(InviteCode),
-    AddCashuMint(MintUrl),
+    AddCashuMint {
+        url: MintUrl,
+        unit: CurrencyUnit,
+    },
     RemoveMint(MintIdentifier),
     RejoinMint(MintIdentifier),
+    /// Joins a federation we used before and brings back the rest of the
+    /// wallet from the manifest in its backup
+    RestoreFromFederation(InviteCode),
     FederationListNeedsUpdate,
     Unlock(String),
     Init {
@@ -196,14 +358,28 @@ pub enum UICoreMsg {
     GetSeedWords,
     SetOnchainReceiveEnabled(bool),
     SetTorEnabled(bool),
+    SetConsolidationEnabled(bool),
+    SetNostrEnabled(bool),
+    /// Stops waiting for the user to approve a cashu mint login
+    CancelCashuLogin,
     TestStatusUpdates,
 }
 
+/// A cashu melt quote for a lightning send, waiting on the user to confirm its fees
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LightningSendQuote {
+    /// The mint the quote is from, the payment has to be sent from it
+    pub mint: MintIdentifier,
+    pub quote_id: String,
+    pub cost: MeltCost,
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum SendSuccessMsg {
     Lightning { preimage: [u8; 32] },
     Onchain { txid: Txid },
     Transfer,
+    PaymentRequest,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -211,6 +387,7 @@ pub enum ReceiveSuccessMsg {
     Lightning,
     Onchain { txid: Txid },
     Transfer,
+    Ecash,
 }
 
 #[derive(Debug, Clone)]
@@ -224,6 +401,33 @@ pub enum CoreUIMsg {
     Sending,
     SendSuccess(SendSuccessMsg),
     SendFailure(String),
+    /// None when the send can't be quoted up front and should go ahead unconfirmed
+    SendEstimate(Option<LightningSendQuote>),
+    SendEcashGenerated(String),
+    PaymentRequestCreated {
+        id: String,
+        request: String,
+    },
+    PaymentRequestPaid(String),
+    CashuPubkey(PublicKey),
+    EcashReclaimed,
+    EcashReclaimFailed(String),
+    TimelockExpiring {
+        token: String,
+        locktime: u64,
+    },
+    /// The cashu mint requires a login (NUT-21), the user approves it by
+    /// entering the code at the verification uri
+    CashuLoginRequired {
+        mint_url: MintUrl,
+        verification_uri: String,
+        user_code: String,
+    },
+    /// Ecash on a keyset the mint deactivated couldn't be moved to its active keyset
+    KeysetSwapFailed {
+        id: MintIdentifier,
+        reason: String,
+    },
     ReceiveGenerating,
     ReceiveInvoiceGenerated(Bolt11Invoice),
     ReceiveAddressGenerated(Address),
@@ -236,13 +440,39 @@ pub enum CoreUIMsg {
         balance: Amount,
     },
     AddMintFailed(String),
+    /// A module of a federation we're recovering from backup made progress
+    FederationRecoveryProgress {
+        id: FederationId,
+        module: ModuleKind,
+        complete: u32,
+        total: u32,
+    },
+    /// Recovery finished and the federation can be used
+    FederationRecovered(FederationId),
+    FederationRecoveryFailed {
+        id: FederationId,
+        reason: String,
+    },
     RemoveFederationFailed(String),
     MintInfo {
         id: MintIdentifier,
         config: Option<ClientConfig>,
         metadata: FederationMeta,
+        /// Units we can hold a wallet for at this mint
+        units: Vec<CurrencyUnit>,
     },
     AddMintSuccess(MintIdentifier),
+    MintsDiscovered(Vec<DiscoveredMint>),
+    /// Logged into the domain with LNURL-auth
+    LnurlAuthSuccess(String),
+    LnurlAuthFailed(String),
+    MintDiscoveryFailed(String),
+    /// Restored the wallet from a federation backup, `rejoined` mints came back
+    /// from its manifest and `failed` lists the ones we couldn't rejoin
+    WalletRestored {
+        rejoined: usize,
+        failed: Vec<String>,
+    },
     RemoveFederationSuccess,
     FederationListNeedsUpdate,
     MintListUpdated(Vec<MintItem>),
@@ -257,10 +487,14 @@ pub enum CoreUIMsg {
     SeedWords(String),
     OnchainReceiveEnabled(bool),
     TorEnabled(bool),
+    ConsolidationEnabled(bool),
+    NostrEnabled(bool),
     InitialProfile {
         seed_words: String,
         onchain_receive_enabled: bool,
         tor_enabled: bool,
+        consolidation_enabled: bool,
+        nostr_enabled: bool,
     },
     StatusUpdate {
         message: String,
@@ -276,12 +510,14 @@ pub struct HarborCore {
     pub data_dir: PathBuf,
     pub tx: Sender<CoreUIMsgPacket>,
     pub clients: Arc<RwLock<HashMap<FederationId, FedimintClient>>>,
-    pub cashu_clients: Arc<RwLock<HashMap<MintUrl, cdk::Wallet>>>,
+    pub cashu_clients: Arc<RwLock<HashMap<(MintUrl, CurrencyUnit), cdk::Wallet>>>,
     pub storage: Arc<dyn DBConnection + Send + Sync>,
     pub cashu_storage: Arc<WalletSqliteDatabase>,
     pub stop: Arc<AtomicBool>,
     pub tor_enabled: Arc<AtomicBool>,
     pub metadata_fetch_cancel: Arc<AtomicBool>,
+    /// Set when the user gives up on a cashu mint login they were asked for
+    pub cashu_login_cancel: Arc<AtomicBool>,
 }
 
 impl HarborCore {
@@ -292,7 +528,7 @@ impl HarborCore {
         data_dir: PathBuf,
         tx: Sender<CoreUIMsgPacket>,
         clients: Arc<RwLock<HashMap<FederationId, FedimintClient>>>,
-        cashu_clients: Arc<RwLock<HashMap<MintUrl, cdk::Wallet>>>,
+        cashu_clients: Arc<RwLock<HashMap<(MintUrl, CurrencyUnit), cdk::Wallet>>>,
         storage: Arc<dyn DBConnection + Send + Sync>,
         cashu_storage: Arc<WalletSqliteDatabase>,
         stop: Arc<AtomicBool>,
@@ -303,9 +539,17 @@ impl HarborCore {
         let pending_onchain_payments = storage.get_pending_onchain_payments()?;
         let pending_lightning_recv = storage.get_pending_lightning_receives()?;
         let pending_lightning_payments = storage.get_pending_lightning_payments()?;
+        let pending_ecash_sends = storage.get_pending_ecash_sends()?;
+        let pending_ecash_receives = storage.get_pending_ecash_receives()?;
 
-        let fed = clients.clone();
-        let fed_clients = fed.read().await;
+        // recovering clients have nothing pending and can't subscribe until they finish
+        let fed_clients: HashMap<FederationId, FedimintClient> = clients
+            .read()
+            .await
+            .iter()
+            .filter(|(_, c)| c.is_usable())
+            .map(|(id, c)| (*id, c.clone()))
+            .collect();
 
         let cashu = cashu_clients.clone();
         let cashus = cashu.read().await;
@@ -387,11 +631,19 @@ impl HarborCore {
                         storage.mark_ln_receive_as_failed(item.operation_id)?;
                     }
                 }
-                MintIdentifier::Cashu(mint_url) => {
-                    if let Some(client) = cashus.get(&mint_url) {
-                        if let Ok(Some(quote)) =
+                MintIdentifier::Cashu(mint_url, unit) => {
+                    if let Some(client) = cashus.get(&(mint_url, unit)) {
+                        if let Ok(Some(mut quote)) =
                             client.localstore.get_mint_quote(&item.operation_id).await
                         {
+                            // the key can always be derived again if the stored quote lost it
+                            if quote.secret_key.is_none() {
+                                if let Some(index) = item.quote_key_index() {
+                                    quote.secret_key =
+                                        Some(keys::cashu_mint_quote_key(&mnemonic, index)?);
+                                    client.localstore.add_mint_quote(quote.clone()).await?;
+                                }
+                            }
                             spawn_lightning_receive_thread(
                                 tx.clone(),
                                 client.clone(),
@@ -399,6 +651,7 @@ impl HarborCore {
                                 quote,
                                 Uuid::nil(),
                                 false,
+                                tor_enabled.load(Ordering::Relaxed),
                             );
                         } else {
                             storage.mark_ln_receive_as_failed(item.operation_id)?;
@@ -451,8 +704,8 @@ impl HarborCore {
                         storage.mark_lightning_payment_as_failed(item.operation_id)?;
                     }
                 }
-                MintIdentifier::Cashu(mint_url) => {
-                    if let Some(client) = cashus.get(&mint_url) {
+                MintIdentifier::Cashu(mint_url, unit) => {
+                    if let Some(client) = cashus.get(&(mint_url, unit)) {
                         if let Ok(Some(quote)) =
                             client.localstore.get_melt_quote(&item.operation_id).await
                         {
@@ -463,6 +716,7 @@ impl HarborCore {
                                 quote,
                                 Uuid::nil(),
                                 false,
+                                tor_enabled.load(Ordering::Relaxed),
                             );
                         } else {
                             storage.mark_lightning_payment_as_failed(item.operation_id)?;
@@ -474,7 +728,80 @@ impl HarborCore {
             }
         }
 
-        Ok(Self {
+        for item in pending_ecash_sends {
+            // cashu tokens have nothing to subscribe to
+            if let Some(federation_id) = item.fedimint_id() {
+                if let Some(client) = fed_clients.get(&federation_id) {
+                    let mint_module = match client
+                        .fedimint_client
+                        .get_first_module::<MintClientModule>()
+                    {
+                        Ok(module) => module,
+                        Err(e) => {
+                            error!(
+                                "Not resuming ecash send {}, federation {federation_id} has no mint module: {e}",
+                                item.operation_id
+                            );
+                            continue;
+                        }
+                    };
+
+                    let op_id = OperationId::from_str(&item.operation_id)?;
+                    if let Ok(sub) = mint_module.subscribe_spend_notes(op_id).await {
+                        spawn_spend_notes_subscription(
+                            tx.clone(),
+                            client.fedimint_client.clone(),
+                            storage.clone(),
+                            op_id,
+                            Uuid::nil(),
+                            sub,
+                        )
+                        .await;
+                    }
+                }
+            }
+        }
+
+        for item in pending_ecash_receives {
+            // swaps are retried once the core is up
+            if item.swap_mint_url().is_some() {
+                continue;
+            }
+            if let Some(federation_id) = item.fedimint_id() {
+                if let Some(client) = fed_clients.get(&federation_id) {
+                    let mint_module = match client
+                        .fedimint_client
+                        .get_first_module::<MintClientModule>()
+                    {
+                        Ok(module) => module,
+                        Err(e) => {
+                            error!(
+                                "Not resuming ecash receive {}, federation {federation_id} has no mint module: {e}",
+                                item.operation_id
+                            );
+                            continue;
+                        }
+                    };
+
+                    let op_id = OperationId::from_str(&item.operation_id)?;
+                    if let Ok(sub) = mint_module.subscribe_reissue_external_notes(op_id).await {
+                        spawn_reissue_notes_subscription(
+                            tx.clone(),
+                            client.fedimint_client.clone(),
+                            storage.clone(),
+                            op_id,
+                            Uuid::nil(),
+                            sub,
+                        )
+                        .await;
+                    } else {
+                        storage.mark_ecash_receive_as_failed(item.operation_id)?;
+                    }
+                }
+            }
+        }
+
+        let core = Self {
             network,
             mnemonic,
             data_dir,
@@ -486,7 +813,141 @@ impl HarborCore {
             stop,
             tor_enabled,
             metadata_fetch_cancel: Arc::new(AtomicBool::new(false)),
-        })
+            cashu_login_cancel: Arc::new(AtomicBool::new(false)),
+        };
+
+        // finish recoveries that were interrupted by a restart
+        for (id, client) in core.clients.read().await.iter() {
+            if client.is_recovering() {
+                core.spawn_fedimint_recovery(*id);
+            }
+        }
+
+        // finish swaps interrupted by a failure or a restart
+        let swapper = core.clone();
+        spawn(async move {
+            if let Err(e) = swapper.retry_ecash_swaps().await {
+                error!("Failed to retry ecash swaps: {e}");
+            }
+        });
+
+        // watch for our outgoing tokens being claimed
+        let checker = core.clone();
+        spawn(async move {
+            while !checker.stop.load(Ordering::Relaxed) {
+                match checker.check_pending_ecash_sends().await {
+                    Ok(true) => {
+                        update_history(
+                            checker.storage.clone(),
+                            Uuid::nil(),
+                            &mut checker.tx.clone(),
+                        )
+                        .await;
+                    }
+                    Ok(false) => {}
+                    Err(e) => error!("Failed to check pending ecash: {e}"),
+                }
+                tokio::time::sleep(PENDING_ECASH_CHECK_INTERVAL).await;
+            }
+        });
+
+        // remind the user before a beneficiary can claim a timelocked token
+        let reminder = core.clone();
+        spawn(async move {
+            while !reminder.stop.load(Ordering::Relaxed) {
+                match reminder.get_expiring_timelocked_ecash() {
+                    Ok(expiring) => {
+                        for (operation_id, token, locktime) in expiring {
+                            reminder
+                                .msg(Uuid::nil(), CoreUIMsg::TimelockExpiring { token, locktime })
+                                .await;
+                            if let Err(e) =
+                                reminder.storage.mark_ecash_send_as_reminded(operation_id)
+                            {
+                                error!("Failed to save timelock reminder: {e}");
+                            }
+                        }
+                    }
+                    Err(e) => error!("Failed to check timelocked ecash: {e}"),
+                }
+                tokio::time::sleep(TIMELOCK_REMINDER_INTERVAL).await;
+            }
+        });
+
+        // move ecash off keysets the mint has rotated out, at startup and then periodically
+        let rotator = core.clone();
+        spawn(async move {
+            while !rotator.stop.load(Ordering::Relaxed) {
+                rotator.check_cashu_keysets().await;
+                tokio::time::sleep(KEYSET_CHECK_INTERVAL).await;
+            }
+        });
+
+        // settle requests that failed in transit, including ones from before a restart
+        let reconciler = core.clone();
+        spawn(async move {
+            while !reconciler.stop.load(Ordering::Relaxed) {
+                if let Err(e) = reconciler.reconcile_cashu_wallets().await {
+                    error!("Failed to reconcile cashu wallets: {e}");
+                }
+                tokio::time::sleep(RECONCILE_INTERVAL).await;
+            }
+        });
+
+        // redeem ecash sent to us as nostr DMs, e.g. for our payment requests,
+        // only once the user turned it on since it connects to public relays
+        for url in DEFAULT_NOSTR_RELAYS {
+            let listener = core.clone();
+            spawn(async move {
+                while !listener.stop.load(Ordering::Relaxed) {
+                    if listener.nostr_enabled() {
+                        if let Err(e) = listener.listen_for_nostr_dms(url).await {
+                            log::warn!("Lost nostr relay {url}: {e}");
+                        }
+                    }
+                    tokio::time::sleep(NOSTR_RECONNECT_DELAY).await;
+                }
+            });
+        }
+
+        // retry DMs whose ecash failed to redeem, relays only send them again on reconnect
+        if let Err(e) = core.storage.release_unfinished_nostr_events() {
+            error!("Failed to release unfinished nostr events: {e}");
+        }
+        let retrier = core.clone();
+        spawn(async move {
+            while !retrier.stop.load(Ordering::Relaxed) {
+                if let Err(e) = retrier.retry_nostr_dms().await {
+                    error!("Failed to retry nostr DMs: {e}");
+                }
+                tokio::time::sleep(NOSTR_RETRY_INTERVAL).await;
+            }
+        });
+
+        // merge fragmented cashu proofs to keep the wallet db small and future input fees low
+        let maintainer = core.clone();
+        spawn(async move {
+            loop {
+                tokio::time::sleep(CONSOLIDATION_INTERVAL).await;
+                if maintainer.stop.load(Ordering::Relaxed) {
+                    break;
+                }
+                match maintainer.consolidate_cashu_proofs().await {
+                    Ok(true) => {
+                        update_history(
+                            maintainer.storage.clone(),
+                            Uuid::nil(),
+                            &mut maintainer.tx.clone(),
+                        )
+                        .await;
+                    }
+                    Ok(false) => {}
+                    Err(e) => error!("Failed to consolidate cashu proofs: {e}"),
+                }
+            }
+        });
+
+        Ok(core)
     }
 
     // Initial setup messages that don't have an id
@@ -528,6 +989,9 @@ impl HarborCore {
             .await;
 
         for client in self.clients.read().await.values() {
+            if !client.is_usable() {
+                continue;
+            }
             let fed_balance = client.fedimint_client.get_balance().await;
             self.send_system_msg(CoreUIMsg::MintBalanceUpdated {
                 id: MintIdentifier::Fedimint(client.fedimint_client.federation_id()),
@@ -539,7 +1003,7 @@ impl HarborCore {
         for client in self.cashu_clients.read().await.values() {
             let bal: u64 = client.total_balance().await?.into();
             self.send_system_msg(CoreUIMsg::MintBalanceUpdated {
-                id: MintIdentifier::Cashu(client.mint_url.clone()),
+                id: MintIdentifier::Cashu(client.mint_url.clone(), client.unit.clone()),
                 balance: Amount::from_sats(bal),
             })
             .await;
@@ -549,6 +1013,9 @@ impl HarborCore {
         self.send_system_msg(CoreUIMsg::TransactionHistoryUpdated(history))
             .await;
 
+        let pubkey = keys::cashu_p2pk_key(&self.mnemonic)?.public_key();
+        self.send_system_msg(CoreUIMsg::CashuPubkey(pubkey)).await;
+
         let profile = self.storage.get_profile()?;
         if let Some(profile) = profile {
             // Send all profile settings in one message
@@ -556,6 +1023,8 @@ impl HarborCore {
                 seed_words: profile.seed_words.clone(),
                 onchain_receive_enabled: profile.onchain_receive_enabled(),
                 tor_enabled: profile.tor_enabled(),
+                consolidation_enabled: profile.consolidation_enabled(),
+                nostr_enabled: profile.nostr_enabled(),
             })
             .await;
         }
@@ -563,18 +1032,19 @@ impl HarborCore {
         Ok(())
     }
 
-    async fn get_client(&self, federation_id: FederationId) -> FedimintClient {
+    async fn get_client(&self, federation_id: FederationId) -> anyhow::Result<FedimintClient> {
         let clients = self.clients.read().await;
-        clients
+        let client = clients
             .get(&federation_id)
-            .expect("No client found for federation")
-            .clone()
+            .expect("No client found for federation");
+        client.ensure_usable()?;
+        Ok(client.clone())
     }
 
-    async fn get_cashu_client(&self, mint_url: &MintUrl) -> cdk::Wallet {
+    async fn get_cashu_client(&self, mint_url: &MintUrl, unit: &CurrencyUnit) -> cdk::Wallet {
         let clients = self.cashu_clients.read().await;
         clients
-            .get(mint_url)
+            .get(&(mint_url.clone(), unit.clone()))
             .expect("No client found for mint url")
             .clone()
     }
@@ -604,6 +1074,7 @@ impl HarborCore {
         msg_id: Uuid,
         from: MintIdentifier,
         invoice: Bolt11Invoice,
+        quote_id: Option<String>,
         is_transfer: bool,
     ) -> anyhow::Result<()> {
         if invoice.amount_milli_satoshis().is_none() {
@@ -614,9 +1085,30 @@ impl HarborCore {
             .await;
 
         match from {
-            MintIdentifier::Cashu(mint_url) => {
-                self.send_lightning_from_cashu(msg_id, mint_url, invoice, is_transfer)
+            MintIdentifier::Cashu(mint_url, unit) => {
+                // split the payment across mints when this one can't cover it alone
+                let balance: u64 = self
+                    .get_cashu_client(&mint_url, &unit)
                     .await
+                    .total_balance()
+                    .await?
+                    .into();
+                let amount_msats = invoice.amount_milli_satoshis().expect("must have amount");
+                if !is_transfer && unit == CurrencyUnit::Sat && balance * 1_000 < amount_msats {
+                    return self
+                        .send_lightning_multi_path(msg_id, mint_url, invoice)
+                        .await;
+                }
+
+                self.send_lightning_from_cashu(
+                    msg_id,
+                    mint_url,
+                    unit,
+                    invoice,
+                    quote_id,
+                    is_transfer,
+                )
+                .await
             }
             MintIdentifier::Fedimint(id) => {
                 self.send_lightning_from_fedimint(msg_id, id, invoice, is_transfer)
@@ -625,34 +1117,87 @@ impl HarborCore {
         }
     }
 
+    /// Quotes what paying the invoice from a cashu mint would cost, including the
+    /// input fees of the proofs we'd select. Returns None when there is nothing to
+    /// confirm up front: fedimint sends, and payments split across several mints.
+    /// The quote is kept so the payment can use it once the user confirms.
+    pub async fn estimate_lightning_send(
+        &self,
+        mint: MintIdentifier,
+        invoice: Bolt11Invoice,
+    ) -> anyhow::Result<Option<LightningSendQuote>> {
+        let MintIdentifier::Cashu(mint_url, unit) = &mint else {
+            return Ok(None);
+        };
+        let Some(amount_msats) = invoice.amount_milli_satoshis() else {
+            return Err(anyhow!("Invoice must have an amount"));
+        };
+
+        let client = self.get_cashu_client(mint_url, unit).await;
+        let balance: u64 = client.total_balance().await?.into();
+        if *unit == CurrencyUnit::Sat && balance * 1_000 < amount_msats {
+            return Ok(None);
+        }
+
+        let quote = client.melt_quote(invoice.to_string(), None).await?;
+        let (_, cost) = select_melt_proofs(&client, &quote).await?;
+        Ok(Some(LightningSendQuote {
+            mint,
+            quote_id: quote.id,
+            cost,
+        }))
+    }
+
     pub async fn send_lightning_from_cashu(
         &self,
         msg_id: Uuid,
         mint_url: MintUrl,
+        unit: CurrencyUnit,
         invoice: Bolt11Invoice,
+        quote_id: Option<String>,
         is_transfer: bool,
     ) -> anyhow::Result<()> {
-        log::info!("Paying lightning invoice: {invoice} from cashu mint: {mint_url}");
-        let amount = Amount::from_msats(invoice.amount_milli_satoshis().expect("must have amount"));
+        log::info!("Paying lightning invoice: {invoice} from cashu mint: {mint_url} ({unit})");
 
-        let client = self.get_cashu_client(&mint_url).await;
+        self.renew_cashu_login(msg_id, &mint_url).await?;
+        let client = self.get_cashu_client(&mint_url, &unit).await;
+
+        let confirmed = match quote_id {
+            Some(id) => confirmed_melt_quote(&client, &id, &invoice.to_string()).await?,
+            None => None,
+        };
+        let quote = match confirmed {
+            Some(quote) => quote,
+            None => {
+                self.status_update(msg_id, "Getting quote").await;
+                client.melt_quote(invoice.to_string(), None).await?
+            }
+        };
 
-        self.status_update(msg_id, "Getting quote").await;
+        // make sure we can cover the input fees of the proofs we'd spend before committing
+        let (_, cost) = select_melt_proofs(&client, &quote).await?;
 
-        let quote = client.melt_quote(invoice.to_string(), None).await?;
+        // other units pay whatever the mint quotes for the invoice
+        let amount = if unit == CurrencyUnit::Sat {
+            Amount::from_msats(invoice.amount_milli_satoshis().expect("must have amount"))
+        } else {
+            Amount::from_sats(quote.amount.into())
+        };
 
         log::info!("Sending lightning invoice: {invoice}");
 
         self.status_update(msg_id, "Creating payment transaction")
             .await;
 
+        // the cost is in the quote's unit, stored as that unit's base amount
         self.storage.create_lightning_payment(
             quote.id.clone(),
             None,
             Some(mint_url),
+            Some(quote.unit.clone()),
             invoice,
             amount,
-            Amount::from_msats(quote.fee_reserve.into()),
+            Amount::from_sats(cost.fee()),
         )?;
 
         spawn_lightning_payment_thread(
@@ -662,6 +1207,7 @@ impl HarborCore {
             quote,
             msg_id,
             is_transfer,
+            self.tor_enabled.load(Ordering::Relaxed),
         );
 
         self.status_update(msg_id, "Waiting for payment confirmation")
@@ -672,6 +1218,119 @@ impl HarborCore {
         Ok(())
     }
 
+    /// Pays an invoice in parts from every cashu mint that supports NUT-15,
+    /// starting with `primary` and then the mints with the largest balances
+    pub async fn send_lightning_multi_path(
+        &self,
+        msg_id: Uuid,
+        primary: MintUrl,
+        invoice: Bolt11Invoice,
+    ) -> anyhow::Result<()> {
+        log::info!("Paying lightning invoice: {invoice} from multiple cashu mints");
+        let amount = Amount::from_msats(invoice.amount_milli_satoshis().expect("must have amount"));
+
+        self.status_update(msg_id, "Finding mints to split the payment")
+            .await;
+
+        let clients: Vec<cdk::Wallet> = self.cashu_clients.read().await.values().cloned().collect();
+        let mut candidates = Vec::with_capacity(clients.len());
+        for client in clients {
+            if client.unit != CurrencyUnit::Sat {
+                continue;
+            }
+            let supports_mpp = client
+                .get_mint_info()
+                .await
+                .ok()
+                .flatten()
+                .is_some_and(|info| {
+                    info.nuts
+                        .nut15
+                        .methods
+                        .iter()
+                        .any(|m| m.unit == CurrencyUnit::Sat)
+                });
+            if !supports_mpp {
+                continue;
+            }
+            let balance: u64 = client.total_balance().await?.into();
+            if balance > 0 {
+                candidates.push((client, balance));
+            }
+        }
+        candidates.sort_by_key(|(client, balance)| {
+            (client.mint_url != primary, std::cmp::Reverse(*balance))
+        });
+
+        self.status_update(msg_id, "Getting quotes").await;
+
+        // each mint pays as much as it can once its part's fees are covered
+        let mut remaining = amount.msats;
+        let mut parts = vec![];
+        for (client, _) in candidates {
+            if remaining == 0 {
+                break;
+            }
+            if let Some((quote, part, cost)) =
+                quote_multi_path_part(&client, &invoice.to_string(), remaining).await?
+            {
+                remaining -= part;
+                parts.push((client, quote, part, cost));
+            }
+        }
+        if remaining > 0 {
+            return Err(anyhow!(
+                "Insufficient balance: Mints that support multi-path payments can't cover {} sats plus fees",
+                amount.sats_round_down()
+            ));
+        }
+
+        self.status_update(msg_id, "Creating payment transaction")
+            .await;
+
+        // the parent row stands in for the whole payment in history
+        let parent_id = Uuid::new_v4().to_string();
+        let fee: u64 = parts.iter().map(|(_, _, _, cost)| cost.fee()).sum();
+        self.storage.create_lightning_payment(
+            parent_id.clone(),
+            None,
+            Some(parts[0].0.mint_url.clone()),
+            None,
+            invoice.clone(),
+            amount,
+            Amount::from_sats(fee),
+        )?;
+        for (client, quote, part, cost) in &parts {
+            self.storage.create_lightning_payment_part(
+                quote.id.clone(),
+                parent_id.clone(),
+                client.mint_url.clone(),
+                CurrencyUnit::Sat,
+                invoice.clone(),
+                Amount::from_msats(*part),
+                Amount::from_sats(cost.fee()),
+            )?;
+        }
+
+        log::info!("Sending lightning invoice in {} parts", parts.len());
+
+        spawn_multi_path_payment_thread(
+            self.tx.clone(),
+            parts
+                .into_iter()
+                .map(|(client, quote, _, _)| (client, quote))
+                .collect(),
+            self.storage.clone(),
+            msg_id,
+            self.tor_enabled.load(Ordering::Relaxed),
+        );
+
+        self.status_update(msg_id, "Waiting for payment confirmation")
+            .await;
+
+        Ok(())
+    }
+
     pub async fn send_lightning_from_fedimint(
         &self,
         msg_id: Uuid,
@@ -682,7 +1341,7 @@ impl HarborCore {
         log::info!("Paying lightning invoice: {invoice} from federation: {federation_id}");
         let amount = Amount::from_msats(invoice.amount_milli_satoshis().expect("must have amount"));
 
-        let client = self.get_client(federation_id).await.fedimint_client;
+        let client = self.get_client(federation_id).await?.fedimint_client;
 
         // Try sending using LNv2 first, if that doesn't work fall back to using LNv1
         match self.send_lnv2(&client, msg_id, invoice.clone()).await {
@@ -710,6 +1369,7 @@ impl HarborCore {
                     operation_id.fmt_full().to_string(),
                     Some(client.federation_id()),
                     None,
+                    None,
                     invoice,
                     amount,
                     fees,
@@ -770,6 +1430,7 @@ impl HarborCore {
                     outgoing.payment_type.operation_id().fmt_full().to_string(),
                     Some(client.federation_id()),
                     None,
+                    None,
                     invoice,
                     amount,
                     fees,
@@ -817,6 +1478,8 @@ impl HarborCore {
         lnurl: LnUrl,
         amount_sats: u64,
     ) -> anyhow::Result<()> {
+        require_sat_mint(&mint_identifier)?;
+
         self.status_update(msg_id, "Starting LNURL-pay flow").await;
 
         log::info!("Sending lnurl pay: {lnurl} from mint: {mint_identifier:?}");
@@ -846,13 +1509,94 @@ impl HarborCore {
             fedimint_ln_common::lightning_invoice::Bolt11Invoice::from_str(&invoice_response.pr)?;
 
         // Now we'll let send_lightning handle the rest of the status updates
-        self.send_lightning(msg_id, mint_identifier, invoice, false)
+        self.send_lightning(msg_id, mint_identifier, invoice, None, false)
             .await?;
 
         Ok(())
     }
 
-    async fn receive_lnv2(
+    /// Logs into a service with LNURL-auth, using a key derived for its domain so
+    /// the same seed gets the same account back. Returns the domain.
+    pub async fn lnurl_auth(&self, msg_id: Uuid, lnurl: LnUrl) -> anyhow::Result<String> {
+        let auth = lightning_address::parse_lnurl_auth(&lnurl)?;
+        log::info!("Logging into {} with lnurl auth", auth.domain);
+        self.status_update(msg_id, "Signing in").await;
+
+        let key = keys::lnurl_auth_key(&self.mnemonic, &auth.domain)?;
+        lightning_address::lnurl_auth(
+            &auth,
+            &key,
+            self.tor_enabled.load(Ordering::Relaxed),
+            self.metadata_fetch_cancel.clone(),
+        )
+        .await?;
+        log::info!("Logged into {}", auth.domain);
+
+        Ok(auth.domain)
+    }
+
+    /// Creates an invoice on the mint for the LNURL-withdraw and has the service pay
+    /// it, the receive is then tracked like any other lightning receive
+    pub async fn receive_lnurl_withdraw(
+        &self,
+        msg_id: Uuid,
+        mint_identifier: MintIdentifier,
+        lnurl: LnUrl,
+        amount: Option<Amount>,
+    ) -> anyhow::Result<Bolt11Invoice> {
+        require_sat_mint(&mint_identifier)?;
+
+        log::info!("Receiving lnurl withdraw: {lnurl} into mint: {mint_identifier:?}");
+
+        let tor_enabled = self.tor_enabled.load(Ordering::Relaxed);
+        self.status_update(msg_id, "Fetching withdraw details")
+            .await;
+
+        let withdraw = lightning_address::make_lnurl_withdraw_request(
+            &lnurl,
+            tor_enabled,
+            self.metadata_fetch_cancel.clone(),
+        )
+        .await?;
+        log::info!("Withdraw response: {withdraw:?}");
+
+        let msats = lightning_address::withdraw_amount(&withdraw, amount.map(|a| a.msats))?;
+        let invoice = self
+            .receive_lightning(msg_id, mint_identifier, Amount::from_msats(msats), false)
+            .await?;
+
+        self.status_update(msg_id, "Requesting payment from the withdraw service")
+            .await;
+        match lightning_address::submit_withdraw_invoice(
+            &withdraw,
+            &invoice.to_string(),
+            tor_enabled,
+            self.metadata_fetch_cancel.clone(),
+        )
+        .await
+        {
+            Ok(Ok(())) => log::info!("Withdraw service accepted invoice: {invoice}"),
+            // the service may have the invoice anyway, keep waiting for it to be paid
+            Err(e) => log::warn!("No answer from the withdraw service, it may still pay: {e}"),
+            Ok(Err(e)) => {
+                // nobody is going to pay the invoice now
+                let receive = self
+                    .storage
+                    .get_pending_lightning_receives()?
+                    .into_iter()
+                    .find(|r| r.bolt11() == invoice);
+                if let Some(receive) = receive {
+                    self.storage
+                        .mark_ln_receive_as_failed(receive.operation_id)?;
+                }
+                return Err(e);
+            }
+        }
+
+        Ok(invoice)
+    }
+
+    async fn receive_lnv2(
         &self,
         client: &ClientHandleArc,
         msg_id: Uuid,
@@ -889,8 +1633,8 @@ impl HarborCore {
         is_transfer: bool,
     ) -> anyhow::Result<Bolt11Invoice> {
         match mint_identifier {
-            MintIdentifier::Cashu(mint_url) => {
-                self.receive_lightning_from_cashu(msg_id, mint_url, amount, is_transfer)
+            MintIdentifier::Cashu(mint_url, unit) => {
+                self.receive_lightning_from_cashu(msg_id, mint_url, unit, amount, is_transfer)
                     .await
             }
             MintIdentifier::Fedimint(id) => {
@@ -912,7 +1656,7 @@ impl HarborCore {
             "Creating lightning invoice, amount: {amount} for federation: {federation_id}. Tor enabled: {tor_enabled}"
         );
 
-        let client = self.get_client(federation_id).await.fedimint_client;
+        let client = self.get_client(federation_id).await?.fedimint_client;
         match self.receive_lnv2(&client, msg_id, amount).await {
             Ok((invoice, operation_id)) => {
                 let operation = client
@@ -936,9 +1680,11 @@ impl HarborCore {
                     operation_id.fmt_full().to_string(),
                     Some(client.federation_id()),
                     None,
+                    None,
                     invoice.clone(),
                     amount,
                     fees,
+                    None,
                 )?;
 
                 let lnv2_module = client
@@ -994,9 +1740,11 @@ impl HarborCore {
                     op_id.fmt_full().to_string(),
                     Some(client.federation_id()),
                     None,
+                    None,
                     invoice.clone(),
                     amount,
                     Amount::ZERO, // todo one day there will be receive fees
+                    None,
                 )?;
 
                 // Create subscription to operation if it exists
@@ -1023,70 +1771,1462 @@ impl HarborCore {
         }
     }
 
-    pub async fn receive_lightning_from_cashu(
+    pub async fn receive_lightning_from_cashu(
+        &self,
+        msg_id: Uuid,
+        mint: MintUrl,
+        unit: CurrencyUnit,
+        amount: Amount,
+        is_transfer: bool,
+    ) -> anyhow::Result<Bolt11Invoice> {
+        let tor_enabled = self.tor_enabled.load(Ordering::Relaxed);
+        log::info!(
+            "Creating lightning invoice, amount: {amount} for mint: {mint} ({unit}). Tor enabled: {tor_enabled}"
+        );
+
+        self.status_update(msg_id, "Connecting to mint").await;
+
+        let client = self.get_cashu_client(&mint, &unit).await;
+
+        self.status_update(msg_id, "Generating invoice").await;
+
+        // lock the quote to a seed derived key when the mint supports it (NUT-20)
+        let supports_nut20 = client
+            .get_mint_info()
+            .await?
+            .is_some_and(|info| info.nuts.nut20.supported);
+        let quote_amount = cdk::Amount::from(amount.msats / 1000);
+        let (quote, quote_key_index) = if supports_nut20 {
+            let index = self.storage.next_cashu_quote_key_index()?;
+            let key = keys::cashu_mint_quote_key(&self.mnemonic, index)?;
+            (
+                mint_quote_locked(&client, quote_amount, key).await?,
+                Some(index),
+            )
+        } else {
+            (client.mint_quote(quote_amount, None).await?, None)
+        };
+
+        let invoice = Bolt11Invoice::from_str(&quote.request)?;
+
+        log::info!("Invoice created: {invoice}");
+
+        self.storage.create_ln_receive(
+            quote.id.clone(),
+            None,
+            Some(mint),
+            Some(unit),
+            invoice.clone(),
+            amount,
+            Amount::ZERO, // todo one day there will be receive fees
+            quote_key_index,
+        )?;
+
+        spawn_lightning_receive_thread(
+            self.tx.clone(),
+            client,
+            self.storage.clone(),
+            quote,
+            msg_id,
+            is_transfer,
+            self.tor_enabled.load(Ordering::Relaxed),
+        );
+        Ok(invoice)
+    }
+
+    pub async fn transfer(
+        &self,
+        msg_id: Uuid,
+        to: MintIdentifier,
+        from: MintIdentifier,
+        amount: Amount,
+    ) -> anyhow::Result<()> {
+        log::info!("Transferring {amount} from {from:?} to {to:?}");
+
+        // amounts are in sats, other units would need an exchange rate
+        if to.unit() != CurrencyUnit::Sat || from.unit() != CurrencyUnit::Sat {
+            return Err(anyhow!("Transfers are only supported between sat balances"));
+        }
+
+        self.status_update(msg_id, "Generating invoice on destination mint")
+            .await;
+
+        let invoice = self.receive_lightning(msg_id, to, amount, true).await?;
+
+        self.status_update(msg_id, "Paying invoice from source mint")
+            .await;
+
+        self.send_lightning(msg_id, from, invoice, None, true)
+            .await?;
+        Ok(())
+    }
+
+    /// Creates an ecash token for the given amount that can be handed to anyone,
+    /// or only to the owner of `lock_to` if given.
+    ///
+    /// With `unlock_at` the token stays locked to our own key and `lock_to`
+    /// is only added as a refund key, so we can take it back until then.
+    pub async fn send_ecash(
+        &self,
+        msg_id: Uuid,
+        mint: MintIdentifier,
+        amount: Amount,
+        lock_to: Option<PublicKey>,
+        unlock_at: Option<u64>,
+    ) -> anyhow::Result<String> {
+        log::info!("Creating ecash token for {amount} from {mint:?}");
+
+        match mint {
+            MintIdentifier::Cashu(mint_url, unit) => {
+                let conditions = match (lock_to, unlock_at) {
+                    (None, None) => None,
+                    (Some(pubkey), None) => Some(SpendingConditions::new_p2pk(pubkey, None)),
+                    (Some(beneficiary), Some(locktime)) => {
+                        let ours = keys::cashu_p2pk_key(&self.mnemonic)?.public_key();
+                        let conditions = Conditions {
+                            locktime: Some(locktime),
+                            refund_keys: Some(vec![beneficiary]),
+                            ..Conditions::default()
+                        };
+                        Some(SpendingConditions::new_p2pk(ours, Some(conditions)))
+                    }
+                    (None, Some(_)) => {
+                        return Err(anyhow!("A timelocked token needs a pubkey to unlock to"));
+                    }
+                };
+
+                self.send_ecash_from_cashu(msg_id, mint_url, unit, amount, conditions)
+                    .await
+            }
+            MintIdentifier::Fedimint(federation_id) => {
+                if lock_to.is_some() || unlock_at.is_some() {
+                    return Err(anyhow!("Federations do not support locked ecash"));
+                }
+                self.send_ecash_from_fedimint(msg_id, federation_id, amount)
+                    .await
+            }
+        }
+    }
+
+    pub async fn send_ecash_from_fedimint(
+        &self,
+        msg_id: Uuid,
+        federation_id: FederationId,
+        amount: Amount,
+    ) -> anyhow::Result<String> {
+        let client = self.get_client(federation_id).await?.fedimint_client;
+        let mint_module = client.get_first_module::<MintClientModule>()?;
+
+        let balance = client.get_balance().await;
+        if amount > balance {
+            return Err(anyhow!(
+                "Insufficient balance: Cannot send {} sats, current balance is only {} sats",
+                amount.sats_round_down(),
+                balance.sats_round_down()
+            ));
+        }
+
+        self.status_update(msg_id, "Selecting ecash notes").await;
+
+        let (op_id, notes) = mint_module
+            .spend_notes_with_selector(
+                &SelectNotesWithAtleastAmount,
+                amount,
+                ECASH_RECLAIM_TIMEOUT,
+                true,
+                (),
+            )
+            .await?;
+
+        let notes_str = notes.to_string();
+
+        log::info!("Created ecash notes from federation: {federation_id}");
+
+        // the selector can hand out more than requested if exact change isn't available
+        self.storage.create_ecash_send(
+            op_id.fmt_full().to_string(),
+            Some(federation_id),
+            None,
+            None,
+            notes_str.clone(),
+            notes.total_amount(),
+            Amount::ZERO,
+            None,
+        )?;
+
+        let sub = mint_module.subscribe_spend_notes(op_id).await?;
+        spawn_spend_notes_subscription(
+            self.tx.clone(),
+            client.clone(),
+            self.storage.clone(),
+            op_id,
+            msg_id,
+            sub,
+        )
+        .await;
+
+        let new_balance = client.get_balance().await;
+        self.msg(
+            msg_id,
+            CoreUIMsg::MintBalanceUpdated {
+                id: MintIdentifier::Fedimint(federation_id),
+                balance: new_balance,
+            },
+        )
+        .await;
+
+        update_history(self.storage.clone(), msg_id, &mut self.tx.clone()).await;
+
+        Ok(notes_str)
+    }
+
+    pub async fn send_ecash_from_cashu(
+        &self,
+        msg_id: Uuid,
+        mint_url: MintUrl,
+        unit: CurrencyUnit,
+        amount: Amount,
+        conditions: Option<SpendingConditions>,
+    ) -> anyhow::Result<String> {
+        self.renew_cashu_login(msg_id, &mint_url).await?;
+        let client = self.get_cashu_client(&mint_url, &unit).await;
+
+        self.status_update(msg_id, "Selecting ecash").await;
+
+        let options = SendOptions {
+            conditions: conditions.clone(),
+            ..SendOptions::default()
+        };
+        let guard = lock_wallet(&client).await;
+        let prepared = client
+            .prepare_send(cdk::Amount::from(amount.sats_round_down()), options)
+            .await?;
+        let fee: u64 = prepared.fee().into();
+
+        // recorded before the proofs are spent so the send can't go missing
+        let operation_id = Uuid::new_v4().to_string();
+        self.storage.create_unconfirmed_ecash_send(
+            operation_id.clone(),
+            mint_url.clone(),
+            unit.clone(),
+            amount,
+            Amount::from_sats(fee),
+            conditions,
+        )?;
+
+        self.status_update(msg_id, "Creating ecash token").await;
+
+        let token = match prepared.confirm(None).await {
+            Ok(token) => token.to_string(),
+            Err(e) => {
+                self.storage.mark_ecash_send_as_failed(operation_id)?;
+                return Err(e.into());
+            }
+        };
+        drop(guard);
+
+        log::info!("Created ecash token from cashu mint: {mint_url}");
+
+        self.storage
+            .confirm_ecash_send(operation_id, token.clone())?;
+
+        let bal: u64 = client.total_balance().await?.into();
+        self.msg(
+            msg_id,
+            CoreUIMsg::MintBalanceUpdated {
+                id: MintIdentifier::Cashu(mint_url, unit),
+                balance: Amount::from_sats(bal),
+            },
+        )
+        .await;
+
+        update_history(self.storage.clone(), msg_id, &mut self.tx.clone()).await;
+
+        Ok(token)
+    }
+
+    /// Publishes a payment request (NUT-18) for ecash from the given cashu mint.
+    /// Payers can send the ecash to our nostr key if we listen for DMs, where it's
+    /// redeemed as it arrives, or hand it back in-band. `amount` is in the base
+    /// amount of the mint's unit. Returns the request id and encoded request.
+    pub async fn create_payment_request(
+        &self,
+        mint: MintIdentifier,
+        amount: Option<u64>,
+        description: Option<String>,
+    ) -> anyhow::Result<(String, String)> {
+        let MintIdentifier::Cashu(mint_url, unit) = mint else {
+            return Err(anyhow!(
+                "Payment requests are only supported for cashu mints"
+            ));
+        };
+
+        let id = Uuid::new_v4().simple().to_string()[..8].to_string();
+        let mut builder = PaymentRequest::builder()
+            .payment_id(id.clone())
+            .unit(unit.clone())
+            .single_use(true)
+            .add_mint(mint_url.clone());
+        if let Some(amount) = amount {
+            builder = builder.amount(amount);
+        }
+        if let Some(description) = description.clone() {
+            builder = builder.description(description);
+        }
+        if self.nostr_enabled() {
+            let relays = DEFAULT_NOSTR_RELAYS
+                .iter()
+                .map(|r| RelayUrl::parse(r))
+                .collect::<Result<Vec<_>, _>>()?;
+            let nprofile =
+                Nip19Profile::new(keys::nostr_keys(&self.mnemonic)?.public_key(), relays)
+                    .to_bech32()?;
+            builder = builder.add_transport(Transport {
+                _type: TransportType::Nostr,
+                target: nprofile,
+                // we read NIP-17 direct messages
+                tags: Some(vec![vec!["n".to_string(), "17".to_string()]]),
+            });
+        }
+        let request = builder.build().to_string();
+
+        self.storage.create_payment_request(
+            id.clone(),
+            &mint_url,
+            &unit,
+            amount,
+            description,
+            request.clone(),
+        )?;
+
+        log::info!("Created payment request {id} for cashu mint: {mint_url}");
+
+        Ok((id, request))
+    }
+
+    /// Pays a payment request (NUT-18) with ecash from `mint`. Requests with an
+    /// HTTP POST or nostr transport are delivered to the payee, otherwise the
+    /// token is returned for the user to hand over in-band. If delivery fails the
+    /// ecash is reclaimed. `amount` is in the base amount of the mint's unit.
+    pub async fn pay_payment_request(
+        &self,
+        msg_id: Uuid,
+        mint: MintIdentifier,
+        request: PaymentRequest,
+        amount: Option<u64>,
+    ) -> anyhow::Result<Option<String>> {
+        let MintIdentifier::Cashu(mint_url, unit) = mint else {
+            return Err(anyhow!(
+                "Payment requests can only be paid from cashu mints"
+            ));
+        };
+
+        // an amount without a unit is in sats
+        let requested_unit = match (&request.unit, request.amount) {
+            (Some(u), _) => Some(u.clone()),
+            (None, Some(_)) => Some(CurrencyUnit::Sat),
+            (None, None) => None,
+        };
+        if requested_unit.is_some_and(|u| u != unit) {
+            return Err(anyhow!("Payment request is not for {unit}"));
+        }
+        if request
+            .mints
+            .as_ref()
+            .is_some_and(|mints| !mints.is_empty() && !mints.contains(&mint_url))
+        {
+            return Err(anyhow!(
+                "Payment request does not accept ecash from {mint_url}"
+            ));
+        }
+        let amount = match (request.amount, amount) {
+            (Some(requested), _) => u64::from(requested),
+            (None, Some(amount)) => amount,
+            (None, None) => return Err(anyhow!("Payment request needs an amount")),
+        };
+
+        let target = |kind: TransportType| {
+            request
+                .transports
+                .iter()
+                .find(|t| t._type == kind)
+                .map(|t| t.target.clone())
+        };
+        let post_target = target(TransportType::HttpPost);
+        let nostr_target = target(TransportType::Nostr)
+            .map(|t| Nip19Profile::from_bech32(&t))
+            .transpose()?;
+
+        let token = self
+            .send_ecash_from_cashu(
+                msg_id,
+                mint_url.clone(),
+                unit.clone(),
+                Amount::from_sats(amount),
+                None,
+            )
+            .await?;
+
+        if post_target.is_none() && nostr_target.is_none() {
+            return Ok(Some(token));
+        }
+
+        self.status_update(msg_id, "Sending ecash to payee").await;
+
+        let client = self.get_cashu_client(&mint_url, &unit).await;
+        let keysets = client.get_mint_keysets().await?;
+        let payload = PaymentRequestPayload {
+            id: request.payment_id.clone(),
+            memo: request.description.clone(),
+            mint: mint_url,
+            unit,
+            proofs: Token::from_str(&token)?.proofs(&keysets)?,
+        };
+
+        if let Err(e) = self
+            .deliver_payment_request_payment(&payload, post_target, nostr_target)
+            .await
+        {
+            error!("Failed to deliver payment request payment: {e}");
+            self.status_update(msg_id, "Reclaiming ecash").await;
+            if let Err(reclaim) = self.reclaim_ecash(msg_id, token.clone()).await {
+                // the payee got the ecash after all
+                let claimed = self
+                    .storage
+                    .get_ecash_send_by_token(&token)?
+                    .is_some_and(|send| send.status() == PaymentStatus::Success);
+                if claimed {
+                    return Ok(None);
+                }
+                error!("Failed to reclaim undelivered ecash: {reclaim}");
+                return Err(anyhow!(
+                    "Failed to deliver the ecash, it can be reclaimed from history: {e}"
+                ));
+            }
+            return Err(anyhow!(
+                "Failed to deliver the ecash, it was returned to your wallet: {e}"
+            ));
+        }
+
+        Ok(None)
+    }
+
+    /// Hands a payment request payment to the payee over HTTP POST, or nostr
+    /// when the request has no POST transport
+    async fn deliver_payment_request_payment(
+        &self,
+        payload: &PaymentRequestPayload,
+        post_target: Option<String>,
+        nostr_target: Option<Nip19Profile>,
+    ) -> anyhow::Result<()> {
+        let tor_enabled = self.tor_enabled.load(Ordering::Relaxed);
+
+        let Some(target) = post_target else {
+            let profile = nostr_target.ok_or(anyhow!("Payment request has no transport"))?;
+            let relays: Vec<String> = if profile.relays.is_empty() {
+                DEFAULT_NOSTR_RELAYS
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect()
+            } else {
+                profile.relays.iter().map(ToString::to_string).collect()
+            };
+            // a throwaway key so payments can't be linked to each other
+            send_dm(
+                &Keys::generate(),
+                profile.public_key,
+                &relays,
+                serde_json::to_string(payload)?,
+                tor_enabled,
+                self.metadata_fetch_cancel.clone(),
+            )
+            .await?;
+
+            log::info!("Paid payment request over nostr to {}", profile.public_key);
+            return Ok(());
+        };
+
+        let (status, _) = make_raw_request(
+            &target,
+            Some(RequestBody::Json(serde_json::to_string(payload)?)),
+            &[],
+            tor_enabled,
+            self.metadata_fetch_cancel.clone(),
+        )
+        .await?;
+        if !status.is_success() {
+            return Err(anyhow!("Payee rejected the payment with status {status}"));
+        }
+
+        log::info!("Paid payment request to {target}");
+
+        Ok(())
+    }
+
+    /// Swaps proofs on inactive keysets into the active one for every cashu wallet,
+    /// warning the user about any wallet where that fails
+    pub async fn check_cashu_keysets(&self) {
+        let clients: Vec<cdk::Wallet> = self.cashu_clients.read().await.values().cloned().collect();
+        for client in clients {
+            let id = MintIdentifier::Cashu(client.mint_url.clone(), client.unit.clone());
+            match swap_inactive_keyset_proofs(&client).await {
+                Ok(Ok(0)) => {}
+                Ok(Ok(amount)) => {
+                    log::info!(
+                        "Moved {amount} {} to the active keyset at {}",
+                        client.unit,
+                        client.mint_url
+                    );
+                    if let Ok(balance) = client.total_balance().await {
+                        self.send_system_msg(CoreUIMsg::MintBalanceUpdated {
+                            id,
+                            balance: Amount::from_sats(balance.into()),
+                        })
+                        .await;
+                    }
+                }
+                // we'll check again next time, the mint may just be offline
+                Err(e) => {
+                    log::warn!("Could not check keysets at {}: {e}", client.mint_url);
+                }
+                Ok(Err(e)) => {
+                    error!(
+                        "Failed to swap inactive keyset proofs at {}: {e}",
+                        client.mint_url
+                    );
+                    self.send_system_msg(CoreUIMsg::KeysetSwapFailed {
+                        id,
+                        reason: e.to_string(),
+                    })
+                    .await;
+                }
+            }
+        }
+    }
+
+    /// Checks our proofs against every cashu mint where a swap, mint or melt
+    /// failed without an answer, skipping wallets with a lightning payment or
+    /// receive in flight until it settles
+    pub async fn reconcile_cashu_wallets(&self) -> anyhow::Result<()> {
+        let busy: HashSet<MintIdentifier> = self
+            .storage
+            .get_pending_lightning_payments()?
+            .iter()
+            .map(|p| p.mint_identifier())
+            .chain(
+                self.storage
+                    .get_pending_lightning_receives()?
+                    .iter()
+                    .map(|r| r.mint_identifier()),
+            )
+            .collect();
+
+        let clients: Vec<cdk::Wallet> = self.cashu_clients.read().await.values().cloned().collect();
+        let mut flagged: HashMap<MintUrl, Vec<cdk::Wallet>> = HashMap::new();
+        for client in clients {
+            if self.storage.cashu_mint_needs_reconcile(&client.mint_url)? {
+                flagged
+                    .entry(client.mint_url.clone())
+                    .or_default()
+                    .push(client);
+            }
+        }
+
+        for (mint_url, clients) in flagged {
+            // the flag is per mint, keep it until all of its wallets are settled
+            let mut settled = true;
+            for client in clients {
+                let id = MintIdentifier::Cashu(client.mint_url.clone(), client.unit.clone());
+                if busy.contains(&id) {
+                    settled = false;
+                    continue;
+                }
+
+                match reconcile_proofs(&client).await {
+                    Ok(result) => {
+                        log::info!(
+                            "Reconciled {} wallet at {mint_url}: {} spent, {} released",
+                            client.unit,
+                            result.spent,
+                            result.released
+                        );
+                        if let Ok(balance) = client.total_balance().await {
+                            self.send_system_msg(CoreUIMsg::MintBalanceUpdated {
+                                id,
+                                balance: Amount::from_sats(balance.into()),
+                            })
+                            .await;
+                        }
+                    }
+                    Err(e) => {
+                        error!(
+                            "Failed to reconcile {} wallet at {mint_url}: {e}",
+                            client.unit
+                        );
+                        settled = false;
+                    }
+                }
+            }
+
+            if settled {
+                self.storage
+                    .set_cashu_mint_needs_reconcile(&mint_url, false)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Consolidates fragmented proofs in every cashu wallet without a lightning
+    /// payment or receive in flight, if enabled in settings.
+    /// Returns true if any wallet was consolidated.
+    pub async fn consolidate_cashu_proofs(&self) -> anyhow::Result<bool> {
+        let enabled = self
+            .storage
+            .get_profile()?
+            .is_some_and(|p| p.consolidation_enabled());
+        if !enabled {
+            return Ok(false);
+        }
+
+        let busy: HashSet<MintIdentifier> = self
+            .storage
+            .get_pending_lightning_payments()?
+            .iter()
+            .map(|p| p.mint_identifier())
+            .chain(
+                self.storage
+                    .get_pending_lightning_receives()?
+                    .iter()
+                    .map(|r| r.mint_identifier()),
+            )
+            .collect();
+
+        let clients: Vec<cdk::Wallet> = self.cashu_clients.read().await.values().cloned().collect();
+        let mut consolidated = false;
+        for client in clients {
+            let id = MintIdentifier::Cashu(client.mint_url.clone(), client.unit.clone());
+            if busy.contains(&id) {
+                continue;
+            }
+
+            match consolidate_proofs(&client).await {
+                Ok(Some(consolidation)) => {
+                    log::info!(
+                        "Consolidated {} proofs into {} at {}, fee: {}",
+                        consolidation.proofs_before,
+                        consolidation.proofs_after,
+                        client.mint_url,
+                        consolidation.fee
+                    );
+                    self.storage.create_cashu_consolidation(
+                        Uuid::new_v4().to_string(),
+                        &client.mint_url,
+                        &client.unit,
+                        consolidation.amount,
+                        consolidation.fee,
+                        consolidation.proofs_before,
+                        consolidation.proofs_after,
+                    )?;
+
+                    let balance: u64 = client.total_balance().await?.into();
+                    self.send_system_msg(CoreUIMsg::MintBalanceUpdated {
+                        id,
+                        balance: Amount::from_sats(balance),
+                    })
+                    .await;
+                    consolidated = true;
+                }
+                Ok(None) => {}
+                Err(e) => error!("Failed to consolidate proofs at {}: {e}", client.mint_url),
+            }
+        }
+
+        Ok(consolidated)
+    }
+
+    /// Checks whether our unclaimed tokens and notes have been spent,
+    /// returns true if any of them was marked as claimed.
+    /// A mint we can't reach is skipped until the next check.
+    pub async fn check_pending_ecash_sends(&self) -> anyhow::Result<bool> {
+        let pending = self.storage.get_pending_ecash_sends()?;
+
+        let mut updated = false;
+        for send in pending {
+            match self.ecash_send_claimed(&send).await {
+                Ok(true) => {
+                    log::info!("Ecash token claimed: {}", send.operation_id);
+                    self.storage.mark_ecash_send_as_success(send.operation_id)?;
+                    updated = true;
+                }
+                Ok(false) => {}
+                Err(e) => {
+                    log::warn!("Could not check ecash token {}: {e}", send.operation_id);
+                }
+            }
+        }
+
+        Ok(updated)
+    }
+
+    /// Whether the recipient claimed all of the token
+    async fn ecash_send_claimed(&self, send: &EcashSend) -> anyhow::Result<bool> {
+        match send.mint_identifier() {
+            MintIdentifier::Cashu(mint_url, unit) => {
+                let Some(client) = self
+                    .cashu_clients
+                    .read()
+                    .await
+                    .get(&(mint_url, unit))
+                    .cloned()
+                else {
+                    return Ok(false);
+                };
+
+                let token = Token::from_str(send.token())?;
+                let keysets = client.get_mint_keysets().await?;
+                let proofs = token.proofs(&keysets)?;
+
+                let states = client.check_proofs_spent(proofs).await?;
+                Ok(states.iter().all(|s| s.state == State::Spent))
+            }
+            MintIdentifier::Fedimint(federation_id) => {
+                let Some(client) = self.clients.read().await.get(&federation_id).cloned() else {
+                    return Ok(false);
+                };
+                if !client.is_usable() {
+                    return Ok(false);
+                }
+
+                let notes = OOBNotes::from_str(send.token())?;
+                notes_spent(&client.fedimint_client, &notes).await
+            }
+        }
+    }
+
+    /// Unclaimed timelocked tokens whose locktime passes within the reminder
+    /// window and that the user hasn't been reminded about yet
+    pub fn get_expiring_timelocked_ecash(&self) -> anyhow::Result<Vec<(String, String, u64)>> {
+        let cutoff = (SystemTime::now() + TIMELOCK_REMINDER_WINDOW)
+            .duration_since(UNIX_EPOCH)?
+            .as_secs();
+
+        Ok(self
+            .storage
+            .get_pending_timelocked_ecash_sends()?
+            .into_iter()
+            .filter_map(|send| {
+                let locktime = send.locktime()?;
+                (locktime <= cutoff).then(|| {
+                    (
+                        send.operation_id.clone(),
+                        send.token().to_string(),
+                        locktime,
+                    )
+                })
+            })
+            .collect())
+    }
+
+    /// Takes back an unclaimed token we sent by swapping its unspent proofs
+    /// or cancelling the out-of-band notes
+    pub async fn reclaim_ecash(&self, msg_id: Uuid, token: String) -> anyhow::Result<()> {
+        let send = self
+            .storage
+            .get_ecash_send_by_token(&token)?
+            .ok_or(anyhow!("Unknown ecash token"))?;
+
+        if send.status() != PaymentStatus::Pending {
+            return Err(anyhow!("Ecash token is no longer pending"));
+        }
+        if !send.reclaimable() {
+            return Err(anyhow!("Ecash token is locked to someone else's key"));
+        }
+
+        log::info!("Reclaiming ecash token: {}", send.operation_id);
+
+        match send.mint_identifier() {
+            MintIdentifier::Cashu(mint_url, unit) => {
+                let client = self.get_cashu_client(&mint_url, &unit).await;
+
+                self.status_update(msg_id, "Checking token state").await;
+
+                let token = Token::from_str(&token)?;
+                let keysets = client.get_mint_keysets().await?;
+                let proofs = token.proofs(&keysets)?;
+
+                let states = client.check_proofs_spent(proofs.clone()).await?;
+                let mut spent = Proofs::new();
+                let mut unspent = Proofs::new();
+                for (proof, state) in proofs.into_iter().zip(states) {
+                    if state.state == State::Spent {
+                        spent.push(proof);
+                    } else {
+                        unspent.push(proof);
+                    }
+                }
+                if unspent.is_empty() {
+                    self.storage.mark_ecash_send_as_success(send.operation_id)?;
+                    update_history(self.storage.clone(), msg_id, &mut self.tx.clone()).await;
+                    return Err(anyhow!("Token was already claimed"));
+                }
+
+                self.status_update(msg_id, "Reclaiming ecash").await;
+
+                // timelocked tokens are locked to our key so they need signing
+                if send.locktime().is_some() {
+                    let unspent = Token::new(
+                        mint_url.clone(),
+                        unspent,
+                        token.memo().clone(),
+                        unit.clone(),
+                    );
+                    client
+                        .receive(&unspent.to_string(), self.receive_options()?)
+                        .await?;
+                } else {
+                    client.reclaim_unspent(unspent).await?;
+                }
+
+                // the recipient may have claimed some of the proofs already
+                if spent.is_empty() {
+                    self.storage.mark_ecash_send_as_failed(send.operation_id)?;
+                } else {
+                    let claimed: u64 = spent.total_amount()?.into();
+                    log::info!(
+                        "Ecash token {} was partly claimed, reclaimed the rest",
+                        send.operation_id
+                    );
+                    self.storage.mark_ecash_send_as_partially_reclaimed(
+                        send.operation_id,
+                        Amount::from_sats(claimed),
+                    )?;
+                }
+
+                let bal: u64 = client.total_balance().await?.into();
+                self.msg(
+                    msg_id,
+                    CoreUIMsg::MintBalanceUpdated {
+                        id: MintIdentifier::Cashu(mint_url, unit),
+                        balance: Amount::from_sats(bal),
+                    },
+                )
+                .await;
+            }
+            MintIdentifier::Fedimint(federation_id) => {
+                let client = self.get_client(federation_id).await?.fedimint_client;
+                let mint_module = client.get_first_module::<MintClientModule>()?;
+                let op_id = OperationId::from_str(&send.operation_id)?;
+
+                self.status_update(msg_id, "Cancelling notes").await;
+
+                // the spend subscription records the outcome and updates the balance
+                mint_module.try_cancel_spend_notes(op_id).await;
+
+                let mut stream = mint_module
+                    .subscribe_spend_notes(op_id)
+                    .await?
+                    .into_stream();
+                while let Some(state) = stream.next().await {
+                    match state {
+                        SpendOOBState::UserCanceledSuccess | SpendOOBState::Refunded => break,
+                        SpendOOBState::UserCanceledFailure | SpendOOBState::Success => {
+                            return Err(anyhow!("Notes were already claimed"));
+                        }
+                        SpendOOBState::Created | SpendOOBState::UserCanceledProcessing => {}
+                    }
+                }
+            }
+        }
+
+        self.msg(msg_id, CoreUIMsg::EcashReclaimed).await;
+
+        update_history(self.storage.clone(), msg_id, &mut self.tx.clone()).await;
+
+        Ok(())
+    }
+
+    /// Lets us redeem tokens that were locked to our pubkey
+    fn receive_options(&self) -> anyhow::Result<ReceiveOptions> {
+        Ok(ReceiveOptions {
+            p2pk_signing_keys: vec![keys::cashu_p2pk_key(&self.mnemonic)?],
+            ..ReceiveOptions::default()
+        })
+    }
+
+    /// Reissues out-of-band notes into the federation that issued them
+    pub async fn receive_fedimint_notes(
+        &self,
+        msg_id: Uuid,
+        notes: OOBNotes,
+    ) -> anyhow::Result<()> {
+        // notes only carry a prefix of the federation id
+        let prefix = notes.federation_id_prefix();
+        let client = self
+            .clients
+            .read()
+            .await
+            .values()
+            .find(|c| c.fedimint_client.federation_id().to_prefix() == prefix)
+            .cloned()
+            .ok_or(anyhow!("Notes are from a federation you have not joined"))?;
+        client.ensure_usable()?;
+        let client = client.fedimint_client;
+        let federation_id = client.federation_id();
+
+        log::info!("Reissuing ecash notes into federation: {federation_id}");
+
+        self.status_update(msg_id, "Reissuing notes").await;
+
+        let mint_module = client.get_first_module::<MintClientModule>()?;
+        let op_id = mint_module
+            .reissue_external_notes(notes.clone(), ())
+            .await?;
+
+        self.storage.create_ecash_receive(
+            op_id.fmt_full().to_string(),
+            Some(federation_id),
+            None,
+            None,
+            notes.to_string(),
+            notes.total_amount(),
+        )?;
+
+        let sub = mint_module.subscribe_reissue_external_notes(op_id).await?;
+        spawn_reissue_notes_subscription(
+            self.tx.clone(),
+            client,
+            self.storage.clone(),
+            op_id,
+            msg_id,
+            sub,
+        )
+        .await;
+
+        Ok(())
+    }
+
+    /// Redeems a cashu token into the wallet for the mint that issued it,
+    /// returning the id of the ecash receive
+    pub async fn receive_cashu_token(&self, msg_id: Uuid, token: Token) -> anyhow::Result<String> {
+        let mint_url = token.mint_url()?;
+        let unit = token.unit().unwrap_or(CurrencyUnit::Sat);
+        log::info!("Redeeming {unit} ecash token from cashu mint: {mint_url}");
+
+        let key = (mint_url.clone(), unit.clone());
+        if !self.cashu_clients.read().await.contains_key(&key) {
+            return Err(anyhow!(
+                "Token is from a mint or unit you have not added: {mint_url} ({unit})"
+            ));
+        }
+
+        let client = self.get_cashu_client(&mint_url, &unit).await;
+
+        let value: u64 = token.value()?.into();
+        let amount = Amount::from_sats(value);
+
+        let operation_id = Uuid::new_v4().to_string();
+        self.storage.create_ecash_receive(
+            operation_id.clone(),
+            None,
+            Some(mint_url.clone()),
+            Some(unit.clone()),
+            token.to_string(),
+            amount,
+        )?;
+
+        self.status_update(msg_id, "Redeeming ecash").await;
+
+        let received = match client
+            .receive(&token.to_string(), self.receive_options()?)
+            .await
+        {
+            Ok(received) => u64::from(received),
+            Err(e) => {
+                error!("Failed to redeem ecash token: {e}");
+                self.storage.mark_ecash_receive_as_failed(operation_id)?;
+                return Err(e.into());
+            }
+        };
+
+        let fee = Amount::from_sats(value.saturating_sub(received));
+        self.storage
+            .mark_ecash_receive_as_success(operation_id.clone(), fee)?;
+
+        log::info!("Redeemed {received} {unit} of ecash from cashu mint: {mint_url}");
+
+        self.msg(msg_id, CoreUIMsg::ReceiveSuccess(ReceiveSuccessMsg::Ecash))
+            .await;
+
+        let bal: u64 = client.total_balance().await?.into();
+        self.msg(
+            msg_id,
+            CoreUIMsg::MintBalanceUpdated {
+                id: MintIdentifier::Cashu(mint_url, unit),
+                balance: Amount::from_sats(bal),
+            },
+        )
+        .await;
+
+        update_history(self.storage.clone(), msg_id, &mut self.tx.clone()).await;
+
+        Ok(operation_id)
+    }
+
+    /// Redeems ecash the payer handed over for one of our payment requests,
+    /// either as a NUT-18 payload or a plain token
+    pub async fn receive_payment_request_payment(
+        &self,
+        msg_id: Uuid,
+        id: String,
+        payment: &str,
+    ) -> anyhow::Result<()> {
+        let request = self
+            .storage
+            .get_payment_request(&id)?
+            .ok_or(anyhow!("Unknown payment request: {id}"))?;
+        if request.status() == PaymentStatus::Success {
+            return Err(anyhow!("Payment request has already been paid"));
+        }
+
+        let token = match serde_json::from_str::<PaymentRequestPayload>(payment.trim()) {
+            Ok(payload) => {
+                if payload.id.as_ref().is_some_and(|p| *p != id) {
+                    return Err(anyhow!("Payment is for a different request"));
+                }
+                Token::new(payload.mint, payload.proofs, payload.memo, payload.unit)
+            }
+            Err(_) => Token::from_str(payment.trim())?,
+        };
+
+        if token.mint_url()? != request.mint_url() {
+            return Err(anyhow!("Payment is not from the requested mint"));
+        }
+        if token.unit().is_some_and(|u| u != request.unit()) {
+            return Err(anyhow!("Payment is not in the requested unit"));
+        }
+        if let Some(amount) = request.amount() {
+            let value: u64 = token.value()?.into();
+            if value < amount {
+                return Err(anyhow!(
+                    "Payment of {value} is less than the requested {amount}"
+                ));
+            }
+        }
+
+        let operation_id = self.receive_cashu_token(msg_id, token).await?;
+        self.storage.mark_payment_request_paid(&id, operation_id)?;
+
+        log::info!("Payment request {id} paid");
+
+        self.msg(msg_id, CoreUIMsg::PaymentRequestPaid(id)).await;
+
+        Ok(())
+    }
+
+    /// Listens on a nostr relay for ecash DMs sent to us until we shut down or
+    /// the relay drops the connection
+    async fn listen_for_nostr_dms(&self, url: &str) -> anyhow::Result<()> {
+        let keys = keys::nostr_keys(&self.mnemonic)?;
+        let mut relay = NostrRelay::connect(
+            url,
+            self.tor_enabled.load(Ordering::Relaxed),
+            self.metadata_fetch_cancel.clone(),
+        )
+        .await?;
+        relay
+            .subscribe("harbor-dms", &dm_filter(keys.public_key()))
+            .await?;
+        log::info!("Listening for ecash DMs on {url}");
+
+        while !self.stop.load(Ordering::Relaxed) && self.nostr_enabled() {
+            let Ok(msg) =
+                tokio::time::timeout(NOSTR_STOP_CHECK_INTERVAL, relay.next_message()).await
+            else {
+                continue;
+            };
+            match msg? {
+                Some(RelayMessage::Event { event, .. }) => {
+                    if let Err(e) = self.receive_nostr_dm(&keys, &event).await {
+                        error!("Failed to redeem ecash from nostr DM: {e}");
+                    }
+                }
+                Some(RelayMessage::Closed { message, .. }) => {
+                    return Err(anyhow!("Subscription closed: {message}"));
+                }
+                Some(_) => {}
+                None => return Err(anyhow!("Connection closed")),
+            }
+        }
+
+        relay.close().await;
+        Ok(())
+    }
+
+    /// Redeems the ecash in a DM sent to us, each DM only once. Ecash from a
+    /// mint we haven't joined is left for when we have, relays keep sending
+    /// the DM until it's too old to match our subscription.
+    ///
+    /// The DM is claimed while it's redeemed so the copy another relay sends
+    /// is skipped. If redeeming fails it's retried with backoff, the user
+    /// hears about the first failure only.
+    async fn receive_nostr_dm(&self, keys: &Keys, event: &nostr::Event) -> anyhow::Result<()> {
+        let (sender, message) = unwrap_dm(keys, event).await?;
+        let Some(ecash) = parse_ecash_message(&message) else {
+            log::debug!("Ignoring nostr DM without ecash from {sender}");
+            return Ok(());
+        };
+
+        let joined = match &ecash {
+            NostrEcash::Cashu { token, .. } => {
+                let key = (token.mint_url()?, token.unit().unwrap_or(CurrencyUnit::Sat));
+                self.cashu_clients.read().await.contains_key(&key)
+            }
+            NostrEcash::Fedimint(notes) => {
+                let prefix = notes.federation_id_prefix();
+                self.clients
+                    .read()
+                    .await
+                    .keys()
+                    .any(|id| id.to_prefix() == prefix)
+            }
+        };
+        if !joined {
+            log::warn!("Got ecash over nostr from {sender} for a mint we haven't joined");
+            return Ok(());
+        }
+
+        let event_id = event.id.to_hex();
+        if !self
+            .storage
+            .claim_nostr_event(&event_id, &event.as_json())?
+        {
+            return Ok(());
+        }
+
+        log::info!("Redeeming ecash sent over nostr by {sender}");
+        let redeemed = match ecash {
+            // a payer retrying a request we've already been paid for still sent us ecash
+            NostrEcash::Cashu {
+                token,
+                request_id: Some(id),
+            } if self
+                .storage
+                .get_payment_request(&id)?
+                .is_some_and(|r| r.status() != PaymentStatus::Success) =>
+            {
+                self.receive_payment_request_payment(Uuid::nil(), id, &token.to_string())
+                    .await
+            }
+            NostrEcash::Cashu { token, .. } => self
+                .receive_cashu_token(Uuid::nil(), token)
+                .await
+                .map(|_| ()),
+            NostrEcash::Fedimint(notes) => self.receive_fedimint_notes(Uuid::nil(), notes).await,
+        };
+
+        match redeemed {
+            Ok(()) => self.storage.mark_nostr_event_as_redeemed(&event_id),
+            Err(e) => {
+                let attempts = self.storage.mark_nostr_event_as_failed(&event_id)?;
+                if attempts == 1 {
+                    self.send_system_msg(CoreUIMsg::ReceiveFailed(e.to_string()))
+                        .await;
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// Tries again to redeem the ecash of DMs that failed before and are due
+    async fn retry_nostr_dms(&self) -> anyhow::Result<()> {
+        let events = self.storage.get_retryable_nostr_events()?;
+        if events.is_empty() {
+            return Ok(());
+        }
+
+        let keys = keys::nostr_keys(&self.mnemonic)?;
+        for stored in events {
+            let Some(event) = stored.event().and_then(|e| nostr::Event::from_json(e).ok()) else {
+                self.storage.mark_nostr_event_as_failed(&stored.event_id)?;
+                continue;
+            };
+            log::info!(
+                "Retrying ecash from nostr DM {}, attempt {}",
+                stored.event_id,
+                stored.attempts() + 1
+            );
+            if let Err(e) = self.receive_nostr_dm(&keys, &event).await {
+                error!("Failed to redeem ecash from nostr DM: {e}");
+            }
+        }
+        Ok(())
+    }
+
+    fn nostr_enabled(&self) -> bool {
+        self.storage
+            .get_profile()
+            .ok()
+            .flatten()
+            .is_some_and(|p| p.nostr_enabled())
+    }
+
+    /// Moves a token from a mint we don't use into one of ours by melting it
+    /// into an invoice from `mint`, so we never have to join the token's mint.
+    ///
+    /// The swap is recorded before the token is claimed, so if it fails after
+    /// that the ecash is kept at the token's mint and the swap is retried on
+    /// the next start.
+    pub async fn swap_in_cashu_token(
+        &self,
+        msg_id: Uuid,
+        token: Token,
+        mint: MintIdentifier,
+    ) -> anyhow::Result<()> {
+        let token_mint = token.mint_url()?;
+        log::info!("Swapping in ecash token from {token_mint} to {mint:?}");
+
+        if token.unit().is_some_and(|u| u != CurrencyUnit::Sat) || mint.unit() != CurrencyUnit::Sat
+        {
+            return Err(anyhow!("Only sat denominated tokens are supported"));
+        }
+
+        self.status_update(msg_id, "Connecting to token's mint")
+            .await;
+        let wallet = self.swap_wallet(&token_mint)?;
+
+        // claiming and melting both cost input fees, a token that can't cover
+        // them would leave its ecash at a mint we don't use
+        let value: u64 = token.value()?.into();
+        let keysets = wallet.get_mint_keysets().await?;
+        let input_fee: u64 = wallet
+            .get_proofs_fee(&token.proofs(&keysets)?)
+            .await?
+            .into();
+        if value <= input_fee * 2 + estimated_fee_reserve(value) {
+            return Err(anyhow!("Token is too small to cover lightning fees"));
+        }
+
+        let operation_id = Uuid::new_v4().to_string();
+        self.storage.create_ecash_swap(
+            operation_id.clone(),
+            mint.federation_id(),
+            mint.mint_url(),
+            None,
+            token_mint.clone(),
+            token.to_string(),
+            Amount::from_sats(value),
+        )?;
+
+        self.status_update(msg_id, "Claiming ecash").await;
+        if let Err(e) = wallet
+            .receive(&token.to_string(), self.receive_options()?)
+            .await
+        {
+            self.storage.mark_ecash_receive_as_failed(operation_id)?;
+            return Err(e.into());
+        }
+        self.storage
+            .mark_ecash_swap_as_claimed(operation_id.clone())?;
+
+        let id = operation_id.clone();
+        self.finish_ecash_swap(msg_id, operation_id, &wallet, mint, value, None)
+            .await
+            .inspect_err(|e| error!("Swap {id} failed, the claimed ecash is kept for a retry: {e}"))
+    }
+
+    /// A wallet for a mint we haven't joined. Its proofs go into our cashu store
+    /// like any other, so claimed ecash and melt change are still ours if a
+    /// swap fails, and are swapped in along with the next token from the mint.
+    fn swap_wallet(&self, mint_url: &MintUrl) -> anyhow::Result<cdk::Wallet> {
+        let seed = self.mnemonic.to_seed_normalized("");
+        Ok(WalletBuilder::new()
+            .mint_url(mint_url.clone())
+            .unit(CurrencyUnit::Sat)
+            .localstore(self.cashu_storage.clone())
+            .seed(&seed)
+            .client(DleqVerifyingConnector::new(
+                mint_url.clone(),
+                self.tor_enabled.load(Ordering::Relaxed),
+                self.storage.clone(),
+            ))
+            .build()?)
+    }
+
+    /// Melts everything the swap wallet holds into an invoice from `mint`.
+    ///
+    /// `started` is the payment hash and melt quote of an earlier attempt, which
+    /// is checked first so we never pay the token's ecash out twice.
+    async fn finish_ecash_swap(
         &self,
         msg_id: Uuid,
-        mint: MintUrl,
-        amount: Amount,
-        is_transfer: bool,
-    ) -> anyhow::Result<Bolt11Invoice> {
-        let tor_enabled = self.tor_enabled.load(Ordering::Relaxed);
-        log::info!(
-            "Creating lightning invoice, amount: {amount} for mint: {mint}. Tor enabled: {tor_enabled}"
-        );
+        operation_id: String,
+        wallet: &cdk::Wallet,
+        mint: MintIdentifier,
+        value: u64,
+        started: Option<(&str, &str)>,
+    ) -> anyhow::Result<()> {
+        let mut quote = None;
+        if let Some((payment_hash, quote_id)) = started {
+            let receive = self
+                .storage
+                .get_lightning_receive_by_payment_hash(payment_hash)?;
+            // fetching the status also stores any change the mint returned
+            let status = wallet.melt_quote_status(quote_id).await?;
+            let received = receive
+                .as_ref()
+                .is_some_and(|r| r.status() == PaymentStatus::Success);
+            if received || status.state == MeltQuoteState::Paid {
+                return self
+                    .complete_ecash_swap(msg_id, operation_id, wallet, status.amount.into(), value)
+                    .await;
+            }
+            match status.state {
+                MeltQuoteState::Unpaid | MeltQuoteState::Failed => {}
+                state => return Err(anyhow!("Payment from token's mint is {state}")),
+            }
 
-        self.status_update(msg_id, "Connecting to mint").await;
+            // our invoice is still open, pay it with the same quote if that's still good
+            let invoice_open = receive.is_some_and(|r| r.status() == PaymentStatus::Pending);
+            if invoice_open {
+                quote = wallet
+                    .localstore
+                    .get_melt_quote(quote_id)
+                    .await?
+                    .filter(|q| q.expiry > unix_time());
+            }
+        }
 
-        let client = self.get_cashu_client(&mint).await;
+        let quote = match quote {
+            Some(quote) => quote,
+            None => self
+                .quote_ecash_swap(msg_id, &operation_id, wallet, mint)
+                .await?
+                .ok_or(anyhow!(
+                    "Swapped ecash can't cover lightning fees, kept at {} for a retry",
+                    wallet.mint_url
+                ))?,
+        };
+        let amount: u64 = quote.amount.into();
 
-        self.status_update(msg_id, "Generating invoice").await;
+        self.status_update(msg_id, "Paying invoice from token's mint")
+            .await;
+        let (_, fee_paid) =
+            melt_and_wait(wallet, &quote, self.tor_enabled.load(Ordering::Relaxed)).await?;
+        log::info!("Swapping in {amount} sats, {fee_paid} sats in fees");
 
-        let quote = client
-            .mint_quote(cdk::Amount::from(amount.msats / 1000), None)
-            .await?;
+        self.complete_ecash_swap(msg_id, operation_id, wallet, amount, value)
+            .await
+    }
 
-        let invoice = Bolt11Invoice::from_str(&quote.request)?;
+    /// Gets an invoice from `mint` for everything the swap wallet holds less
+    /// the fees, and a quote to pay it from the token's mint, recorded before
+    /// anything is melted. Starts from an estimate of the fee reserve and only
+    /// asks for a smaller invoice if the mint quotes more, the unpaid one just
+    /// expires. Returns None if the fees would take everything.
+    async fn quote_ecash_swap(
+        &self,
+        msg_id: Uuid,
+        operation_id: &str,
+        wallet: &cdk::Wallet,
+        mint: MintIdentifier,
+    ) -> anyhow::Result<Option<MeltQuote>> {
+        let available: u64 = wallet.total_balance().await?.into();
+        let input_fee: u64 = wallet
+            .get_proofs_fee(&wallet.get_unspent_proofs().await?)
+            .await?
+            .into();
+
+        let mut reserve = estimated_fee_reserve(available);
+        for _ in 0..SWAP_QUOTE_ATTEMPTS {
+            let amount = available.saturating_sub(reserve + input_fee);
+            if amount == 0 {
+                return Ok(None);
+            }
 
-        log::info!("Invoice created: {invoice}");
+            self.status_update(msg_id, "Generating invoice").await;
+            let invoice = self
+                .receive_lightning(msg_id, mint.clone(), Amount::from_sats(amount), false)
+                .await?;
 
-        self.storage.create_ln_receive(
-            quote.id.clone(),
-            None,
-            Some(mint),
-            invoice.clone(),
-            amount,
-            Amount::ZERO, // todo one day there will be receive fees
-        )?;
+            self.status_update(msg_id, "Getting a quote from token's mint")
+                .await;
+            let quote = wallet.melt_quote(invoice.to_string(), None).await?;
+            let quoted: u64 = quote.fee_reserve.into();
+            if quoted <= reserve {
+                self.storage.set_ecash_swap_melt_quote(
+                    operation_id.to_string(),
+                    invoice.payment_hash().to_string(),
+                    quote.id.clone(),
+                )?;
+                return Ok(Some(quote));
+            }
+            reserve = quoted;
+        }
 
-        spawn_lightning_receive_thread(
-            self.tx.clone(),
-            client,
-            self.storage.clone(),
-            quote,
-            msg_id,
-            is_transfer,
-        );
-        Ok(invoice)
+        Err(anyhow!("Token's mint keeps raising its fee reserve"))
     }
 
-    pub async fn transfer(
+    async fn complete_ecash_swap(
         &self,
         msg_id: Uuid,
-        to: MintIdentifier,
-        from: MintIdentifier,
-        amount: Amount,
+        operation_id: String,
+        wallet: &cdk::Wallet,
+        amount: u64,
+        value: u64,
     ) -> anyhow::Result<()> {
-        log::info!("Transferring {amount} from {from:?} to {to:?}");
+        // change stays in the swap wallet for the next token from this mint
+        let change: u64 = wallet.total_balance().await?.into();
+        log::info!(
+            "Swapped in {amount} sats from {}, {change} sats of change kept",
+            wallet.mint_url
+        );
+        let fee = value.saturating_sub(amount + change);
+        self.storage
+            .mark_ecash_receive_as_success(operation_id, Amount::from_sats(fee))?;
 
-        self.status_update(msg_id, "Generating invoice on destination mint")
-            .await;
+        // the lightning receive subscription reports the success
+        update_history(self.storage.clone(), msg_id, &mut self.tx.clone()).await;
 
-        let invoice = self.receive_lightning(msg_id, to, amount, true).await?;
+        Ok(())
+    }
 
-        self.status_update(msg_id, "Paying invoice from source mint")
-            .await;
+    /// Finishes swaps that failed or were interrupted
+    async fn retry_ecash_swaps(&self) -> anyhow::Result<()> {
+        for swap in self.storage.get_pending_ecash_receives()? {
+            let Some(token_mint) = swap.swap_mint_url() else {
+                continue;
+            };
+            log::info!("Retrying swap {} from {token_mint}", swap.operation_id);
+
+            let wallet = self.swap_wallet(&token_mint)?;
+            if !swap.swap_claimed() {
+                if let Err(e) = wallet.receive(swap.token(), self.receive_options()?).await {
+                    error!("Could not claim token for swap {}: {e}", swap.operation_id);
+                    self.storage
+                        .mark_ecash_receive_as_failed(swap.operation_id.clone())?;
+                    continue;
+                }
+                self.storage
+                    .mark_ecash_swap_as_claimed(swap.operation_id.clone())?;
+            }
 
-        self.send_lightning(msg_id, from, invoice, true).await?;
+            let started = swap.swap_payment_hash().zip(swap.swap_quote_id());
+            let value = swap.amount().sats_round_down();
+            if let Err(e) = self
+                .finish_ecash_swap(
+                    Uuid::nil(),
+                    swap.operation_id.clone(),
+                    &wallet,
+                    swap.mint_identifier(),
+                    value,
+                    started,
+                )
+                .await
+            {
+                error!("Could not finish swap {}: {e}", swap.operation_id);
+            }
+        }
         Ok(())
     }
 
@@ -1105,7 +3245,7 @@ impl HarborCore {
         log::info!(
             "Sending onchain payment to address: {address} from federation: {federation_id}",
         );
-        let client = self.get_client(federation_id).await.fedimint_client;
+        let client = self.get_client(federation_id).await?.fedimint_client;
         let onchain = client
             .get_first_module::<WalletClientModule>()
             .expect("must have wallet module");
@@ -1193,7 +3333,7 @@ impl HarborCore {
 
         self.status_update(msg_id, "Connecting to mint").await;
 
-        let client = self.get_client(federation_id).await.fedimint_client;
+        let client = self.get_client(federation_id).await?.fedimint_client;
         let onchain = client
             .get_first_module::<WalletClientModule>()
             .expect("must have wallet module");
@@ -1224,6 +3364,40 @@ impl HarborCore {
         Ok(address)
     }
 
+    /// Mints announced on nostr for our network, leaving out ones we've already joined
+    pub async fn discover_mints(&self, msg_id: Uuid) -> anyhow::Result<Vec<DiscoveredMint>> {
+        log::info!("Discovering mints on nostr");
+        self.status_update(msg_id, "Searching for mints").await;
+
+        let mints = discover_mints(
+            &DEFAULT_NOSTR_RELAYS,
+            self.network,
+            self.tor_enabled.load(Ordering::Relaxed),
+            self.metadata_fetch_cancel.clone(),
+        )
+        .await?;
+
+        let joined_federations = self.storage.list_federations()?;
+        let joined_mints: Vec<MintUrl> = self
+            .storage
+            .list_cashu_mints()?
+            .into_iter()
+            .map(|(url, _)| url)
+            .collect();
+        let mints: Vec<DiscoveredMint> = mints
+            .into_iter()
+            .filter(|m| match &m.info {
+                MintConnectionInfo::Cashu(url) => !joined_mints.contains(url),
+                MintConnectionInfo::Fedimint(invite_code) => {
+                    !joined_federations.contains(&invite_code.federation_id().to_string())
+                }
+            })
+            .collect();
+        log::info!("Discovered {} mints", mints.len());
+
+        Ok(mints)
+    }
+
     pub async fn get_cashu_mint_info(
         &self,
         msg_id: Uuid,
@@ -1239,16 +3413,12 @@ impl HarborCore {
             .mint_url(mint_url.clone())
             .unit(CurrencyUnit::Sat)
             .localstore(self.cashu_storage.clone())
-            .seed(&seed);
-
-        let builder = if self.tor_enabled.load(Ordering::Relaxed) {
-            builder.client(TorMintConnector::new(
-                mint_url,
-                Arc::new(AtomicBool::new(false)),
-            ))
-        } else {
-            builder
-        };
+            .seed(&seed)
+            .client(DleqVerifyingConnector::new(
+                mint_url.clone(),
+                self.tor_enabled.load(Ordering::Relaxed),
+                self.storage.clone(),
+            ));
 
         let wallet = builder.build()?;
 
@@ -1256,6 +3426,30 @@ impl HarborCore {
 
         let info = wallet.get_mint_info().await?;
 
+        // checking the network needs a mint quote, which auth mints protect.
+        // We don't log in just to look at a mint, it's checked once added.
+        let logged_in = self.storage.get_cashu_mint_auth(&mint_url)?.is_some();
+        let tor_enabled = self.tor_enabled.load(Ordering::Relaxed);
+        if !logged_in
+            && cashu_auth::get_auth_settings(&mint_url, tor_enabled)
+                .await?
+                .is_some()
+        {
+            log::info!("{mint_url} requires auth, checking its network once it's added");
+            return Ok(info);
+        }
+
+        self.check_cashu_mint_network(msg_id, &wallet).await?;
+
+        Ok(info)
+    }
+
+    /// Makes sure the mint's invoices are for our network
+    async fn check_cashu_mint_network(
+        &self,
+        msg_id: Uuid,
+        wallet: &cdk::Wallet,
+    ) -> anyhow::Result<()> {
         self.status_update(msg_id, "Checking mint network").await;
 
         let quote = wallet.mint_quote(cdk::Amount::ONE, None).await?;
@@ -1274,7 +3468,88 @@ impl HarborCore {
             ));
         }
 
-        Ok(info)
+        Ok(())
+    }
+
+    /// Logs in to a cashu mint that requires auth (NUT-21/22), the user
+    /// approves the login in their browser and can cancel it with
+    /// [`Self::cancel_cashu_login`]. Does nothing if the mint doesn't need auth
+    /// or we are still logged in. Returns whether the mint requires auth.
+    async fn login_cashu_mint(&self, msg_id: Uuid, mint_url: &MintUrl) -> anyhow::Result<bool> {
+        if let Some(auth) = self.storage.get_cashu_mint_auth(mint_url)? {
+            let expired = auth
+                .expires_at()
+                .is_some_and(|t| t <= cdk::util::unix_time());
+            if !expired || auth.refresh_token.is_some() {
+                return Ok(true);
+            }
+        }
+
+        let tor_enabled = self.tor_enabled.load(Ordering::Relaxed);
+        let Some(settings) = cashu_auth::get_auth_settings(mint_url, tor_enabled).await? else {
+            return Ok(false);
+        };
+        self.cashu_login_cancel.store(false, Ordering::Relaxed);
+
+        log::info!("Cashu mint {mint_url} requires auth, starting login");
+        self.status_update(msg_id, "Mint requires login").await;
+
+        let login = DeviceLogin::start(&settings, tor_enabled).await?;
+        self.msg(
+            msg_id,
+            CoreUIMsg::CashuLoginRequired {
+                mint_url: mint_url.clone(),
+                verification_uri: login.verification_uri.clone(),
+                user_code: login.user_code.clone(),
+            },
+        )
+        .await;
+
+        self.status_update(msg_id, "Waiting for login").await;
+        let tokens = login.wait(tor_enabled, &self.cashu_login_cancel).await?;
+
+        self.storage.save_cashu_mint_auth(
+            mint_url,
+            &settings,
+            tokens.access_token,
+            tokens.refresh_token,
+            tokens.expires_at,
+        )?;
+        log::info!("Logged in to cashu mint {mint_url}");
+
+        Ok(true)
+    }
+
+    /// Logs in again before spending from a mint whose login expired
+    async fn renew_cashu_login(&self, msg_id: Uuid, mint_url: &MintUrl) -> anyhow::Result<()> {
+        if self.storage.get_cashu_mint_auth(mint_url)?.is_some() {
+            self.login_cashu_mint(msg_id, mint_url).await?;
+        }
+        Ok(())
+    }
+
+    /// Gives up on the cashu mint login we're waiting for
+    pub fn cancel_cashu_login(&self) {
+        self.cashu_login_cancel.store(true, Ordering::Relaxed);
+    }
+
+    /// Units the mint can both issue and melt ecash for, sat first
+    pub fn cashu_mint_units(info: Option<&MintInfo>) -> Vec<CurrencyUnit> {
+        let Some(info) = info else {
+            return vec![CurrencyUnit::Sat];
+        };
+
+        let mut units: Vec<CurrencyUnit> = info
+            .nuts
+            .nut04
+            .methods
+            .iter()
+            .map(|m| m.unit.clone())
+            .filter(|unit| info.nuts.nut05.methods.iter().any(|m| &m.unit == unit))
+            .collect();
+        units.sort_by_key(|unit| (*unit != CurrencyUnit::Sat, unit.to_string()));
+        units.dedup();
+        units
     }
 
     pub async fn get_federation_info(
@@ -1360,6 +3635,10 @@ impl HarborCore {
 
         clients.insert(id, client.clone());
 
+        if client.is_recovering() {
+            self.spawn_fedimint_recovery(id);
+        }
+
         let tx = self.tx.clone();
         let tor_enabled = self.tor_enabled.load(Ordering::Relaxed);
         let metadata_fetch_cancel = self.metadata_fetch_cancel.clone();
@@ -1377,17 +3656,27 @@ impl HarborCore {
 
         self.status_update(msg_id, "Mint setup complete!").await;
 
+        self.spawn_manifest_backup();
+
         Ok(())
     }
 
-    pub async fn add_cashu_mint(&self, msg_id: Uuid, mint_url: MintUrl) -> anyhow::Result<()> {
-        log::info!("Adding cashu mint: {mint_url}");
+    pub async fn add_cashu_mint(
+        &self,
+        msg_id: Uuid,
+        mint_url: MintUrl,
+        unit: CurrencyUnit,
+    ) -> anyhow::Result<()> {
+        log::info!("Adding cashu mint: {mint_url} ({unit})");
         let url = mint_url.to_string();
 
         self.status_update(msg_id, "Starting mint setup").await;
 
-        let mut clients = self.cashu_clients.write().await;
-        if clients.get(&mint_url).is_some() {
+        // before taking the lock, the user may take a while to log in
+        let requires_auth = self.login_cashu_mint(msg_id, &mint_url).await?;
+
+        let key = (mint_url.clone(), unit.clone());
+        if self.cashu_clients.read().await.contains_key(&key) {
             return Err(anyhow!("Mint already added"));
         }
 
@@ -1398,23 +3687,41 @@ impl HarborCore {
 
         let builder = WalletBuilder::new()
             .mint_url(mint_url.clone())
-            .unit(CurrencyUnit::Sat)
+            .unit(unit.clone())
             .localstore(self.cashu_storage.clone())
-            .seed(&seed);
-
-        let builder = if self.tor_enabled.load(Ordering::Relaxed) {
-            builder.client(TorMintConnector::new(
+            .seed(&seed)
+            .client(DleqVerifyingConnector::new(
                 mint_url.clone(),
-                Arc::new(AtomicBool::new(false)),
-            ))
-        } else {
-            builder
-        };
+                self.tor_enabled.load(Ordering::Relaxed),
+                self.storage.clone(),
+            ));
 
         let wallet = builder.build()?;
 
+        // we couldn't check it before logging in
+        if requires_auth {
+            self.check_cashu_mint_network(msg_id, &wallet).await?;
+        }
+
+        if unit != CurrencyUnit::Sat {
+            let info = wallet.get_mint_info().await?;
+            if !Self::cashu_mint_units(info.as_ref()).contains(&unit) {
+                return Err(anyhow!("Mint does not support {unit}"));
+            }
+        }
+
+        self.status_update(msg_id, "Registering with mint").await;
+
+        // only lock once the mint answered, it may be slow or unreachable
+        let mut clients = self.cashu_clients.write().await;
+        if clients.contains_key(&key) {
+            return Err(anyhow!("Mint already added"));
+        }
+        clients.insert(key, wallet.clone());
+        drop(clients);
+
         // start background task to attempt to restore
-        let w = wallet.clone();
+        let w = wallet;
         let mut tx = self.tx.clone();
         spawn(async move {
             match w.restore().await {
@@ -1431,7 +3738,10 @@ impl HarborCore {
                                     &mut tx,
                                     None,
                                     CoreUIMsg::MintBalanceUpdated {
-                                        id: MintIdentifier::Cashu(w.mint_url.clone()),
+                                        id: MintIdentifier::Cashu(
+                                            w.mint_url.clone(),
+                                            w.unit.clone(),
+                                        ),
                                         balance: Amount::from_sats(balance),
                                     },
                                 )
@@ -1443,16 +3753,14 @@ impl HarborCore {
             }
         });
 
-        self.status_update(msg_id, "Registering with mint").await;
-
-        clients.insert(mint_url, wallet);
-
         self.status_update(msg_id, "Saving to database").await;
 
-        self.storage.insert_new_cashu_mint(url)?;
+        self.storage.insert_new_cashu_mint(url, unit)?;
 
         self.status_update(msg_id, "Mint setup complete!").await;
 
+        self.spawn_manifest_backup();
+
         Ok(())
     }
 
@@ -1483,11 +3791,17 @@ impl HarborCore {
         self.metadata_fetch_cancel.store(false, Ordering::Relaxed);
 
         log::info!("Successfully removed federation: {id}");
+        self.spawn_manifest_backup();
         Ok(())
     }
 
-    pub async fn remove_cashu_mint(&self, _msg_id: Uuid, url: &MintUrl) -> anyhow::Result<()> {
-        log::info!("Removing cashu mint: {url}");
+    pub async fn remove_cashu_mint(
+        &self,
+        _msg_id: Uuid,
+        url: &MintUrl,
+        unit: &CurrencyUnit,
+    ) -> anyhow::Result<()> {
+        log::info!("Removing cashu mint: {url} ({unit})");
 
         // Cancel any ongoing metadata fetch
         self.metadata_fetch_cancel.store(true, Ordering::Relaxed);
@@ -1498,21 +3812,23 @@ impl HarborCore {
         let mut clients = self.cashu_clients.write().await;
 
         // Check if federation exists before attempting removal
-        if !clients.contains_key(url) {
+        let key = (url.clone(), unit.clone());
+        if !clients.contains_key(&key) {
             return Err(anyhow!("Cashu mint doesn't exist"));
         }
 
         // Remove from clients first
-        clients.remove(url);
+        clients.remove(&key);
         drop(clients);
 
         // Then remove from storage
-        self.storage.remove_cashu_mint(url)?;
+        self.storage.remove_cashu_mint(url, unit)?;
 
         // Reset cancellation flag
         self.metadata_fetch_cancel.store(false, Ordering::Relaxed);
 
         log::info!("Successfully removed cashu mint: {url}");
+        self.spawn_manifest_backup();
         Ok(())
     }
 
@@ -1527,7 +3843,11 @@ impl HarborCore {
         // Tell the UI about any clients we have
         let mut res = Vec::with_capacity(clients.len() + cashu_clients.len());
         for c in clients.values() {
-            let balance = c.fedimint_client.get_balance().await;
+            let balance = if !c.is_usable() {
+                Amount::ZERO
+            } else {
+                c.fedimint_client.get_balance().await
+            };
             let config = c.fedimint_client.config().await;
 
             let guardians: Vec<String> = config
@@ -1569,11 +3889,15 @@ impl HarborCore {
                     .get_config_meta("federation_name")
                     .unwrap_or("Unknown".to_string()),
                 balance: balance.sats_round_down(),
+                unit: CurrencyUnit::Sat,
+                dleq_audit: DleqAudit::default(),
                 guardians: Some(guardians),
                 module_kinds: Some(module_kinds),
                 metadata: metadata.unwrap_or_default(),
                 on_chain_supported,
                 active: true,
+                recovering: c.is_recovering(),
+                recovery_error: c.recovery_error().map(ToString::to_string),
             });
         }
 
@@ -1598,17 +3922,24 @@ impl HarborCore {
             };
 
             res.push(MintItem {
-                id: MintIdentifier::Cashu(c.mint_url.clone()),
-                name: metadata
-                    .federation_name
-                    .clone()
-                    .unwrap_or("Unknown".to_string()),
+                id: MintIdentifier::Cashu(c.mint_url.clone(), c.unit.clone()),
+                name: cashu_wallet_name(
+                    metadata
+                        .federation_name
+                        .clone()
+                        .unwrap_or("Unknown".to_string()),
+                    &c.unit,
+                ),
                 balance,
+                unit: c.unit.clone(),
+                dleq_audit: self.storage.get_cashu_dleq_audit(&c.mint_url)?,
                 guardians: None,
                 module_kinds: None,
                 metadata,
                 on_chain_supported: false,
                 active: true,
+                recovering: false,
+                recovery_error: None,
             });
         }
 
@@ -1639,31 +3970,41 @@ impl HarborCore {
                 id: MintIdentifier::Fedimint(FederationId::from_str(&m.id)?),
                 name: m.name.clone().unwrap_or("Unknown".to_string()),
                 balance: 0,
+                unit: CurrencyUnit::Sat,
+                dleq_audit: DleqAudit::default(),
                 guardians: None,
                 module_kinds: None,
                 metadata: m.into(),
                 on_chain_supported: false,
                 active: false,
+                recovering: false,
+                recovery_error: None,
             };
             res.push(item);
         }
 
         // get archived cashu mints
         let archived = self.storage.list_archived_cashu_mints()?;
-        for mint_url in archived {
+        for (mint_url, unit) in archived {
             let info = self.cashu_storage.get_mint(mint_url.clone()).await?;
             let item = MintItem {
-                id: MintIdentifier::Cashu(mint_url.clone()),
-                name: info
-                    .as_ref()
-                    .and_then(|i| i.name.clone())
-                    .unwrap_or(mint_url.to_string()),
+                id: MintIdentifier::Cashu(mint_url.clone(), unit.clone()),
+                name: cashu_wallet_name(
+                    info.as_ref()
+                        .and_then(|i| i.name.clone())
+                        .unwrap_or(mint_url.to_string()),
+                    &unit,
+                ),
                 balance: 0,
+                unit,
+                dleq_audit: self.storage.get_cashu_dleq_audit(&mint_url)?,
                 guardians: None,
                 module_kinds: None,
                 metadata: info.into(),
                 on_chain_supported: false,
                 active: false,
+                recovering: false,
+                recovery_error: None,
             };
             res.push(item);
         }
@@ -1704,6 +4045,223 @@ impl HarborCore {
         }
     }
 
+    /// Joins a federation this seed used before, recovering its ecash, then
+    /// rejoins every mint and restores the settings listed in the wallet
+    /// manifest of its backup. Cashu wallets restore their proofs as they're added.
+    ///
+    /// Backups are held off until it's done so the half restored wallet can't
+    /// overwrite the manifest, then every federation gets the merged one.
+    pub async fn restore_from_federation(
+        &self,
+        msg_id: Uuid,
+        invite_code: InviteCode,
+    ) -> anyhow::Result<(usize, Vec<String>)> {
+        let pause = BackupPause::new();
+        let result = self.restore_wallet_manifest(msg_id, invite_code).await;
+        // every federation needs the restored manifest, along with any
+        // backup held off while we were restoring
+        pause.queue_backup();
+        if pause.finish() {
+            self.spawn_manifest_backup();
+        }
+        result
+    }
+
+    async fn restore_wallet_manifest(
+        &self,
+        msg_id: Uuid,
+        invite_code: InviteCode,
+    ) -> anyhow::Result<(usize, Vec<String>)> {
+        let federation_id = invite_code.federation_id();
+        self.add_federation(msg_id, invite_code).await?;
+
+        let metadata = self
+            .clients
+            .read()
+            .await
+            .get(&federation_id)
+            .and_then(|c| c.backup_metadata.clone());
+        let Some(manifest) = metadata
+            .map(|m| WalletManifest::decrypt(&m, &self.mnemonic))
+            .transpose()?
+            .flatten()
+        else {
+            log::info!("No wallet manifest in the backup from {federation_id}");
+            return Ok((0, vec![]));
+        };
+
+        log::info!(
+            "Restoring {} federations and {} cashu mints from the wallet manifest",
+            manifest.federations.len(),
+            manifest.cashu_mints.len()
+        );
+
+        // keep anything already set up here, only add what the manifest has on top
+        let local = WalletManifest::from_storage(self.storage.as_ref())?;
+        let merged = local.clone().union(manifest);
+
+        self.storage
+            .set_onchain_receive_enabled(merged.onchain_receive_enabled)?;
+        self.storage
+            .set_consolidation_enabled(merged.consolidation_enabled)?;
+        self.storage.set_tor_enabled(merged.tor_enabled)?;
+        self.tor_enabled
+            .store(merged.tor_enabled, Ordering::Relaxed);
+        self.send_system_msg(CoreUIMsg::OnchainReceiveEnabled(
+            merged.onchain_receive_enabled,
+        ))
+        .await;
+        self.send_system_msg(CoreUIMsg::ConsolidationEnabled(
+            merged.consolidation_enabled,
+        ))
+        .await;
+        self.send_system_msg(CoreUIMsg::TorEnabled(merged.tor_enabled))
+            .await;
+
+        let mut rejoined = 0;
+        let mut failed = vec![];
+        for invite_code in merged.federations {
+            let id = invite_code.federation_id();
+            if id == federation_id || local.federations.iter().any(|f| f.federation_id() == id) {
+                continue;
+            }
+            self.status_update(
+                msg_id,
+                &format!("Rejoining federation {}", invite_code.federation_id()),
+            )
+            .await;
+            match self.add_federation(msg_id, invite_code.clone()).await {
+                Ok(()) => rejoined += 1,
+                Err(e) => {
+                    error!(
+                        "Failed to rejoin federation {}: {e}",
+                        invite_code.federation_id()
+                    );
+                    failed.push(invite_code.federation_id().to_string());
+                }
+            }
+        }
+        for (mint_url, unit) in merged.cashu_mints {
+            if local
+                .cashu_mints
+                .contains(&(mint_url.clone(), unit.clone()))
+            {
+                continue;
+            }
+            self.status_update(msg_id, &format!("Rejoining cashu mint {mint_url}"))
+                .await;
+            match self.add_cashu_mint(msg_id, mint_url.clone(), unit).await {
+                Ok(()) => rejoined += 1,
+                Err(e) => {
+                    error!("Failed to rejoin cashu mint {mint_url}: {e}");
+                    failed.push(mint_url.to_string());
+                }
+            }
+        }
+
+        Ok((rejoined, failed))
+    }
+
+    /// Waits for the federation's recovery in the background, then swaps in a
+    /// freshly opened client that can be used
+    fn spawn_fedimint_recovery(&self, federation_id: FederationId) {
+        let core = self.clone();
+        spawn(async move {
+            if let Err(e) = core.finish_fedimint_recovery(federation_id).await {
+                error!("Could not recover federation {federation_id}: {e}");
+                if let Some(client) = core.clients.write().await.get_mut(&federation_id) {
+                    client.fail_recovery(e.to_string());
+                }
+                core.send_system_msg(CoreUIMsg::FederationRecoveryFailed {
+                    id: federation_id,
+                    reason: e.to_string(),
+                })
+                .await;
+                match core.get_mint_items().await {
+                    Ok(items) => {
+                        core.send_system_msg(CoreUIMsg::MintListUpdated(items))
+                            .await
+                    }
+                    Err(e) => error!("Failed to get mint items: {e}"),
+                }
+            }
+        });
+    }
+
+    async fn finish_fedimint_recovery(&self, federation_id: FederationId) -> anyhow::Result<()> {
+        let Some((client, backup_metadata)) = self
+            .clients
+            .read()
+            .await
+            .get(&federation_id)
+            .map(|c| (c.fedimint_client.clone(), c.backup_metadata.clone()))
+        else {
+            return Ok(());
+        };
+
+        log::info!("Waiting for federation {federation_id} to recover");
+        wait_for_recovery(&client, self.tx.clone()).await?;
+        log::info!("Federation {federation_id} successfully recovered");
+        drop(client);
+
+        // opening the client can take a while, don't hold up everyone else meanwhile
+        let mut client = FedimintClient::new(
+            self.storage.clone(),
+            FederationInviteOrId::Id(federation_id),
+            &self.mnemonic,
+            self.network,
+            self.stop.clone(),
+            self.tx.clone(),
+            None,
+        )
+        .await?;
+        client.backup_metadata = backup_metadata;
+        let balance = client.fedimint_client.get_balance().await;
+
+        let mut clients = self.clients.write().await;
+        // removed while it was recovering
+        if !clients.contains_key(&federation_id) {
+            return Ok(());
+        }
+        clients.insert(federation_id, client);
+        drop(clients);
+
+        self.send_system_msg(CoreUIMsg::FederationRecovered(federation_id))
+            .await;
+        self.send_system_msg(CoreUIMsg::MintListUpdated(self.get_mint_items().await?))
+            .await;
+        self.send_system_msg(CoreUIMsg::MintBalanceUpdated {
+            id: MintIdentifier::Fedimint(federation_id),
+            balance,
+        })
+        .await;
+
+        Ok(())
+    }
+
+    /// Refreshes the wallet manifest in every federation's backup, in the background
+    fn spawn_manifest_backup(&self) {
+        let clients = self.clients.clone();
+        let storage = self.storage.clone();
+        spawn(async move {
+            let clients: Vec<ClientHandleArc> = clients
+                .read()
+                .await
+                .values()
+                .filter(|c| c.is_usable())
+                .map(|c| c.fedimint_client.clone())
+                .collect();
+            for client in clients {
+                if let Err(e) = backup_to_federation(&client, storage.as_ref()).await {
+                    error!(
+                        "Could not back up to federation {}: {e}",
+                        client.federation_id()
+                    );
+                }
+            }
+        });
+    }
+
     pub fn get_seed_words(&self) -> String {
         self.mnemonic.to_string()
     }
@@ -1711,6 +4269,7 @@ impl HarborCore {
     pub fn set_onchain_receive_enabled(&self, enabled: bool) -> anyhow::Result<()> {
         log::info!("Setting on-chain receive enabled to: {}", enabled);
         self.storage.set_onchain_receive_enabled(enabled)?;
+        self.spawn_manifest_backup();
         log::info!(
             "Successfully {} on-chain receive",
             if enabled { "enabled" } else { "disabled" }
@@ -1718,10 +4277,25 @@ impl HarborCore {
         Ok(())
     }
 
+    pub fn set_consolidation_enabled(&self, enabled: bool) -> anyhow::Result<()> {
+        log::info!("Setting proof consolidation enabled to: {enabled}");
+        self.storage.set_consolidation_enabled(enabled)?;
+        self.spawn_manifest_backup();
+        Ok(())
+    }
+
+    /// The relay listeners pick the setting up on their next reconnect check
+    pub fn set_nostr_enabled(&self, enabled: bool) -> anyhow::Result<()> {
+        log::info!("Setting nostr DMs enabled to: {enabled}");
+        self.storage.set_nostr_enabled(enabled)?;
+        Ok(())
+    }
+
     pub fn set_tor_enabled(&self, enabled: bool) -> anyhow::Result<()> {
         log::info!("Setting Tor enabled to: {}", enabled);
         self.tor_enabled.swap(enabled, Ordering::Relaxed);
         self.storage.set_tor_enabled(enabled)?;
+        self.spawn_manifest_backup();
         log::info!(
             "Successfully {} Tor",
             if enabled { "enabled" } else { "disabled" }
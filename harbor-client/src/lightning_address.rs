@@ -1,12 +1,17 @@
 use crate::http::{make_get_request_direct, make_get_request_tor};
+use bitcoin::secp256k1::{Message, Secp256k1, SecretKey};
 use lnurl::{
+    LnUrlResponse, Response,
     lightning_address::LightningAddress,
     lnurl::LnUrl,
     pay::{LnURLPayInvoice, PayResponse},
+    withdraw::WithdrawalResponse,
 };
+use serde_json::Value;
 use std::str::FromStr;
 use std::sync::Arc;
 use std::sync::atomic::AtomicBool;
+use url::Url;
 
 pub fn parse_lnurl(address: &str) -> anyhow::Result<LnUrl> {
     match LightningAddress::from_str(address) {
@@ -41,14 +46,181 @@ pub async fn make_lnurl_request(
     tor_enabled: bool,
     cancel_handle: Arc<AtomicBool>,
 ) -> anyhow::Result<PayResponse> {
-    let lnurlp = lnurl.url.clone();
-    log::info!("Making lnurl request: {lnurlp}, tor_enabled: {tor_enabled}");
+    match fetch_lnurl(lnurl, tor_enabled, cancel_handle).await? {
+        LnUrlResponse::LnUrlPayResponse(pay) => Ok(pay),
+        LnUrlResponse::LnUrlWithdrawResponse(_) => Err(anyhow::anyhow!(
+            "This is an LNURL-withdraw, receive it from the Deposit screen"
+        )),
+        LnUrlResponse::LnUrlChannelResponse(_) => {
+            Err(anyhow::anyhow!("LNURL-channel is not supported"))
+        }
+    }
+}
 
-    if tor_enabled {
-        make_get_request_tor(&lnurlp, cancel_handle).await
+pub async fn make_lnurl_withdraw_request(
+    lnurl: &LnUrl,
+    tor_enabled: bool,
+    cancel_handle: Arc<AtomicBool>,
+) -> anyhow::Result<WithdrawalResponse> {
+    match fetch_lnurl(lnurl, tor_enabled, cancel_handle).await? {
+        LnUrlResponse::LnUrlWithdrawResponse(withdraw) => Ok(withdraw),
+        _ => Err(anyhow::anyhow!("LNURL is not a withdraw request")),
+    }
+}
+
+/// Fetches what the lnurl points at, a pay, withdraw or channel request
+async fn fetch_lnurl(
+    lnurl: &LnUrl,
+    tor_enabled: bool,
+    cancel_handle: Arc<AtomicBool>,
+) -> anyhow::Result<LnUrlResponse> {
+    let url = lnurl.url.clone();
+    log::info!("Making lnurl request: {url}, tor_enabled: {tor_enabled}");
+
+    let json: Value = if tor_enabled {
+        make_get_request_tor(&url, cancel_handle).await?
     } else {
-        make_get_request_direct(&lnurlp).await
+        make_get_request_direct(&url).await?
+    };
+
+    if let Some(reason) = error_reason(&json) {
+        return Err(anyhow::anyhow!("LNURL error: {reason}"));
     }
+    lnurl::decode_ln_url_response_from_json(json)
+        .map_err(|e| anyhow::anyhow!("Invalid LNURL response: {e}"))
+}
+
+/// The amount to withdraw in msats, all of it if `msats` is None. Rounded down
+/// to whole sats since that's what mints issue.
+pub fn withdraw_amount(withdraw: &WithdrawalResponse, msats: Option<u64>) -> anyhow::Result<u64> {
+    let min = withdraw.min_withdrawable.unwrap_or(0);
+    let max = withdraw.max_withdrawable;
+    let amount = msats.unwrap_or(max) / 1_000 * 1_000;
+
+    if amount == 0 || amount < min || amount > max {
+        return Err(anyhow::anyhow!(
+            "Amount must be between {} and {} sats",
+            min.div_ceil(1_000),
+            max / 1_000
+        ));
+    }
+    Ok(amount)
+}
+
+/// Hands the invoice to the withdraw service so it pays it.
+///
+/// The outer error means we don't know if the service got the invoice, the
+/// inner one that it refused to pay it.
+pub async fn submit_withdraw_invoice(
+    withdraw: &WithdrawalResponse,
+    invoice: &str,
+    tor_enabled: bool,
+    cancel_handle: Arc<AtomicBool>,
+) -> anyhow::Result<anyhow::Result<()>> {
+    let url = withdraw_callback_url(withdraw, invoice)?;
+
+    let response: Response = if tor_enabled {
+        make_get_request_tor(url.as_str(), cancel_handle).await?
+    } else {
+        make_get_request_direct(url.as_str()).await?
+    };
+
+    match response {
+        Response::Ok { .. } => Ok(Ok(())),
+        Response::Error { reason } => Ok(Err(anyhow::anyhow!("Withdraw failed: {reason}"))),
+    }
+}
+
+fn withdraw_callback_url(withdraw: &WithdrawalResponse, invoice: &str) -> anyhow::Result<Url> {
+    let mut url = Url::parse(&withdraw.callback)?;
+    url.query_pairs_mut()
+        .append_pair("k1", &withdraw.k1)
+        .append_pair("pr", invoice);
+    Ok(url)
+}
+
+/// A login challenge from a service (LUD-04)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LnurlAuth {
+    url: Url,
+    /// The service's domain, which picks the key we log in with
+    pub domain: String,
+    k1: [u8; 32],
+    /// What the login is for: register, login, link or auth
+    pub action: Option<String>,
+}
+
+/// Reads a login challenge out of the lnurl, it doesn't need a request to the service
+pub fn parse_lnurl_auth(lnurl: &LnUrl) -> anyhow::Result<LnurlAuth> {
+    let url = Url::parse(&lnurl.url)?;
+    let query = |key: &str| {
+        url.query_pairs()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.to_string())
+    };
+
+    if query("tag").as_deref() != Some("login") {
+        return Err(anyhow::anyhow!("LNURL is not a login request"));
+    }
+    let k1 = query("k1").ok_or(anyhow::anyhow!("Login request is missing its challenge"))?;
+    let k1: [u8; 32] = hex::decode(k1)?
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("Login challenge must be 32 bytes"))?;
+    let domain = url
+        .host_str()
+        .ok_or(anyhow::anyhow!("Login request has no domain"))?
+        .to_string();
+
+    Ok(LnurlAuth {
+        action: query("action"),
+        url,
+        domain,
+        k1,
+    })
+}
+
+/// Signs the challenge with `key` and sends it back to the service to log in
+pub async fn lnurl_auth(
+    auth: &LnurlAuth,
+    key: &SecretKey,
+    tor_enabled: bool,
+    cancel_handle: Arc<AtomicBool>,
+) -> anyhow::Result<()> {
+    let url = auth_callback_url(auth, key);
+
+    let response: Response = if tor_enabled {
+        make_get_request_tor(url.as_str(), cancel_handle).await?
+    } else {
+        make_get_request_direct(url.as_str()).await?
+    };
+
+    match response {
+        Response::Ok { .. } => Ok(()),
+        Response::Error { reason } => Err(anyhow::anyhow!("Login failed: {reason}")),
+    }
+}
+
+fn auth_callback_url(auth: &LnurlAuth, key: &SecretKey) -> Url {
+    let secp = Secp256k1::new();
+    let sig = secp.sign_ecdsa(&Message::from_digest(auth.k1), key);
+
+    let mut url = auth.url.clone();
+    url.query_pairs_mut()
+        .append_pair("sig", &hex::encode(sig.serialize_der()))
+        .append_pair("key", &key.public_key(&secp).to_string());
+    url
+}
+
+/// LNURL services answer `{"status": "ERROR", "reason": ...}` when they can't serve a request
+fn error_reason(json: &Value) -> Option<&str> {
+    if json.get("status").and_then(Value::as_str) != Some("ERROR") {
+        return None;
+    }
+    Some(
+        json.get("reason")
+            .and_then(Value::as_str)
+            .unwrap_or("unknown error"),
+    )
 }
 
 #[cfg(test)]
@@ -109,6 +281,77 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_withdraw_amount() {
+        let withdraw: WithdrawalResponse = serde_json::from_value(serde_json::json!({
+            "tag": "withdrawRequest",
+            "callback": "https://voucher.example.com/withdraw",
+            "k1": "abcd",
+            "defaultDescription": "voucher",
+            "minWithdrawable": 10_000,
+            "maxWithdrawable": 100_500,
+        }))
+        .unwrap();
+
+        // everything the voucher holds, in whole sats
+        assert_eq!(withdraw_amount(&withdraw, None).unwrap(), 100_000);
+        assert_eq!(withdraw_amount(&withdraw, Some(50_000)).unwrap(), 50_000);
+        assert!(withdraw_amount(&withdraw, Some(5_000)).is_err());
+        assert!(withdraw_amount(&withdraw, Some(200_000)).is_err());
+
+        // k1 is opaque to us, it may need escaping
+        let withdraw = WithdrawalResponse {
+            callback: "https://voucher.example.com/withdraw?id=1".to_string(),
+            k1: "a+b&c=d".to_string(),
+            ..withdraw
+        };
+        let url = withdraw_callback_url(&withdraw, "lnbc1").unwrap();
+        assert_eq!(
+            url.as_str(),
+            "https://voucher.example.com/withdraw?id=1&k1=a%2Bb%26c%3Dd&pr=lnbc1"
+        );
+
+        let error = serde_json::json!({"status": "ERROR", "reason": "voucher already used"});
+        assert_eq!(error_reason(&error), Some("voucher already used"));
+        assert_eq!(
+            error_reason(&serde_json::json!({"tag": "withdrawRequest"})),
+            None
+        );
+    }
+
+    #[test]
+    fn test_lnurl_auth() {
+        let k1 = "e2af6254a8df433264fa23f67eb8188635d15ce883e8fc020989d5f82ae6f11e";
+        let lnurl = LnUrl::from_url(format!(
+            "https://site.com/auth?tag=login&k1={k1}&action=login"
+        ));
+        let auth = parse_lnurl_auth(&lnurl).unwrap();
+        assert_eq!(auth.domain, "site.com");
+        assert_eq!(auth.action.as_deref(), Some("login"));
+
+        // the service checks the signature over k1 against the key we send
+        let secp = Secp256k1::new();
+        let key = SecretKey::from_slice(&[1; 32]).unwrap();
+        let url = auth_callback_url(&auth, &key);
+        let query = |name: &str| {
+            url.query_pairs()
+                .find(|(k, _)| k == name)
+                .map(|(_, v)| v.to_string())
+                .unwrap()
+        };
+        assert_eq!(query("k1"), k1);
+        let sig =
+            bitcoin::secp256k1::ecdsa::Signature::from_der(&hex::decode(query("sig")).unwrap())
+                .unwrap();
+        let pubkey = bitcoin::secp256k1::PublicKey::from_str(&query("key")).unwrap();
+        assert_eq!(pubkey, key.public_key(&secp));
+        let msg = Message::from_digest(hex::decode(k1).unwrap().try_into().unwrap());
+        assert!(secp.verify_ecdsa(&msg, &sig, &pubkey).is_ok());
+
+        let pay = LnUrl::from_url("https://site.com/lnurlp/alice".to_string());
+        assert!(parse_lnurl_auth(&pay).is_err());
+    }
+
     #[tokio::test]
     async fn test_lightning_address_flow() -> anyhow::Result<()> {
         init();
@@ -0,0 +1,368 @@
+//! Finds mints to join from nostr, using the announcements mints publish about
+//! themselves and the recommendations users publish about them (NIP-87).
+//!
+//! Relays are reached through [`NostrRelay`] so discovery goes over Tor when
+//! it is enabled.
+
+use crate::MintConnectionInfo;
+use crate::nostr_client::{NostrRelay, RelayMessage};
+use bitcoin::Network;
+use nostr::{Event, Filter, Kind, PublicKey};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::time::Duration;
+
+/// A cashu mint announcing itself
+pub const KIND_CASHU_MINT_ANNOUNCEMENT: u16 = 38172;
+/// A fedimint federation announcing itself
+pub const KIND_FEDIMINT_ANNOUNCEMENT: u16 = 38173;
+/// A user recommending a mint
+pub const KIND_MINT_RECOMMENDATION: u16 = 38000;
+
+/// How many events we ask each relay for
+const DISCOVERY_LIMIT: usize = 500;
+
+/// How long we wait on a relay to send its stored events
+const DISCOVERY_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// A mint found on nostr that we could join
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiscoveredMint {
+    pub info: MintConnectionInfo,
+    /// Name from the mint's announcement, if it gave one
+    pub name: Option<String>,
+    /// Number of different users recommending the mint
+    pub recommendations: usize,
+}
+
+/// Matches mint announcements and recommendations
+pub fn discovery_filter() -> Filter {
+    Filter::new()
+        .kinds([
+            Kind::Custom(KIND_CASHU_MINT_ANNOUNCEMENT),
+            Kind::Custom(KIND_FEDIMINT_ANNOUNCEMENT),
+            Kind::Custom(KIND_MINT_RECOMMENDATION),
+        ])
+        .limit(DISCOVERY_LIMIT)
+}
+
+/// Asks each relay for mint announcements and recommendations and turns them
+/// into mints we can join on `network`, most recommended first. Relays that
+/// fail are skipped, it's an error only if none of them answer.
+pub async fn discover_mints(
+    relays: &[&str],
+    network: Network,
+    tor_enabled: bool,
+    cancel_handle: Arc<AtomicBool>,
+) -> anyhow::Result<Vec<DiscoveredMint>> {
+    let mut events = vec![];
+    let mut last_error = None;
+    for url in relays {
+        match fetch_events(url, tor_enabled, cancel_handle.clone()).await {
+            Ok(mut fetched) => {
+                log::debug!("Got {} mint events from {url}", fetched.len());
+                events.append(&mut fetched);
+            }
+            Err(e) => {
+                log::warn!("Could not discover mints on {url}: {e}");
+                last_error = Some(e);
+            }
+        }
+    }
+
+    match last_error {
+        Some(e) if events.is_empty() => Err(e),
+        _ => Ok(collect_mints(&events, network)),
+    }
+}
+
+/// Collects the relay's stored events for [`discovery_filter`]
+async fn fetch_events(
+    url: &str,
+    tor_enabled: bool,
+    cancel_handle: Arc<AtomicBool>,
+) -> anyhow::Result<Vec<Event>> {
+    let mut relay = NostrRelay::connect(url, tor_enabled, cancel_handle).await?;
+    relay.subscribe("harbor-mints", &discovery_filter()).await?;
+
+    let mut events = vec![];
+    let collected = tokio::time::timeout(DISCOVERY_TIMEOUT, async {
+        loop {
+            match relay.next_message().await? {
+                Some(RelayMessage::Event { event, .. }) => events.push(*event),
+                Some(RelayMessage::EndOfStoredEvents(_)) | None => return Ok(()),
+                Some(RelayMessage::Closed { message, .. }) => {
+                    return Err(anyhow::anyhow!("{url} closed the subscription: {message}"));
+                }
+                Some(_) => {}
+            }
+        }
+    })
+    .await;
+    relay.close().await;
+
+    match collected {
+        Ok(result) => result.map(|()| events),
+        // keep what we got from a slow relay
+        Err(_) => {
+            log::debug!("{url} did not finish sending mint events in time");
+            Ok(events)
+        }
+    }
+}
+
+/// Turns announcement and recommendation events into mints, deduplicated and
+/// most recommended first. Each user counts once per mint.
+///
+/// Recommendations don't say which network a mint is on, so only mints that
+/// announced themselves on `network` are kept.
+pub fn collect_mints(events: &[Event], network: Network) -> Vec<DiscoveredMint> {
+    let mut mints: HashMap<String, DiscoveredMint> = HashMap::new();
+    let mut recommenders: HashMap<String, HashSet<PublicKey>> = HashMap::new();
+    let mut announced = HashSet::new();
+    let mut seen = HashSet::new();
+
+    for event in events {
+        // the same event comes back from every relay that has it
+        if !seen.insert(event.id) {
+            continue;
+        }
+
+        let kind = event.kind.as_u16();
+        let is_announcement =
+            kind == KIND_CASHU_MINT_ANNOUNCEMENT || kind == KIND_FEDIMINT_ANNOUNCEMENT;
+        let is_recommendation = kind == KIND_MINT_RECOMMENDATION
+            && tag_values(event, "k").any(|k| {
+                k == KIND_CASHU_MINT_ANNOUNCEMENT.to_string()
+                    || k == KIND_FEDIMINT_ANNOUNCEMENT.to_string()
+            });
+        if !is_announcement && !is_recommendation {
+            continue;
+        }
+
+        // announcements without a network are assumed to be on mainnet
+        let event_network = tag_values(event, "n").next().unwrap_or("mainnet");
+        if is_announcement && event_network != network_tag(network) {
+            continue;
+        }
+
+        let name = is_announcement.then(|| announced_name(event)).flatten();
+        for info in tag_values(event, "u").filter_map(|u| MintConnectionInfo::from_str(u).ok()) {
+            let key = mint_key(&info);
+            let mint = mints.entry(key.clone()).or_insert_with(|| DiscoveredMint {
+                info,
+                name: None,
+                recommendations: 0,
+            });
+            if mint.name.is_none() {
+                mint.name.clone_from(&name);
+            }
+            if is_recommendation {
+                recommenders.entry(key).or_default().insert(event.pubkey);
+            } else {
+                announced.insert(key);
+            }
+        }
+    }
+
+    let mut mints: Vec<DiscoveredMint> = mints
+        .into_iter()
+        .filter(|(key, _)| announced.contains(key))
+        .map(|(key, mut mint)| {
+            mint.recommendations = recommenders.get(&key).map_or(0, HashSet::len);
+            mint
+        })
+        .collect();
+    mints.sort_by(|a, b| {
+        b.recommendations
+            .cmp(&a.recommendations)
+            .then_with(|| a.info.cmp(&b.info))
+    });
+    mints
+}
+
+/// Federations are shared with a different invite code per guardian, so they're
+/// matched on their id
+fn mint_key(info: &MintConnectionInfo) -> String {
+    match info {
+        MintConnectionInfo::Cashu(url) => url.to_string(),
+        MintConnectionInfo::Fedimint(invite_code) => invite_code.federation_id().to_string(),
+    }
+}
+
+fn tag_values<'a>(event: &'a Event, name: &'a str) -> impl Iterator<Item = &'a str> {
+    event
+        .tags
+        .iter()
+        .filter_map(move |tag| match tag.as_slice() {
+            [tag_name, value, ..] if tag_name == name => Some(value.as_str()),
+            _ => None,
+        })
+}
+
+/// Announcements may carry the mint's metadata as json content
+fn announced_name(event: &Event) -> Option<String> {
+    let content: Value = serde_json::from_str(&event.content).ok()?;
+    content
+        .get("name")
+        .or_else(|| content.get("federation_name"))
+        .and_then(Value::as_str)
+        .map(ToString::to_string)
+}
+
+fn network_tag(network: Network) -> &'static str {
+    match network {
+        Network::Bitcoin => "mainnet",
+        Network::Testnet | Network::Testnet4 => "testnet",
+        Network::Signet => "signet",
+        Network::Regtest => "regtest",
+        _ => "unknown",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nostr_client::NostrRelay;
+    use nostr::{EventBuilder, Keys, Tag};
+
+    const INVITE_CODE: &str = "fed11qgqzc2nhwden5te0vejkg6tdd9h8gepwvejkg6tdd9h8garhduhx6at5d9h8jmn9wshxxmmd9uqqzgxg6s3evnr6m9zdxr6hxkdkukexpcs3mn7mj3g5pc5dfh63l4tj6g9zk4er";
+    const MINT_URL: &str = "https://mint.example.com";
+
+    fn event(keys: &Keys, kind: u16, content: &str, tags: &[&[&str]]) -> Event {
+        let tags = tags.iter().map(|t| Tag::parse(t.iter().copied()).unwrap());
+        EventBuilder::new(Kind::Custom(kind), content)
+            .tags(tags)
+            .sign_with_keys(keys)
+            .unwrap()
+    }
+
+    fn announcements(keys: &Keys) -> Vec<Event> {
+        vec![
+            event(
+                keys,
+                KIND_FEDIMINT_ANNOUNCEMENT,
+                r#"{"name":"Test Federation"}"#,
+                &[&["d", "test"], &["u", INVITE_CODE], &["n", "signet"]],
+            ),
+            event(
+                keys,
+                KIND_CASHU_MINT_ANNOUNCEMENT,
+                "",
+                &[&["d", "test"], &["u", MINT_URL], &["n", "signet"]],
+            ),
+        ]
+    }
+
+    fn recommendation(keys: &Keys, kind: u16, u: &str) -> Event {
+        event(
+            keys,
+            KIND_MINT_RECOMMENDATION,
+            "",
+            &[&["k", &kind.to_string()], &["d", "test"], &["u", u]],
+        )
+    }
+
+    #[test]
+    fn test_collect_mints() {
+        let mint = Keys::generate();
+        let alice = Keys::generate();
+        let bob = Keys::generate();
+
+        let mut events = announcements(&mint);
+        events.push(recommendation(
+            &alice,
+            KIND_FEDIMINT_ANNOUNCEMENT,
+            INVITE_CODE,
+        ));
+        events.push(recommendation(
+            &bob,
+            KIND_FEDIMINT_ANNOUNCEMENT,
+            INVITE_CODE,
+        ));
+        events.push(recommendation(
+            &alice,
+            KIND_CASHU_MINT_ANNOUNCEMENT,
+            MINT_URL,
+        ));
+        // duplicates from a second relay and a second recommendation from alice
+        events.extend(events.clone());
+        events.push(recommendation(
+            &alice,
+            KIND_CASHU_MINT_ANNOUNCEMENT,
+            MINT_URL,
+        ));
+
+        let mints = collect_mints(&events, Network::Signet);
+        assert_eq!(mints.len(), 2);
+
+        assert_eq!(
+            mints[0].info,
+            MintConnectionInfo::from_str(INVITE_CODE).unwrap()
+        );
+        assert_eq!(mints[0].name.as_deref(), Some("Test Federation"));
+        assert_eq!(mints[0].recommendations, 2);
+
+        assert_eq!(
+            mints[1].info,
+            MintConnectionInfo::from_str(MINT_URL).unwrap()
+        );
+        assert_eq!(mints[1].name, None);
+        assert_eq!(mints[1].recommendations, 1);
+
+        // announced on another network
+        assert!(collect_mints(&announcements(&mint), Network::Bitcoin).is_empty());
+
+        // recommended but never announced, it could be on any network
+        let unannounced = [recommendation(
+            &alice,
+            KIND_CASHU_MINT_ANNOUNCEMENT,
+            "https://other.example.com",
+        )];
+        assert!(collect_mints(&unannounced, Network::Signet).is_empty());
+        assert_eq!(collect_mints(&events, Network::Bitcoin), vec![]);
+    }
+
+    /// Runs against the relay in `NOSTR_TEST_RELAY`, e.g. a local `ws://localhost:7777`
+    #[tokio::test]
+    async fn test_discover_mints() {
+        let Ok(url) = std::env::var("NOSTR_TEST_RELAY") else {
+            log::info!("NOSTR_TEST_RELAY not set, skipping");
+            return;
+        };
+        let cancel = Arc::new(AtomicBool::new(false));
+
+        let mint = Keys::generate();
+        let mut events = announcements(&mint);
+        events.push(recommendation(
+            &Keys::generate(),
+            KIND_CASHU_MINT_ANNOUNCEMENT,
+            MINT_URL,
+        ));
+
+        let mut relay = NostrRelay::connect(&url, false, cancel.clone())
+            .await
+            .unwrap();
+        for event in &events {
+            relay.publish(event).await.unwrap();
+        }
+        relay.close().await;
+
+        let mints = discover_mints(&[url.as_str()], Network::Signet, false, cancel)
+            .await
+            .unwrap();
+        let found = mints
+            .iter()
+            .find(|m| m.info == MintConnectionInfo::from_str(MINT_URL).unwrap())
+            .expect("relay should return the announced mint");
+        assert!(found.recommendations >= 1);
+        assert!(
+            mints
+                .iter()
+                .any(|m| m.info == MintConnectionInfo::from_str(INVITE_CODE).unwrap())
+        );
+    }
+}
@@ -0,0 +1,383 @@
+//! A small nostr relay client so ecash can be delivered to us as encrypted
+//! direct messages (NIP-17 gift wraps), the nostr transport of cashu payment
+//! requests (NUT-18).
+//!
+//! Relays are reached over [`WebSocket`] so they get the same Tor handling as
+//! everything else.
+
+use crate::websocket::WebSocket;
+use anyhow::anyhow;
+use cdk::nuts::Token;
+use cdk::nuts::nut18::PaymentRequestPayload;
+use cdk::util::unix_time;
+use fedimint_mint_client::OOBNotes;
+use nostr::nips::nip59::UnwrappedGift;
+use nostr::{Event, EventBuilder, Filter, Keys, Kind, PublicKey, Timestamp};
+use serde_json::Value;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::time::Duration;
+
+/// Relays we listen on and advertise in our payment requests
+pub const DEFAULT_NOSTR_RELAYS: [&str; 3] = [
+    "wss://relay.damus.io",
+    "wss://nos.lol",
+    "wss://relay.primal.net",
+];
+
+/// Gift wraps are backdated by up to two days (NIP-59) to hide when they were sent
+const GIFT_WRAP_LOOKBACK: u64 = 2 * 24 * 60 * 60;
+
+const PUBLISH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Relays can go quiet for a long time between DMs, so we ping them after this
+/// long and reconnect if they don't answer, instead of waiting on a dead socket
+const RELAY_KEEPALIVE: Duration = Duration::from_secs(60);
+
+/// A message from a relay (NIP-01), the ones we don't use are dropped
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RelayMessage {
+    Event {
+        subscription_id: String,
+        event: Box<Event>,
+    },
+    EndOfStoredEvents(String),
+    Ok {
+        event_id: String,
+        accepted: bool,
+        message: String,
+    },
+    Closed {
+        subscription_id: String,
+        message: String,
+    },
+    Notice(String),
+}
+
+impl FromStr for RelayMessage {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let msg: Vec<Value> = serde_json::from_str(s)?;
+        let text = |i: usize| {
+            msg.get(i)
+                .and_then(Value::as_str)
+                .map(ToString::to_string)
+                .ok_or_else(|| anyhow!("Malformed relay message: {s}"))
+        };
+
+        match msg.first().and_then(Value::as_str) {
+            Some("EVENT") => {
+                let event = msg
+                    .get(2)
+                    .cloned()
+                    .ok_or_else(|| anyhow!("Relay sent an empty event"))?;
+                Ok(Self::Event {
+                    subscription_id: text(1)?,
+                    event: Box::new(serde_json::from_value(event)?),
+                })
+            }
+            Some("EOSE") => Ok(Self::EndOfStoredEvents(text(1)?)),
+            Some("OK") => Ok(Self::Ok {
+                event_id: text(1)?,
+                accepted: msg.get(2).and_then(Value::as_bool).unwrap_or(false),
+                message: text(3).unwrap_or_default(),
+            }),
+            Some("CLOSED") => Ok(Self::Closed {
+                subscription_id: text(1)?,
+                message: text(2).unwrap_or_default(),
+            }),
+            Some("NOTICE") => Ok(Self::Notice(text(1)?)),
+            _ => Err(anyhow!("Unknown relay message: {s}")),
+        }
+    }
+}
+
+/// A connection to a single relay
+pub struct NostrRelay {
+    url: String,
+    socket: WebSocket,
+}
+
+impl NostrRelay {
+    pub async fn connect(
+        url: &str,
+        tor_enabled: bool,
+        cancel_handle: Arc<AtomicBool>,
+    ) -> anyhow::Result<Self> {
+        let socket = WebSocket::connect(url, tor_enabled, cancel_handle)
+            .await?
+            .keepalive(RELAY_KEEPALIVE);
+        Ok(Self {
+            url: url.to_string(),
+            socket,
+        })
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub async fn subscribe(
+        &mut self,
+        subscription_id: &str,
+        filter: &Filter,
+    ) -> anyhow::Result<()> {
+        self.socket
+            .send_json(&serde_json::json!(["REQ", subscription_id, filter]))
+            .await
+    }
+
+    /// Sends the event and waits for the relay to accept it
+    pub async fn publish(&mut self, event: &Event) -> anyhow::Result<()> {
+        self.socket
+            .send_json(&serde_json::json!(["EVENT", event]))
+            .await?;
+
+        let event_id = event.id.to_hex();
+        tokio::time::timeout(PUBLISH_TIMEOUT, async {
+            loop {
+                match self.next_message().await? {
+                    Some(RelayMessage::Ok {
+                        event_id: id,
+                        accepted,
+                        message,
+                    }) if id == event_id => {
+                        return if accepted {
+                            Ok(())
+                        } else {
+                            Err(anyhow!("{} rejected event: {message}", self.url))
+                        };
+                    }
+                    Some(_) => {}
+                    None => return Err(anyhow!("{} closed the connection", self.url)),
+                }
+            }
+        })
+        .await
+        .map_err(|_| anyhow!("{} did not confirm the event in time", self.url))?
+    }
+
+    /// Waits for the next message we understand, None once the relay hangs up.
+    /// Events with an invalid signature are dropped here.
+    pub async fn next_message(&mut self) -> anyhow::Result<Option<RelayMessage>> {
+        while let Some(text) = self.socket.next_text().await? {
+            match RelayMessage::from_str(&text) {
+                Ok(RelayMessage::Event { event, .. }) if event.verify().is_err() => {
+                    log::warn!("Dropping event with invalid signature from {}", self.url);
+                }
+                Ok(msg) => return Ok(Some(msg)),
+                Err(e) => log::debug!("Ignoring message from {}: {e}", self.url),
+            }
+        }
+        Ok(None)
+    }
+
+    pub async fn close(self) {
+        self.socket.close().await;
+    }
+}
+
+/// Matches the gift wrapped DMs sent to `pubkey`, including any we missed while offline
+pub fn dm_filter(pubkey: PublicKey) -> Filter {
+    Filter::new()
+        .kind(Kind::GiftWrap)
+        .pubkey(pubkey)
+        .since(Timestamp::from(
+            unix_time().saturating_sub(GIFT_WRAP_LOOKBACK),
+        ))
+}
+
+/// Opens a gift wrapped DM sent to us, returning the sender and the message
+pub async fn unwrap_dm(keys: &Keys, event: &Event) -> anyhow::Result<(PublicKey, String)> {
+    if event.kind != Kind::GiftWrap {
+        return Err(anyhow!("Event is not a gift wrap"));
+    }
+    let gift = UnwrappedGift::from_gift_wrap(keys, event).await?;
+    if gift.rumor.kind != Kind::PrivateDirectMessage {
+        return Err(anyhow!("Gift wrap does not contain a direct message"));
+    }
+    Ok((gift.sender, gift.rumor.content))
+}
+
+/// Sends `message` as a gift wrapped DM to `receiver`, succeeding once any of
+/// the relays accepts it
+pub async fn send_dm(
+    keys: &Keys,
+    receiver: PublicKey,
+    relays: &[String],
+    message: String,
+    tor_enabled: bool,
+    cancel_handle: Arc<AtomicBool>,
+) -> anyhow::Result<()> {
+    let event = EventBuilder::private_msg(keys, receiver, message, []).await?;
+
+    let mut last_error = anyhow!("No relays to send the message to");
+    for url in relays {
+        let result = async {
+            let mut relay = NostrRelay::connect(url, tor_enabled, cancel_handle.clone()).await?;
+            let published = relay.publish(&event).await;
+            relay.close().await;
+            published
+        }
+        .await;
+
+        match result {
+            Ok(()) => {
+                log::info!("Sent nostr DM through {url}");
+                return Ok(());
+            }
+            Err(e) => {
+                log::warn!("Failed to send nostr DM through {url}: {e}");
+                last_error = e;
+            }
+        }
+    }
+    Err(last_error)
+}
+
+/// Ecash someone sent us in a DM
+#[derive(Debug, Clone)]
+pub enum NostrEcash {
+    Cashu {
+        token: Token,
+        /// Set when the ecash pays one of our payment requests
+        request_id: Option<String>,
+    },
+    Fedimint(OOBNotes),
+}
+
+/// Reads ecash out of a DM, either a payment request payload or a bare
+/// cashu token or set of fedimint notes. Anything else is None.
+pub fn parse_ecash_message(message: &str) -> Option<NostrEcash> {
+    let message = message.trim();
+
+    if let Ok(payload) = serde_json::from_str::<PaymentRequestPayload>(message) {
+        return Some(NostrEcash::Cashu {
+            token: Token::new(payload.mint, payload.proofs, payload.memo, payload.unit),
+            request_id: payload.id,
+        });
+    }
+
+    if let Ok(token) = Token::from_str(message) {
+        return Some(NostrEcash::Cashu {
+            token,
+            request_id: None,
+        });
+    }
+
+    OOBNotes::from_str(message).ok().map(NostrEcash::Fedimint)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cdk::mint_url::MintUrl;
+    use cdk::nuts::{CurrencyUnit, Proofs};
+
+    fn test_token() -> String {
+        let mint_url = MintUrl::from_str("https://mint.example.com").unwrap();
+        Token::new(mint_url, Proofs::new(), None, CurrencyUnit::Sat).to_string()
+    }
+
+    #[test]
+    fn test_parse_relay_messages() {
+        assert_eq!(
+            RelayMessage::from_str(r#"["EOSE","harbor"]"#).unwrap(),
+            RelayMessage::EndOfStoredEvents("harbor".to_string())
+        );
+        assert_eq!(
+            RelayMessage::from_str(r#"["OK","abcd",false,"blocked: spam"]"#).unwrap(),
+            RelayMessage::Ok {
+                event_id: "abcd".to_string(),
+                accepted: false,
+                message: "blocked: spam".to_string(),
+            }
+        );
+        assert_eq!(
+            RelayMessage::from_str(r#"["NOTICE","slow down"]"#).unwrap(),
+            RelayMessage::Notice("slow down".to_string())
+        );
+        assert!(RelayMessage::from_str(r#"["AUTH","challenge"]"#).is_err());
+        assert!(RelayMessage::from_str("not json").is_err());
+    }
+
+    #[test]
+    fn test_parse_ecash_message() {
+        let encoded = test_token();
+        match parse_ecash_message(&format!("  {encoded}\n")) {
+            Some(NostrEcash::Cashu { token, request_id }) => {
+                assert_eq!(token.to_string(), encoded);
+                assert_eq!(request_id, None);
+            }
+            other => panic!("expected a cashu token, got {other:?}"),
+        }
+
+        let payload = serde_json::json!({
+            "id": "b7a90176",
+            "mint": "https://mint.example.com",
+            "unit": "sat",
+            "proofs": [],
+        });
+        match parse_ecash_message(&payload.to_string()) {
+            Some(NostrEcash::Cashu { request_id, .. }) => {
+                assert_eq!(request_id.as_deref(), Some("b7a90176"));
+            }
+            other => panic!("expected a payment request payload, got {other:?}"),
+        }
+
+        assert!(parse_ecash_message("gm").is_none());
+    }
+
+    /// Runs against the relay in `NOSTR_TEST_RELAY`, e.g. a local `ws://localhost:7777`
+    #[tokio::test]
+    async fn test_dm_round_trip() {
+        let Ok(url) = std::env::var("NOSTR_TEST_RELAY") else {
+            log::info!("NOSTR_TEST_RELAY not set, skipping");
+            return;
+        };
+        let cancel = Arc::new(AtomicBool::new(false));
+
+        let sender = Keys::generate();
+        let receiver = Keys::generate();
+
+        let mut relay = NostrRelay::connect(&url, false, cancel.clone())
+            .await
+            .unwrap();
+        relay
+            .subscribe("harbor-test", &dm_filter(receiver.public_key()))
+            .await
+            .unwrap();
+
+        send_dm(
+            &sender,
+            receiver.public_key(),
+            &[url.clone()],
+            test_token(),
+            false,
+            cancel,
+        )
+        .await
+        .unwrap();
+
+        let event = tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                if let Some(RelayMessage::Event { event, .. }) = relay.next_message().await.unwrap()
+                {
+                    return event;
+                }
+            }
+        })
+        .await
+        .expect("relay should deliver the DM");
+        relay.close().await;
+
+        let (from, message) = unwrap_dm(&receiver, &event).await.unwrap();
+        assert_eq!(from, sender.public_key());
+        assert!(matches!(
+            parse_ecash_message(&message),
+            Some(NostrEcash::Cashu { .. })
+        ));
+    }
+}
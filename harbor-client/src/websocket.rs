@@ -0,0 +1,148 @@
+use crate::http::{connect_tor_tls, tls_handshake};
+use anyhow::anyhow;
+use fedimint_core::util::SafeUrl;
+use futures::{SinkExt, StreamExt};
+use serde::Serialize;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpStream;
+use tokio_tungstenite::WebSocketStream;
+use tokio_tungstenite::tungstenite::Message;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+
+trait Io: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Io for T {}
+
+/// A text websocket connection, routed through Tor when enabled.
+///
+/// Shared by everything that needs a long lived connection to a server
+/// so they all get the same Tor handling as our http requests.
+pub struct WebSocket {
+    stream: WebSocketStream<Box<dyn Io>>,
+    /// How long the server may stay quiet before we ping it, and then how
+    /// long it has to answer. Without it we wait on the server forever.
+    keepalive: Option<Duration>,
+    /// When we last heard from the server or pinged it
+    quiet_since: Instant,
+    pinged: bool,
+}
+
+impl WebSocket {
+    /// Connects to a `wss://` url, plain `ws://` is only allowed without Tor
+    pub async fn connect(
+        url: &str,
+        tor_enabled: bool,
+        cancel_handle: Arc<AtomicBool>,
+    ) -> anyhow::Result<Self> {
+        let safe_url = SafeUrl::parse(url)?;
+        let secure = match safe_url.scheme() {
+            "wss" => true,
+            "ws" if !tor_enabled => false,
+            "ws" => return Err(anyhow!("Only secure websockets are supported over Tor")),
+            scheme => return Err(anyhow!("Unsupported websocket scheme: {scheme}")),
+        };
+
+        log::debug!("Opening websocket to {url}, Tor enabled: {tor_enabled}");
+
+        let stream: Box<dyn Io> = if tor_enabled {
+            let (_, tls_stream) = connect_tor_tls(&safe_url, cancel_handle).await?;
+            Box::new(tls_stream)
+        } else {
+            let host = safe_url
+                .host_str()
+                .ok_or_else(|| anyhow!("Expected host str"))?
+                .to_string();
+            let port = safe_url
+                .port_or_known_default()
+                .ok_or_else(|| anyhow!("Expected port number"))?;
+
+            let tcp =
+                tokio::time::timeout(CONNECT_TIMEOUT, TcpStream::connect((host.as_str(), port)))
+                    .await
+                    .map_err(|_| anyhow!("Connection to {host} timed out"))??;
+
+            if secure {
+                Box::new(tls_handshake(&host, tcp, cancel_handle).await?)
+            } else {
+                Box::new(tcp)
+            }
+        };
+
+        let (stream, _) = tokio::time::timeout(
+            CONNECT_TIMEOUT,
+            tokio_tungstenite::client_async(url, stream),
+        )
+        .await
+        .map_err(|_| anyhow!("Websocket handshake with {url} timed out"))??;
+
+        Ok(Self {
+            stream,
+            keepalive: None,
+            quiet_since: Instant::now(),
+            pinged: false,
+        })
+    }
+
+    /// Pings the server after it has been quiet for `idle` and gives up on the
+    /// connection if it doesn't answer within another `idle`
+    #[must_use]
+    pub const fn keepalive(mut self, idle: Duration) -> Self {
+        self.keepalive = Some(idle);
+        self
+    }
+
+    pub async fn send_json<T: Serialize>(&mut self, msg: &T) -> anyhow::Result<()> {
+        let text = serde_json::to_string(msg)?;
+        self.stream.send(Message::text(text)).await?;
+        Ok(())
+    }
+
+    /// Waits for the next text message, returns None once the connection is closed.
+    /// Pings are answered by the underlying stream while we read.
+    ///
+    /// The keepalive state lives on the socket, so this can be cancelled and
+    /// called again, e.g. by a caller that checks for shutdown in between.
+    pub async fn next_text(&mut self) -> anyhow::Result<Option<String>> {
+        loop {
+            let next = match self.keepalive {
+                Some(idle) => {
+                    let wait = idle.saturating_sub(self.quiet_since.elapsed());
+                    match tokio::time::timeout(wait, self.stream.next()).await {
+                        Ok(next) => next,
+                        Err(_) if self.pinged => {
+                            return Err(anyhow!("Server stopped responding"));
+                        }
+                        Err(_) => {
+                            self.stream.send(Message::Ping(Default::default())).await?;
+                            self.pinged = true;
+                            self.quiet_since = Instant::now();
+                            continue;
+                        }
+                    }
+                }
+                None => self.stream.next().await,
+            };
+            let Some(msg) = next else {
+                return Ok(None);
+            };
+
+            self.pinged = false;
+            self.quiet_since = Instant::now();
+            match msg? {
+                Message::Text(text) => return Ok(Some(text.to_string())),
+                Message::Close(_) => return Ok(None),
+                Message::Binary(_) | Message::Ping(_) | Message::Pong(_) | Message::Frame(_) => {}
+            }
+        }
+    }
+
+    pub async fn close(mut self) {
+        if let Err(e) = self.stream.close(None).await {
+            log::debug!("Error closing websocket: {e}");
+        }
+    }
+}
@@ -2,8 +2,7 @@ use crate::Message;
 use crate::config::read_config;
 use crate::keyring::{save_to_keyring, try_get_keyring_password};
 use harbor_client::bitcoin::Network;
-use harbor_client::cashu_client::TorMintConnector;
-use harbor_client::cdk::mint_url::MintUrl;
+use harbor_client::cashu_client::DleqVerifyingConnector;
 use harbor_client::cdk::nuts::CurrencyUnit;
 use harbor_client::cdk::wallet::WalletBuilder;
 use harbor_client::cdk_sqlite::WalletSqliteDatabase;
@@ -12,7 +11,8 @@ use harbor_client::fedimint_client::{FederationInviteOrId, FedimintClient};
 use harbor_client::fedimint_core::config::FederationId;
 use harbor_client::metadata::FederationMeta;
 use harbor_client::{
-    CoreUIMsg, CoreUIMsgPacket, HarborCore, MintIdentifier, UICoreMsg, UICoreMsgPacket, data_dir,
+    CoreUIMsg, CoreUIMsgPacket, HarborCore, MintIdentifier, SendSuccessMsg, UICoreMsg,
+    UICoreMsgPacket, data_dir,
 };
 use iced::futures::channel::mpsc::Sender;
 use iced::futures::{SinkExt, Stream, StreamExt};
@@ -136,33 +136,27 @@ async fn setup_harbor_core(
     );
 
     // Setup cashu clients
-    let mint_urls = db
+    let mints = db
         .list_cashu_mints()
         .expect("should load initial fedimints");
-    let mut cashu_clients = HashMap::with_capacity(mint_urls.len());
-    for url in mint_urls {
+    let mut cashu_clients = HashMap::with_capacity(mints.len());
+    for (mint_url, unit) in mints {
         let seed = mnemonic.to_seed_normalized("");
 
-        let mint_url = MintUrl::from_str(&url).expect("Could not create MintUrl");
-
         let builder = WalletBuilder::new()
             .mint_url(mint_url.clone())
-            .unit(CurrencyUnit::Sat)
+            .unit(unit)
             .localstore(cashu_db.clone())
-            .seed(&seed);
-
-        let builder = if profile.tor_enabled() {
-            builder.client(TorMintConnector::new(
+            .seed(&seed)
+            .client(DleqVerifyingConnector::new(
                 mint_url,
-                Arc::new(AtomicBool::new(false)),
-            ))
-        } else {
-            builder
-        };
+                profile.tor_enabled(),
+                db.clone(),
+            ));
 
         let wallet = builder.build().expect("Could not create cashu client");
 
-        cashu_clients.insert(wallet.mint_url.clone(), wallet);
+        cashu_clients.insert((wallet.mint_url.clone(), wallet.unit.clone()), wallet);
     }
 
     // setup message passing thread
@@ -499,15 +493,34 @@ async fn process_core(core_handle: &mut CoreHandle, core: &HarborCore) {
         tokio::spawn(async move {
             if let Some(msg) = msg {
                 match msg.msg {
-                    UICoreMsg::SendLightning { mint, invoice } => {
+                    UICoreMsg::SendLightning {
+                        mint,
+                        invoice,
+                        quote_id,
+                    } => {
                         log::info!("Got UICoreMsg::Send");
                         core.msg(msg.id, CoreUIMsg::Sending).await;
-                        if let Err(e) = core.send_lightning(msg.id, mint, invoice, false).await {
+                        if let Err(e) = core
+                            .send_lightning(msg.id, mint, invoice, quote_id, false)
+                            .await
+                        {
                             error!("Error sending: {e}");
                             core.msg(msg.id, CoreUIMsg::SendFailure(e.to_string()))
                                 .await;
                         }
                     }
+                    UICoreMsg::EstimateLightningSend { mint, invoice } => {
+                        match core.estimate_lightning_send(mint, invoice).await {
+                            Ok(estimate) => {
+                                core.msg(msg.id, CoreUIMsg::SendEstimate(estimate)).await;
+                            }
+                            Err(e) => {
+                                error!("Error estimating send: {e}");
+                                core.msg(msg.id, CoreUIMsg::SendFailure(e.to_string()))
+                                    .await;
+                            }
+                        }
+                    }
                     UICoreMsg::ReceiveLightning { mint, amount } => {
                         core.msg(msg.id, CoreUIMsg::ReceiveGenerating).await;
                         match core.receive_lightning(msg.id, mint, amount, false).await {
@@ -521,6 +534,37 @@ async fn process_core(core_handle: &mut CoreHandle, core: &HarborCore) {
                             }
                         }
                     }
+                    UICoreMsg::LnurlAuth(lnurl) => match core.lnurl_auth(msg.id, lnurl).await {
+                        Err(e) => {
+                            error!("Error logging in with lnurl auth: {e}");
+                            core.msg(msg.id, CoreUIMsg::LnurlAuthFailed(e.to_string()))
+                                .await;
+                        }
+                        Ok(domain) => {
+                            core.msg(msg.id, CoreUIMsg::LnurlAuthSuccess(domain)).await;
+                        }
+                    },
+                    UICoreMsg::ReceiveLnurlWithdraw {
+                        mint,
+                        lnurl,
+                        amount,
+                    } => {
+                        core.msg(msg.id, CoreUIMsg::ReceiveGenerating).await;
+                        match core
+                            .receive_lnurl_withdraw(msg.id, mint, lnurl, amount)
+                            .await
+                        {
+                            Err(e) => {
+                                error!("Error withdrawing from lnurl: {e}");
+                                core.msg(msg.id, CoreUIMsg::ReceiveFailed(e.to_string()))
+                                    .await;
+                            }
+                            Ok(invoice) => {
+                                core.msg(msg.id, CoreUIMsg::ReceiveInvoiceGenerated(invoice))
+                                    .await;
+                            }
+                        }
+                    }
                     UICoreMsg::SendLnurlPay {
                         mint,
                         lnurl,
@@ -543,7 +587,7 @@ async fn process_core(core_handle: &mut CoreHandle, core: &HarborCore) {
                         log::info!("Got UICoreMsg::SendOnChain");
                         core.msg(msg.id, CoreUIMsg::Sending).await;
                         let federation_id = match mint {
-                            MintIdentifier::Cashu(_) => panic!("should not receive cashu"), // todo
+                            MintIdentifier::Cashu(..) => panic!("should not receive cashu"), // todo
                             MintIdentifier::Fedimint(mint) => mint,
                         };
                         if let Err(e) = core
@@ -558,7 +602,7 @@ async fn process_core(core_handle: &mut CoreHandle, core: &HarborCore) {
                     UICoreMsg::ReceiveOnChain { mint } => {
                         core.msg(msg.id, CoreUIMsg::ReceiveGenerating).await;
                         let federation_id = match mint {
-                            MintIdentifier::Cashu(_) => panic!("should not receive cashu"), // todo
+                            MintIdentifier::Cashu(..) => panic!("should not receive cashu"), // todo
                             MintIdentifier::Fedimint(mint) => mint,
                         };
 
@@ -573,6 +617,123 @@ async fn process_core(core_handle: &mut CoreHandle, core: &HarborCore) {
                             }
                         }
                     }
+                    UICoreMsg::SendEcash {
+                        mint,
+                        amount,
+                        lock_to,
+                        unlock_at,
+                    } => {
+                        log::info!("Got UICoreMsg::SendEcash");
+                        core.msg(msg.id, CoreUIMsg::Sending).await;
+                        match core
+                            .send_ecash(msg.id, mint, amount, lock_to, unlock_at)
+                            .await
+                        {
+                            Err(e) => {
+                                error!("Error sending ecash: {e}");
+                                core.msg(msg.id, CoreUIMsg::SendFailure(e.to_string()))
+                                    .await;
+                            }
+                            Ok(token) => {
+                                core.msg(msg.id, CoreUIMsg::SendEcashGenerated(token)).await;
+                            }
+                        }
+                    }
+                    UICoreMsg::ReceiveFedimintNotes(notes) => {
+                        log::info!("Got UICoreMsg::ReceiveFedimintNotes");
+                        core.msg(msg.id, CoreUIMsg::ReceiveGenerating).await;
+                        if let Err(e) = core.receive_fedimint_notes(msg.id, notes).await {
+                            error!("Error reissuing notes: {e}");
+                            core.msg(msg.id, CoreUIMsg::ReceiveFailed(e.to_string()))
+                                .await;
+                        }
+                    }
+                    UICoreMsg::SwapInCashuToken { token, mint } => {
+                        log::info!("Got UICoreMsg::SwapInCashuToken");
+                        core.msg(msg.id, CoreUIMsg::ReceiveGenerating).await;
+                        if let Err(e) = core.swap_in_cashu_token(msg.id, token, mint).await {
+                            error!("Error swapping in ecash: {e}");
+                            core.msg(msg.id, CoreUIMsg::ReceiveFailed(e.to_string()))
+                                .await;
+                        }
+                    }
+                    UICoreMsg::ReclaimEcash(token) => {
+                        log::info!("Got UICoreMsg::ReclaimEcash");
+                        if let Err(e) = core.reclaim_ecash(msg.id, token).await {
+                            error!("Error reclaiming ecash: {e}");
+                            core.msg(msg.id, CoreUIMsg::EcashReclaimFailed(e.to_string()))
+                                .await;
+                        }
+                    }
+                    UICoreMsg::CreatePaymentRequest {
+                        mint,
+                        amount,
+                        description,
+                    } => {
+                        log::info!("Got UICoreMsg::CreatePaymentRequest");
+                        core.msg(msg.id, CoreUIMsg::ReceiveGenerating).await;
+                        match core.create_payment_request(mint, amount, description).await {
+                            Err(e) => {
+                                error!("Error creating payment request: {e}");
+                                core.msg(msg.id, CoreUIMsg::ReceiveFailed(e.to_string()))
+                                    .await;
+                            }
+                            Ok((id, request)) => {
+                                core.msg(msg.id, CoreUIMsg::PaymentRequestCreated { id, request })
+                                    .await;
+                            }
+                        }
+                    }
+                    UICoreMsg::PayPaymentRequest {
+                        mint,
+                        request,
+                        amount,
+                    } => {
+                        log::info!("Got UICoreMsg::PayPaymentRequest");
+                        core.msg(msg.id, CoreUIMsg::Sending).await;
+                        match core
+                            .pay_payment_request(msg.id, mint, request, amount)
+                            .await
+                        {
+                            Err(e) => {
+                                error!("Error paying payment request: {e}");
+                                core.msg(msg.id, CoreUIMsg::SendFailure(e.to_string()))
+                                    .await;
+                            }
+                            // No transport, the payer hands the token over themselves
+                            Ok(Some(token)) => {
+                                core.msg(msg.id, CoreUIMsg::SendEcashGenerated(token)).await;
+                            }
+                            Ok(None) => {
+                                core.msg(
+                                    msg.id,
+                                    CoreUIMsg::SendSuccess(SendSuccessMsg::PaymentRequest),
+                                )
+                                .await;
+                            }
+                        }
+                    }
+                    UICoreMsg::ReceivePaymentRequestPayment { id, payment } => {
+                        log::info!("Got UICoreMsg::ReceivePaymentRequestPayment");
+                        core.msg(msg.id, CoreUIMsg::ReceiveGenerating).await;
+                        if let Err(e) = core
+                            .receive_payment_request_payment(msg.id, id, &payment)
+                            .await
+                        {
+                            error!("Error redeeming payment request payment: {e}");
+                            core.msg(msg.id, CoreUIMsg::ReceiveFailed(e.to_string()))
+                                .await;
+                        }
+                    }
+                    UICoreMsg::ReceiveCashuToken(token) => {
+                        log::info!("Got UICoreMsg::ReceiveCashuToken");
+                        core.msg(msg.id, CoreUIMsg::ReceiveGenerating).await;
+                        if let Err(e) = core.receive_cashu_token(msg.id, token).await {
+                            error!("Error redeeming ecash: {e}");
+                            core.msg(msg.id, CoreUIMsg::ReceiveFailed(e.to_string()))
+                                .await;
+                        }
+                    }
                     UICoreMsg::Transfer { to, from, amount } => {
                         if let Err(e) = core.transfer(msg.id, to, from, amount).await {
                             error!("Error transferring: {e}");
@@ -596,12 +757,23 @@ async fn process_core(core_handle: &mut CoreHandle, core: &HarborCore) {
                                         ),
                                         config: Some(config),
                                         metadata,
+                                        units: vec![CurrencyUnit::Sat],
                                     },
                                 )
                                 .await;
                             }
                         }
                     }
+                    UICoreMsg::DiscoverMints => match core.discover_mints(msg.id).await {
+                        Err(e) => {
+                            error!("Error discovering mints: {e}");
+                            core.msg(msg.id, CoreUIMsg::MintDiscoveryFailed(e.to_string()))
+                                .await;
+                        }
+                        Ok(mints) => {
+                            core.msg(msg.id, CoreUIMsg::MintsDiscovered(mints)).await;
+                        }
+                    },
                     UICoreMsg::GetCashuMintInfo(mint_url) => {
                         match core.get_cashu_mint_info(msg.id, mint_url.clone()).await {
                             Err(e) => {
@@ -610,6 +782,8 @@ async fn process_core(core_handle: &mut CoreHandle, core: &HarborCore) {
                                     .await;
                             }
                             Ok(info) => {
+                                let units = HarborCore::cashu_mint_units(info.as_ref());
+                                let unit = units.first().cloned().unwrap_or(CurrencyUnit::Sat);
                                 let metadata = FederationMeta {
                                     federation_name: info
                                         .as_ref()
@@ -629,9 +803,10 @@ async fn process_core(core_handle: &mut CoreHandle, core: &HarborCore) {
                                 core.msg(
                                     msg.id,
                                     CoreUIMsg::MintInfo {
-                                        id: MintIdentifier::Cashu(mint_url),
+                                        id: MintIdentifier::Cashu(mint_url, unit),
                                         config: None,
                                         metadata,
+                                        units,
                                     },
                                 )
                                 .await;
@@ -662,8 +837,8 @@ async fn process_core(core_handle: &mut CoreHandle, core: &HarborCore) {
                             }
                         }
                     }
-                    UICoreMsg::AddCashuMint(url) => match core
-                        .add_cashu_mint(msg.id, url.clone())
+                    UICoreMsg::AddCashuMint { url, unit } => match core
+                        .add_cashu_mint(msg.id, url.clone(), unit.clone())
                         .await
                     {
                         Err(e) => {
@@ -678,7 +853,7 @@ async fn process_core(core_handle: &mut CoreHandle, core: &HarborCore) {
                             }
                             core.msg(
                                 msg.id,
-                                CoreUIMsg::AddMintSuccess(MintIdentifier::Cashu(url)),
+                                CoreUIMsg::AddMintSuccess(MintIdentifier::Cashu(url, unit)),
                             )
                             .await;
                         }
@@ -719,8 +894,8 @@ async fn process_core(core_handle: &mut CoreHandle, core: &HarborCore) {
                                     }
                                 }
                             }
-                            MintIdentifier::Cashu(url) => {
-                                match core.remove_cashu_mint(msg.id, &url).await {
+                            MintIdentifier::Cashu(url, unit) => {
+                                match core.remove_cashu_mint(msg.id, &url, &unit).await {
                                     Err(e) => {
                                         error!("Error removing cashu mint: {e}");
                                         core.msg(
@@ -771,8 +946,11 @@ async fn process_core(core_handle: &mut CoreHandle, core: &HarborCore) {
                                 }
                             }
                         }
-                        MintIdentifier::Cashu(ref mint_url) => {
-                            match core.add_cashu_mint(msg.id, mint_url.clone()).await {
+                        MintIdentifier::Cashu(ref mint_url, ref unit) => {
+                            match core
+                                .add_cashu_mint(msg.id, mint_url.clone(), unit.clone())
+                                .await
+                            {
                                 Err(e) => {
                                     error!("Error adding cashu mint: {e}");
                                     core.msg(msg.id, CoreUIMsg::AddMintFailed(e.to_string()))
@@ -789,6 +967,22 @@ async fn process_core(core_handle: &mut CoreHandle, core: &HarborCore) {
                             }
                         }
                     },
+                    UICoreMsg::RestoreFromFederation(invite_code) => {
+                        match core.restore_from_federation(msg.id, invite_code).await {
+                            Err(e) => {
+                                error!("Error restoring from federation: {e}");
+                                core.msg(msg.id, CoreUIMsg::AddMintFailed(e.to_string()))
+                                    .await;
+                            }
+                            Ok((rejoined, failed)) => {
+                                if let Ok(new_list) = core.get_mint_items().await {
+                                    core.msg(msg.id, CoreUIMsg::MintListUpdated(new_list)).await;
+                                }
+                                core.msg(msg.id, CoreUIMsg::WalletRestored { rejoined, failed })
+                                    .await;
+                            }
+                        }
+                    }
                     UICoreMsg::FederationListNeedsUpdate => {
                         if let Ok(new_federation_list) = core.get_mint_items().await {
                             core.msg(msg.id, CoreUIMsg::MintListUpdated(new_federation_list))
@@ -818,6 +1012,28 @@ async fn process_core(core_handle: &mut CoreHandle, core: &HarborCore) {
                             core.msg(msg.id, CoreUIMsg::TorEnabled(enabled)).await;
                         }
                     },
+                    UICoreMsg::SetConsolidationEnabled(enabled) => {
+                        match core.set_consolidation_enabled(enabled) {
+                            Err(e) => {
+                                error!("error setting consolidation enabled: {e}");
+                            }
+                            _ => {
+                                core.msg(msg.id, CoreUIMsg::ConsolidationEnabled(enabled))
+                                    .await;
+                            }
+                        }
+                    }
+                    UICoreMsg::SetNostrEnabled(enabled) => match core.set_nostr_enabled(enabled) {
+                        Err(e) => {
+                            error!("error setting nostr enabled: {e}");
+                        }
+                        _ => {
+                            core.msg(msg.id, CoreUIMsg::NostrEnabled(enabled)).await;
+                        }
+                    },
+                    UICoreMsg::CancelCashuLogin => {
+                        core.cancel_cashu_login();
+                    }
                     UICoreMsg::TestStatusUpdates => {
                         core.test_status_updates(msg.id).await;
                     }
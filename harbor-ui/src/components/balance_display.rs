@@ -1,11 +1,12 @@
+use harbor_client::cdk::nuts::CurrencyUnit;
 use iced::Element;
 use iced::widget::{column, text};
 
-use super::{format_amount, subtitle};
+use super::{format_unit_amount, subtitle};
 use crate::Message;
 
-pub fn h_balance_display(balance: u64) -> Element<'static, Message> {
-    let balance_row = text(format_amount(balance)).size(24);
+pub fn h_balance_display(balance: u64, unit: &CurrencyUnit) -> Element<'static, Message> {
+    let balance_row = text(format_unit_amount(balance, unit)).size(24);
     let balance_subtitle = text("Your balance").size(18).style(subtitle);
     column![balance_row, balance_subtitle].spacing(4).into()
 }
@@ -1,7 +1,8 @@
 use crate::{AddFederationStatus, HarborWallet, Message};
-use harbor_client::MintIdentifier;
 use harbor_client::db_models::MintItem;
 use harbor_client::metadata::FederationMeta;
+use harbor_client::mint_discovery::DiscoveredMint;
+use harbor_client::{MintConnectionInfo, MintIdentifier};
 use iced::{
     Alignment, Element, Length,
     widget::{column, container, horizontal_space, row, text},
@@ -9,7 +10,7 @@ use iced::{
 
 use super::{
     ConfirmModalState, SvgIcon, h_balance_display, h_small_button, light_container_style, map_icon,
-    subtitle, tag_style,
+    red, subtitle, tag_style,
 };
 
 // Helper function to create the common mint info layout
@@ -21,7 +22,7 @@ fn mint_info<'a>(
 ) -> iced::widget::Column<'a, Message> {
     // Choose the right icon based on the mint type
     let mint_icon = match id {
-        MintIdentifier::Cashu(_) => map_icon(SvgIcon::Squirrel, 24., 24.),
+        MintIdentifier::Cashu(..) => map_icon(SvgIcon::Squirrel, 24., 24.),
         MintIdentifier::Fedimint(_) => map_icon(SvgIcon::People, 24., 24.),
     };
 
@@ -64,10 +65,10 @@ pub fn h_federation_item_preview(item: &MintItem) -> Element<Message> {
         .into()
 }
 
-pub fn h_federation_item(item: &MintItem) -> Element<Message> {
+pub fn h_federation_item<'a>(item: &'a MintItem, harbor: &'a HarborWallet) -> Element<'a, Message> {
     let mut column = mint_info(&item.name, &item.guardians, &item.metadata, &item.id);
 
-    column = column.push(h_balance_display(item.balance));
+    column = column.push(h_balance_display(item.balance, &item.unit));
 
     let remove_button = h_small_button("", SvgIcon::Trash, false).on_press(
         Message::SetConfirmModal(Some(ConfirmModalState {
@@ -79,10 +80,44 @@ pub fn h_federation_item(item: &MintItem) -> Element<Message> {
         })),
     );
 
-    column = column.push(row![
-        horizontal_space().width(Length::Fill),
-        remove_button.width(48)
-    ]);
+    let mut button_row = row![].align_y(Alignment::Center).spacing(16);
+
+    // the balance fills in once recovering from the backup finishes
+    if item.recovering {
+        let status = match harbor.recovery_progress(&item.id) {
+            Some(progress) => format!("Recovering {:.0}%", progress * 100.),
+            None => "Recovering".to_string(),
+        };
+        let recovering_tag = container(text(status).size(18).style(subtitle))
+            .padding(8)
+            .style(tag_style);
+        button_row = button_row.push(recovering_tag);
+    }
+    if let Some(reason) = &item.recovery_error {
+        let failed_tag = container(
+            text(format!("Recovery failed: {reason}"))
+                .size(18)
+                .color(red()),
+        )
+        .padding(8)
+        .style(tag_style);
+        button_row = button_row.push(failed_tag);
+    }
+
+    // the mint handed us signatures we couldn't prove came from its public keys
+    if item.dleq_audit.has_failures() {
+        let warning = format!(
+            "Unverified ecash: {} missing, {} invalid, {} unchecked DLEQ proofs",
+            item.dleq_audit.missing, item.dleq_audit.invalid, item.dleq_audit.unverified
+        );
+        let warning_tag = container(text(warning).size(18).color(red()))
+            .padding(8)
+            .style(tag_style);
+        button_row = button_row.push(warning_tag);
+    }
+
+    button_row = button_row.push(horizontal_space().width(Length::Fill));
+    column = column.push(button_row.push(remove_button.width(48)));
 
     container(column)
         .padding(16)
@@ -90,6 +125,48 @@ pub fn h_federation_item(item: &MintItem) -> Element<Message> {
         .into()
 }
 
+/// A mint from the nostr directory, with a button to preview it before joining
+pub fn h_discovered_mint(mint: &DiscoveredMint) -> Element<Message> {
+    let (icon, fallback_name) = match &mint.info {
+        MintConnectionInfo::Cashu(url) => (SvgIcon::Squirrel, url.to_string()),
+        MintConnectionInfo::Fedimint(invite_code) => (
+            SvgIcon::People,
+            format!("Federation {}", invite_code.federation_id().to_prefix()),
+        ),
+    };
+    let name = mint.name.clone().unwrap_or(fallback_name);
+
+    let recommendations = match mint.recommendations {
+        0 => "No recommendations".to_string(),
+        1 => "1 recommendation".to_string(),
+        n => format!("{n} recommendations"),
+    };
+
+    let preview_button = h_small_button("Preview", SvgIcon::Eye, false)
+        .on_press(Message::PreviewDiscoveredMint(mint.info.clone()));
+
+    let details = column![
+        row![map_icon(icon, 24., 24.), text(name).size(18)]
+            .align_y(Alignment::Center)
+            .spacing(16),
+        text(recommendations).size(14).style(subtitle),
+    ]
+    .spacing(8);
+
+    container(
+        row![
+            details,
+            horizontal_space().width(Length::Fill),
+            preview_button
+        ]
+        .align_y(Alignment::Center)
+        .spacing(16),
+    )
+    .padding(16)
+    .style(light_container_style)
+    .into()
+}
+
 pub fn h_federation_archived<'a>(
     item: &'a MintItem,
     harbor: &'a HarborWallet,
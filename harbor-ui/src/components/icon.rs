@@ -31,6 +31,7 @@ pub enum SvgIcon {
     Shield,
     FolderLock,
     ShieldAlert,
+    Ecash,
 }
 
 macro_rules! icon_handle {
@@ -71,6 +72,7 @@ pub fn map_icon<'a>(icon: SvgIcon, width: f32, height: f32) -> Svg<'a, Theme> {
         SvgIcon::Shield => icon_handle!("shield.svg"),
         SvgIcon::FolderLock => icon_handle!("folder_lock.svg"),
         SvgIcon::ShieldAlert => icon_handle!("shield_alert.svg"),
+        SvgIcon::Ecash => icon_handle!("ecash.svg"),
     }
     .width(width)
     .height(height)
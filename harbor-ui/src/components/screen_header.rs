@@ -1,6 +1,6 @@
 use super::{
-    SvgIcon, borderless_pick_list_style, format_amount, gray, green, hr, map_icon, menu_style, red,
-    vr,
+    SvgIcon, borderless_pick_list_style, format_unit_amount, gray, green, hr, map_icon, menu_style,
+    red, vr,
 };
 use crate::{HarborWallet, Message, ReceiveStatus};
 use harbor_client::MintIdentifier;
@@ -18,7 +18,7 @@ pub fn h_screen_header(
 
         // Choose the right icon based on the mint type
         let mint_icon = match id {
-            MintIdentifier::Cashu(_) => map_icon(SvgIcon::Squirrel, 24., 24.),
+            MintIdentifier::Cashu(..) => map_icon(SvgIcon::Squirrel, 24., 24.),
             MintIdentifier::Fedimint(_) => map_icon(SvgIcon::People, 24., 24.),
         };
 
@@ -60,7 +60,7 @@ pub fn h_screen_header(
             .spacing(16)
             .width(Length::Shrink)
             .padding(Padding::new(0.).left(16));
-        let formatted_balance = format_amount(item.balance);
+        let formatted_balance = format_unit_amount(item.balance, &item.unit);
 
         let balance = row![text(formatted_balance).size(24)]
             .align_y(Alignment::Center)
@@ -1,6 +1,6 @@
-use super::{format_amount, format_timestamp, side_panel_style, subtitle};
+use super::{format_timestamp, format_unit_amount, side_panel_style, subtitle};
 use crate::Message;
-use crate::components::{SvgIcon, map_icon, text_link};
+use crate::components::{SvgIcon, h_small_button, map_icon, text_link};
 use harbor_client::MintIdentifier;
 use harbor_client::bitcoin::Network;
 use harbor_client::bitcoin::hex::DisplayHex;
@@ -16,10 +16,12 @@ pub fn h_transaction_details<'a>(
     item: &'a TransactionItem,
     federation_list: &'a [MintItem],
     network: Network,
+    reclaiming: bool,
 ) -> Element<'a, Message> {
     let TransactionItem {
         kind,
         amount,
+        unit,
         fee_msats,
         direction,
         mint_identifier,
@@ -27,6 +29,8 @@ pub fn h_transaction_details<'a>(
         status: _,
         txid,
         preimage,
+        token,
+        reclaimable,
     } = item;
 
     // Create title based on type and direction
@@ -35,9 +39,12 @@ pub fn h_transaction_details<'a>(
         (TransactionItemKind::Lightning, TransactionDirection::Outgoing) => "Lightning Send",
         (TransactionItemKind::Onchain, TransactionDirection::Incoming) => "On-chain Receive",
         (TransactionItemKind::Onchain, TransactionDirection::Outgoing) => "On-chain Send",
+        (TransactionItemKind::Ecash, TransactionDirection::Incoming) => "Ecash Receive",
+        (TransactionItemKind::Ecash, TransactionDirection::Outgoing) => "Ecash Send",
+        (TransactionItemKind::Consolidation, _) => "Ecash Consolidation",
     };
 
-    let formatted_amount = format_amount(*amount);
+    let formatted_amount = format_unit_amount(*amount, unit);
     let formatted_timestamp = format_timestamp(timestamp);
 
     // Create the mint section with appropriate label
@@ -58,7 +65,7 @@ pub fn h_transaction_details<'a>(
 
     // Choose the right icon based on the mint type
     let mint_icon = match mint_identifier {
-        MintIdentifier::Cashu(_) => map_icon(SvgIcon::Squirrel, 16., 16.),
+        MintIdentifier::Cashu(..) => map_icon(SvgIcon::Squirrel, 16., 16.),
         MintIdentifier::Fedimint(_) => map_icon(SvgIcon::People, 16., 16.),
     };
 
@@ -90,7 +97,7 @@ pub fn h_transaction_details<'a>(
             column![mint_section, amount_section, time_section].spacing(16)
         }
         TransactionDirection::Outgoing => {
-            let formatted_fee = format_amount(*fee_msats / 1000); // Convert msats to sats
+            let formatted_fee = format_unit_amount(*fee_msats / 1000, unit); // Convert msats to sats
             let fee_section = column![
                 text("Fee").size(16).style(subtitle),
                 text(formatted_fee).size(16)
@@ -137,6 +144,32 @@ pub fn h_transaction_details<'a>(
         );
     }
 
+    // Add the token so it can be copied again
+    if let Some(token) = token {
+        let first_ten = token.chars().take(10).collect::<String>();
+        let last_ten = token
+            .chars()
+            .skip(token.chars().count().saturating_sub(10))
+            .collect::<String>();
+        details = details.push(
+            column![
+                text("Token").size(16).style(subtitle),
+                row![
+                    rich_text([span(format!("{first_ten}...{last_ten}")).link(token.clone())])
+                        .on_link_click(move |a: String| Message::CopyToClipboard(a))
+                ]
+            ]
+            .spacing(8),
+        );
+    }
+
+    // Unclaimed tokens we sent can be taken back, unless they're locked to someone else
+    if let Some(token) = token.as_ref().filter(|_| *reclaimable) {
+        let reclaim_button = h_small_button("Reclaim", SvgIcon::Restart, reclaiming)
+            .on_press(Message::ReclaimEcash(token.clone()));
+        details = details.push(reclaim_button);
+    }
+
     let title_row = row![text(title).size(24),].align_y(Alignment::Center);
 
     container(
@@ -1,5 +1,5 @@
 use super::{
-    MUTINY_GREEN, MUTINY_RED, darken, format_amount, format_timestamp, lighten, link, map_icon,
+    MUTINY_GREEN, MUTINY_RED, darken, format_timestamp, format_unit_amount, lighten, link, map_icon,
 };
 use crate::Message;
 use harbor_client::db_models::PaymentStatus;
@@ -15,6 +15,7 @@ pub fn h_transaction_item(item: &TransactionItem, is_selected: bool) -> Element<
     let TransactionItem {
         kind,
         amount,
+        unit,
         fee_msats: _,
         direction,
         timestamp,
@@ -22,10 +23,15 @@ pub fn h_transaction_item(item: &TransactionItem, is_selected: bool) -> Element<
         status,
         txid: _,
         preimage: _,
+        token: _,
+        reclaimable: _,
     } = item;
     let kind_icon = match kind {
         TransactionItemKind::Lightning => map_icon(super::SvgIcon::Bolt, 24., 24.),
         TransactionItemKind::Onchain => map_icon(super::SvgIcon::Chain, 24., 24.),
+        TransactionItemKind::Ecash | TransactionItemKind::Consolidation => {
+            map_icon(super::SvgIcon::Ecash, 24., 24.)
+        }
     };
 
     let direction_icon = match direction {
@@ -45,9 +51,14 @@ pub fn h_transaction_item(item: &TransactionItem, is_selected: bool) -> Element<
         && matches!(direction, TransactionDirection::Incoming)
         && matches!(status, PaymentStatus::WaitingConfirmation)
     {
-        format!("{} (Pending)", format_amount(*amount))
+        format!("{} (Pending)", format_unit_amount(*amount, unit))
+    } else if matches!(kind, TransactionItemKind::Ecash) && matches!(status, PaymentStatus::Pending)
+    {
+        format!("{} (Unclaimed)", format_unit_amount(*amount, unit))
+    } else if matches!(kind, TransactionItemKind::Consolidation) {
+        format!("{} (Consolidation fee)", format_unit_amount(*amount, unit))
     } else {
-        format_amount(*amount)
+        format_unit_amount(*amount, unit)
     };
 
     let formatted_amount = text(amount_str).size(24);
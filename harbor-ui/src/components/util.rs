@@ -1,4 +1,5 @@
 use chrono::{DateTime, Local};
+use harbor_client::cdk::nuts::CurrencyUnit;
 use iced::Color;
 use palette::{FromColor, Hsl, rgb::Rgb};
 
@@ -41,13 +42,10 @@ pub fn format_timestamp(timestamp: &u64) -> String {
     format!("{}", date_time.format("%m/%d/%Y, %l:%M %P"))
 }
 
-pub fn format_amount(amount: u64) -> String {
-    if amount == 1 {
-        return "1 sat".to_string();
-    }
+fn with_separators(amount: u64) -> String {
     // https://stackoverflow.com/questions/26998485/is-it-possible-to-print-a-number-formatted-with-thousand-separator-in-rust
     // Rust is a real baby about doing useful things
-    let num = amount
+    amount
         .to_string()
         .as_bytes()
         .rchunks(3)
@@ -55,9 +53,25 @@ pub fn format_amount(amount: u64) -> String {
         .map(std::str::from_utf8)
         .collect::<Result<Vec<&str>, _>>()
         .unwrap()
-        .join(",");
+        .join(",")
+}
 
-    format!("{num} sats")
+pub fn format_amount(amount: u64) -> String {
+    if amount == 1 {
+        return "1 sat".to_string();
+    }
+
+    format!("{} sats", with_separators(amount))
+}
+
+/// Formats an amount given in the base amount of `unit`, e.g. cents for usd
+pub fn format_unit_amount(amount: u64, unit: &CurrencyUnit) -> String {
+    match unit {
+        CurrencyUnit::Sat => format_amount(amount),
+        CurrencyUnit::Usd => format!("${}.{:02}", with_separators(amount / 100), amount % 100),
+        CurrencyUnit::Eur => format!("€{}.{:02}", with_separators(amount / 100), amount % 100),
+        unit => format!("{} {unit}", with_separators(amount)),
+    }
 }
 
 pub fn truncate_text(input: &str, max_len: usize, center: bool) -> String {
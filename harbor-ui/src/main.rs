@@ -31,20 +31,25 @@
 use crate::bridge::run_core;
 use crate::components::confirm_modal::{BasicModalState, ConfirmModalState};
 use crate::components::focus_input_id;
-use crate::components::{Toast, ToastManager, ToastStatus};
+use crate::components::{Toast, ToastManager, ToastStatus, format_timestamp, format_unit_amount};
 use crate::config::{Config, write_config};
 use components::{MUTINY_GREEN, MUTINY_RED};
 use harbor_client::Bolt11Invoice;
 use harbor_client::bip39::Mnemonic;
 use harbor_client::bitcoin::address::NetworkUnchecked;
 use harbor_client::bitcoin::{Address, Network};
-use harbor_client::db_models::MintItem;
+use harbor_client::cdk::nuts::nut18::PaymentRequest;
+use harbor_client::cdk::nuts::{CurrencyUnit, PublicKey, Token};
 use harbor_client::db_models::transaction_item::TransactionItem;
+use harbor_client::db_models::{DleqAudit, MintItem};
 use harbor_client::fedimint_core::Amount;
+use harbor_client::fedimint_core::config::FederationId;
 use harbor_client::fedimint_core::core::ModuleKind;
+use harbor_client::fedimint_core::invite_code::InviteCode;
+use harbor_client::mint_discovery::DiscoveredMint;
 use harbor_client::{
-    CoreUIMsg, CoreUIMsgPacket, MintConnectionInfo, MintIdentifier, ReceiveSuccessMsg,
-    SendSuccessMsg, UICoreMsg, data_dir,
+    CoreUIMsg, CoreUIMsgPacket, LightningSendQuote, MintConnectionInfo, MintIdentifier, OOBNotes,
+    ReceiveSuccessMsg, SendSuccessMsg, UICoreMsg, data_dir,
 };
 use iced::Font;
 use iced::Subscription;
@@ -144,6 +149,8 @@ pub enum ReceiveMethod {
     #[default]
     Lightning,
     OnChain,
+    Ecash,
+    Request,
 }
 
 #[derive(Default, Debug, Clone, PartialEq)]
@@ -165,6 +172,9 @@ pub enum SendDestination {
     Invoice(Bolt11Invoice),
     LnUrl(LnUrl),
     Address(Address<NetworkUnchecked>),
+    CashuToken(Token),
+    FedimintNotes(OOBNotes),
+    PaymentRequest(PaymentRequest),
 }
 
 #[derive(Debug, Clone)]
@@ -178,14 +188,20 @@ pub enum Message {
     SetConfirmModal(Option<ConfirmModalState>),
     SetBasicModal(Option<BasicModalState>),
     ReceiveAmountChanged(String),
+    ReceiveTokenInputChanged(String),
+    ReceiveMemoChanged(String),
+    ReceiveLnurlChanged(String),
     ReceiveStateReset,
     SendDestInputChanged(String),
     SendAmountInputChanged(String),
+    SendLockPubkeyChanged(String),
+    SendUnlockDaysChanged(String),
     SetIsMax(bool),
     SendStateReset,
     TransferStateReset,
     PasswordInputChanged(String),
     SeedInputChanged(String),
+    RestoreInviteChanged(String),
     MintInviteCodeInputChanged(String),
     DonateAmountChanged(String),
     CopyToClipboard(String),
@@ -194,6 +210,7 @@ pub enum Message {
     AddToast(Toast),
     CloseToast(usize),
     CancelAddFederation,
+    CancelCashuLogin,
     SetTransferFrom(String),
     SetTransferTo(String),
     TransferAmountInputChanged(String),
@@ -207,20 +224,35 @@ pub enum Message {
     // Config commands
     ChangeNetwork(Network),
     SetTorEnabled(bool),
+    SetConsolidationEnabled(bool),
+    SetNostrEnabled(bool),
+    LnurlAuthInputChanged(String),
+    LnurlAuth(LnUrl),
     // Async commands we fire from the UI to core
     Noop,
     Send(SendDestination),
+    ConfirmSendLightning,
+    SendEcash,
     Transfer,
     GenerateInvoice,
+    WithdrawLnurl(LnUrl),
     GenerateAddress,
+    CreatePaymentRequest,
+    RedeemPaymentRequestPayment,
+    RedeemEcash(Token),
+    RedeemFedimintNotes(OOBNotes),
+    ReclaimEcash(String),
     Unlock(String),
     Init {
         password: String,
         seed: Option<String>,
     },
     AddMint(MintConnectionInfo),
+    AddCashuUnitChanged(CurrencyUnit),
     RejoinMint(MintIdentifier),
     PeekMint(MintConnectionInfo),
+    DiscoverMints,
+    PreviewDiscoveredMint(MintConnectionInfo),
     RemoveMint(MintIdentifier),
     ChangeMint(MintIdentifier),
     Donate,
@@ -254,12 +286,16 @@ pub struct HarborWallet {
     selected_transaction: Option<TransactionItem>,
     mint_list: Vec<MintItem>,
     active_mint: Option<MintIdentifier>,
+    /// Progress of federations recovering from backup, per module
+    recovery_progress: HashMap<FederationId, HashMap<ModuleKind, (u32, u32)>>,
     // Modal
     confirm_modal: Option<ConfirmModalState>,
     basic_modal: Option<BasicModalState>,
     // Welcome screen
     init_status: WelcomeStatus,
     seed_input_str: String,
+    /// Federation to restore the rest of the wallet from after init
+    restore_invite_str: String,
     init_failure_reason: Option<String>,
     // Lock screen
     password_input_str: String,
@@ -271,28 +307,52 @@ pub struct HarborWallet {
     send_success_msg: Option<SendSuccessMsg>,
     send_dest_input_str: String,
     send_amount_input_str: String,
+    send_lock_pubkey_str: String,
+    send_unlock_days_str: String,
     is_max: bool,
     input_has_amount: bool,
     current_send_id: Option<Uuid>,
+    // Invoice waiting on the user to confirm its fees, with the mint it's sent from
+    pending_send_invoice: Option<(MintIdentifier, Bolt11Invoice)>,
+    // Melt quote the user is confirming the fees of
+    pending_send_quote: Option<LightningSendQuote>,
+    send_ecash_token: Option<String>,
+    send_qr_data: Option<Data>,
     current_receive_id: Option<Uuid>,
     current_transfer_id: Option<Uuid>,
+    current_reclaim_id: Option<Uuid>,
     // Receive
     receive_failure_reason: Option<String>,
     receive_success_msg: Option<ReceiveSuccessMsg>,
     receive_status: ReceiveStatus,
     receive_amount_str: String,
+    receive_token_str: String,
+    receive_memo_str: String,
+    /// An LNURL-withdraw to pull funds from, e.g. a voucher
+    receive_lnurl_str: String,
+    // Id and encoded request of the payment request we're waiting on
+    receive_payment_request: Option<(String, String)>,
     receive_invoice: Option<Bolt11Invoice>,
     receive_address: Option<Address>,
     receive_qr_data: Option<Data>,
     receive_method: ReceiveMethod,
+    cashu_pubkey: Option<PublicKey>,
     // Mints
     peek_federation_item: Option<MintItem>,
+    // Units of the previewed cashu mint we don't have a wallet for yet
+    peek_cashu_units: Vec<CurrencyUnit>,
+    add_cashu_unit: Option<CurrencyUnit>,
     mint_invite_code_str: String,
     peek_status: PeekStatus,
+    /// Mints found on nostr for the Add Mint directory
+    discovered_mints: Vec<DiscoveredMint>,
+    current_discover_id: Option<Uuid>,
     add_federation_status: AddFederationStatus,
     current_peek_id: Option<Uuid>,
     current_add_id: Option<Uuid>,
     current_rejoin_id: Option<MintIdentifier>,
+    // Notes waiting on us to join their federation
+    pending_fedimint_notes: Option<OOBNotes>,
     // Transfer
     transfer_from_federation_selection: Option<String>,
     transfer_to_federation_selection: Option<String>,
@@ -304,6 +364,11 @@ pub struct HarborWallet {
     settings_show_seed_words: bool,
     seed_words: Option<String>,
     tor_enabled: bool,
+    consolidation_enabled: bool,
+    nostr_enabled: bool,
+    /// An LNURL-auth challenge to log into a service with
+    lnurl_auth_str: String,
+    current_lnurl_auth_id: Option<Uuid>,
     // Onboarding
     show_add_a_mint_cta: bool,
     has_navigated_to_mints: bool,
@@ -319,6 +384,15 @@ impl HarborWallet {
             .and_then(|id| self.mint_list.iter().find(|f| &f.id == id))
     }
 
+    /// How far along the mint's recovery is, from 0 to 1
+    fn recovery_progress(&self, id: &MintIdentifier) -> Option<f64> {
+        let modules = self.recovery_progress.get(&id.federation_id()?)?;
+        let (complete, total) = modules.values().fold((0, 0), |(c, t), (complete, total)| {
+            (c + complete, t + total)
+        });
+        (total > 0).then(|| f64::from(complete) / f64::from(total))
+    }
+
     fn next_federation(&self, name: &str) -> MintItem {
         let fed = self
             .mint_list
@@ -354,6 +428,8 @@ impl HarborWallet {
 
     fn clear_add_federation_state(&mut self) {
         self.peek_federation_item = None;
+        self.peek_cashu_units = vec![];
+        self.add_cashu_unit = None;
         self.mint_invite_code_str = String::new();
         self.peek_status = PeekStatus::Idle;
         self.add_federation_status = AddFederationStatus::Idle;
@@ -366,6 +442,10 @@ impl HarborWallet {
         self.receive_failure_reason = None;
         self.receive_status = ReceiveStatus::Idle;
         self.receive_amount_str = String::new();
+        self.receive_token_str = String::new();
+        self.receive_memo_str = String::new();
+        self.receive_lnurl_str = String::new();
+        self.receive_payment_request = None;
         self.receive_invoice = None;
         self.receive_address = None;
         self.receive_qr_data = None;
@@ -379,10 +459,16 @@ impl HarborWallet {
         self.send_status = SendStatus::Idle;
         self.send_dest_input_str = String::new();
         self.send_amount_input_str = String::new();
+        self.send_lock_pubkey_str = String::new();
+        self.send_unlock_days_str = String::new();
         self.is_max = false;
         self.input_has_amount = false;
         self.confirm_modal = None;
         self.current_send_id = None;
+        self.pending_send_invoice = None;
+        self.pending_send_quote = None;
+        self.send_ecash_token = None;
+        self.send_qr_data = None;
         // We dont' clear the success msg so the history screen can show the most recent
         // transaction
     }
@@ -510,10 +596,28 @@ impl HarborWallet {
                 self.receive_amount_str = amount;
                 Task::none()
             }
+            Message::ReceiveTokenInputChanged(input) => {
+                self.receive_token_str = input;
+                Task::none()
+            }
+            Message::ReceiveMemoChanged(input) => {
+                self.receive_memo_str = input;
+                Task::none()
+            }
+            Message::ReceiveLnurlChanged(input) => {
+                self.receive_lnurl_str = input;
+                Task::none()
+            }
             Message::SendDestInputChanged(input) => {
                 let msats = Bolt11Invoice::from_str(&input)
                     .ok()
-                    .and_then(|i| i.amount_milli_satoshis());
+                    .and_then(|i| i.amount_milli_satoshis())
+                    .or_else(|| {
+                        PaymentRequest::from_str(input.trim())
+                            .ok()
+                            .and_then(|r| r.amount)
+                            .map(|a| u64::from(a) * 1_000)
+                    });
                 self.input_has_amount = msats.is_some();
                 if let Some(amt) = msats {
                     self.send_amount_input_str = (amt / 1_000).to_string();
@@ -527,6 +631,14 @@ impl HarborWallet {
                 self.send_amount_input_str = input;
                 Task::none()
             }
+            Message::SendLockPubkeyChanged(input) => {
+                self.send_lock_pubkey_str = input;
+                Task::none()
+            }
+            Message::SendUnlockDaysChanged(input) => {
+                self.send_unlock_days_str = input;
+                Task::none()
+            }
             Message::SetIsMax(is_max) => {
                 self.is_max = is_max;
                 Task::none()
@@ -539,6 +651,10 @@ impl HarborWallet {
                 self.seed_input_str = input;
                 Task::none()
             }
+            Message::RestoreInviteChanged(input) => {
+                self.restore_invite_str = input;
+                Task::none()
+            }
             Message::MintInviteCodeInputChanged(input) => {
                 self.mint_invite_code_str = input;
                 Task::none()
@@ -581,6 +697,7 @@ impl HarborWallet {
             }
             Message::CancelAddFederation => {
                 self.clear_add_federation_state();
+                self.pending_fedimint_notes = None;
                 self.active_route = Route::Mints(routes::MintSubroute::List);
 
                 Task::none()
@@ -639,8 +756,10 @@ impl HarborWallet {
 
                     match destination {
                         SendDestination::Invoice(invoice) => {
-                            let (id, task) =
-                                self.send_from_ui(UICoreMsg::SendLightning { mint, invoice });
+                            // quote the fees first so they can be confirmed before we pay
+                            self.pending_send_invoice = Some((mint.clone(), invoice.clone()));
+                            let (id, task) = self
+                                .send_from_ui(UICoreMsg::EstimateLightningSend { mint, invoice });
                             self.current_send_id = Some(id);
                             task
                         }
@@ -691,9 +810,117 @@ impl HarborWallet {
                             self.current_send_id = Some(id);
                             task
                         }
+                        // Pasted tokens are received, not sent
+                        SendDestination::CashuToken(token) => {
+                            Task::done(Message::RedeemEcash(token))
+                        }
+                        SendDestination::FedimintNotes(notes) => {
+                            Task::done(Message::RedeemFedimintNotes(notes))
+                        }
+                        SendDestination::PaymentRequest(request) => {
+                            let amount = if request.amount.is_some() {
+                                None
+                            } else {
+                                match self.send_amount_input_str.parse::<u64>() {
+                                    Ok(amount) => Some(amount),
+                                    Err(e) => {
+                                        error!("Error parsing amount: {e}");
+                                        self.send_failure_reason = Some(e.to_string());
+                                        return Task::none();
+                                    }
+                                }
+                            };
+                            let (id, task) = self.send_from_ui(UICoreMsg::PayPaymentRequest {
+                                mint,
+                                request,
+                                amount,
+                            });
+                            self.current_send_id = Some(id);
+                            task
+                        }
                     }
                 }
             },
+            Message::ConfirmSendLightning => {
+                self.confirm_modal = None;
+                let Some((mint, invoice)) = self.pending_send_invoice.take() else {
+                    return Task::none();
+                };
+                // a quote can only be paid from the mint that gave it
+                let (mint, quote_id) = match self.pending_send_quote.take() {
+                    Some(quote) => (quote.mint, Some(quote.quote_id)),
+                    None => (mint, None),
+                };
+                let (id, task) = self.send_from_ui(UICoreMsg::SendLightning {
+                    mint,
+                    invoice,
+                    quote_id,
+                });
+                self.current_send_id = Some(id);
+                task
+            }
+            Message::SendEcash => match self.send_status {
+                SendStatus::Sending => Task::none(),
+                SendStatus::Idle => {
+                    self.send_failure_reason = None;
+                    let Some(mint) = self.active_mint.clone() else {
+                        error!("No active mint");
+                        return Task::done(Message::AddToast(Toast {
+                            title: "Cannot send".to_string(),
+                            body: Some("No active mint selected".to_string()),
+                            status: ToastStatus::Bad,
+                        }));
+                    };
+
+                    let amount = match self.send_amount_input_str.parse::<u64>() {
+                        Ok(amount) => amount,
+                        Err(e) => {
+                            error!("Error parsing amount: {e}");
+                            self.send_failure_reason = Some(e.to_string());
+                            return Task::none();
+                        }
+                    };
+
+                    let lock_to = if self.send_lock_pubkey_str.is_empty() {
+                        None
+                    } else {
+                        match PublicKey::from_str(self.send_lock_pubkey_str.trim()) {
+                            Ok(pubkey) => Some(pubkey),
+                            Err(e) => {
+                                error!("Error parsing pubkey: {e}");
+                                self.send_failure_reason = Some(e.to_string());
+                                return Task::none();
+                            }
+                        }
+                    };
+
+                    let unlock_at = if self.send_unlock_days_str.is_empty() {
+                        None
+                    } else {
+                        match self.send_unlock_days_str.trim().parse::<u32>() {
+                            Ok(days) => {
+                                let unlock =
+                                    chrono::Utc::now() + chrono::Duration::days(days.into());
+                                Some(unlock.timestamp() as u64)
+                            }
+                            Err(e) => {
+                                error!("Error parsing unlock days: {e}");
+                                self.send_failure_reason = Some(e.to_string());
+                                return Task::none();
+                            }
+                        }
+                    };
+
+                    let (id, task) = self.send_from_ui(UICoreMsg::SendEcash {
+                        mint,
+                        amount: Amount::from_sats(amount),
+                        lock_to,
+                        unlock_at,
+                    });
+                    self.current_send_id = Some(id);
+                    task
+                }
+            },
             Message::Transfer => {
                 let from = if let Some(name) = &self.transfer_from_federation_selection {
                     self.mint_list
@@ -780,6 +1007,174 @@ impl HarborWallet {
                     }
                 }
             },
+            Message::WithdrawLnurl(lnurl) => match self.receive_status {
+                ReceiveStatus::Generating => Task::none(),
+                _ => {
+                    let Some(mint) = self.active_mint.clone() else {
+                        return Task::done(Message::AddToast(Toast {
+                            title: "Failed to withdraw".to_string(),
+                            body: Some("No active mint selected".to_string()),
+                            status: ToastStatus::Bad,
+                        }));
+                    };
+                    // without an amount we take everything the service allows
+                    let amount = if self.receive_amount_str.is_empty() {
+                        None
+                    } else {
+                        match self.receive_amount_str.parse::<u64>() {
+                            Ok(amount) => Some(Amount::from_sats(amount)),
+                            Err(e) => {
+                                return Task::done(Message::AddToast(Toast {
+                                    title: "Failed to withdraw".to_string(),
+                                    body: Some(e.to_string()),
+                                    status: ToastStatus::Bad,
+                                }));
+                            }
+                        }
+                    };
+                    let (id, task) = self.send_from_ui(UICoreMsg::ReceiveLnurlWithdraw {
+                        mint,
+                        lnurl,
+                        amount,
+                    });
+                    self.current_receive_id = Some(id);
+                    self.receive_failure_reason = None;
+                    task
+                }
+            },
+            Message::CreatePaymentRequest => match self.receive_status {
+                ReceiveStatus::Generating => Task::none(),
+                _ => {
+                    let Some(mint) = self.active_mint.clone() else {
+                        return Task::done(Message::AddToast(Toast {
+                            title: "Failed to create request".to_string(),
+                            body: Some("No active mint selected".to_string()),
+                            status: ToastStatus::Bad,
+                        }));
+                    };
+                    // An empty amount lets the payer choose
+                    let amount = if self.receive_amount_str.is_empty() {
+                        None
+                    } else {
+                        match self.receive_amount_str.parse::<u64>() {
+                            Ok(amount) => Some(amount),
+                            Err(e) => {
+                                self.receive_amount_str = String::new();
+                                error!("Error parsing amount: {e}");
+                                return Task::done(Message::AddToast(Toast {
+                                    title: "Failed to create request".to_string(),
+                                    body: Some(e.to_string()),
+                                    status: ToastStatus::Bad,
+                                }));
+                            }
+                        }
+                    };
+                    let description =
+                        Some(self.receive_memo_str.trim().to_string()).filter(|m| !m.is_empty());
+                    let (id, task) = self.send_from_ui(UICoreMsg::CreatePaymentRequest {
+                        mint,
+                        amount,
+                        description,
+                    });
+                    self.current_receive_id = Some(id);
+                    self.receive_failure_reason = None;
+                    task
+                }
+            },
+            Message::RedeemPaymentRequestPayment => match self.receive_status {
+                ReceiveStatus::Generating => Task::none(),
+                _ => {
+                    let Some((request_id, _)) = self.receive_payment_request.clone() else {
+                        return Task::none();
+                    };
+                    let (id, task) = self.send_from_ui(UICoreMsg::ReceivePaymentRequestPayment {
+                        id: request_id,
+                        payment: self.receive_token_str.clone(),
+                    });
+                    self.current_receive_id = Some(id);
+                    self.receive_failure_reason = None;
+                    task
+                }
+            },
+            Message::RedeemEcash(token) => match self.receive_status {
+                ReceiveStatus::Generating => Task::none(),
+                _ => {
+                    let token_mint = token.mint_url().ok();
+                    let joined = self
+                        .mint_list
+                        .iter()
+                        .any(|m| m.active && token_mint.is_some() && m.id.mint_url() == token_mint);
+
+                    // Tokens from mints we don't use get swapped into the active mint
+                    let msg = if joined {
+                        UICoreMsg::ReceiveCashuToken(token)
+                    } else if let Some(mint) = self.active_mint.clone() {
+                        UICoreMsg::SwapInCashuToken { token, mint }
+                    } else {
+                        return Task::done(Message::AddToast(Toast {
+                            title: "Cannot redeem token".to_string(),
+                            body: Some("No active mint selected".to_string()),
+                            status: ToastStatus::Bad,
+                        }));
+                    };
+                    let (id, task) = self.send_from_ui(msg);
+                    self.current_receive_id = Some(id);
+                    self.receive_failure_reason = None;
+                    task
+                }
+            },
+            Message::RedeemFedimintNotes(notes) => match self.receive_status {
+                ReceiveStatus::Generating => Task::none(),
+                _ => {
+                    let prefix = notes.federation_id_prefix();
+                    let joined = self.mint_list.iter().any(|m| {
+                        m.active
+                            && m.id
+                                .federation_id()
+                                .is_some_and(|f| f.to_prefix() == prefix)
+                    });
+                    if joined {
+                        let (id, task) = self.send_from_ui(UICoreMsg::ReceiveFedimintNotes(notes));
+                        self.current_receive_id = Some(id);
+                        self.receive_failure_reason = None;
+                        return task;
+                    }
+
+                    // Offer to join the federation first, we redeem once it's added
+                    let Some(invite_code) = notes.federation_invite() else {
+                        return Task::done(Message::AddToast(Toast {
+                            title: "Cannot redeem notes".to_string(),
+                            body: Some(
+                                "Notes are from an unknown federation and have no invite code"
+                                    .to_string(),
+                            ),
+                            status: ToastStatus::Bad,
+                        }));
+                    };
+                    self.pending_fedimint_notes = Some(notes);
+                    self.mint_invite_code_str = invite_code.to_string();
+                    self.active_route = Route::Mints(routes::MintSubroute::Add);
+                    Task::batch([
+                        Task::done(Message::PeekMint(MintConnectionInfo::Fedimint(invite_code))),
+                        Task::done(Message::AddToast(Toast {
+                            title: "Join federation to redeem".to_string(),
+                            body: Some(
+                                "These notes are from a federation you haven't joined yet"
+                                    .to_string(),
+                            ),
+                            status: ToastStatus::Neutral,
+                        })),
+                    ])
+                }
+            },
+            Message::ReclaimEcash(token) => {
+                if self.current_reclaim_id.is_some() {
+                    return Task::none();
+                }
+                let (id, task) = self.send_from_ui(UICoreMsg::ReclaimEcash(token));
+                self.current_reclaim_id = Some(id);
+                task
+            }
             Message::GenerateAddress => match self.receive_status {
                 ReceiveStatus::Generating => Task::none(),
                 _ => {
@@ -866,6 +1261,17 @@ impl HarborWallet {
                             },
                         };
 
+                        let restore_invite = self.restore_invite_str.trim();
+                        if !restore_invite.is_empty()
+                            && InviteCode::from_str(restore_invite).is_err()
+                        {
+                            return Task::done(Message::AddToast(Toast {
+                                title: "Error".to_string(),
+                                body: Some("Invalid federation invite code".to_string()),
+                                status: ToastStatus::Bad,
+                            }));
+                        }
+
                         let (_, task) = self.send_from_ui(UICoreMsg::Init { password, seed });
                         task
                     }
@@ -878,14 +1284,37 @@ impl HarborWallet {
                     MintConnectionInfo::Fedimint(invite_code) => {
                         self.send_from_ui(UICoreMsg::AddFederation(invite_code))
                     }
-                    MintConnectionInfo::Cashu(mint_url) => {
-                        self.send_from_ui(UICoreMsg::AddCashuMint(mint_url))
+                    MintConnectionInfo::Cashu(url) => {
+                        let unit = self.add_cashu_unit.clone().unwrap_or(CurrencyUnit::Sat);
+                        self.send_from_ui(UICoreMsg::AddCashuMint { url, unit })
                     }
                 };
 
                 self.current_add_id = Some(id);
                 task
             }
+            Message::AddCashuUnitChanged(unit) => {
+                if let Some(item) = self.peek_federation_item.as_mut() {
+                    if let MintIdentifier::Cashu(url, _) = &item.id {
+                        item.id = MintIdentifier::Cashu(url.clone(), unit.clone());
+                        item.unit = unit.clone();
+                    }
+                }
+                self.add_cashu_unit = Some(unit);
+                Task::none()
+            }
+            Message::DiscoverMints => {
+                if self.current_discover_id.is_some() {
+                    return Task::none();
+                }
+                let (id, task) = self.send_from_ui(UICoreMsg::DiscoverMints);
+                self.current_discover_id = Some(id);
+                task
+            }
+            Message::PreviewDiscoveredMint(connection_info) => {
+                self.mint_invite_code_str = connection_info.to_string();
+                Task::done(Message::PeekMint(connection_info))
+            }
             Message::PeekMint(connection_info) => {
                 let (id, task) = match connection_info {
                     MintConnectionInfo::Fedimint(invite_code) => {
@@ -903,18 +1332,8 @@ impl HarborWallet {
                         }
                         self.send_from_ui(UICoreMsg::GetFederationInfo(invite_code))
                     }
+                    // we only know which units are left to add once we have the mint's info
                     MintConnectionInfo::Cashu(mint_url) => {
-                        if self
-                            .mint_list
-                            .iter()
-                            .any(|m| m.active && m.id.mint_url().is_some_and(|u| u == mint_url))
-                        {
-                            return Task::done(Message::AddToast(Toast {
-                                title: "Mint already added".to_string(),
-                                body: None,
-                                status: ToastStatus::Bad,
-                            }));
-                        }
                         self.send_from_ui(UICoreMsg::GetCashuMintInfo(mint_url))
                     }
                 };
@@ -987,6 +1406,32 @@ impl HarborWallet {
                 let (_, task) = self.send_from_ui(UICoreMsg::SetTorEnabled(enabled));
                 task
             }
+            Message::LnurlAuthInputChanged(input) => {
+                self.lnurl_auth_str = input;
+                Task::none()
+            }
+            Message::LnurlAuth(lnurl) => {
+                self.confirm_modal = None;
+                if self.current_lnurl_auth_id.is_some() {
+                    return Task::none();
+                }
+                let (id, task) = self.send_from_ui(UICoreMsg::LnurlAuth(lnurl));
+                self.current_lnurl_auth_id = Some(id);
+                task
+            }
+            Message::SetConsolidationEnabled(enabled) => {
+                let (_, task) = self.send_from_ui(UICoreMsg::SetConsolidationEnabled(enabled));
+                task
+            }
+            Message::SetNostrEnabled(enabled) => {
+                let (_, task) = self.send_from_ui(UICoreMsg::SetNostrEnabled(enabled));
+                task
+            }
+            Message::CancelCashuLogin => {
+                self.confirm_modal = None;
+                let (_, task) = self.send_from_ui(UICoreMsg::CancelCashuLogin);
+                task
+            }
             Message::SelectTransaction(transaction) => {
                 self.selected_transaction = transaction;
                 Task::none()
@@ -1027,6 +1472,12 @@ impl HarborWallet {
                     // Toast success
                     if params == SendSuccessMsg::Transfer {
                         Task::none()
+                    } else if params == SendSuccessMsg::PaymentRequest {
+                        Task::done(Message::AddToast(Toast {
+                            title: "Payment request paid".to_string(),
+                            body: None,
+                            status: ToastStatus::Good,
+                        }))
                     } else {
                         Task::done(Message::AddToast(Toast {
                             title: "Payment sent".to_string(),
@@ -1047,6 +1498,138 @@ impl HarborWallet {
                         status: ToastStatus::Bad,
                     }))
                 }
+                CoreUIMsg::SendEstimate(estimate) => {
+                    if self.current_send_id != msg.id {
+                        return Task::none();
+                    }
+                    let Some(quote) = estimate else {
+                        return Task::done(Message::ConfirmSendLightning);
+                    };
+                    let cost = quote.cost;
+                    let unit = match &quote.mint {
+                        MintIdentifier::Cashu(_, unit) => unit.clone(),
+                        MintIdentifier::Fedimint(_) => CurrencyUnit::Sat,
+                    };
+                    self.pending_send_quote = Some(quote);
+                    self.confirm_modal = Some(ConfirmModalState {
+                        title: "Confirm Payment".to_string(),
+                        description: format!(
+                            "Amount: {}\nLightning fee reserve: {}\nMint input fee: {}\nTotal: {}\n\nAny unused fee reserve is returned as change.",
+                            format_unit_amount(cost.amount, &unit),
+                            format_unit_amount(cost.fee_reserve, &unit),
+                            format_unit_amount(cost.input_fee, &unit),
+                            format_unit_amount(cost.total(), &unit),
+                        ),
+                        confirm_action: Box::new(Message::ConfirmSendLightning),
+                        cancel_action: Box::new(Message::SetConfirmModal(None)),
+                        confirm_button_text: "Pay".to_string(),
+                    });
+                    Task::none()
+                }
+                CoreUIMsg::SendEcashGenerated(token) => {
+                    if self.current_send_id == msg.id {
+                        self.send_status = SendStatus::Idle;
+                        self.current_send_id = None;
+                        // Large tokens may not fit in a QR code, the text can still be copied
+                        self.send_qr_data = Data::with_error_correction(
+                            token.clone(),
+                            iced::widget::qr_code::ErrorCorrection::Low,
+                        )
+                        .ok();
+                        self.send_ecash_token = Some(token);
+                    }
+                    Task::done(Message::AddToast(Toast {
+                        title: "Ecash token created".to_string(),
+                        body: None,
+                        status: ToastStatus::Good,
+                    }))
+                }
+                CoreUIMsg::PaymentRequestCreated { id, request } => {
+                    if self.current_receive_id == msg.id {
+                        self.current_receive_id = None;
+                        self.receive_status = ReceiveStatus::WaitingToReceive;
+                        self.receive_qr_data = Data::with_error_correction(
+                            request.clone(),
+                            iced::widget::qr_code::ErrorCorrection::Low,
+                        )
+                        .ok();
+                        self.receive_payment_request = Some((id, request));
+                    }
+                    Task::none()
+                }
+                CoreUIMsg::PaymentRequestPaid(id) => {
+                    // Paid over nostr while we were showing the request
+                    if self
+                        .receive_payment_request
+                        .as_ref()
+                        .is_some_and(|(request_id, _)| *request_id == id)
+                    {
+                        self.active_route = Route::History;
+                        self.clear_receive_state();
+                    }
+                    Task::none()
+                }
+                CoreUIMsg::EcashReclaimed => {
+                    if self.current_reclaim_id == msg.id {
+                        self.current_reclaim_id = None;
+                        self.selected_transaction = None;
+                    }
+                    Task::done(Message::AddToast(Toast {
+                        title: "Ecash reclaimed".to_string(),
+                        body: None,
+                        status: ToastStatus::Good,
+                    }))
+                }
+                CoreUIMsg::TimelockExpiring { token: _, locktime } => {
+                    Task::done(Message::AddToast(Toast {
+                        title: "Timelocked ecash unlocking soon".to_string(),
+                        body: Some(format!(
+                            "The recipient can claim it after {}. Reclaim it before then if you want to keep it.",
+                            format_timestamp(&locktime)
+                        )),
+                        status: ToastStatus::Neutral,
+                    }))
+                }
+                CoreUIMsg::CashuLoginRequired {
+                    mint_url,
+                    verification_uri,
+                    user_code,
+                } => {
+                    self.confirm_modal = Some(ConfirmModalState {
+                        title: "Log in to mint".to_string(),
+                        description: format!(
+                            "{mint_url} requires an account. Open {verification_uri} and confirm the code {user_code}, we continue once you approve the login."
+                        ),
+                        confirm_action: Box::new(Message::OpenUrl(verification_uri)),
+                        cancel_action: Box::new(Message::CancelCashuLogin),
+                        confirm_button_text: "Open Login Page".to_string(),
+                    });
+                    Task::none()
+                }
+                CoreUIMsg::KeysetSwapFailed { id, reason } => {
+                    let name = self
+                        .mint_list
+                        .iter()
+                        .find(|m| m.id == id)
+                        .map_or_else(|| "a mint".to_string(), |m| m.name.clone());
+                    Task::done(Message::AddToast(Toast {
+                        title: format!("Could not refresh ecash at {name}"),
+                        body: Some(format!(
+                            "The mint rotated its keys and moving your ecash failed, it may become unspendable once the old keys are retired: {reason}"
+                        )),
+                        status: ToastStatus::Bad,
+                    }))
+                }
+                CoreUIMsg::EcashReclaimFailed(reason) => {
+                    if self.current_reclaim_id == msg.id {
+                        self.current_reclaim_id = None;
+                    }
+                    Task::done(Message::AddToast(Toast {
+                        title: "Failed to reclaim ecash".to_string(),
+                        body: Some(reason),
+                        status: ToastStatus::Bad,
+                    }))
+                }
                 CoreUIMsg::ReceiveSuccess(params) => {
                     info!("Receive success: {params:?}");
                     if self.current_receive_id == msg.id {
@@ -1056,6 +1639,10 @@ impl HarborWallet {
                         // Navigate to the history screen
                         self.active_route = Route::History;
                         self.clear_receive_state();
+                        // Tokens can also be redeemed from the send screen
+                        if params == ReceiveSuccessMsg::Ecash {
+                            self.clear_send_state();
+                        }
                     } else if self.current_transfer_id == msg.id && msg.id.is_some() {
                         self.current_transfer_id = None;
 
@@ -1082,7 +1669,12 @@ impl HarborWallet {
                         self.receive_status = ReceiveStatus::Idle;
                         self.receive_failure_reason = Some(reason.clone());
                         self.current_receive_id = None;
-                        self.clear_receive_state();
+                        // Keep the payment request we're waiting on so the payer can retry
+                        if self.receive_payment_request.is_some() {
+                            self.receive_token_str = String::new();
+                        } else {
+                            self.clear_receive_state();
+                        }
                     }
                     Task::done(Message::AddToast(Toast {
                         title: "Failed to receive".to_string(),
@@ -1159,7 +1751,35 @@ impl HarborWallet {
                     id,
                     config,
                     metadata,
+                    units,
                 } => {
+                    let units: Vec<CurrencyUnit> = units
+                        .into_iter()
+                        .filter(|unit| {
+                            !self.mint_list.iter().any(|m| {
+                                m.active
+                                    && m.id.mint_url().is_some()
+                                    && m.id.mint_url() == id.mint_url()
+                                    && m.unit == *unit
+                            })
+                        })
+                        .collect();
+                    if id.mint_url().is_some() && units.is_empty() {
+                        self.peek_status = PeekStatus::Idle;
+                        return Task::done(Message::AddToast(Toast {
+                            title: "Mint already added".to_string(),
+                            body: None,
+                            status: ToastStatus::Bad,
+                        }));
+                    }
+
+                    // preview the unit that will be added, not one that's already joined
+                    let unit = units.first().cloned().unwrap_or(CurrencyUnit::Sat);
+                    let id = match id {
+                        MintIdentifier::Cashu(url, _) => MintIdentifier::Cashu(url, unit.clone()),
+                        id => id,
+                    };
+
                     let name = metadata.federation_name.clone().unwrap_or_else(|| {
                         match config.as_ref().map(|c| c.meta::<String>("federation_name")) {
                             Some(Ok(Some(n))) => n,
@@ -1191,27 +1811,112 @@ impl HarborWallet {
                         id,
                         name,
                         balance: 0,
+                        unit,
+                        dleq_audit: DleqAudit::default(),
                         guardians: Some(guardians),
                         module_kinds: Some(module_kinds),
                         metadata,
                         on_chain_supported: false,
                         active: true,
+                        recovering: false,
+                        recovery_error: None,
                     };
 
                     self.peek_federation_item = Some(item);
+                    self.add_cashu_unit = units.first().cloned();
+                    self.peek_cashu_units = units;
                     self.peek_status = PeekStatus::Idle;
                     Task::none()
                 }
+                CoreUIMsg::MintsDiscovered(mints) => {
+                    self.current_discover_id = None;
+                    let toast = if mints.is_empty() {
+                        Task::done(Message::AddToast(Toast {
+                            title: "No mints found".to_string(),
+                            body: Some("No new mints are announced for this network".to_string()),
+                            status: ToastStatus::Neutral,
+                        }))
+                    } else {
+                        Task::none()
+                    };
+                    self.discovered_mints = mints;
+                    toast
+                }
+                CoreUIMsg::LnurlAuthSuccess(domain) => {
+                    self.current_lnurl_auth_id = None;
+                    self.lnurl_auth_str = String::new();
+                    Task::done(Message::AddToast(Toast {
+                        title: "Logged in".to_string(),
+                        body: Some(format!("Signed in to {domain}")),
+                        status: ToastStatus::Good,
+                    }))
+                }
+                CoreUIMsg::LnurlAuthFailed(reason) => {
+                    self.current_lnurl_auth_id = None;
+                    Task::done(Message::AddToast(Toast {
+                        title: "Failed to log in".to_string(),
+                        body: Some(reason),
+                        status: ToastStatus::Bad,
+                    }))
+                }
+                CoreUIMsg::MintDiscoveryFailed(reason) => {
+                    self.current_discover_id = None;
+                    Task::done(Message::AddToast(Toast {
+                        title: "Failed to discover mints".to_string(),
+                        body: Some(reason),
+                        status: ToastStatus::Bad,
+                    }))
+                }
+                CoreUIMsg::FederationRecoveryProgress {
+                    id,
+                    module,
+                    complete,
+                    total,
+                } => {
+                    self.recovery_progress
+                        .entry(id)
+                        .or_default()
+                        .insert(module, (complete, total));
+                    Task::none()
+                }
+                CoreUIMsg::FederationRecovered(id) => {
+                    self.recovery_progress.remove(&id);
+                    Task::done(Message::AddToast(Toast {
+                        title: "Mint recovered".to_string(),
+                        body: None,
+                        status: ToastStatus::Good,
+                    }))
+                }
+                CoreUIMsg::FederationRecoveryFailed { id, reason } => {
+                    self.recovery_progress.remove(&id);
+                    Task::done(Message::AddToast(Toast {
+                        title: "Failed to recover mint".to_string(),
+                        body: Some(reason),
+                        status: ToastStatus::Bad,
+                    }))
+                }
                 CoreUIMsg::AddMintSuccess(id) => {
                     self.clear_add_federation_state();
                     // Route to the mints list
                     self.active_route = Route::Mints(routes::MintSubroute::List);
-                    self.active_mint = Some(id);
-                    Task::done(Message::AddToast(Toast {
+                    let toast = Task::done(Message::AddToast(Toast {
                         title: "Mint added".to_string(),
                         body: None,
                         status: ToastStatus::Neutral,
-                    }))
+                    }));
+
+                    // Redeem any notes that were waiting on this federation
+                    let notes = self.pending_fedimint_notes.take_if(|n| {
+                        id.federation_id()
+                            .is_some_and(|f| f.to_prefix() == n.federation_id_prefix())
+                    });
+                    self.active_mint = Some(id);
+                    match notes {
+                        Some(notes) => {
+                            Task::batch([toast, Task::done(Message::RedeemFedimintNotes(notes))])
+                        }
+                        None => toast,
+                    }
                 }
                 CoreUIMsg::RemoveFederationSuccess => {
                     self.clear_add_federation_state();
@@ -1265,7 +1970,43 @@ impl HarborWallet {
                 CoreUIMsg::InitSuccess => {
                     self.init_status = WelcomeStatus::Inited;
                     self.active_route = Route::Home;
-                    Task::none()
+                    match InviteCode::from_str(self.restore_invite_str.trim()) {
+                        Ok(invite_code) => {
+                            self.restore_invite_str = String::new();
+                            let (_, task) =
+                                self.send_from_ui(UICoreMsg::RestoreFromFederation(invite_code));
+                            Task::batch([
+                                task,
+                                Task::done(Message::AddToast(Toast {
+                                    title: "Restoring wallet".to_string(),
+                                    body: Some(
+                                        "Rejoining your mints, this may take a while".to_string(),
+                                    ),
+                                    status: ToastStatus::Neutral,
+                                })),
+                            ])
+                        }
+                        Err(_) => Task::none(),
+                    }
+                }
+                CoreUIMsg::WalletRestored { rejoined, failed } => {
+                    let body = if failed.is_empty() {
+                        format!("Rejoined {rejoined} other mints from your backup")
+                    } else {
+                        format!(
+                            "Rejoined {rejoined} other mints from your backup, could not rejoin: {}",
+                            failed.join(", ")
+                        )
+                    };
+                    Task::done(Message::AddToast(Toast {
+                        title: "Wallet restored".to_string(),
+                        body: Some(body),
+                        status: if failed.is_empty() {
+                            ToastStatus::Good
+                        } else {
+                            ToastStatus::Neutral
+                        },
+                    }))
                 }
                 CoreUIMsg::InitFailed(reason) => {
                     self.init_status = WelcomeStatus::NeedsInit;
@@ -1312,6 +2053,14 @@ impl HarborWallet {
                     self.onchain_receive_enabled = enabled;
                     Task::done(Message::Noop)
                 }
+                CoreUIMsg::ConsolidationEnabled(enabled) => {
+                    self.consolidation_enabled = enabled;
+                    Task::none()
+                }
+                CoreUIMsg::NostrEnabled(enabled) => {
+                    self.nostr_enabled = enabled;
+                    Task::none()
+                }
                 CoreUIMsg::TorEnabled(enabled) => {
                     self.tor_enabled = enabled;
 
@@ -1325,10 +2074,18 @@ impl HarborWallet {
                     seed_words,
                     onchain_receive_enabled,
                     tor_enabled,
+                    consolidation_enabled,
+                    nostr_enabled,
                 } => {
                     self.seed_words = Some(seed_words);
                     self.onchain_receive_enabled = onchain_receive_enabled;
                     self.tor_enabled = tor_enabled;
+                    self.consolidation_enabled = consolidation_enabled;
+                    self.nostr_enabled = nostr_enabled;
+                    Task::none()
+                }
+                CoreUIMsg::CashuPubkey(pubkey) => {
+                    self.cashu_pubkey = Some(pubkey);
                     Task::none()
                 }
                 CoreUIMsg::StatusUpdate {
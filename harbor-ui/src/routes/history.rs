@@ -2,35 +2,48 @@ use iced::{Element, Length};
 
 use crate::components::{basic_layout, h_header, h_transaction_details, h_transaction_item, hr};
 use crate::{HarborWallet, Message};
-use iced::widget::{column, horizontal_space, row, stack, text};
+use harbor_client::db_models::PaymentStatus;
+use harbor_client::db_models::transaction_item::{
+    TransactionDirection, TransactionItem, TransactionItemKind,
+};
+use iced::widget::{Column, column, horizontal_space, row, stack, text};
 
 pub fn history(harbor: &HarborWallet) -> Element<Message> {
     let header = h_header("History", "Here's what's happened so far.");
 
+    // Tokens we sent that haven't been claimed yet can still be reclaimed
+    let (unclaimed, settled): (Vec<_>, Vec<_>) = harbor
+        .transaction_history
+        .iter()
+        .partition(|item| is_unclaimed_ecash(item));
+
     let transactions = if harbor.transaction_history.is_empty() {
         column![text("Nothing has happened yet.").size(18)]
     } else {
-        harbor
-            .transaction_history
-            .iter()
-            .fold(column![], |column, item| {
-                let is_selected = harbor
-                    .selected_transaction
-                    .as_ref()
-                    .is_some_and(|selected| selected == item);
-                column
-                    .push(h_transaction_item(item, is_selected))
-                    .push(hr())
-            })
-            .spacing(16)
+        transaction_list(harbor, settled)
     };
-    let left_column = column![header, transactions].spacing(48);
+
+    let mut left_column = column![header].spacing(48);
+    if !unclaimed.is_empty() {
+        let unclaimed_section = column![
+            text("Unclaimed ecash").size(24),
+            transaction_list(harbor, unclaimed)
+        ]
+        .spacing(16);
+        left_column = left_column.push(unclaimed_section);
+    }
+    let left_column = left_column.push(transactions);
 
     let content = basic_layout(left_column);
     let mut layers = stack![content];
 
     if let Some(selected_tx) = &harbor.selected_transaction {
-        let details = h_transaction_details(selected_tx, &harbor.mint_list, harbor.config.network);
+        let details = h_transaction_details(
+            selected_tx,
+            &harbor.mint_list,
+            harbor.config.network,
+            harbor.current_reclaim_id.is_some(),
+        );
 
         layers = layers.push(row![
             horizontal_space(),
@@ -41,3 +54,27 @@ pub fn history(harbor: &HarborWallet) -> Element<Message> {
 
     layers.into()
 }
+
+fn is_unclaimed_ecash(item: &TransactionItem) -> bool {
+    item.kind == TransactionItemKind::Ecash
+        && item.direction == TransactionDirection::Outgoing
+        && item.status == PaymentStatus::Pending
+}
+
+fn transaction_list<'a>(
+    harbor: &'a HarborWallet,
+    items: Vec<&'a TransactionItem>,
+) -> Column<'a, Message> {
+    items
+        .into_iter()
+        .fold(column![], |column, item| {
+            let is_selected = harbor
+                .selected_transaction
+                .as_ref()
+                .is_some_and(|selected| selected == item);
+            column
+                .push(h_transaction_item(item, is_selected))
+                .push(hr())
+        })
+        .spacing(16)
+}
@@ -1,4 +1,4 @@
-use crate::components::{SvgIcon, format_amount, h_button, h_screen_header};
+use crate::components::{SvgIcon, format_amount, format_unit_amount, h_button, h_screen_header};
 use iced::widget::{center, column, container, row, text};
 use iced::{Alignment, Element, Length};
 
@@ -7,9 +7,10 @@ use crate::{HarborWallet, Message};
 use super::Route;
 
 pub fn home(harbor: &HarborWallet) -> Element<Message> {
-    let formatted_balance = harbor
-        .active_federation()
-        .map_or_else(|| format_amount(0), |f| format_amount(f.balance));
+    let formatted_balance = harbor.active_federation().map_or_else(
+        || format_amount(0),
+        |f| format_unit_amount(f.balance, &f.unit),
+    );
 
     let balance = text(formatted_balance).size(64);
     let send_disabled = harbor.active_federation().is_none_or(|f| f.balance == 0);
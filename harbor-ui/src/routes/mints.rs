@@ -1,12 +1,13 @@
 use std::str::FromStr;
 
 use harbor_client::MintConnectionInfo;
-use iced::Element;
-use iced::widget::{column, row};
+use iced::widget::{column, pick_list, row, text};
+use iced::{Element, Length, Padding};
 
 use crate::components::{
-    InputArgs, SvgIcon, basic_layout, h_button, h_federation_archived, h_federation_item,
-    h_federation_item_preview, h_header, h_input, operation_status_for_id,
+    InputArgs, SvgIcon, basic_layout, h_button, h_discovered_mint, h_federation_archived,
+    h_federation_item, h_federation_item_preview, h_header, h_input, menu_style,
+    operation_status_for_id, pick_list_style, regular_text, very_subtle,
 };
 use crate::{AddFederationStatus, HarborWallet, Message, PeekStatus};
 
@@ -22,7 +23,7 @@ fn mints_list(harbor: &HarborWallet) -> Element<Message> {
         .iter()
         .filter(|a| a.active)
         .fold(column![], |column, item| {
-            column.push(h_federation_item(item))
+            column.push(h_federation_item(item, harbor))
         })
         .spacing(48);
 
@@ -87,7 +88,7 @@ fn mints_add(harbor: &HarborWallet) -> Element<Message> {
                 }
             }
 
-            column![header, peek_column].spacing(48)
+            column![header, peek_column, mint_directory(harbor)].spacing(48)
         }
 
         Some(peek_federation_item) => {
@@ -102,7 +103,32 @@ fn mints_add(harbor: &HarborWallet) -> Element<Message> {
                 .on_press(Message::CancelAddFederation);
 
             let button_row = row![start_over_button, add_mint_button].spacing(16);
-            let mut preview_column = column![federation_preview, button_row].spacing(16);
+            let mut preview_column = column![federation_preview].spacing(16);
+
+            // cashu mints can hold a separate wallet per unit
+            if harbor.peek_cashu_units.len() > 1 {
+                let unit_label = regular_text("Unit".to_string(), 24);
+                let unit_description = text("Balances are kept separately for each unit.")
+                    .style(very_subtle)
+                    .size(14);
+                let unit_list = pick_list(
+                    harbor.peek_cashu_units.as_slice(),
+                    harbor.add_cashu_unit.clone(),
+                    Message::AddCashuUnitChanged,
+                )
+                .style(pick_list_style)
+                .padding(Padding::from(16))
+                .width(Length::Fill)
+                .handle(pick_list::Handle::Arrow {
+                    size: Some(iced::Pixels(24.)),
+                })
+                .menu_style(menu_style);
+
+                preview_column = preview_column
+                    .push(column![unit_label, unit_list, unit_description].spacing(8));
+            }
+
+            preview_column = preview_column.push(button_row);
 
             // Add status display for add operation
             if let Some(current_add_id) = harbor.current_add_id {
@@ -118,6 +144,30 @@ fn mints_add(harbor: &HarborWallet) -> Element<Message> {
     basic_layout(column)
 }
 
+// Mints announced and recommended on nostr, fetched on request
+fn mint_directory(harbor: &HarborWallet) -> Element<Message> {
+    let title = regular_text("Discover Mints".to_string(), 24);
+    let description = text("Mints announced on nostr, most recommended first.")
+        .style(very_subtle)
+        .size(14);
+
+    let browse_button = h_button(
+        "Browse Mints",
+        SvgIcon::People,
+        harbor.current_discover_id.is_some(),
+    )
+    .on_press(Message::DiscoverMints);
+
+    harbor
+        .discovered_mints
+        .iter()
+        .fold(
+            column![column![title, description].spacing(8), browse_button].spacing(16),
+            |column, mint| column.push(h_discovered_mint(mint)),
+        )
+        .into()
+}
+
 pub fn mints(harbor: &HarborWallet) -> Element<Message> {
     if harbor.mint_list.iter().filter(|f| f.active).count() == 0 {
         mints_add(harbor)
@@ -3,13 +3,21 @@ use crate::components::{
     h_screen_header, h_small_button, operation_status_for_id,
 };
 use crate::{HarborWallet, Message, ReceiveMethod, ReceiveStatus};
+use harbor_client::OOBNotes;
+use harbor_client::cdk::nuts::Token;
+use harbor_client::lightning_address::parse_lnurl;
 use iced::widget::container::Style;
 use iced::widget::{column, container, horizontal_space, qr_code, radio, row, text};
 use iced::{Border, Element};
 use iced::{Color, Length};
+use std::str::FromStr;
 
 /// Main view function.
 pub fn receive(harbor: &HarborWallet) -> Element<Message> {
+    if let Some((_, request)) = harbor.receive_payment_request.as_ref() {
+        return render_payment_request_view(request, harbor);
+    }
+
     if let Some(receive_string) = harbor
         .receive_invoice
         .as_ref()
@@ -36,23 +44,23 @@ fn render_receive_form(harbor: &HarborWallet) -> Element<Message> {
                 .is_some_and(|x| x.on_chain_supported));
 
     let header = if on_chain_enabled {
-        h_header("Deposit", "Receive on-chain or via lightning.")
+        h_header("Deposit", "Receive on-chain, via lightning, or with ecash.")
     } else {
-        h_header("Deposit", "Receive via lightning.")
+        h_header("Deposit", "Receive via lightning or with ecash.")
     };
 
-    let content = if on_chain_enabled {
-        let method_choice = render_method_choice(harbor);
-        match harbor.receive_method {
-            ReceiveMethod::Lightning => {
-                column![header, method_choice, render_lightning_view(harbor)]
-            }
-            ReceiveMethod::OnChain => {
-                column![header, method_choice, render_onchain_view(harbor)]
-            }
+    let method_choice = render_method_choice(harbor, on_chain_enabled);
+    let content = match harbor.receive_method {
+        ReceiveMethod::Lightning => {
+            column![header, method_choice, render_lightning_view(harbor)]
+        }
+        ReceiveMethod::OnChain => {
+            column![header, method_choice, render_onchain_view(harbor)]
+        }
+        ReceiveMethod::Ecash => column![header, method_choice, render_ecash_view(harbor)],
+        ReceiveMethod::Request => {
+            column![header, method_choice, render_request_view(harbor)]
         }
-    } else {
-        column![header, render_lightning_view(harbor)]
     };
 
     column![
@@ -100,7 +108,31 @@ fn render_lightning_view(harbor: &HarborWallet) -> Element<Message> {
         column![generate_invoice_button]
     };
 
-    column![amount_input, buttons].spacing(48).into()
+    // vouchers and ATM codes pay us through LNURL-withdraw, using the amount above if set
+    let lnurl_input = h_input(InputArgs {
+        label: "LNURL-withdraw",
+        placeholder: "lnurl1...",
+        value: &harbor.receive_lnurl_str,
+        on_input: Message::ReceiveLnurlChanged,
+        disabled: generating,
+        ..InputArgs::default()
+    });
+    let withdraw = parse_lnurl(harbor.receive_lnurl_str.trim())
+        .ok()
+        .map(Message::WithdrawLnurl);
+    let withdraw_button =
+        h_button("Withdraw", SvgIcon::DownLeft, generating).on_press_maybe(withdraw);
+    let withdraw_section = column![
+        text("Redeem a voucher").size(16),
+        h_caption_text("Leave the amount empty to withdraw everything."),
+        lnurl_input,
+        withdraw_button
+    ]
+    .spacing(16);
+
+    column![amount_input, buttons, withdraw_section]
+        .spacing(48)
+        .into()
 }
 
 /// Renders the on-chain view.
@@ -130,8 +162,98 @@ fn render_onchain_view(harbor: &HarborWallet) -> Element<Message> {
     buttons.into()
 }
 
-/// Renders the method selector for on-chain enabled wallets.
-fn render_method_choice(harbor: &HarborWallet) -> Element<Message> {
+/// Renders the ecash view with the token input.
+fn render_ecash_view(harbor: &HarborWallet) -> Element<Message> {
+    let generating = harbor.receive_status == ReceiveStatus::Generating;
+
+    let token_input = h_input(InputArgs {
+        label: "Token",
+        placeholder: "cashuB... or Fedimint notes",
+        value: &harbor.receive_token_str,
+        on_input: Message::ReceiveTokenInputChanged,
+        disabled: generating,
+        ..InputArgs::default()
+    });
+
+    let input = harbor.receive_token_str.trim();
+    let redeem = Token::from_str(input)
+        .map(Message::RedeemEcash)
+        .ok()
+        .or_else(|| {
+            OOBNotes::from_str(input)
+                .map(Message::RedeemFedimintNotes)
+                .ok()
+        });
+    let redeem_button = h_button("Redeem", SvgIcon::DownLeft, generating).on_press_maybe(redeem);
+
+    let mut buttons = column![redeem_button];
+    if let Some(status) = harbor
+        .current_receive_id
+        .filter(|_| generating)
+        .and_then(|id| operation_status_for_id(harbor, Some(id)))
+    {
+        buttons = buttons.push(status).spacing(16);
+    }
+
+    let mut content = column![token_input, buttons].spacing(48);
+
+    // Senders can lock tokens to this key so only we can redeem them
+    if let Some(pubkey) = harbor.cashu_pubkey.as_ref() {
+        let pubkey = pubkey.to_string();
+        let copy_button = h_small_button("", SvgIcon::Copy, false)
+            .on_press(Message::CopyToClipboard(pubkey.clone()));
+        let pubkey_row = row![
+            text(pubkey).size(14).font(font_mono()),
+            horizontal_space().width(Length::Fixed(8.)),
+            copy_button
+        ]
+        .align_y(iced::Alignment::Center);
+        let pubkey_section = column![
+            text("Your ecash pubkey").size(16),
+            h_caption_text("Share this so tokens can be locked to you."),
+            pubkey_row
+        ]
+        .spacing(8);
+        content = content.push(pubkey_section);
+    }
+
+    content.into()
+}
+
+/// Renders the payment request form, the amount is optional so the payer can choose.
+fn render_request_view(harbor: &HarborWallet) -> Element<Message> {
+    let generating = harbor.receive_status == ReceiveStatus::Generating;
+
+    let amount_input = h_input(InputArgs {
+        label: "Amount (optional)",
+        placeholder: "420",
+        value: &harbor.receive_amount_str,
+        on_input: Message::ReceiveAmountChanged,
+        numeric: true,
+        suffix: Some("sats"),
+        disabled: generating,
+        ..InputArgs::default()
+    });
+
+    let memo_input = h_input(InputArgs {
+        label: "Memo (optional)",
+        placeholder: "Coffee",
+        value: &harbor.receive_memo_str,
+        on_input: Message::ReceiveMemoChanged,
+        disabled: generating,
+        ..InputArgs::default()
+    });
+
+    let create_button =
+        h_button("Create Request", SvgIcon::Qr, generating).on_press(Message::CreatePaymentRequest);
+
+    column![amount_input, memo_input, create_button]
+        .spacing(48)
+        .into()
+}
+
+/// Renders the method selector, on-chain is only offered when enabled.
+fn render_method_choice(harbor: &HarborWallet, on_chain_enabled: bool) -> Element<Message> {
     let lightning_choice = radio(
         "Lightning",
         ReceiveMethod::Lightning,
@@ -156,11 +278,47 @@ fn render_method_choice(harbor: &HarborWallet) -> Element<Message> {
     );
     let onchain = column![onchain_choice, onchain_caption].spacing(8);
 
+    let ecash_choice = radio(
+        "Ecash",
+        ReceiveMethod::Ecash,
+        Some(harbor.receive_method),
+        Message::ReceiveMethodChanged,
+    )
+    .text_size(18);
+
+    let ecash_caption =
+        h_caption_text("Paste a Cashu token or Fedimint notes to redeem them into their mint.");
+    let ecash = column![ecash_choice, ecash_caption].spacing(8);
+
+    let request_choice = radio(
+        "Request ecash",
+        ReceiveMethod::Request,
+        Some(harbor.receive_method),
+        Message::ReceiveMethodChanged,
+    )
+    .text_size(18);
+
+    let request_caption =
+        h_caption_text("Share a Cashu payment request, the payer sends ecash from this mint.");
+    let request = column![request_choice, request_caption].spacing(8);
+
+    // Payment requests are a cashu feature
+    let request_enabled = harbor
+        .active_mint
+        .as_ref()
+        .is_some_and(|a| a.federation_id().is_none());
+
     let method_choice_label = text("Method").size(24);
 
-    column![method_choice_label, lightning, onchain]
-        .spacing(16)
-        .into()
+    let mut choices = column![method_choice_label, lightning].spacing(16);
+    if on_chain_enabled {
+        choices = choices.push(onchain);
+    }
+    choices = choices.push(ecash);
+    if request_enabled {
+        choices = choices.push(request);
+    }
+    choices.into()
 }
 
 /// Renders the view for a generated invoice/address.
@@ -249,3 +407,95 @@ fn render_generated_view(receive_string: String, harbor: &HarborWallet) -> Eleme
     ]
     .into()
 }
+
+/// Renders a published payment request and takes the ecash the payer hands back.
+fn render_payment_request_view<'a>(
+    request: &'a str,
+    harbor: &'a HarborWallet,
+) -> Element<'a, Message> {
+    let generating = harbor.receive_status == ReceiveStatus::Generating;
+
+    let header = if harbor.nostr_enabled {
+        h_header(
+            "Payment Request",
+            "Share this request. Ecash sent over Nostr is redeemed as it arrives, or paste what the payer sends back.",
+        )
+    } else {
+        h_header(
+            "Payment Request",
+            "Share this request, then paste what the payer sends back.",
+        )
+    };
+
+    let copy_button = h_small_button("", SvgIcon::Copy, false)
+        .on_press(Message::CopyToClipboard(request.to_string()));
+
+    let text_and_copy = row![
+        text(request).size(14).font(font_mono()).color(Color::BLACK),
+        horizontal_space().width(Length::Fixed(8.)),
+        copy_button
+    ]
+    .align_y(iced::Alignment::Center);
+
+    let mut request_column = column![
+        text("Payment Request")
+            .size(16)
+            .font(font_mono())
+            .color(Color::BLACK)
+    ]
+    .spacing(16);
+
+    if let Some(data) = harbor.receive_qr_data.as_ref() {
+        let qr = qr_code(data)
+            .total_size(iced::Pixels(256.))
+            .style(|_theme| iced::widget::qr_code::Style {
+                background: Color::WHITE,
+                cell: Color::BLACK,
+            });
+        request_column = request_column.push(
+            container(qr)
+                .align_x(iced::Alignment::Center)
+                .width(Length::Fill),
+        );
+    }
+
+    request_column = request_column.push(text_and_copy);
+
+    let request_container = container(request_column).padding(16).style(|_theme| Style {
+        background: Some(iced::Background::Color(Color::WHITE)),
+        border: Border {
+            radius: (8.).into(),
+            ..Border::default()
+        },
+        ..Style::default()
+    });
+
+    let payment_input = h_input(InputArgs {
+        label: "Payment",
+        placeholder: "cashuB... or payment JSON",
+        value: &harbor.receive_token_str,
+        on_input: Message::ReceiveTokenInputChanged,
+        disabled: generating,
+        ..InputArgs::default()
+    });
+
+    let redeem_button = h_button("Redeem", SvgIcon::DownLeft, generating).on_press_maybe(
+        (!harbor.receive_token_str.trim().is_empty())
+            .then_some(Message::RedeemPaymentRequestPayment),
+    );
+    let reset_button =
+        h_button("Start over", SvgIcon::Restart, false).on_press(Message::ReceiveStateReset);
+
+    let mut buttons = column![row![reset_button, redeem_button].spacing(8)];
+    if let Some(status) = harbor
+        .current_receive_id
+        .filter(|_| generating)
+        .and_then(|id| operation_status_for_id(harbor, Some(id)))
+    {
+        buttons = buttons.push(status).spacing(16);
+    }
+
+    let content = column![header, request_container, payment_input, buttons].spacing(48);
+
+    column![h_screen_header(harbor, true, true), basic_layout(content)].into()
+}
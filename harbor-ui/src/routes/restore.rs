@@ -70,6 +70,17 @@ pub fn restore(harbor: &HarborWallet) -> Element<Message> {
                     ..InputArgs::default()
                 });
 
+                let invite_input = h_input(InputArgs {
+                    label: "Federation Invite Code (optional)",
+                    value: &harbor.restore_invite_str,
+                    on_input: Message::RestoreInviteChanged,
+                    on_submit: action.clone(),
+                    disabled: harbor.unlock_status == UnlockStatus::Unlocking,
+                    secure: false,
+                    placeholder: "Restores your other mints from its backup",
+                    ..InputArgs::default()
+                });
+
                 let confirm_button = h_button(
                     "Restore Wallet",
                     SvgIcon::Restart,
@@ -83,6 +94,7 @@ pub fn restore(harbor: &HarborWallet) -> Element<Message> {
                     welcome_message,
                     password_input,
                     seed_input,
+                    invite_input,
                     confirm_button
                 ]
                 .spacing(32)
@@ -1,21 +1,31 @@
 use std::str::FromStr;
 
-use iced::Element;
-use iced::widget::{column, row};
+use iced::widget::container::Style;
+use iced::widget::{column, container, horizontal_space, qr_code, row, text};
+use iced::{Border, Color, Element, Length};
 
-use harbor_client::Bolt11Invoice;
 use harbor_client::bitcoin::Address;
 use harbor_client::bitcoin::address::NetworkUnchecked;
+use harbor_client::cdk::nuts::nut18::PaymentRequest;
+use harbor_client::cdk::nuts::{PublicKey, Token};
 use harbor_client::lightning_address::parse_lnurl;
+use harbor_client::{Bolt11Invoice, OOBNotes};
 
 use crate::components::{
-    ConfirmModalState, InputArgs, SvgIcon, basic_layout, h_button, h_checkbox, h_header, h_input,
-    h_screen_header, operation_status_for_id,
+    ConfirmModalState, InputArgs, SvgIcon, basic_layout, font_mono, h_button, h_checkbox, h_header,
+    h_input, h_screen_header, h_small_button, operation_status_for_id,
 };
 use crate::{HarborWallet, Message, SendDestination, SendStatus};
 
 pub fn send(harbor: &HarborWallet) -> Element<Message> {
-    let header = h_header("Send", "Send to an on-chain address or lightning invoice.");
+    if let Some(token) = harbor.send_ecash_token.as_ref() {
+        return render_ecash_token_view(token, harbor);
+    }
+
+    let header = h_header(
+        "Send",
+        "Send to an on-chain address, lightning invoice or payment request, or create and redeem ecash.",
+    );
 
     let dest_input = h_input(InputArgs {
         label: "Destination",
@@ -36,12 +46,57 @@ pub fn send(harbor: &HarborWallet) -> Element<Message> {
         ..InputArgs::default()
     });
 
+    let destination = parse_send_destination(&harbor.send_dest_input_str);
+    let send_label = if matches!(
+        destination,
+        Some(SendDestination::CashuToken(_) | SendDestination::FedimintNotes(_))
+    ) {
+        "Redeem"
+    } else {
+        "Send"
+    };
     let send_button = h_button(
-        "Send",
+        send_label,
         SvgIcon::UpRight,
         harbor.send_status == SendStatus::Sending,
     )
-    .on_press_maybe(parse_send_destination(&harbor.send_dest_input_str).map(Message::Send));
+    .on_press_maybe(destination.map(Message::Send));
+
+    let lock_input = h_input(InputArgs {
+        label: "Lock to pubkey (optional)",
+        placeholder: "02...",
+        value: &harbor.send_lock_pubkey_str,
+        on_input: Message::SendLockPubkeyChanged,
+        ..InputArgs::default()
+    });
+
+    let unlock_input = h_input(InputArgs {
+        label: "Claimable after (days, optional)",
+        placeholder: "30",
+        value: &harbor.send_unlock_days_str,
+        on_input: Message::SendUnlockDaysChanged,
+        numeric: true,
+        ..InputArgs::default()
+    });
+
+    // Ecash tokens don't need a destination, just an amount
+    let lock_valid = harbor.send_lock_pubkey_str.is_empty()
+        || PublicKey::from_str(harbor.send_lock_pubkey_str.trim()).is_ok();
+    // a timelock only makes sense with someone to unlock it for
+    let unlock_valid = harbor.send_unlock_days_str.is_empty()
+        || (!harbor.send_lock_pubkey_str.is_empty()
+            && harbor.send_unlock_days_str.trim().parse::<u32>().is_ok());
+    let can_create_ecash = harbor.send_dest_input_str.is_empty()
+        && !harbor.is_max
+        && lock_valid
+        && unlock_valid
+        && harbor.send_amount_input_str.parse::<u64>().is_ok();
+    let ecash_button = h_button(
+        "Create Ecash",
+        SvgIcon::Ecash,
+        harbor.send_status == SendStatus::Sending,
+    )
+    .on_press_maybe(can_create_ecash.then_some(Message::SendEcash));
 
     let checkbox = h_checkbox(
         "Send Max",
@@ -63,7 +118,7 @@ pub fn send(harbor: &HarborWallet) -> Element<Message> {
             })));
         column![row![start_over_button, send_button].spacing(8)]
     } else {
-        column![send_button]
+        column![row![send_button, ecash_button].spacing(8)]
     };
 
     // Add status display with 16px spacing
@@ -79,6 +134,8 @@ pub fn send(harbor: &HarborWallet) -> Element<Message> {
         dest_input,
         amount_input,
         checkbox,
+        lock_input,
+        unlock_input,
         button_and_status
     ]
     .spacing(48);
@@ -86,6 +143,76 @@ pub fn send(harbor: &HarborWallet) -> Element<Message> {
     column![h_screen_header(harbor, true, false), basic_layout(content)].into()
 }
 
+/// Renders a freshly created ecash token so it can be shared.
+fn render_ecash_token_view<'a>(token: &'a str, harbor: &'a HarborWallet) -> Element<'a, Message> {
+    let header = if harbor.send_lock_pubkey_str.is_empty() {
+        h_header(
+            "Ecash Token",
+            "Anyone with this token can claim it. Scan this QR or copy the token.",
+        )
+    } else if !harbor.send_unlock_days_str.is_empty() {
+        h_header(
+            "Ecash Token",
+            "The owner of the pubkey can claim it once it unlocks, until then you can reclaim it. Scan this QR or copy the token.",
+        )
+    } else {
+        h_header(
+            "Ecash Token",
+            "Only the owner of the pubkey can claim it and you can't reclaim it. Scan this QR or copy the token.",
+        )
+    };
+
+    let copy_button = h_small_button("", SvgIcon::Copy, false)
+        .on_press(Message::CopyToClipboard(token.to_string()));
+
+    let text_and_copy = row![
+        text(token).size(14).font(font_mono()).color(Color::BLACK),
+        horizontal_space().width(Length::Fixed(8.)),
+        copy_button
+    ]
+    .align_y(iced::Alignment::Center);
+
+    let mut token_column = column![
+        text("Ecash Token")
+            .size(16)
+            .font(font_mono())
+            .color(Color::BLACK)
+    ]
+    .spacing(16);
+
+    if let Some(data) = harbor.send_qr_data.as_ref() {
+        let qr = qr_code(data)
+            .total_size(iced::Pixels(256.))
+            .style(|_theme| iced::widget::qr_code::Style {
+                background: Color::WHITE,
+                cell: Color::BLACK,
+            });
+        token_column = token_column.push(
+            container(qr)
+                .align_x(iced::Alignment::Center)
+                .width(Length::Fill),
+        );
+    }
+
+    token_column = token_column.push(text_and_copy);
+
+    let token_container = container(token_column).padding(16).style(|_theme| Style {
+        background: Some(iced::Background::Color(Color::WHITE)),
+        border: Border {
+            radius: (8.).into(),
+            ..Border::default()
+        },
+        ..Style::default()
+    });
+
+    let done_button =
+        h_button("Done", SvgIcon::SmallCheck, false).on_press(Message::SendStateReset);
+
+    let content = column![header, column![token_container, done_button].spacing(16)].spacing(48);
+
+    column![h_screen_header(harbor, true, true), basic_layout(content)].into()
+}
+
 fn parse_send_destination(input: &str) -> Option<SendDestination> {
     if let Ok(invoice) = Bolt11Invoice::from_str(input) {
         return Some(SendDestination::Invoice(invoice));
@@ -99,5 +226,17 @@ fn parse_send_destination(input: &str) -> Option<SendDestination> {
         return Some(SendDestination::Address(address));
     }
 
+    if let Ok(request) = PaymentRequest::from_str(input.trim()) {
+        return Some(SendDestination::PaymentRequest(request));
+    }
+
+    if let Ok(token) = Token::from_str(input.trim()) {
+        return Some(SendDestination::CashuToken(token));
+    }
+
+    if let Ok(notes) = OOBNotes::from_str(input.trim()) {
+        return Some(SendDestination::FedimintNotes(notes));
+    }
+
     None
 }
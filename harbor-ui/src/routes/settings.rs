@@ -1,10 +1,11 @@
 use harbor_client::bitcoin::Network;
+use harbor_client::lightning_address::{parse_lnurl, parse_lnurl_auth};
 use iced::widget::{column, pick_list, row, text};
 use iced::{Element, Length, Padding};
 
 use crate::components::{
-    SvgIcon, basic_layout, debug_stuff, h_button, h_checkbox, h_header, menu_style,
-    pick_list_style, regular_text, very_subtle,
+    InputArgs, SvgIcon, basic_layout, debug_stuff, h_button, h_checkbox, h_header, h_input,
+    menu_style, pick_list_style, regular_text, very_subtle,
 };
 use crate::{HarborWallet, Message};
 
@@ -89,6 +90,66 @@ pub fn settings(harbor: &HarborWallet) -> Element<Message> {
         },
     );
 
+    let consolidation_checkbox = h_checkbox(
+        "Consolidate Ecash",
+        Some("Merge small Cashu proofs in the background to keep future fees low."),
+        harbor.consolidation_enabled,
+        false,
+        Message::SetConsolidationEnabled,
+    );
+
+    let nostr_checkbox = h_checkbox(
+        "Receive Over Nostr",
+        Some("Listen on public nostr relays for ecash sent to you, e.g. for payment requests."),
+        harbor.nostr_enabled,
+        false,
+        Message::SetNostrEnabled,
+    );
+
+    let lnurl_auth_label = regular_text("Log In With Lightning".to_string(), 24);
+    let lnurl_auth_description = text(
+        "Sign in to services that support LNURL-auth. Each site gets its own key from your seed.",
+    )
+    .style(very_subtle)
+    .size(14);
+    let logging_in = harbor.current_lnurl_auth_id.is_some();
+    let lnurl_auth_input = h_input(InputArgs {
+        label: "LNURL",
+        placeholder: "lnurl1...",
+        value: &harbor.lnurl_auth_str,
+        on_input: Message::LnurlAuthInputChanged,
+        disabled: logging_in,
+        ..InputArgs::default()
+    });
+    // confirm the domain before signing anything for it
+    let confirm_login = parse_lnurl(harbor.lnurl_auth_str.trim())
+        .ok()
+        .and_then(|lnurl| {
+            let auth = parse_lnurl_auth(&lnurl).ok()?;
+            let action = auth.action.as_deref().unwrap_or("login");
+            Some(Message::SetConfirmModal(Some(
+                crate::components::ConfirmModalState {
+                    title: format!("Log in to {}?", auth.domain),
+                    description: format!(
+                        "{} is asking you to {action}. Harbor will sign its challenge with the key for {} only.",
+                        auth.domain, auth.domain
+                    ),
+                    confirm_action: Box::new(Message::LnurlAuth(lnurl)),
+                    cancel_action: Box::new(Message::SetConfirmModal(None)),
+                    confirm_button_text: "Log In".to_string(),
+                },
+            )))
+        });
+    let lnurl_auth_button =
+        h_button("Log In", SvgIcon::Shield, logging_in).on_press_maybe(confirm_login);
+    let lnurl_auth_column = column![
+        lnurl_auth_label,
+        lnurl_auth_description,
+        lnurl_auth_input,
+        lnurl_auth_button
+    ]
+    .spacing(8);
+
     let show_seed_words_button =
         h_button("Show Seed Words", SvgIcon::Eye, false).on_press(Message::ShowSeedWords(true));
 
@@ -102,7 +163,10 @@ pub fn settings(harbor: &HarborWallet) -> Element<Message> {
         header,
         onchain_receive_checkbox,
         tor_enabled_checkbox,
+        consolidation_checkbox,
+        nostr_checkbox,
         network_column,
+        lnurl_auth_column,
         show_seed_words_button,
         open_data_dir_button,
     ]
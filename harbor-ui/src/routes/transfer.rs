@@ -37,7 +37,7 @@ pub fn transfer(harbor: &HarborWallet) -> Element<Message> {
     // Show balance for source federation if selected
     if let Some(source_fed) = harbor.transfer_from_federation_selection.as_ref() {
         if let Some(federation) = harbor.mint_list.iter().find(|f| f.name == *source_fed) {
-            source_row = source_row.push(h_balance_display(federation.balance));
+            source_row = source_row.push(h_balance_display(federation.balance, &federation.unit));
         }
     }
 
@@ -61,7 +61,8 @@ pub fn transfer(harbor: &HarborWallet) -> Element<Message> {
     // Show balance for destination federation if selected
     if let Some(dest_fed) = harbor.transfer_to_federation_selection.as_ref() {
         if let Some(federation) = harbor.mint_list.iter().find(|f| f.name == *dest_fed) {
-            destination_row = destination_row.push(h_balance_display(federation.balance));
+            destination_row =
+                destination_row.push(h_balance_display(federation.balance, &federation.unit));
         }
     }
 